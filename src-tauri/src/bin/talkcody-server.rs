@@ -0,0 +1,5 @@
+//! Headless TalkCody backend server, for CI runners and containers
+
+fn main() -> std::process::ExitCode {
+    tauri_app_lib::run_server()
+}
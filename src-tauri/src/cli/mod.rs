@@ -0,0 +1,49 @@
+//! Command Line Entry Points
+//!
+//! Headless front ends to the core runtime: the `talkcody-server` binary that
+//! serves the cloud backend API without the desktop app, and `talkcody run`
+//! for one-shot agent tasks in the terminal.
+
+pub mod run;
+pub mod server;
+
+use std::path::PathBuf;
+
+/// Data directory shared by the headless commands, separate from the desktop
+/// app's so both can run side by side
+pub(crate) fn default_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("talkcody-server"))
+        .unwrap_or_else(|| PathBuf::from(".talkcody-server"))
+}
+
+/// Split `--name=value` into its name and inline value
+pub(crate) fn split_flag(arg: &str) -> (&str, Option<&str>) {
+    match arg.split_once('=') {
+        Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+        _ => (arg, None),
+    }
+}
+
+/// Value of a flag, given inline or as the next argument
+pub(crate) fn flag_value(
+    flag: &str,
+    inline: Option<&str>,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String, String> {
+    match inline {
+        Some(value) => Ok(value.to_string()),
+        None => args
+            .next()
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| format!("{} requires a value", flag)),
+    }
+}
+
+/// Parse a flag or environment value
+pub(crate) fn parse_value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' for {}", value, name))
+}
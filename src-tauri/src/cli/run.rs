@@ -0,0 +1,624 @@
+//! One-Shot Agent Runs
+//!
+//! `talkcody run` starts a single task on an in-process `CoreRuntime`, streams
+//! its output to the terminal and settles tool approvals by policy or by asking
+//! on the terminal. The exit code reflects the final `RuntimeTaskState`; with
+//! `--json` every `RuntimeEvent` is printed as one JSON line instead.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin};
+
+use crate::cli::{default_data_dir, flag_value, split_flag};
+use crate::core::questions;
+use crate::core::runtime::CoreRuntime;
+use crate::core::types::{
+    EventReceiver, RuntimeEvent, RuntimeTaskState, TaskAction, TaskHandle, TaskInput, ToolRequest,
+    UserQuestion,
+};
+use crate::storage::models::{MessageContent, MessageRole, TaskSettings, WorkspaceInfo};
+use crate::storage::Storage;
+
+/// How long to keep reading after `TaskCompleted` for its trailing error event
+const TRAILING_EVENTS_WAIT: Duration = Duration::from_millis(100);
+/// Longest tool input shown in a tool call line
+const TOOL_INPUT_PREVIEW: usize = 120;
+
+const USAGE: &str = "\
+Usage: talkcody run [OPTIONS] [PROMPT]...
+
+Runs one agent task and exits. The prompt is read from stdin when not given;
+approvals then can't be asked for, so `ask` behaves like `deny`.
+
+Options:
+  --workspace <DIR>      Directory the agent works in [default: current directory]
+  --model <MODEL>        Model to use instead of the agent's default
+  --agent <ID>           Agent profile to run as
+  --approval <POLICY>    ask: prompt y/n for each tool call needing approval,
+                         auto: approve everything, deny: reject them [default: ask]
+  --json                 Print runtime events as JSON lines
+  --data-dir <DIR>       Directory for databases [env: TALKCODY_DATA_DIR]
+  -h, --help             Print this help
+
+Exit status: 0 completed, 1 failed, 2 usage error, 3 cancelled.
+";
+
+/// How tool calls that need approval are settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    /// Ask on the terminal; rejected when stdin is closed
+    Ask,
+    /// Approve every call
+    Auto,
+    /// Reject every call
+    Deny,
+}
+
+impl std::str::FromStr for ApprovalPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ask" => Ok(ApprovalPolicy::Ask),
+            "auto" => Ok(ApprovalPolicy::Auto),
+            "deny" => Ok(ApprovalPolicy::Deny),
+            _ => Err(format!(
+                "Unknown approval policy '{}' (expected ask, auto or deny)",
+                s
+            )),
+        }
+    }
+}
+
+/// Options of `talkcody run`
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Prompt from the command line; read from stdin when `None`
+    pub prompt: Option<String>,
+    pub workspace: PathBuf,
+    pub model: Option<String>,
+    pub agent: Option<String>,
+    pub approval: ApprovalPolicy,
+    pub json: bool,
+    pub data_dir: PathBuf,
+}
+
+impl RunOptions {
+    /// Parse the arguments following `run`; `None` means help was requested
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Self>, String> {
+        let mut options = Self {
+            prompt: None,
+            workspace: std::env::current_dir()
+                .map_err(|e| format!("Failed to read current directory: {}", e))?,
+            model: None,
+            agent: None,
+            approval: ApprovalPolicy::Ask,
+            json: false,
+            data_dir: env("TALKCODY_DATA_DIR")
+                .filter(|dir| !dir.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(default_data_dir),
+        };
+
+        let mut prompt = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = split_flag(&arg);
+            match flag {
+                "-h" | "--help" => return Ok(None),
+                "--json" => options.json = true,
+                "--workspace" => {
+                    options.workspace = PathBuf::from(flag_value(flag, inline, &mut args)?)
+                }
+                "--model" => options.model = Some(flag_value(flag, inline, &mut args)?),
+                "--agent" => options.agent = Some(flag_value(flag, inline, &mut args)?),
+                "--approval" => options.approval = flag_value(flag, inline, &mut args)?.parse()?,
+                "--data-dir" => {
+                    options.data_dir = PathBuf::from(flag_value(flag, inline, &mut args)?)
+                }
+                // Everything after `--` is prompt text
+                "--" => prompt.extend(args.by_ref()),
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(format!("Unknown argument '{}'", arg))
+                }
+                _ => prompt.push(arg),
+            }
+        }
+
+        let prompt = prompt.join(" ");
+        if !prompt.trim().is_empty() && prompt != "-" {
+            options.prompt = Some(prompt);
+        }
+
+        Ok(Some(options))
+    }
+
+    fn task_settings(&self) -> TaskSettings {
+        let mut settings = TaskSettings {
+            auto_approve_edits: Some(self.approval == ApprovalPolicy::Auto),
+            ..TaskSettings::default()
+        };
+        if let Some(model) = &self.model {
+            settings
+                .extra
+                .insert("model".to_string(), serde_json::json!(model));
+        }
+        settings
+    }
+}
+
+/// Entry point of `talkcody run`, given the arguments after `run`
+pub fn run(args: Vec<String>) -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let mut options = match RunOptions::parse(args, |name| std::env::var(name).ok()) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("talkcody run: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if options.prompt.is_none() {
+        if std::io::stdin().is_terminal() {
+            eprintln!("talkcody run: no prompt given\n\n{}", USAGE);
+            return ExitCode::from(2);
+        }
+        let mut prompt = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut prompt) {
+            eprintln!("talkcody run: failed to read prompt from stdin: {}", e);
+            return ExitCode::from(2);
+        }
+        if prompt.trim().is_empty() {
+            eprintln!("talkcody run: the prompt is empty");
+            return ExitCode::from(2);
+        }
+        // Answers can't come from stdin once the prompt consumed it
+        if options.approval == ApprovalPolicy::Ask {
+            options.approval = ApprovalPolicy::Deny;
+        }
+        options.prompt = Some(prompt);
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("talkcody run: failed to start async runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match runtime.block_on(run_task(options)) {
+        Ok(state) => exit_code(state),
+        Err(e) => {
+            eprintln!("talkcody run: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Exit status for the final state of the task
+pub fn exit_code(state: RuntimeTaskState) -> ExitCode {
+    match state {
+        RuntimeTaskState::Completed => ExitCode::SUCCESS,
+        RuntimeTaskState::Cancelled => ExitCode::from(3),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+/// Start the task and drive it to a terminal state
+async fn run_task(options: RunOptions) -> Result<RuntimeTaskState, String> {
+    let workspace = options
+        .workspace
+        .canonicalize()
+        .map_err(|e| format!("Invalid workspace {}: {}", options.workspace.display(), e))?;
+    std::fs::create_dir_all(&options.data_dir)
+        .map_err(|e| format!("Failed to create {}: {}", options.data_dir.display(), e))?;
+
+    let storage = Storage::new(
+        options.data_dir.clone(),
+        options.data_dir.join("attachments"),
+    )
+    .await?;
+    let (event_tx, events) = tokio::sync::mpsc::unbounded_channel();
+    let runtime = crate::core::init_runtime(storage, event_tx, options.data_dir.clone()).await?;
+
+    let prompt = options.prompt.clone().unwrap_or_default();
+    let settings = options.task_settings();
+    let session = runtime
+        .session_manager()
+        .create_session(None, Some(session_title(&prompt)), Some(settings.clone()))
+        .await?;
+
+    let handle = runtime
+        .start_task(TaskInput {
+            session_id: session.id,
+            agent_id: options.agent.clone(),
+            project_id: None,
+            initial_message: prompt,
+            settings: Some(settings),
+            workspace: Some(WorkspaceInfo {
+                root_path: workspace.to_string_lossy().to_string(),
+                worktree_path: None,
+                repository_url: None,
+                branch: None,
+            }),
+        })
+        .await?;
+
+    drive_task(&runtime, &handle, events, &options).await
+}
+
+/// Print events and answer the requests of the task and its sub-agents until
+/// the task finishes
+async fn drive_task(
+    runtime: &CoreRuntime,
+    handle: &TaskHandle,
+    mut events: EventReceiver,
+    options: &RunOptions,
+) -> Result<RuntimeTaskState, String> {
+    let mut printer = Printer::new(options.json);
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    // Tasks waiting for the user: the run's own task or a `callAgent` child
+    let mut waiting_for_user = HashSet::new();
+    let mut final_state = None;
+    let mut interrupted = false;
+
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = tokio::signal::ctrl_c(), if !interrupted => {
+                interrupted = true;
+                eprintln!("\nCancelling...");
+                let _ = handle.cancel();
+                continue;
+            }
+        };
+        printer.print(&event);
+
+        match innermost_event(event) {
+            RuntimeEvent::TaskStateChanged { task_id, state, .. } => {
+                if state == RuntimeTaskState::WaitingForUser {
+                    waiting_for_user.insert(task_id.clone());
+                } else {
+                    waiting_for_user.remove(&task_id);
+                }
+                if task_id == handle.task_id && state.is_terminal() {
+                    final_state = Some(state);
+                }
+            }
+            // While waiting, a tool call request is the one to decide on
+            RuntimeEvent::ToolCallRequested {
+                task_id, request, ..
+            } if waiting_for_user.remove(&task_id) => {
+                printer.finish_line();
+                let action = decide_tool_call(options.approval, &request, &mut stdin).await;
+                event_task(runtime, handle, &task_id)
+                    .await?
+                    .send_action(action)?;
+            }
+            RuntimeEvent::QuestionsAsked {
+                task_id,
+                tool_call_id,
+                questions,
+                ..
+            } => {
+                waiting_for_user.remove(&task_id);
+                printer.finish_line();
+                let action =
+                    answer_questions(options.approval, tool_call_id, &questions, &mut stdin).await;
+                event_task(runtime, handle, &task_id)
+                    .await?
+                    .send_action(action)?;
+            }
+            RuntimeEvent::TaskCompleted { task_id, .. } if task_id == handle.task_id => break,
+            _ => {}
+        }
+    }
+
+    // A failed task reports its error right after completing
+    while let Ok(Some(event)) = tokio::time::timeout(TRAILING_EVENTS_WAIT, events.recv()).await {
+        printer.print(&event);
+    }
+    printer.finish_line();
+
+    Ok(final_state.unwrap_or(RuntimeTaskState::Failed))
+}
+
+/// Unwrap `RuntimeEvent::SubAgent` nesting down to the sub-agent's own event
+fn innermost_event(event: RuntimeEvent) -> RuntimeEvent {
+    match event {
+        RuntimeEvent::SubAgent { event, .. } => innermost_event(*event),
+        event => event,
+    }
+}
+
+/// Handle of the task an event came from: the run's task or one of its sub-agents
+async fn event_task(
+    runtime: &CoreRuntime,
+    handle: &TaskHandle,
+    task_id: &str,
+) -> Result<TaskHandle, String> {
+    if task_id == handle.task_id {
+        return Ok(handle.clone());
+    }
+    runtime
+        .get_task(task_id)
+        .await
+        .ok_or_else(|| format!("Task '{}' not found", task_id))
+}
+
+/// Approve or reject a tool call according to the policy
+async fn decide_tool_call(
+    policy: ApprovalPolicy,
+    request: &ToolRequest,
+    stdin: &mut Lines<BufReader<Stdin>>,
+) -> TaskAction {
+    let approved = match policy {
+        ApprovalPolicy::Auto => true,
+        ApprovalPolicy::Deny => false,
+        ApprovalPolicy::Ask => {
+            eprint!("Allow {}? [y/N] ", describe_tool_call(request));
+            let _ = std::io::stderr().flush();
+            matches!(stdin.next_line().await, Ok(Some(line)) if is_yes(&line))
+        }
+    };
+
+    if approved {
+        TaskAction::Approve {
+            tool_call_id: request.tool_call_id.clone(),
+        }
+    } else {
+        eprintln!("Rejected {}", request.name);
+        TaskAction::Reject {
+            tool_call_id: request.tool_call_id.clone(),
+            reason: Some(match policy {
+                ApprovalPolicy::Ask => "Rejected by the user".to_string(),
+                _ => "Rejected by the approval policy".to_string(),
+            }),
+        }
+    }
+}
+
+/// Ask the user the agent's questions; without a terminal the task is cancelled
+async fn answer_questions(
+    policy: ApprovalPolicy,
+    tool_call_id: String,
+    questions: &[UserQuestion],
+    stdin: &mut Lines<BufReader<Stdin>>,
+) -> TaskAction {
+    if policy != ApprovalPolicy::Ask {
+        eprintln!("The agent asked questions, which need --approval ask; cancelling");
+        return TaskAction::Cancel;
+    }
+
+    eprintln!("{}", questions::render_questions(questions));
+    loop {
+        let mut reply = Vec::new();
+        while reply.len() < questions.len() {
+            match stdin.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => {}
+                Ok(Some(line)) => reply.push(line),
+                _ => return TaskAction::Cancel,
+            }
+        }
+
+        match questions::parse_reply(questions, &reply.join("\n")) {
+            Ok(answers) => {
+                return TaskAction::AnswerQuestions {
+                    tool_call_id,
+                    answers,
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+fn is_yes(line: &str) -> bool {
+    matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+fn describe_tool_call(request: &ToolRequest) -> String {
+    let mut input = request.input.to_string();
+    if input.chars().count() > TOOL_INPUT_PREVIEW {
+        input = input.chars().take(TOOL_INPUT_PREVIEW).collect::<String>() + "...";
+    }
+    format!("{} {}", request.name, input)
+}
+
+fn session_title(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or_default().trim();
+    match line.char_indices().nth(60) {
+        Some((index, _)) => format!("{}...", &line[..index]),
+        None => line.to_string(),
+    }
+}
+
+/// Terminal output of the run
+///
+/// Assistant text goes to stdout; tool calls, errors and prompts to stderr so
+/// the answer can be piped. In JSON mode each event is one line on stdout.
+struct Printer {
+    json: bool,
+    /// Whether streamed text is waiting for a line break
+    mid_line: bool,
+    /// Whether tokens were streamed since the last assistant message
+    streamed: bool,
+    shown_tool_calls: HashSet<String>,
+    tool_names: HashMap<String, String>,
+}
+
+impl Printer {
+    fn new(json: bool) -> Self {
+        Self {
+            json,
+            mid_line: false,
+            streamed: false,
+            shown_tool_calls: HashSet::new(),
+            tool_names: HashMap::new(),
+        }
+    }
+
+    fn print(&mut self, event: &RuntimeEvent) {
+        if self.json {
+            match serde_json::to_string(event) {
+                Ok(line) => println!("{}", line),
+                Err(e) => log::warn!("Failed to serialize runtime event: {}", e),
+            }
+            return;
+        }
+
+        match event {
+            RuntimeEvent::Token { token, .. } => {
+                print!("{}", token);
+                let _ = std::io::stdout().flush();
+                self.mid_line = !token.ends_with('\n');
+                self.streamed = true;
+            }
+            RuntimeEvent::MessageCreated { message, .. } => {
+                if message.role != MessageRole::Assistant {
+                    return;
+                }
+                if let MessageContent::Text { text } = &message.content {
+                    if !self.streamed && !text.is_empty() {
+                        print!("{}", text);
+                        self.mid_line = !text.ends_with('\n');
+                    }
+                }
+                self.streamed = false;
+                self.finish_line();
+            }
+            RuntimeEvent::ToolCallRequested { request, .. } => {
+                if self.shown_tool_calls.insert(request.tool_call_id.clone()) {
+                    self.finish_line();
+                    self.tool_names
+                        .insert(request.tool_call_id.clone(), request.name.clone());
+                    eprintln!("> {}", describe_tool_call(request));
+                }
+            }
+            RuntimeEvent::ToolCallCompleted { result, .. } => {
+                let name = result
+                    .name
+                    .clone()
+                    .or_else(|| self.tool_names.get(&result.tool_call_id).cloned())
+                    .unwrap_or_else(|| "tool".to_string());
+                self.finish_line();
+                match (&result.error, result.success) {
+                    (_, true) => eprintln!("  {} done", name),
+                    (Some(error), false) => eprintln!("  {} failed: {}", name, error),
+                    (None, false) => eprintln!("  {} failed", name),
+                }
+            }
+            RuntimeEvent::Error { message, .. } => {
+                self.finish_line();
+                eprintln!("error: {}", message);
+            }
+            RuntimeEvent::SubAgent { agent, event, .. } => {
+                if let RuntimeEvent::ToolCallRequested { request, .. } = event.as_ref() {
+                    self.finish_line();
+                    eprintln!("> [{}] {}", agent, describe_tool_call(request));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// End a line of streamed text before printing anything else
+    fn finish_line(&mut self) {
+        if self.mid_line {
+            println!();
+            self.mid_line = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<RunOptions>, String> {
+        RunOptions::parse(args.iter().map(|a| a.to_string()), |_| None)
+    }
+
+    #[test]
+    fn test_parse_run_options() {
+        let options = parse(&[
+            "--model",
+            "gpt-4.1",
+            "--approval=auto",
+            "--json",
+            "fix",
+            "the",
+            "tests",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.prompt.as_deref(), Some("fix the tests"));
+        assert_eq!(options.approval, ApprovalPolicy::Auto);
+        assert!(options.json);
+
+        let settings = options.task_settings();
+        assert_eq!(settings.auto_approve_edits, Some(true));
+        assert_eq!(settings.extra["model"], serde_json::json!("gpt-4.1"));
+
+        let options = parse(&["--", "--not-a-flag"]).unwrap().unwrap();
+        assert_eq!(options.prompt.as_deref(), Some("--not-a-flag"));
+        assert_eq!(options.approval, ApprovalPolicy::Ask);
+
+        assert!(parse(&[]).unwrap().unwrap().prompt.is_none());
+        assert!(parse(&["-"]).unwrap().unwrap().prompt.is_none());
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["--approval", "sometimes"]).is_err());
+        assert!(parse(&["--verbose", "hi"]).is_err());
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(RuntimeTaskState::Completed), ExitCode::SUCCESS);
+        assert_eq!(exit_code(RuntimeTaskState::Failed), ExitCode::FAILURE);
+        assert_eq!(exit_code(RuntimeTaskState::Cancelled), ExitCode::from(3));
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert!(is_yes(" Y "));
+        assert!(is_yes("yes"));
+        assert!(!is_yes(""));
+        assert!(!is_yes("no"));
+
+        assert_eq!(session_title("short\nsecond line"), "short");
+        assert!(session_title(&"x".repeat(100)).ends_with("..."));
+
+        let nested = RuntimeEvent::SubAgent {
+            parent_task_id: "parent".to_string(),
+            agent: "outer".to_string(),
+            event: Box::new(RuntimeEvent::SubAgent {
+                parent_task_id: "child".to_string(),
+                agent: "inner".to_string(),
+                event: Box::new(RuntimeEvent::TaskCompleted {
+                    task_id: "grandchild".to_string(),
+                    session_id: "session".to_string(),
+                }),
+            }),
+        };
+        assert!(matches!(
+            innermost_event(nested),
+            RuntimeEvent::TaskCompleted { task_id, .. } if task_id == "grandchild"
+        ));
+    }
+}
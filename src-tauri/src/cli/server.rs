@@ -0,0 +1,376 @@
+//! Headless Server
+//!
+//! Runs the cloud backend API without the Tauri app: storage, core runtime,
+//! trace writer and HTTP server, configured from flags or `TALKCODY_*`
+//! environment variables. On SIGTERM or Ctrl-C the server stops accepting
+//! connections and lets active tasks finish before exiting.
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cli::{default_data_dir, flag_value, parse_value, split_flag};
+use crate::database::Database;
+use crate::llm::tracing::schema::init_tracing_schema;
+use crate::llm::tracing::TraceWriter;
+use crate::security::tokens;
+use crate::server::config::DEFAULT_BODY_LIMIT;
+use crate::server::streaming_bridge::init_streaming_bridge;
+use crate::server::{ServerConfig, ServerState};
+use crate::storage::Storage;
+
+const DEFAULT_PORT: u16 = 8765;
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 60;
+
+const USAGE: &str = "\
+Usage: talkcody-server [OPTIONS]
+
+Options:
+  --workspace <DIR>        Workspace root agents work in [env: TALKCODY_WORKSPACE] [default: current directory]
+  --data-dir <DIR>         Directory for databases and attachments [env: TALKCODY_DATA_DIR]
+  --host <ADDR>            Address to bind [env: TALKCODY_HOST] [default: 127.0.0.1]
+  --port <PORT>            Port to bind, 0 for any free port [env: TALKCODY_PORT] [default: 8765]
+  --tls-cert <FILE>        PEM certificate chain, enables HTTPS [env: TALKCODY_TLS_CERT]
+  --tls-key <FILE>         PEM private key for --tls-cert [env: TALKCODY_TLS_KEY]
+  --cors-origin <ORIGIN>   Allowed browser origin, repeatable [env: TALKCODY_CORS_ORIGINS, comma separated]
+  --body-limit <BYTES>     Maximum request body size [env: TALKCODY_BODY_LIMIT]
+  --drain-timeout <SECS>   Time active tasks get to finish on shutdown [env: TALKCODY_DRAIN_TIMEOUT] [default: 60]
+  -h, --help               Print this help
+";
+
+/// Options of the `talkcody-server` binary
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    pub workspace: PathBuf,
+    pub data_dir: PathBuf,
+    pub host: IpAddr,
+    pub port: u16,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub cors_origins: Vec<String>,
+    pub body_limit: usize,
+    pub drain_timeout: Duration,
+}
+
+impl ServerOptions {
+    /// Parse options from command line arguments (without the program name)
+    /// over `TALKCODY_*` environment variables; `None` means help was requested
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Self>, String> {
+        let env = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+
+        let mut options = Self {
+            workspace: match env("TALKCODY_WORKSPACE") {
+                Some(workspace) => PathBuf::from(workspace),
+                None => std::env::current_dir()
+                    .map_err(|e| format!("Failed to read current directory: {}", e))?,
+            },
+            data_dir: env("TALKCODY_DATA_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(default_data_dir),
+            host: match env("TALKCODY_HOST") {
+                Some(host) => parse_value("TALKCODY_HOST", &host)?,
+                None => IpAddr::from([127, 0, 0, 1]),
+            },
+            port: match env("TALKCODY_PORT") {
+                Some(port) => parse_value("TALKCODY_PORT", &port)?,
+                None => DEFAULT_PORT,
+            },
+            tls_cert: env("TALKCODY_TLS_CERT").map(PathBuf::from),
+            tls_key: env("TALKCODY_TLS_KEY").map(PathBuf::from),
+            cors_origins: env("TALKCODY_CORS_ORIGINS")
+                .map(|origins| {
+                    origins
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|origin| !origin.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            body_limit: match env("TALKCODY_BODY_LIMIT") {
+                Some(limit) => parse_value("TALKCODY_BODY_LIMIT", &limit)?,
+                None => DEFAULT_BODY_LIMIT,
+            },
+            drain_timeout: Duration::from_secs(match env("TALKCODY_DRAIN_TIMEOUT") {
+                Some(secs) => parse_value("TALKCODY_DRAIN_TIMEOUT", &secs)?,
+                None => DEFAULT_DRAIN_TIMEOUT_SECS,
+            }),
+        };
+
+        let mut cors_from_flags = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = split_flag(&arg);
+            match flag {
+                "-h" | "--help" => return Ok(None),
+                "--workspace" => {
+                    options.workspace = PathBuf::from(flag_value(flag, inline, &mut args)?)
+                }
+                "--data-dir" => {
+                    options.data_dir = PathBuf::from(flag_value(flag, inline, &mut args)?)
+                }
+                "--host" => {
+                    options.host = parse_value(flag, &flag_value(flag, inline, &mut args)?)?
+                }
+                "--port" => {
+                    options.port = parse_value(flag, &flag_value(flag, inline, &mut args)?)?
+                }
+                "--tls-cert" => {
+                    options.tls_cert = Some(PathBuf::from(flag_value(flag, inline, &mut args)?))
+                }
+                "--tls-key" => {
+                    options.tls_key = Some(PathBuf::from(flag_value(flag, inline, &mut args)?))
+                }
+                "--cors-origin" => cors_from_flags.push(flag_value(flag, inline, &mut args)?),
+                "--body-limit" => {
+                    options.body_limit = parse_value(flag, &flag_value(flag, inline, &mut args)?)?
+                }
+                "--drain-timeout" => {
+                    options.drain_timeout = Duration::from_secs(parse_value(
+                        flag,
+                        &flag_value(flag, inline, &mut args)?,
+                    )?)
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+        if !cors_from_flags.is_empty() {
+            options.cors_origins = cors_from_flags;
+        }
+
+        if options.tls_cert.is_some() != options.tls_key.is_some() {
+            return Err("--tls-cert and --tls-key must be given together".to_string());
+        }
+
+        Ok(Some(options))
+    }
+
+    /// Server configuration for these options
+    pub fn server_config(&self) -> ServerConfig {
+        let mut config = ServerConfig::new(self.workspace.clone(), self.data_dir.clone())
+            .with_bind_address(self.host)
+            .with_port(self.port)
+            .with_cors_origins(self.cors_origins.clone())
+            .with_body_limit(self.body_limit);
+        if let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) {
+            config = config.with_tls(cert.clone(), key.clone());
+        }
+        config
+    }
+}
+
+/// Entry point of the `talkcody-server` binary
+pub fn run() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let options =
+        match ServerOptions::parse(std::env::args().skip(1), |name| std::env::var(name).ok()) {
+            Ok(Some(options)) => options,
+            Ok(None) => {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            Err(e) => {
+                eprintln!("talkcody-server: {}\n\n{}", e, USAGE);
+                return ExitCode::from(2);
+            }
+        };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("talkcody-server: failed to start async runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match runtime.block_on(serve(options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{}", e);
+            eprintln!("talkcody-server: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the server until a shutdown signal arrives
+async fn serve(options: ServerOptions) -> Result<(), String> {
+    std::fs::create_dir_all(&options.data_dir)
+        .map_err(|e| format!("Failed to create {}: {}", options.data_dir.display(), e))?;
+    if !options.workspace.is_dir() {
+        return Err(format!(
+            "Workspace {} is not a directory",
+            options.workspace.display()
+        ));
+    }
+
+    let config = options.server_config();
+    let storage = Storage::new(config.data_root.clone(), config.attachments_root.clone()).await?;
+    if let Some(secret) = tokens::ensure_bootstrap_token(&storage.settings).await? {
+        println!(
+            "No API tokens were configured; created admin token '{}':\n  {}\nStore it now, it is not shown again.",
+            tokens::BOOTSTRAP_TOKEN_LABEL,
+            secret
+        );
+    }
+    let trace_writer = start_trace_writer(&config.data_root).await?;
+
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
+    let runtime = crate::core::init_runtime(storage.clone(), event_tx, config.data_root.clone())
+        .await?
+        .with_trace_writer(trace_writer.clone());
+    let state = ServerState::new(config, runtime, storage);
+    init_streaming_bridge(state.clone(), event_rx);
+
+    let handle = crate::server::start_server_with_state(state).await?;
+    println!("talkcody-server listening on {}", handle.url());
+
+    shutdown_signal().await;
+    log::info!(
+        "Shutting down; waiting up to {:?} for active tasks",
+        options.drain_timeout
+    );
+    handle.shutdown(options.drain_timeout).await;
+    trace_writer.shutdown().await;
+
+    Ok(())
+}
+
+/// Trace writer over `talkcody.db` in the data directory, like the desktop app
+async fn start_trace_writer(data_dir: &Path) -> Result<Arc<TraceWriter>, String> {
+    let db = Arc::new(Database::new(
+        data_dir.join("talkcody.db").to_string_lossy().to_string(),
+    ));
+    db.connect()
+        .await
+        .map_err(|e| format!("Failed to connect to talkcody.db: {}", e))?;
+    init_tracing_schema(&db).await?;
+
+    let trace_writer = Arc::new(TraceWriter::new(db));
+    trace_writer.start();
+    Ok(trace_writer)
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                log::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(args: &[&str], env: &[(&str, &str)]) -> Result<Option<ServerOptions>, String> {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ServerOptions::parse(args.iter().map(|a| a.to_string()), |name| {
+            env.get(name).cloned()
+        })
+    }
+
+    #[test]
+    fn test_defaults_and_env() {
+        let options = parse(&[], &[("TALKCODY_DATA_DIR", "/srv/talkcody")])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.host, IpAddr::from([127, 0, 0, 1]));
+        assert_eq!(options.port, DEFAULT_PORT);
+        assert_eq!(options.data_dir, PathBuf::from("/srv/talkcody"));
+        assert_eq!(options.body_limit, DEFAULT_BODY_LIMIT);
+        assert!(options.tls_cert.is_none());
+
+        let options = parse(
+            &[],
+            &[
+                ("TALKCODY_HOST", "0.0.0.0"),
+                ("TALKCODY_PORT", "9000"),
+                ("TALKCODY_CORS_ORIGINS", "http://a.test, http://b.test"),
+                ("TALKCODY_DRAIN_TIMEOUT", "5"),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.host, IpAddr::from([0, 0, 0, 0]));
+        assert_eq!(options.port, 9000);
+        assert_eq!(options.cors_origins, vec!["http://a.test", "http://b.test"]);
+        assert_eq!(options.drain_timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_flags_override_env() {
+        let options = parse(
+            &[
+                "--port=9100",
+                "--host",
+                "::",
+                "--cors-origin",
+                "http://c.test",
+                "--tls-cert",
+                "cert.pem",
+                "--tls-key",
+                "key.pem",
+                "--workspace",
+                "/work",
+            ],
+            &[
+                ("TALKCODY_PORT", "9000"),
+                ("TALKCODY_CORS_ORIGINS", "http://a.test"),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.port, 9100);
+        assert!(options.host.is_unspecified());
+        assert_eq!(options.cors_origins, vec!["http://c.test"]);
+        assert_eq!(options.workspace, PathBuf::from("/work"));
+
+        let config = options.server_config();
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.tls.unwrap().key_path, PathBuf::from("key.pem"));
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(parse(&["--help"], &[]).unwrap().is_none());
+        assert!(parse(&["--port", "http"], &[]).is_err());
+        assert!(parse(&["--port"], &[]).is_err());
+        assert!(parse(&["--verbose"], &[]).is_err());
+        assert!(parse(&["--tls-cert", "cert.pem"], &[]).is_err());
+        assert!(parse(&[], &[("TALKCODY_PORT", "70000")]).is_err());
+    }
+}
@@ -395,7 +395,7 @@ impl CodeNavigationService {
     }
 
     /// Get language ID from file path based on extension
-    fn get_lang_id_from_path(file_path: &str) -> Option<String> {
+    pub(crate) fn get_lang_id_from_path(file_path: &str) -> Option<String> {
         let ext = file_path.rsplit('.').next()?;
         match ext.to_lowercase().as_str() {
             "py" => Some("python".to_string()),
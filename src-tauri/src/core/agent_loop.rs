@@ -6,14 +6,26 @@
 //! 3. Handles tool calls and dispatches to platform tools
 //! 4. Manages the conversation flow until completion
 
-use crate::core::tools::{ToolContext, ToolDispatchResult, ToolDispatcher, ToolRegistry};
+use crate::core::checkpoints::CheckpointStore;
+use crate::core::session::SessionManager;
+use crate::core::todos::TodoStore;
+use crate::core::tools::{
+    SubAgentRunner, ToolContext, ToolDispatchResult, ToolDispatcher, ToolRegistry,
+};
 use crate::core::types::*;
 use crate::llm::ai_services::stream_runner::StreamRunner;
 use crate::llm::providers::provider_registry::ProviderRegistry;
+use crate::llm::tracing::types::{attributes, float_attr, int_attr, string_attr};
+use crate::llm::tracing::TraceWriter;
 use crate::llm::types::{
-    Message as LlmMessage, StreamEvent, StreamTextRequest, ToolDefinition as LlmToolDefinition,
+    ContentPart, Message as LlmMessage, MessageContent as LlmMessageContent, StreamEvent,
+    StreamTextRequest, ToolDefinition as LlmToolDefinition,
 };
+use crate::platform::paths::resolve_path;
+use crate::platform::types::PlatformContext;
 use crate::storage::models::*;
+use base64::Engine;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, RwLock};
@@ -25,6 +37,16 @@ pub struct AgentLoop {
     event_sender: EventSender,
     registry: ProviderRegistry,
     api_keys: crate::llm::auth::api_key_manager::ApiKeyManager,
+    /// Session manager used to persist tool-call and tool-result messages
+    session_manager: Option<Arc<SessionManager>>,
+    /// Runner handed to `callAgent` tool calls
+    sub_agents: Option<Arc<dyn SubAgentRunner>>,
+    /// Todo store handed to `todoWrite` tool calls
+    todos: Option<TodoStore>,
+    /// Checkpoint store recording files before tools write them
+    checkpoints: Option<CheckpointStore>,
+    /// Trace writer recording each model call under the task's trace
+    trace_writer: Option<Arc<TraceWriter>>,
 }
 
 /// Context for a single agent loop execution
@@ -37,6 +59,11 @@ pub struct AgentLoopContext {
     pub settings: TaskSettings,
     pub messages: Vec<Message>,
     pub model: Option<String>,
+    /// System prompt sent ahead of the history (e.g. from the task's agent)
+    pub system_prompt: Option<String>,
+    /// Model turns the task has taken so far; kept across pauses for approvals
+    /// and answers so `max_iterations` bounds the whole task, not each resume
+    pub iterations: u32,
 }
 
 /// Result of agent loop execution
@@ -46,8 +73,9 @@ pub enum AgentLoopResult {
     Completed { message: String },
     /// Waiting for user approval of tool call
     WaitingForApproval { request: ToolRequest },
-    /// Waiting for tool result
-    WaitingForToolResult { tool_call_id: ToolCallId },
+    /// Waiting for the user to supply the result of a tool call (e.g. answers
+    /// to `askUserQuestions`)
+    WaitingForToolResult { request: ToolRequest },
     /// Error occurred
     Error { message: String },
     /// Maximum iterations reached
@@ -56,6 +84,15 @@ pub enum AgentLoopResult {
     Cancelled,
 }
 
+/// Outcome of a single agent loop iteration
+#[derive(Debug, Clone)]
+pub enum IterationOutcome {
+    /// Tool results were appended to the history; the model must be invoked again
+    Continue,
+    /// The loop is finished with the given result
+    Finished(AgentLoopResult),
+}
+
 /// Stream processor state for handling LLM events
 #[derive(Debug, Default)]
 struct StreamProcessorState {
@@ -63,6 +100,8 @@ struct StreamProcessorState {
     tool_calls: Vec<ToolRequest>,
     has_error: bool,
     error_message: Option<String>,
+    finish_reason: Option<String>,
+    usage: Option<serde_json::Value>,
 }
 
 impl AgentLoop {
@@ -79,37 +118,64 @@ impl AgentLoop {
             event_sender,
             registry,
             api_keys,
+            session_manager: None,
+            sub_agents: None,
+            todos: None,
+            checkpoints: None,
+            trace_writer: None,
         }
     }
 
+    /// Persist tool-call and tool-result messages through the given session manager
+    pub fn with_session_manager(mut self, session_manager: Arc<SessionManager>) -> Self {
+        self.session_manager = Some(session_manager);
+        self
+    }
+
+    /// Let `callAgent` tool calls run sub-agents through the given runner
+    pub fn with_sub_agent_runner(mut self, sub_agents: Arc<dyn SubAgentRunner>) -> Self {
+        self.sub_agents = Some(sub_agents);
+        self
+    }
+
+    /// Let `todoWrite` tool calls maintain session todo lists in the given store
+    pub fn with_todo_store(mut self, todos: TodoStore) -> Self {
+        self.todos = Some(todos);
+        self
+    }
+
+    /// Checkpoint files in the given store before tool calls write them
+    pub fn with_checkpoint_store(mut self, checkpoints: CheckpointStore) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Record model calls as spans of the task's trace in the given writer
+    pub fn with_trace_writer(mut self, trace_writer: Arc<TraceWriter>) -> Self {
+        self.trace_writer = Some(trace_writer);
+        self
+    }
+
     /// Run the agent loop with full LLM integration
-    pub async fn run(&self, ctx: &AgentLoopContext) -> Result<AgentLoopResult, String> {
-        let mut iteration = 0;
+    ///
+    /// Each iteration invokes the model with the accumulated history. Tool calls
+    /// are executed and their results appended before the next iteration; the loop
+    /// stops on a plain-text answer, a tool awaiting approval, an error, or once
+    /// the task has taken `max_iterations` model turns, counting the turns of
+    /// earlier runs recorded in `ctx.iterations`.
+    pub async fn run(&self, ctx: &mut AgentLoopContext) -> Result<AgentLoopResult, String> {
         let mut messages = ctx.messages.clone();
 
-        while iteration < self.config.max_iterations {
-            iteration += 1;
+        // Finish tool calls left unresolved by a previous run (e.g. after an approval)
+        if let Some(result) = self.execute_pending_tool_calls(ctx, &mut messages).await? {
+            return Ok(result);
+        }
 
-            // Run a single iteration
-            match self.run_iteration(ctx, &messages).await? {
-                AgentLoopResult::Completed { message } => {
-                    return Ok(AgentLoopResult::Completed { message });
-                }
-                AgentLoopResult::WaitingForApproval { request } => {
-                    return Ok(AgentLoopResult::WaitingForApproval { request });
-                }
-                AgentLoopResult::WaitingForToolResult { tool_call_id } => {
-                    return Ok(AgentLoopResult::WaitingForToolResult { tool_call_id });
-                }
-                AgentLoopResult::Error { message } => {
-                    return Ok(AgentLoopResult::Error { message });
-                }
-                AgentLoopResult::MaxIterationsReached => {
-                    return Ok(AgentLoopResult::MaxIterationsReached);
-                }
-                AgentLoopResult::Cancelled => {
-                    return Ok(AgentLoopResult::Cancelled);
-                }
+        while ctx.iterations < self.config.max_iterations {
+            ctx.iterations += 1;
+            match self.run_iteration(ctx, &mut messages).await? {
+                IterationOutcome::Continue => continue,
+                IterationOutcome::Finished(result) => return Ok(result),
             }
         }
 
@@ -117,16 +183,28 @@ impl AgentLoop {
     }
 
     /// Run a single iteration with LLM streaming
+    ///
+    /// Messages created during the iteration (assistant tool calls and tool
+    /// results) are appended to `messages` so the next iteration sees them.
     pub async fn run_iteration(
         &self,
         ctx: &AgentLoopContext,
-        messages: &[Message],
-    ) -> Result<AgentLoopResult, String> {
+        messages: &mut Vec<Message>,
+    ) -> Result<IterationOutcome, String> {
         // Convert messages to LLM format
-        let llm_messages: Vec<LlmMessage> = messages
-            .iter()
-            .map(|m| self.convert_message_to_llm(m))
-            .collect();
+        let platform_ctx = crate::platform::Platform::new()
+            .create_context(&ctx.workspace_root, ctx.worktree_path.as_deref());
+        let images = load_turn_images(messages, &platform_ctx).await;
+        let mut llm_messages = convert_messages_to_llm(messages, &images);
+        if let Some(system_prompt) = ctx.system_prompt.as_ref().filter(|p| !p.trim().is_empty()) {
+            llm_messages.insert(
+                0,
+                LlmMessage::System {
+                    content: system_prompt.clone(),
+                    provider_options: None,
+                },
+            );
+        }
 
         // Build tools for LLM
         let tools = if self.config.enable_tools {
@@ -157,67 +235,237 @@ impl AgentLoop {
         let runner = StreamRunner::new(self.registry.clone(), self.api_keys.clone());
         let mut state = StreamProcessorState::default();
         let timeout = Duration::from_secs(300);
+        let span_id = self.start_llm_span(ctx, &request);
 
         let result = runner
             .stream(request, timeout, |event| {
                 self.process_stream_event(&mut state, event, ctx);
             })
             .await;
+        self.end_llm_span(span_id, &state, result.as_ref().err());
 
         if let Err(e) = result {
-            return Ok(AgentLoopResult::Error { message: e });
+            return Ok(IterationOutcome::Finished(AgentLoopResult::Error {
+                message: e,
+            }));
         }
 
         // Check for errors
         if state.has_error {
-            return Ok(AgentLoopResult::Error {
+            return Ok(IterationOutcome::Finished(AgentLoopResult::Error {
                 message: state
                     .error_message
                     .unwrap_or_else(|| "Unknown error".to_string()),
-            });
+            }));
         }
 
-        // Handle tool calls
-        if !state.tool_calls.is_empty() {
-            // For now, handle first tool call
-            let tool_call = state.tool_calls.remove(0);
-            let tool_context = ToolContext {
-                session_id: ctx.session_id.clone(),
-                task_id: ctx.task_id.clone(),
-                workspace_root: ctx.workspace_root.clone(),
-                worktree_path: ctx.worktree_path.clone(),
-                settings: ctx.settings.clone(),
-            };
+        // No tool calls, the accumulated text is the final answer
+        if state.tool_calls.is_empty() {
+            return Ok(IterationOutcome::Finished(AgentLoopResult::Completed {
+                message: state.accumulated_text,
+            }));
+        }
+
+        // Record the assistant turn: any preamble text followed by its tool calls
+        if !state.accumulated_text.trim().is_empty() {
+            let text_message = self.new_message(
+                ctx,
+                MessageRole::Assistant,
+                MessageContent::Text {
+                    text: std::mem::take(&mut state.accumulated_text),
+                },
+                None,
+            );
+            self.record_message(text_message, messages).await?;
+        }
+
+        let calls = state
+            .tool_calls
+            .iter()
+            .map(|request| ToolCall {
+                id: request.tool_call_id.clone(),
+                name: request.name.clone(),
+                input: request.input.clone(),
+                provider_metadata: request.provider_metadata.clone(),
+            })
+            .collect();
+        let calls_message = self.new_message(
+            ctx,
+            MessageRole::Assistant,
+            MessageContent::ToolCalls { calls },
+            None,
+        );
+        self.record_message(calls_message, messages).await?;
+
+        // Execute every tool call and feed the results back to the model
+        if let Some(result) = self.execute_pending_tool_calls(ctx, messages).await? {
+            return Ok(IterationOutcome::Finished(result));
+        }
 
-            let auto_approve = ctx.settings.auto_approve_edits.unwrap_or(false);
+        Ok(IterationOutcome::Continue)
+    }
+
+    /// Execute the tool calls of the trailing assistant turn that have no result yet
+    ///
+    /// Calls up to the first one that needs approval are executed as a batch
+    /// following the dependency plan; results are emitted and recorded in the
+    /// model's original order. Returns `Some(WaitingForApproval)` when a call needs
+    /// user approval, or `Some(WaitingForToolResult)` when its result must come
+    /// from the user; the calls after it stay pending and are picked up by the
+    /// next `run`.
+    async fn execute_pending_tool_calls(
+        &self,
+        ctx: &AgentLoopContext,
+        messages: &mut Vec<Message>,
+    ) -> Result<Option<AgentLoopResult>, String> {
+        let auto_approve = ctx.settings.auto_approve_edits.unwrap_or(false);
 
-            match self
+        let mut runnable = Vec::new();
+        let mut awaiting = None;
+        for tool_call in pending_tool_calls(messages) {
+            if self.tool_dispatcher.needs_user_result(&tool_call.name) {
+                awaiting = Some(AgentLoopResult::WaitingForToolResult { request: tool_call });
+                break;
+            }
+            if self
                 .tool_dispatcher
-                .dispatch(tool_call.clone(), tool_context, auto_approve)
+                .needs_approval(&tool_call.name, auto_approve)
                 .await
             {
-                Ok(ToolDispatchResult::Completed(result)) => {
-                    // Emit completion event
-                    let _ = self.event_sender.send(RuntimeEvent::ToolCallCompleted {
-                        task_id: ctx.task_id.clone(),
-                        result: result.clone(),
-                    });
+                awaiting = Some(AgentLoopResult::WaitingForApproval { request: tool_call });
+                break;
+            }
+            runnable.push(tool_call);
+        }
 
-                    // Continue with tool result
-                    Ok(AgentLoopResult::Completed {
-                        message: format!("Tool executed: {:?}", result),
-                    })
-                }
-                Ok(ToolDispatchResult::PendingApproval(request)) => {
-                    Ok(AgentLoopResult::WaitingForApproval { request })
-                }
-                Err(e) => Ok(AgentLoopResult::Error { message: e }),
+        if !runnable.is_empty() {
+            let results = self
+                .tool_dispatcher
+                .execute_batch(runnable.clone(), self.tool_context(ctx))
+                .await;
+
+            for (tool_call, result) in runnable.iter().zip(results) {
+                let _ = self.event_sender.send(RuntimeEvent::ToolCallCompleted {
+                    task_id: ctx.task_id.clone(),
+                    session_id: ctx.session_id.clone(),
+                    result: result.clone(),
+                });
+
+                self.record_tool_result(ctx, tool_call, &result, messages)
+                    .await?;
             }
-        } else {
-            // No tool calls, return completed with accumulated text
-            Ok(AgentLoopResult::Completed {
-                message: state.accumulated_text,
-            })
+        }
+
+        Ok(awaiting)
+    }
+
+    /// Record a "Cancelled by user" error for every tool call of the trailing
+    /// assistant turn that has no result, so a cancelled task leaves no
+    /// unanswered calls for the provider to reject on the session's next turn
+    pub async fn cancel_pending_tool_calls(
+        &self,
+        ctx: &AgentLoopContext,
+        messages: &mut Vec<Message>,
+    ) -> Result<(), String> {
+        for tool_call in pending_tool_calls(messages) {
+            let result = ToolResult {
+                tool_call_id: tool_call.tool_call_id.clone(),
+                name: Some(tool_call.name.clone()),
+                success: false,
+                output: serde_json::Value::Null,
+                error: Some("Cancelled by user".to_string()),
+            };
+            let _ = self.event_sender.send(RuntimeEvent::ToolCallCompleted {
+                task_id: ctx.task_id.clone(),
+                session_id: ctx.session_id.clone(),
+                result: result.clone(),
+            });
+            self.record_tool_result(ctx, &tool_call, &result, messages)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Persist a tool result as a `tool` message and append it to the history
+    pub async fn record_tool_result(
+        &self,
+        ctx: &AgentLoopContext,
+        request: &ToolRequest,
+        result: &ToolResult,
+        messages: &mut Vec<Message>,
+    ) -> Result<(), String> {
+        let stored = StoredToolResult {
+            tool_call_id: request.tool_call_id.clone(),
+            tool_name: result.name.clone().unwrap_or_else(|| request.name.clone()),
+            input: Some(request.input.clone()),
+            output: Some(result.output.clone()),
+            status: if result.success {
+                ToolResultStatus::Success
+            } else {
+                ToolResultStatus::Error
+            },
+            error_message: result.error.clone(),
+        };
+
+        let message = self.new_message(
+            ctx,
+            MessageRole::Tool,
+            MessageContent::ToolResult { result: stored },
+            Some(request.tool_call_id.clone()),
+        );
+        self.record_message(message, messages).await
+    }
+
+    /// Build a new message for the context session
+    fn new_message(
+        &self,
+        ctx: &AgentLoopContext,
+        role: MessageRole,
+        content: MessageContent,
+        tool_call_id: Option<ToolCallId>,
+    ) -> Message {
+        Message {
+            id: format!("msg_{}", uuid::Uuid::new_v4()),
+            session_id: ctx.session_id.clone(),
+            role,
+            content,
+            created_at: chrono::Utc::now().timestamp(),
+            tool_call_id,
+            parent_id: None,
+        }
+    }
+
+    /// Persist a message (when a session manager is attached), emit it and
+    /// append it to the in-memory history
+    async fn record_message(
+        &self,
+        message: Message,
+        messages: &mut Vec<Message>,
+    ) -> Result<(), String> {
+        if let Some(session_manager) = &self.session_manager {
+            session_manager.add_message(message.clone()).await?;
+        }
+
+        let _ = self.event_sender.send(RuntimeEvent::MessageCreated {
+            session_id: message.session_id.clone(),
+            message: message.clone(),
+        });
+
+        messages.push(message);
+        Ok(())
+    }
+
+    /// Build the tool execution context for a loop context
+    fn tool_context(&self, ctx: &AgentLoopContext) -> ToolContext {
+        ToolContext {
+            session_id: ctx.session_id.clone(),
+            task_id: ctx.task_id.clone(),
+            workspace_root: ctx.workspace_root.clone(),
+            worktree_path: ctx.worktree_path.clone(),
+            settings: ctx.settings.clone(),
+            sub_agents: self.sub_agents.clone(),
+            todos: self.todos.clone(),
+            checkpoints: self.checkpoints.clone(),
         }
     }
 
@@ -255,6 +503,7 @@ impl AgentLoop {
                 // Emit tool call requested event
                 let _ = self.event_sender.send(RuntimeEvent::ToolCallRequested {
                     task_id: ctx.task_id.clone(),
+                    session_id: ctx.session_id.clone(),
                     request: tool_request,
                 });
             }
@@ -262,47 +511,129 @@ impl AgentLoop {
                 state.has_error = true;
                 state.error_message = Some(message);
             }
+            StreamEvent::Usage {
+                input_tokens,
+                output_tokens,
+                total_tokens,
+                cached_input_tokens,
+                cache_creation_input_tokens,
+            } => {
+                state.usage = Some(serde_json::json!({
+                    "input_tokens": input_tokens,
+                    "output_tokens": output_tokens,
+                    "total_tokens": total_tokens,
+                    "cached_input_tokens": cached_input_tokens,
+                    "cache_creation_input_tokens": cache_creation_input_tokens,
+                }));
+            }
+            StreamEvent::Done { finish_reason } => {
+                state.finish_reason = finish_reason;
+            }
             _ => {}
         }
     }
 
-    /// Convert internal Message to LLM Message format
-    fn convert_message_to_llm(&self, message: &Message) -> LlmMessage {
-        match message.role {
-            MessageRole::User => LlmMessage::User {
-                content: crate::llm::types::MessageContent::Text(match &message.content {
-                    MessageContent::Text { text } => text.clone(),
-                    _ => serde_json::to_string(&message.content).unwrap_or_default(),
-                }),
-                provider_options: None,
-            },
-            MessageRole::Assistant => LlmMessage::Assistant {
-                content: crate::llm::types::MessageContent::Text(match &message.content {
-                    MessageContent::Text { text } => text.clone(),
-                    _ => serde_json::to_string(&message.content).unwrap_or_default(),
-                }),
-                provider_options: None,
-            },
-            MessageRole::System => LlmMessage::System {
-                content: match &message.content {
-                    MessageContent::Text { text } => text.clone(),
-                    _ => serde_json::to_string(&message.content).unwrap_or_default(),
-                },
-                provider_options: None,
-            },
-            MessageRole::Tool => LlmMessage::Tool {
-                content: vec![],
-                provider_options: None,
-            },
+    /// Open a span for a model call in the task's trace, if tracing is enabled
+    fn start_llm_span(
+        &self,
+        ctx: &AgentLoopContext,
+        request: &StreamTextRequest,
+    ) -> Option<String> {
+        let trace_writer = self.trace_writer.as_ref()?;
+
+        let mut span_attributes = HashMap::new();
+        span_attributes.insert(
+            attributes::GEN_AI_REQUEST_MODEL.to_string(),
+            string_attr(&request.model),
+        );
+        if let Some(t) = request.temperature {
+            span_attributes.insert(
+                attributes::GEN_AI_REQUEST_TEMPERATURE.to_string(),
+                float_attr(t as f64),
+            );
         }
+        if let Some(m) = request.max_tokens {
+            span_attributes.insert(
+                attributes::GEN_AI_REQUEST_MAX_TOKENS.to_string(),
+                int_attr(m as i64),
+            );
+        }
+
+        let span_id = trace_writer.start_span(
+            ctx.task_id.clone(),
+            None,
+            "llm.stream_completion".to_string(),
+            span_attributes,
+        );
+        trace_writer.add_event(
+            span_id.clone(),
+            attributes::HTTP_REQUEST_BODY.to_string(),
+            serde_json::to_value(request).ok(),
+        );
+        Some(span_id)
+    }
+
+    /// Record the outcome of a model call and close its span
+    fn end_llm_span(
+        &self,
+        span_id: Option<String>,
+        state: &StreamProcessorState,
+        error: Option<&String>,
+    ) {
+        let (Some(trace_writer), Some(span_id)) = (self.trace_writer.as_ref(), span_id) else {
+            return;
+        };
+
+        if let Some(usage) = &state.usage {
+            trace_writer.add_event(
+                span_id.clone(),
+                "gen_ai.usage".to_string(),
+                Some(usage.clone()),
+            );
+        }
+        if let Some(finish_reason) = &state.finish_reason {
+            trace_writer.add_event(
+                span_id.clone(),
+                "gen_ai.finish_reason".to_string(),
+                Some(serde_json::json!({ "finish_reason": finish_reason })),
+            );
+        }
+        if let Some(message) = error.or(state.error_message.as_ref()) {
+            trace_writer.add_event(
+                span_id.clone(),
+                "error".to_string(),
+                Some(serde_json::json!({ "message": message })),
+            );
+        }
+        trace_writer.add_event(
+            span_id.clone(),
+            attributes::HTTP_RESPONSE_BODY.to_string(),
+            Some(serde_json::json!({
+                "text": state.accumulated_text,
+                "tool_calls": state.tool_calls.len(),
+            })),
+        );
+
+        trace_writer.end_span(span_id, chrono::Utc::now().timestamp_millis());
     }
 
     /// Build tool definitions for LLM
+    ///
+    /// When `available_tools` is set only those tools are offered to the model.
     fn build_tool_definitions(&self) -> Vec<LlmToolDefinition> {
         use crate::core::tool_definitions::get_tool_definitions;
+        use crate::core::tool_name_normalizer::normalize_tool_name;
+
+        let allowed: std::collections::HashSet<String> = self
+            .config
+            .available_tools
+            .iter()
+            .map(|name| normalize_tool_name(name))
+            .collect();
 
         get_tool_definitions()
             .into_iter()
+            .filter(|(def, _)| allowed.is_empty() || allowed.contains(&def.name))
             .map(|(def, _)| LlmToolDefinition {
                 tool_type: "function".to_string(),
                 name: def.name,
@@ -319,13 +650,7 @@ impl AgentLoop {
         ctx: &AgentLoopContext,
         request: ToolRequest,
     ) -> Result<ToolResult, String> {
-        let tool_context = ToolContext {
-            session_id: ctx.session_id.clone(),
-            task_id: ctx.task_id.clone(),
-            workspace_root: ctx.workspace_root.clone(),
-            worktree_path: ctx.worktree_path.clone(),
-            settings: ctx.settings.clone(),
-        };
+        let tool_context = self.tool_context(ctx);
 
         // Check auto-approve settings
         let auto_approve = ctx.settings.auto_approve_edits.unwrap_or(false);
@@ -347,13 +672,7 @@ impl AgentLoop {
         ctx: &AgentLoopContext,
         request: ToolRequest,
     ) -> ToolResult {
-        let tool_context = ToolContext {
-            session_id: ctx.session_id.clone(),
-            task_id: ctx.task_id.clone(),
-            workspace_root: ctx.workspace_root.clone(),
-            worktree_path: ctx.worktree_path.clone(),
-            settings: ctx.settings.clone(),
-        };
+        let tool_context = self.tool_context(ctx);
 
         let result = self
             .tool_dispatcher
@@ -363,6 +682,7 @@ impl AgentLoop {
         // Emit completion event
         let _ = self.event_sender.send(RuntimeEvent::ToolCallCompleted {
             task_id: ctx.task_id.clone(),
+            session_id: ctx.session_id.clone(),
             result: result.clone(),
         });
 
@@ -407,6 +727,261 @@ impl AgentLoop {
     }
 }
 
+/// Tool calls of the trailing assistant turn that have no recorded result yet
+///
+/// Only calls after the last user message are considered, so an abandoned turn
+/// from an earlier task is never re-executed.
+fn pending_tool_calls(messages: &[Message]) -> Vec<ToolRequest> {
+    let turn_start = messages
+        .iter()
+        .rposition(|m| m.role == MessageRole::User)
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    let turn = &messages[turn_start..];
+
+    let calls = match turn.iter().rev().find_map(|m| match (&m.role, &m.content) {
+        (MessageRole::Assistant, MessageContent::ToolCalls { calls }) => Some(calls),
+        _ => None,
+    }) {
+        Some(calls) => calls,
+        None => return vec![],
+    };
+
+    let resolved: std::collections::HashSet<&str> = turn
+        .iter()
+        .filter_map(|m| match &m.content {
+            MessageContent::ToolResult { result } => Some(result.tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    calls
+        .iter()
+        .filter(|call| !resolved.contains(call.id.as_str()))
+        .map(|call| ToolRequest {
+            tool_call_id: call.id.clone(),
+            name: call.name.clone(),
+            input: call.input.clone(),
+            provider_metadata: call.provider_metadata.clone(),
+        })
+        .collect()
+}
+
+/// Convert stored session messages into the LLM message format
+///
+/// Consecutive assistant messages (preamble text followed by tool calls) are merged
+/// into a single assistant turn, and consecutive tool results into a single tool
+/// message, as providers expect.
+fn convert_messages_to_llm(
+    messages: &[Message],
+    turn_images: &HashMap<ToolCallId, ContentPart>,
+) -> Vec<LlmMessage> {
+    let mut result: Vec<LlmMessage> = Vec::new();
+    // Images from the current run of tool results, sent after it as a user turn
+    let mut images: Vec<ContentPart> = Vec::new();
+
+    for message in messages {
+        if !matches!(message.content, MessageContent::ToolResult { .. }) {
+            push_tool_images(&mut result, &mut images);
+        }
+        match (&message.role, &message.content) {
+            (MessageRole::System, content) => result.push(LlmMessage::System {
+                content: content_to_text(content),
+                provider_options: None,
+            }),
+            (MessageRole::User, content) => result.push(LlmMessage::User {
+                content: LlmMessageContent::Text(content_to_text(content)),
+                provider_options: None,
+            }),
+            (MessageRole::Assistant, MessageContent::ToolCalls { calls }) => {
+                let parts = calls
+                    .iter()
+                    .map(|call| ContentPart::ToolCall {
+                        tool_call_id: call.id.clone(),
+                        tool_name: call.name.clone(),
+                        input: call.input.clone(),
+                        provider_metadata: call.provider_metadata.clone(),
+                    })
+                    .collect();
+                push_assistant_parts(&mut result, parts);
+            }
+            (MessageRole::Assistant, content) => {
+                let text = content_to_text(content);
+                match result.last() {
+                    Some(LlmMessage::Assistant { .. }) => {
+                        push_assistant_parts(&mut result, vec![ContentPart::Text { text }])
+                    }
+                    _ => result.push(LlmMessage::Assistant {
+                        content: LlmMessageContent::Text(text),
+                        provider_options: None,
+                    }),
+                }
+            }
+            (_, MessageContent::ToolResult { result: stored }) => {
+                let part = ContentPart::ToolResult {
+                    tool_call_id: stored.tool_call_id.clone(),
+                    tool_name: stored.tool_name.clone(),
+                    output: tool_output_for_llm(stored),
+                };
+                images.extend(turn_images.get(&stored.tool_call_id).cloned());
+                if let Some(LlmMessage::Tool { content, .. }) = result.last_mut() {
+                    content.push(part);
+                } else {
+                    result.push(LlmMessage::Tool {
+                        content: vec![part],
+                        provider_options: None,
+                    });
+                }
+            }
+            (MessageRole::Tool, content) => result.push(LlmMessage::User {
+                content: LlmMessageContent::Text(content_to_text(content)),
+                provider_options: None,
+            }),
+        }
+    }
+    push_tool_images(&mut result, &mut images);
+
+    result
+}
+
+/// Show the model the images its tool calls returned; tool results only
+/// carry text
+fn push_tool_images(messages: &mut Vec<LlmMessage>, images: &mut Vec<ContentPart>) {
+    if images.is_empty() {
+        return;
+    }
+    let mut parts = vec![ContentPart::Text {
+        text: "Images returned by the tool calls above:".to_string(),
+    }];
+    parts.append(images);
+    messages.push(LlmMessage::User {
+        content: LlmMessageContent::Parts(parts),
+        provider_options: None,
+    });
+}
+
+/// Load the images that tool calls since the last user message referenced,
+/// such as an image read by `readFile`, keyed by tool call id
+///
+/// History only stores the path, so images from earlier turns are not sent
+/// again; the model can read them anew if it still needs them.
+async fn load_turn_images(
+    messages: &[Message],
+    platform_ctx: &PlatformContext,
+) -> HashMap<ToolCallId, ContentPart> {
+    let turn_start = messages
+        .iter()
+        .rposition(|m| {
+            m.role == MessageRole::User && matches!(m.content, MessageContent::Text { .. })
+        })
+        .map_or(0, |i| i + 1);
+
+    let mut images = HashMap::new();
+    for message in &messages[turn_start..] {
+        let MessageContent::ToolResult { result } = &message.content else {
+            continue;
+        };
+        if matches!(result.status, ToolResultStatus::Error)
+            || !matches!(result.tool_name.as_str(), "readFile" | "read_file")
+        {
+            continue;
+        }
+        let Some(output) = result
+            .output
+            .as_ref()
+            .filter(|output| output.get("kind").and_then(|v| v.as_str()) == Some("image"))
+        else {
+            continue;
+        };
+        let Some(path) = output.get("filePath").and_then(|v| v.as_str()) else {
+            continue;
+        };
+
+        // Clients can submit the result of a pending call, so the path and
+        // size are checked again rather than trusted
+        let path = match resolve_path(path, platform_ctx) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("Not loading image {}: {}", path, e);
+                continue;
+            }
+        };
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.len() <= platform_ctx.max_file_size as u64 => {}
+            Ok(metadata) => {
+                log::warn!(
+                    "Not loading image {}: {} bytes (max: {})",
+                    path.display(),
+                    metadata.len(),
+                    platform_ctx.max_file_size
+                );
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Failed to load image {}: {}", path.display(), e);
+                continue;
+            }
+        }
+        match tokio::fs::read(&path).await {
+            Ok(data) => {
+                images.insert(
+                    result.tool_call_id.clone(),
+                    ContentPart::Image {
+                        image: base64::engine::general_purpose::STANDARD.encode(data),
+                    },
+                );
+            }
+            Err(e) => log::warn!("Failed to load image {}: {}", path.display(), e),
+        }
+    }
+    images
+}
+
+/// Append parts to the trailing assistant message, or start a new one
+fn push_assistant_parts(messages: &mut Vec<LlmMessage>, parts: Vec<ContentPart>) {
+    if let Some(LlmMessage::Assistant { content, .. }) = messages.last_mut() {
+        let mut merged = match std::mem::replace(content, LlmMessageContent::Parts(vec![])) {
+            LlmMessageContent::Text(text) => vec![ContentPart::Text { text }],
+            LlmMessageContent::Parts(existing) => existing,
+        };
+        merged.extend(parts);
+        *content = LlmMessageContent::Parts(merged);
+        return;
+    }
+
+    messages.push(LlmMessage::Assistant {
+        content: LlmMessageContent::Parts(parts),
+        provider_options: None,
+    });
+}
+
+/// Flatten stored message content to plain text
+fn content_to_text(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text { text } => text.clone(),
+        _ => serde_json::to_string(content).unwrap_or_default(),
+    }
+}
+
+/// Render a stored tool result as the `{ type, value }` output the protocols expect
+fn tool_output_for_llm(result: &StoredToolResult) -> serde_json::Value {
+    let value = match (result.status, &result.output) {
+        (ToolResultStatus::Error, _) => format!(
+            "Error: {}",
+            result
+                .error_message
+                .clone()
+                .unwrap_or_else(|| "Tool execution failed".to_string())
+        ),
+        (_, Some(serde_json::Value::String(text))) => text.clone(),
+        // Images reach the model as a user turn, see `push_tool_images`
+        (_, Some(output)) => output.to_string(),
+        (_, None) => String::new(),
+    };
+
+    serde_json::json!({ "type": "text", "value": value })
+}
+
 /// Factory for creating agent loops with different configurations
 pub struct AgentLoopFactory;
 
@@ -475,11 +1050,28 @@ mod tests {
         (loop_instance, rx)
     }
 
+    #[tokio::test]
+    async fn test_tool_definitions_respect_available_tools() {
+        let (mut agent_loop, _rx) = create_test_loop().await;
+        let all = agent_loop.build_tool_definitions().len();
+
+        agent_loop.config.available_tools = vec!["read_file".to_string(), "callAgent".to_string()];
+        let mut names: Vec<String> = agent_loop
+            .build_tool_definitions()
+            .into_iter()
+            .map(|def| def.name)
+            .collect();
+        names.sort();
+
+        assert_eq!(names, vec!["callAgent", "readFile"]);
+        assert!(all > names.len());
+    }
+
     #[tokio::test]
     async fn test_agent_loop_placeholder() {
         let (agent_loop, _rx) = create_test_loop().await;
 
-        let ctx = AgentLoopContext {
+        let mut ctx = AgentLoopContext {
             session_id: "test-session".to_string(),
             task_id: "test-task".to_string(),
             workspace_root: "/tmp".to_string(),
@@ -487,11 +1079,104 @@ mod tests {
             settings: TaskSettings::default(),
             messages: vec![],
             model: None,
+            system_prompt: None,
+            iterations: 0,
         };
 
         // Test that the loop runs without panicking
-        let result = agent_loop.run(&ctx).await;
+        let result = agent_loop.run(&mut ctx).await;
         assert!(result.is_ok());
+        assert_eq!(ctx.iterations, 1);
+    }
+
+    #[tokio::test]
+    async fn test_model_calls_are_traced_under_the_task() {
+        use crate::database::Database;
+        use crate::llm::tracing::schema::init_tracing_schema;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(
+            temp_dir
+                .path()
+                .join("traces.db")
+                .to_string_lossy()
+                .to_string(),
+        ));
+        db.connect().await.unwrap();
+        init_tracing_schema(&db).await.unwrap();
+        let trace_writer = Arc::new(TraceWriter::new(db.clone()));
+        trace_writer.start();
+
+        let (agent_loop, _rx) = create_test_loop().await;
+        let agent_loop = agent_loop.with_trace_writer(trace_writer.clone());
+        let ctx = AgentLoopContext {
+            session_id: "test-session".to_string(),
+            task_id: "test-task".to_string(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            messages: vec![],
+            model: None,
+            system_prompt: None,
+            iterations: 0,
+        };
+
+        // No provider is configured, so the call fails; its span still closes
+        let outcome = agent_loop.run_iteration(&ctx, &mut vec![]).await.unwrap();
+        assert!(matches!(
+            outcome,
+            IterationOutcome::Finished(AgentLoopResult::Error { .. })
+        ));
+
+        trace_writer.request_flush();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let spans = db
+            .query(
+                "SELECT id FROM spans WHERE trace_id = ? AND ended_at IS NOT NULL",
+                vec![serde_json::json!("test-task")],
+            )
+            .await
+            .unwrap();
+        assert_eq!(spans.rows.len(), 1);
+        let span_id = spans.rows[0]["id"].clone();
+        let events = db
+            .query(
+                "SELECT event_type FROM span_events WHERE span_id = ? ORDER BY timestamp",
+                vec![span_id],
+            )
+            .await
+            .unwrap();
+        let event_types: Vec<_> = events
+            .rows
+            .iter()
+            .filter_map(|row| row["event_type"].as_str())
+            .collect();
+        assert!(event_types.contains(&attributes::HTTP_REQUEST_BODY));
+        assert!(event_types.contains(&"error"));
+    }
+
+    #[tokio::test]
+    async fn test_run_continues_iteration_budget_after_pause() {
+        let (agent_loop, _rx) = create_test_loop().await;
+
+        // A resumed task that already used its turns doesn't get a fresh budget
+        let mut ctx = AgentLoopContext {
+            session_id: "test-session".to_string(),
+            task_id: "test-task".to_string(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            messages: vec![],
+            model: None,
+            system_prompt: None,
+            iterations: agent_loop.config.max_iterations,
+        };
+
+        let result = agent_loop.run(&mut ctx).await.unwrap();
+        assert!(matches!(result, AgentLoopResult::MaxIterationsReached));
+        assert_eq!(ctx.iterations, agent_loop.config.max_iterations);
     }
 
     #[test]
@@ -543,4 +1228,302 @@ mod tests {
         assert!(prompt.contains("User: Hello"));
         assert!(prompt.contains("Assistant: Hi there!"));
     }
+
+    fn stored_message(id: &str, role: MessageRole, content: MessageContent) -> Message {
+        Message {
+            id: id.to_string(),
+            session_id: "test".to_string(),
+            role,
+            content,
+            created_at: 0,
+            tool_call_id: None,
+            parent_id: None,
+        }
+    }
+
+    #[test]
+    fn test_convert_messages_merges_tool_turns() {
+        let messages = vec![
+            stored_message(
+                "msg-1",
+                MessageRole::User,
+                MessageContent::Text {
+                    text: "Read both files".to_string(),
+                },
+            ),
+            stored_message(
+                "msg-2",
+                MessageRole::Assistant,
+                MessageContent::Text {
+                    text: "Reading".to_string(),
+                },
+            ),
+            stored_message(
+                "msg-3",
+                MessageRole::Assistant,
+                MessageContent::ToolCalls {
+                    calls: vec![
+                        ToolCall {
+                            id: "call-1".to_string(),
+                            name: "readFile".to_string(),
+                            input: serde_json::json!({"file_path": "a.rs"}),
+                            provider_metadata: None,
+                        },
+                        ToolCall {
+                            id: "call-2".to_string(),
+                            name: "readFile".to_string(),
+                            input: serde_json::json!({"file_path": "b.rs"}),
+                            provider_metadata: None,
+                        },
+                    ],
+                },
+            ),
+            stored_message(
+                "msg-4",
+                MessageRole::Tool,
+                MessageContent::ToolResult {
+                    result: StoredToolResult {
+                        tool_call_id: "call-1".to_string(),
+                        tool_name: "readFile".to_string(),
+                        input: None,
+                        output: Some(serde_json::json!("fn a() {}")),
+                        status: ToolResultStatus::Success,
+                        error_message: None,
+                    },
+                },
+            ),
+            stored_message(
+                "msg-5",
+                MessageRole::Tool,
+                MessageContent::ToolResult {
+                    result: StoredToolResult {
+                        tool_call_id: "call-2".to_string(),
+                        tool_name: "readFile".to_string(),
+                        input: None,
+                        output: None,
+                        status: ToolResultStatus::Error,
+                        error_message: Some("not found".to_string()),
+                    },
+                },
+            ),
+        ];
+
+        let llm_messages = convert_messages_to_llm(&messages, &HashMap::new());
+        assert_eq!(llm_messages.len(), 3);
+
+        match &llm_messages[1] {
+            LlmMessage::Assistant {
+                content: LlmMessageContent::Parts(parts),
+                ..
+            } => {
+                assert_eq!(parts.len(), 3);
+                assert!(matches!(parts[0], ContentPart::Text { .. }));
+                assert!(matches!(parts[2], ContentPart::ToolCall { .. }));
+            }
+            other => panic!("Expected assistant parts, got {:?}", other),
+        }
+
+        match &llm_messages[2] {
+            LlmMessage::Tool { content, .. } => {
+                assert_eq!(content.len(), 2);
+                match &content[1] {
+                    ContentPart::ToolResult { output, .. } => {
+                        assert_eq!(output["value"], "Error: not found");
+                    }
+                    other => panic!("Expected tool result, got {:?}", other),
+                }
+            }
+            other => panic!("Expected tool message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert_messages_forwards_tool_images() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let logo = temp_dir.path().join("logo.png");
+        std::fs::write(&logo, b"hello").unwrap();
+        let logo = logo.to_string_lossy().to_string();
+        let outside_dir = tempfile::TempDir::new().unwrap();
+        let outside = outside_dir.path().join("secret.png");
+        std::fs::write(&outside, b"secret").unwrap();
+        let outside = outside.to_string_lossy().to_string();
+
+        let image_result = |call_id: &str, path: &str| {
+            stored_message(
+                call_id,
+                MessageRole::Tool,
+                MessageContent::ToolResult {
+                    result: StoredToolResult {
+                        tool_call_id: call_id.to_string(),
+                        tool_name: "readFile".to_string(),
+                        input: None,
+                        output: Some(serde_json::json!({
+                            "kind": "image",
+                            "filePath": path,
+                            "mimeType": "image/png",
+                            "size": 5,
+                        })),
+                        status: ToolResultStatus::Success,
+                        error_message: None,
+                    },
+                },
+            )
+        };
+        let user_text = |id: &str| {
+            stored_message(
+                id,
+                MessageRole::User,
+                MessageContent::Text {
+                    text: "Compare the logos".to_string(),
+                },
+            )
+        };
+        let messages = vec![
+            image_result("call-0", &logo),
+            user_text("msg-1"),
+            image_result("call-1", &logo),
+            image_result("call-2", &logo),
+            stored_message(
+                "msg-3",
+                MessageRole::Assistant,
+                MessageContent::Text {
+                    text: "Two logos".to_string(),
+                },
+            ),
+        ];
+
+        // Only images of the current turn inside the workspace are loaded
+        let mut outside_messages = messages.clone();
+        outside_messages.insert(4, image_result("call-x", &outside));
+        let platform_ctx =
+            crate::platform::Platform::new().create_context(temp_dir.path(), None::<&str>);
+        let images = load_turn_images(&outside_messages, &platform_ctx).await;
+        let mut loaded: Vec<&str> = images.keys().map(String::as_str).collect();
+        loaded.sort();
+        assert_eq!(loaded, vec!["call-1", "call-2"]);
+
+        let llm_messages = convert_messages_to_llm(&messages, &images);
+        assert_eq!(llm_messages.len(), 5);
+        assert!(matches!(llm_messages[1], LlmMessage::User { .. }));
+
+        match &llm_messages[2] {
+            LlmMessage::Tool { content, .. } => match &content[0] {
+                ContentPart::ToolResult { output, .. } => {
+                    assert!(output["value"].as_str().unwrap().contains("logo.png"));
+                }
+                other => panic!("Expected tool result, got {:?}", other),
+            },
+            other => panic!("Expected tool message, got {:?}", other),
+        }
+
+        match &llm_messages[3] {
+            LlmMessage::User {
+                content: LlmMessageContent::Parts(parts),
+                ..
+            } => {
+                assert_eq!(parts.len(), 3);
+                assert!(matches!(&parts[1], ContentPart::Image { image } if image == "aGVsbG8="));
+            }
+            other => panic!("Expected user image parts, got {:?}", other),
+        }
+        assert!(matches!(llm_messages[4], LlmMessage::Assistant { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_record_tool_result_appends_history() {
+        let (agent_loop, mut rx) = create_test_loop().await;
+
+        let ctx = AgentLoopContext {
+            session_id: "test-session".to_string(),
+            task_id: "test-task".to_string(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            messages: vec![],
+            model: None,
+            system_prompt: None,
+            iterations: 0,
+        };
+        let request = ToolRequest {
+            tool_call_id: "call-1".to_string(),
+            name: "readFile".to_string(),
+            input: serde_json::json!({"file_path": "a.rs"}),
+            provider_metadata: None,
+        };
+        let result = ToolResult {
+            tool_call_id: "call-1".to_string(),
+            name: Some("readFile".to_string()),
+            success: true,
+            output: serde_json::json!("contents"),
+            error: None,
+        };
+
+        let mut messages = vec![];
+        agent_loop
+            .record_tool_result(&ctx, &request, &result, &mut messages)
+            .await
+            .expect("Failed to record tool result");
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, MessageRole::Tool);
+        assert_eq!(messages[0].tool_call_id.as_deref(), Some("call-1"));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(RuntimeEvent::MessageCreated { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pending_tool_calls_skips_resolved() {
+        let call = |id: &str| ToolCall {
+            id: id.to_string(),
+            name: "bash".to_string(),
+            input: serde_json::json!({"command": "ls"}),
+            provider_metadata: None,
+        };
+        let mut messages = vec![
+            stored_message(
+                "msg-1",
+                MessageRole::User,
+                MessageContent::Text {
+                    text: "List files".to_string(),
+                },
+            ),
+            stored_message(
+                "msg-2",
+                MessageRole::Assistant,
+                MessageContent::ToolCalls {
+                    calls: vec![call("call-1"), call("call-2")],
+                },
+            ),
+            stored_message(
+                "msg-3",
+                MessageRole::Tool,
+                MessageContent::ToolResult {
+                    result: StoredToolResult {
+                        tool_call_id: "call-1".to_string(),
+                        tool_name: "bash".to_string(),
+                        input: None,
+                        output: Some(serde_json::json!("ok")),
+                        status: ToolResultStatus::Success,
+                        error_message: None,
+                    },
+                },
+            ),
+        ];
+
+        let pending = pending_tool_calls(&messages);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].tool_call_id, "call-2");
+
+        // A newer user message abandons the unresolved turn
+        messages.push(stored_message(
+            "msg-4",
+            MessageRole::User,
+            MessageContent::Text {
+                text: "Never mind".to_string(),
+            },
+        ));
+        assert!(pending_tool_calls(&messages).is_empty());
+    }
 }
@@ -0,0 +1,236 @@
+//! File Checkpoints
+//!
+//! Before `writeFile` or `editFile` changes a file, its previous content is
+//! recorded against the task and tool call. A task's edits can then be undone
+//! one file at a time, or the workspace rolled back to the state before any of
+//! its tool calls. This works the same with or without git.
+
+use crate::storage::models::{FileCheckpoint, RestoredFile};
+use crate::storage::CheckpointsRepository;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Per-task pre-images of files written by tools
+#[derive(Clone)]
+pub struct CheckpointStore {
+    repo: CheckpointsRepository,
+}
+
+impl std::fmt::Debug for CheckpointStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CheckpointStore").finish_non_exhaustive()
+    }
+}
+
+impl CheckpointStore {
+    pub fn new(repo: CheckpointsRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Record the current content of `path` before `tool_call_id` writes it
+    pub async fn snapshot(
+        &self,
+        task_id: &str,
+        session_id: &str,
+        tool_call_id: &str,
+        path: &Path,
+    ) -> Result<(), String> {
+        let content = match tokio::fs::read(path).await {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Failed to read '{}': {}", path.display(), e)),
+        };
+
+        self.repo
+            .create_checkpoint(
+                task_id,
+                session_id,
+                tool_call_id,
+                &path.to_string_lossy(),
+                content.as_deref(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Checkpoints of a task, oldest first
+    pub async fn list(&self, task_id: &str) -> Result<Vec<FileCheckpoint>, String> {
+        self.repo.list_checkpoints(task_id).await
+    }
+
+    /// Put one file back to its content before the checkpoint's tool call
+    pub async fn restore(
+        &self,
+        task_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<RestoredFile, String> {
+        let checkpoint = self.get(task_id, checkpoint_id).await?;
+        self.restore_checkpoint(&checkpoint).await
+    }
+
+    /// Undo every write of the task from `checkpoint_id` on, or all of them
+    ///
+    /// Each file touched since then gets the content it had at its first
+    /// checkpoint in that range; the undone checkpoints are dropped.
+    pub async fn rollback(
+        &self,
+        task_id: &str,
+        checkpoint_id: Option<&str>,
+    ) -> Result<Vec<RestoredFile>, String> {
+        let from_seq = match checkpoint_id {
+            Some(id) => self.get(task_id, id).await?.seq,
+            None => 0,
+        };
+
+        let mut seen = HashSet::new();
+        let mut restored = Vec::new();
+        for checkpoint in self.list(task_id).await? {
+            if checkpoint.seq >= from_seq && seen.insert(checkpoint.path.clone()) {
+                restored.push(self.restore_checkpoint(&checkpoint).await?);
+            }
+        }
+
+        self.repo.delete_checkpoints_from(task_id, from_seq).await?;
+        Ok(restored)
+    }
+
+    async fn get(&self, task_id: &str, checkpoint_id: &str) -> Result<FileCheckpoint, String> {
+        self.repo
+            .get_checkpoint(checkpoint_id)
+            .await?
+            .filter(|checkpoint| checkpoint.task_id == task_id)
+            .ok_or_else(|| {
+                format!(
+                    "Checkpoint '{}' not found for task '{}'",
+                    checkpoint_id, task_id
+                )
+            })
+    }
+
+    async fn restore_checkpoint(
+        &self,
+        checkpoint: &FileCheckpoint,
+    ) -> Result<RestoredFile, String> {
+        let path = Path::new(&checkpoint.path);
+        let deleted = match self.repo.read_content(checkpoint)? {
+            Some(content) => {
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| format!("Failed to create directory: {}", e))?;
+                }
+                tokio::fs::write(path, content)
+                    .await
+                    .map_err(|e| format!("Failed to restore '{}': {}", checkpoint.path, e))?;
+                false
+            }
+            None => {
+                match tokio::fs::remove_file(path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(format!("Failed to remove '{}': {}", checkpoint.path, e)),
+                }
+                true
+            }
+        };
+
+        Ok(RestoredFile {
+            path: checkpoint.path.clone(),
+            deleted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::{Session, SessionStatus};
+    use crate::storage::Storage;
+    use tempfile::TempDir;
+
+    async fn create_store() -> (CheckpointStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("attachments"),
+        )
+        .await
+        .unwrap();
+        let now = chrono::Utc::now().timestamp();
+        storage
+            .chat_history
+            .create_session(&Session {
+                id: "session-1".to_string(),
+                project_id: None,
+                title: None,
+                status: SessionStatus::Running,
+                created_at: now,
+                updated_at: now,
+                last_event_id: None,
+                metadata: None,
+                head_message_id: None,
+                forked_from: None,
+            })
+            .await
+            .unwrap();
+
+        (CheckpointStore::new(storage.checkpoints), temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_restore_and_rollback() {
+        let (store, temp) = create_store().await;
+        let workspace = temp.path().join("workspace");
+        std::fs::create_dir_all(&workspace).unwrap();
+        let a = workspace.join("a.txt");
+        let b = workspace.join("b.txt");
+        std::fs::write(&a, "a0").unwrap();
+
+        // call-1 edits a, call-2 creates b, call-3 edits both
+        store
+            .snapshot("task-1", "session-1", "call-1", &a)
+            .await
+            .unwrap();
+        std::fs::write(&a, "a1").unwrap();
+        store
+            .snapshot("task-1", "session-1", "call-2", &b)
+            .await
+            .unwrap();
+        std::fs::write(&b, "b1").unwrap();
+        store
+            .snapshot("task-1", "session-1", "call-3", &a)
+            .await
+            .unwrap();
+        store
+            .snapshot("task-1", "session-1", "call-3", &b)
+            .await
+            .unwrap();
+        std::fs::write(&a, "a2").unwrap();
+        std::fs::write(&b, "b2").unwrap();
+
+        let checkpoints = store.list("task-1").await.unwrap();
+        assert_eq!(checkpoints.len(), 4);
+
+        // Restoring one file leaves the others and the checkpoints alone
+        let restored = store.restore("task-1", &checkpoints[2].id).await.unwrap();
+        assert!(!restored.deleted);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "a1");
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "b2");
+        assert!(store.restore("task-2", &checkpoints[2].id).await.is_err());
+
+        // Rolling back to before call-2 deletes b, which it created
+        let restored = store
+            .rollback("task-1", Some(&checkpoints[1].id))
+            .await
+            .unwrap();
+        assert_eq!(restored.len(), 2);
+        assert!(!b.exists());
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "a1");
+        assert_eq!(store.list("task-1").await.unwrap().len(), 1);
+
+        // Rolling back the whole task returns to the original content
+        store.rollback("task-1", None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "a0");
+        assert!(store.list("task-1").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,442 @@
+//! File Edits
+//!
+//! Search-and-replace semantics of the `editFile` tool. An edit's `old_string`
+//! must match exactly one location unless `replace_all` is set. When it matches
+//! nowhere, a line-based match that tolerates whitespace and indentation
+//! differences is tried, and its confidence is reported with the result. A
+//! batch is applied to an in-memory copy, so either every edit applies or none.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// One search-and-replace edit
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEdit {
+    pub old_string: String,
+    pub new_string: String,
+    pub replace_all: bool,
+}
+
+/// How an edit's `old_string` was located
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact,
+    /// Lines equal once trailing whitespace is ignored
+    TrailingWhitespace,
+    /// Lines equal once indentation is ignored; the replacement is re-indented
+    Indentation,
+    /// Lines equal once every run of whitespace is collapsed
+    Whitespace,
+}
+
+impl MatchKind {
+    /// Fallbacks, from the most to the least faithful
+    const FALLBACKS: [MatchKind; 3] = [
+        MatchKind::TrailingWhitespace,
+        MatchKind::Indentation,
+        MatchKind::Whitespace,
+    ];
+
+    /// How sure we are that the match is the text the caller meant
+    pub fn confidence(self) -> f64 {
+        match self {
+            MatchKind::Exact => 1.0,
+            MatchKind::TrailingWhitespace => 0.95,
+            MatchKind::Indentation => 0.9,
+            MatchKind::Whitespace => 0.8,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            MatchKind::Exact => "exactly",
+            MatchKind::TrailingWhitespace => "ignoring trailing whitespace",
+            MatchKind::Indentation => "ignoring indentation",
+            MatchKind::Whitespace => "ignoring whitespace",
+        }
+    }
+
+    fn normalize(self, line: &str) -> String {
+        match self {
+            MatchKind::Exact => line.to_string(),
+            MatchKind::TrailingWhitespace => line.trim_end().to_string(),
+            MatchKind::Indentation => line.trim().to_string(),
+            MatchKind::Whitespace => line.split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    fn reindents(self) -> bool {
+        matches!(self, MatchKind::Indentation | MatchKind::Whitespace)
+    }
+}
+
+/// Where and how one edit matched
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditMatch {
+    pub kind: MatchKind,
+    pub confidence: f64,
+    pub replacements: usize,
+}
+
+/// Content after a batch of edits
+#[derive(Debug, Clone)]
+pub struct EditOutcome {
+    pub content: String,
+    pub matches: Vec<EditMatch>,
+}
+
+impl EditOutcome {
+    pub fn replacements(&self) -> usize {
+        self.matches.iter().map(|m| m.replacements).sum()
+    }
+}
+
+/// Read the edits of an `editFile` call
+///
+/// Accepts an `edits` array or a single top-level `old_string`/`new_string`.
+/// A top-level `replace_all` applies to edits that do not set their own.
+pub fn parse_edits(input: &Value) -> Result<Vec<FileEdit>, String> {
+    let replace_all = input
+        .get("replace_all")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let edits: Vec<&Value> = match input.get("edits").and_then(|v| v.as_array()) {
+        Some(edits) => edits.iter().collect(),
+        None if input.get("old_string").is_some() => vec![input],
+        None => return Err("No edits provided".to_string()),
+    };
+    if edits.is_empty() {
+        return Err("No edits provided".to_string());
+    }
+
+    edits
+        .into_iter()
+        .enumerate()
+        .map(|(i, edit)| {
+            let field = |name: &str| {
+                edit.get(name)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("Edit {} is missing '{}'", i + 1, name))
+            };
+            Ok(FileEdit {
+                old_string: field("old_string")?,
+                new_string: field("new_string")?,
+                replace_all: edit
+                    .get("replace_all")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(replace_all),
+            })
+        })
+        .collect()
+}
+
+/// Apply edits in order; later edits see the result of earlier ones
+///
+/// Fails without a result if any edit fails, so nothing is half applied.
+pub fn apply_edits(content: &str, edits: &[FileEdit]) -> Result<EditOutcome, String> {
+    if edits.is_empty() {
+        return Err("No edits provided".to_string());
+    }
+
+    let mut content = content.to_string();
+    let mut matches = Vec::with_capacity(edits.len());
+    for (i, edit) in edits.iter().enumerate() {
+        let (next, edit_match) = apply_edit(&content, edit).map_err(|e| {
+            if edits.len() > 1 {
+                format!(
+                    "Edit {} of {}: {}. No edits were applied.",
+                    i + 1,
+                    edits.len(),
+                    e
+                )
+            } else {
+                e
+            }
+        })?;
+        content = next;
+        matches.push(edit_match);
+    }
+
+    Ok(EditOutcome { content, matches })
+}
+
+fn apply_edit(content: &str, edit: &FileEdit) -> Result<(String, EditMatch), String> {
+    if edit.old_string.is_empty() {
+        return Err("old_string must not be empty".to_string());
+    }
+    if edit.old_string == edit.new_string {
+        return Err("old_string and new_string are identical".to_string());
+    }
+
+    let count = content.matches(edit.old_string.as_str()).count();
+    if count > 0 {
+        check_unique(count, edit.replace_all, MatchKind::Exact)?;
+        let content = content.replacen(&edit.old_string, &edit.new_string, count);
+        return Ok((content, edit_match(MatchKind::Exact, count)));
+    }
+
+    for kind in MatchKind::FALLBACKS {
+        let found = find_lines(content, &edit.old_string, kind);
+        if found.is_empty() {
+            continue;
+        }
+        check_unique(found.len(), edit.replace_all, kind)?;
+        let content = replace_lines(content, &found, &edit.new_string, kind);
+        return Ok((content, edit_match(kind, found.len())));
+    }
+
+    Err(
+        "old_string not found in file. Read the file again and copy the text to replace \
+         exactly, including whitespace and indentation"
+            .to_string(),
+    )
+}
+
+fn check_unique(count: usize, replace_all: bool, kind: MatchKind) -> Result<(), String> {
+    if count > 1 && !replace_all {
+        return Err(format!(
+            "old_string matches {} locations ({}). Include more surrounding context to make \
+             it unique, or set replace_all to replace every occurrence",
+            count,
+            kind.describe()
+        ));
+    }
+    Ok(())
+}
+
+fn edit_match(kind: MatchKind, replacements: usize) -> EditMatch {
+    EditMatch {
+        kind,
+        confidence: kind.confidence(),
+        replacements,
+    }
+}
+
+/// A run of whole lines matched by a fallback
+struct LineMatch {
+    /// Byte offset of the first line
+    start: usize,
+    /// Byte offset of the end of the last line, before its line break
+    end: usize,
+    /// Byte offset after the last line's line break
+    end_with_break: usize,
+    /// Indentation of the first non-blank matched line
+    indent: String,
+}
+
+/// Non-overlapping runs of lines equal to `old` under `kind`
+fn find_lines(content: &str, old: &str, kind: MatchKind) -> Vec<LineMatch> {
+    let old_lines: Vec<String> = old
+        .trim_end_matches(['\r', '\n'])
+        .lines()
+        .map(|line| kind.normalize(line))
+        .collect();
+    if old_lines.is_empty() || old_lines.iter().all(|line| line.is_empty()) {
+        return Vec::new();
+    }
+
+    // (start, end, end with line break, text) of every line
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\r', '\n']);
+        lines.push((offset, offset + text.len(), offset + line.len(), text));
+        offset += line.len();
+    }
+
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i + old_lines.len() <= lines.len() {
+        let window = &lines[i..i + old_lines.len()];
+        let equal = window
+            .iter()
+            .zip(&old_lines)
+            .all(|((_, _, _, text), old)| kind.normalize(text) == *old);
+        if !equal {
+            i += 1;
+            continue;
+        }
+
+        let last = window[window.len() - 1];
+        found.push(LineMatch {
+            start: window[0].0,
+            end: last.1,
+            end_with_break: last.2,
+            indent: first_indent(window.iter().map(|line| line.3)).to_string(),
+        });
+        i += old_lines.len();
+    }
+    found
+}
+
+fn replace_lines(content: &str, found: &[LineMatch], new: &str, kind: MatchKind) -> String {
+    let line_break = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let new = new.trim_end_matches(['\r', '\n']);
+    let new_indent = first_indent(new.lines());
+
+    let mut result = String::with_capacity(content.len() + new.len());
+    let mut cursor = 0;
+    for m in found {
+        result.push_str(&content[cursor..m.start]);
+        if new.is_empty() {
+            // Deleting lines also removes their line breaks
+            cursor = m.end_with_break;
+            continue;
+        }
+
+        let lines: Vec<String> = new
+            .lines()
+            .map(|line| match line.strip_prefix(new_indent) {
+                Some(rest) if kind.reindents() && !line.trim().is_empty() => {
+                    format!("{}{}", m.indent, rest)
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+        result.push_str(&lines.join(line_break));
+        cursor = m.end;
+    }
+    result.push_str(&content[cursor..]);
+    result
+}
+
+/// Leading whitespace of the first non-blank line
+fn first_indent<'a>(lines: impl IntoIterator<Item = &'a str>) -> &'a str {
+    lines
+        .into_iter()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(old: &str, new: &str) -> FileEdit {
+        FileEdit {
+            old_string: old.to_string(),
+            new_string: new.to_string(),
+            replace_all: false,
+        }
+    }
+
+    #[test]
+    fn test_exact_edit() {
+        let outcome = apply_edits("let a = 1;\nlet b = 2;\n", &[edit("b = 2", "b = 3")]).unwrap();
+        assert_eq!(outcome.content, "let a = 1;\nlet b = 3;\n");
+        assert_eq!(outcome.matches[0].kind, MatchKind::Exact);
+        assert_eq!(outcome.matches[0].confidence, 1.0);
+        assert_eq!(outcome.replacements(), 1);
+    }
+
+    #[test]
+    fn test_missing_and_ambiguous_old_string() {
+        let content = "foo();\nbar();\nfoo();\n";
+
+        let err = apply_edits(content, &[edit("baz();", "qux();")]).unwrap_err();
+        assert!(err.contains("not found"), "{}", err);
+
+        let err = apply_edits(content, &[edit("foo();", "qux();")]).unwrap_err();
+        assert!(err.contains("matches 2 locations"), "{}", err);
+
+        let mut all = edit("foo();", "qux();");
+        all.replace_all = true;
+        let outcome = apply_edits(content, &[all]).unwrap();
+        assert_eq!(outcome.content, "qux();\nbar();\nqux();\n");
+        assert_eq!(outcome.replacements(), 2);
+
+        assert!(apply_edits(content, &[edit("", "x")]).is_err());
+        assert!(apply_edits(content, &[edit("bar();", "bar();")]).is_err());
+    }
+
+    #[test]
+    fn test_indentation_tolerant_match_reindents() {
+        let content = "fn main() {\n    if ready {\n        run();\n    }\n}\n";
+        let outcome = apply_edits(
+            content,
+            &[edit(
+                "if ready {\n    run();\n}",
+                "if ready {\n    start();\n    run();\n}",
+            )],
+        )
+        .unwrap();
+
+        assert_eq!(
+            outcome.content,
+            "fn main() {\n    if ready {\n        start();\n        run();\n    }\n}\n"
+        );
+        assert_eq!(outcome.matches[0].kind, MatchKind::Indentation);
+        assert_eq!(outcome.matches[0].confidence, 0.9);
+    }
+
+    #[test]
+    fn test_whitespace_tolerant_matches() {
+        let content = "a = 1;   \r\nb  =  2;\r\nc = 3;\r\n";
+
+        let outcome = apply_edits(content, &[edit("a = 1;\n", "a = 10;\n")]).unwrap();
+        assert_eq!(outcome.content, "a = 10;\r\nb  =  2;\r\nc = 3;\r\n");
+        assert_eq!(outcome.matches[0].kind, MatchKind::TrailingWhitespace);
+
+        let outcome = apply_edits(content, &[edit("b = 2;", "b = 20;")]).unwrap();
+        assert_eq!(outcome.content, "a = 1;   \r\nb = 20;\r\nc = 3;\r\n");
+        assert_eq!(outcome.matches[0].kind, MatchKind::Whitespace);
+        assert_eq!(outcome.matches[0].confidence, 0.8);
+
+        let outcome = apply_edits(content, &[edit("b = 2;\n", "")]).unwrap();
+        assert_eq!(outcome.content, "a = 1;   \r\nc = 3;\r\n");
+    }
+
+    #[test]
+    fn test_batch_is_all_or_nothing() {
+        let content = "one\ntwo\nthree\n";
+
+        let outcome = apply_edits(content, &[edit("one", "1"), edit("1\ntwo", "1\n2")]).unwrap();
+        assert_eq!(outcome.content, "1\n2\nthree\n");
+        assert_eq!(outcome.matches.len(), 2);
+
+        let err = apply_edits(content, &[edit("one", "1"), edit("four", "4")]).unwrap_err();
+        assert!(err.starts_with("Edit 2 of 2"), "{}", err);
+        assert!(err.contains("No edits were applied"));
+    }
+
+    #[test]
+    fn test_parse_edits() {
+        let single = serde_json::json!({
+            "file_path": "a.rs",
+            "old_string": "a",
+            "new_string": "b",
+            "replace_all": true
+        });
+        assert_eq!(
+            parse_edits(&single).unwrap(),
+            vec![FileEdit {
+                old_string: "a".to_string(),
+                new_string: "b".to_string(),
+                replace_all: true,
+            }]
+        );
+
+        let batch = serde_json::json!({
+            "edits": [
+                {"old_string": "a", "new_string": "b"},
+                {"old_string": "c", "new_string": "d", "replace_all": true}
+            ]
+        });
+        let edits = parse_edits(&batch).unwrap();
+        assert!(!edits[0].replace_all);
+        assert!(edits[1].replace_all);
+
+        assert!(parse_edits(&serde_json::json!({"edits": []})).is_err());
+        assert!(parse_edits(&serde_json::json!({"edits": [{"old_string": "a"}]})).is_err());
+        assert!(parse_edits(&serde_json::json!({"file_path": "a.rs"})).is_err());
+    }
+}
@@ -4,17 +4,26 @@
 //! and tool execution. This module is the heart of the cloud backend.
 
 pub mod agent_loop;
+pub mod checkpoints;
 pub mod completion_hooks;
+pub mod file_edit;
+pub mod questions;
 pub mod runtime;
 pub mod session;
+pub mod todos;
 pub mod tool_definitions;
 pub mod tool_dependency_analyzer;
 pub mod tool_name_normalizer;
 pub mod tools;
 pub mod types;
+pub mod web_fetch;
+pub mod web_search;
+pub mod workspace_search;
 
 // Re-export main types for convenience
-pub use agent_loop::{AgentLoop, AgentLoopContext, AgentLoopFactory, AgentLoopResult};
+pub use agent_loop::{
+    AgentLoop, AgentLoopContext, AgentLoopFactory, AgentLoopResult, IterationOutcome,
+};
 pub use runtime::{CoreRuntime, SettingsValidator};
 pub use session::{SessionManager, SessionState};
 pub use tool_name_normalizer::{is_known_tool_name, normalize_tool_name};
@@ -22,13 +31,15 @@ pub use tools::{ToolContext, ToolDispatcher, ToolExecutionOutput, ToolHandler, T
 pub use types::*;
 
 /// Initialize the core runtime with storage
+///
+/// `data_root` holds provider files such as custom model definitions.
 pub async fn init_runtime(
     storage: crate::storage::Storage,
     event_sender: types::EventSender,
+    data_root: std::path::PathBuf,
 ) -> Result<CoreRuntime, String> {
     let provider_registry = crate::llm::providers::provider_registry::ProviderRegistry::default();
     let db = storage.settings.get_db();
-    let api_key_manager =
-        crate::llm::auth::api_key_manager::ApiKeyManager::new(db, std::path::PathBuf::from("."));
+    let api_key_manager = crate::llm::auth::api_key_manager::ApiKeyManager::new(db, data_root);
     CoreRuntime::new(storage, event_sender, provider_registry, api_key_manager).await
 }
@@ -0,0 +1,341 @@
+//! User Questions
+//!
+//! Support for the `askUserQuestions` tool. The agent loop suspends on the call,
+//! the runtime publishes the questions as `RuntimeEvent::QuestionsAsked` and the
+//! task resumes once answers arrive as `TaskAction::AnswerQuestions`, either
+//! structured (API, desktop UI) or parsed from a free-text IM reply.
+
+use crate::core::types::{QuestionAnswer, UserQuestion};
+use std::collections::HashSet;
+
+/// Parse and validate the questions of an `askUserQuestions` call
+pub fn parse_questions(input: &serde_json::Value) -> Result<Vec<UserQuestion>, String> {
+    let questions: Vec<UserQuestion> = input
+        .get("questions")
+        .cloned()
+        .map(serde_json::from_value)
+        .ok_or("Missing required parameter 'questions'")?
+        .map_err(|e| format!("Invalid questions: {}", e))?;
+
+    if questions.is_empty() {
+        return Err("At least one question is required".to_string());
+    }
+
+    let mut ids = HashSet::new();
+    for question in &questions {
+        if question.id.trim().is_empty() || question.question.trim().is_empty() {
+            return Err("Every question needs an id and text".to_string());
+        }
+        if !ids.insert(question.id.as_str()) {
+            return Err(format!("Duplicate question id '{}'", question.id));
+        }
+    }
+
+    Ok(questions)
+}
+
+/// Validate answers against the questions and build the tool output
+///
+/// Every question must be answered; single-select questions take exactly one
+/// answer. Answers outside the suggested options are accepted as free text.
+pub fn answers_to_output(
+    questions: &[UserQuestion],
+    answers: &[QuestionAnswer],
+) -> Result<serde_json::Value, String> {
+    if let Some(unknown) = answers
+        .iter()
+        .find(|answer| !questions.iter().any(|q| q.id == answer.question_id))
+    {
+        return Err(format!("Unknown question id '{}'", unknown.question_id));
+    }
+
+    let mut output = Vec::with_capacity(questions.len());
+    for question in questions {
+        let values: Vec<&String> = answers
+            .iter()
+            .filter(|answer| answer.question_id == question.id)
+            .flat_map(|answer| answer.answers.iter())
+            .filter(|value| !value.trim().is_empty())
+            .collect();
+
+        if values.is_empty() {
+            return Err(format!("Question '{}' was not answered", question.id));
+        }
+        if !question.multi_select && values.len() > 1 {
+            return Err(format!("Question '{}' takes a single answer", question.id));
+        }
+
+        output.push(serde_json::json!({
+            "id": question.id,
+            "question": question.question,
+            "answers": values,
+        }));
+    }
+
+    Ok(serde_json::json!({ "answers": output }))
+}
+
+/// Parse a free-text reply (e.g. from Telegram or Feishu) into answers
+///
+/// A single question takes the whole reply. For several questions the reply has
+/// one line per question, optionally prefixed with the question number (`2.`,
+/// `2)`, `2:`) or id (`db: ...`); unprefixed lines fill the remaining questions
+/// in order. Option numbers are resolved to the option text, and multi-select
+/// answers are comma separated.
+pub fn parse_reply(questions: &[UserQuestion], text: &str) -> Result<Vec<QuestionAnswer>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("The reply is empty".to_string());
+    }
+
+    let mut raw: Vec<Option<String>> = vec![None; questions.len()];
+    if questions.len() == 1 {
+        raw[0] = Some(text.to_string());
+    } else {
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line_target(questions, line) {
+                Some((index, rest)) => raw[index] = Some(rest.to_string()),
+                None => match raw.iter().position(Option::is_none) {
+                    Some(index) => raw[index] = Some(line.to_string()),
+                    None => return Err("The reply has more answers than questions".to_string()),
+                },
+            }
+        }
+    }
+
+    questions
+        .iter()
+        .zip(raw)
+        .map(|(question, raw)| match raw {
+            Some(raw) if !raw.trim().is_empty() => Ok(QuestionAnswer {
+                question_id: question.id.clone(),
+                answers: resolve_answer(question, raw.trim()),
+            }),
+            _ => Err(format!(
+                "Please answer all {} questions, one per line",
+                questions.len()
+            )),
+        })
+        .collect()
+}
+
+/// The question a reply line is addressed to, by number or id prefix
+fn line_target<'a>(questions: &[UserQuestion], line: &'a str) -> Option<(usize, &'a str)> {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(rest) = rest.strip_prefix(['.', ')', ':']) {
+            let number: usize = line[..digits].parse().ok()?;
+            if (1..=questions.len()).contains(&number) {
+                return Some((number - 1, rest.trim()));
+            }
+        }
+    }
+
+    let (prefix, rest) = line.split_once(':')?;
+    questions
+        .iter()
+        .position(|q| q.id.eq_ignore_ascii_case(prefix.trim()))
+        .map(|index| (index, rest.trim()))
+}
+
+/// Resolve option numbers and case-insensitive option names to the option text
+fn resolve_answer(question: &UserQuestion, raw: &str) -> Vec<String> {
+    let resolve = |token: &str| {
+        if let Ok(number) = token.parse::<usize>() {
+            if let Some(option) = number.checked_sub(1).and_then(|i| question.options.get(i)) {
+                return option.clone();
+            }
+        }
+        question
+            .options
+            .iter()
+            .find(|option| option.eq_ignore_ascii_case(token))
+            .cloned()
+            .unwrap_or_else(|| token.to_string())
+    };
+
+    if question.multi_select {
+        raw.split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(resolve)
+            .collect()
+    } else {
+        vec![resolve(raw)]
+    }
+}
+
+/// Render questions as plain text for channels without structured UI
+pub fn render_questions(questions: &[UserQuestion]) -> String {
+    let mut rendered = String::new();
+
+    for (index, question) in questions.iter().enumerate() {
+        rendered.push_str(&format!("{}. {}\n", index + 1, question.question));
+        for (option_index, option) in question.options.iter().enumerate() {
+            rendered.push_str(&format!("   {}) {}\n", option_index + 1, option));
+        }
+        if question.multi_select {
+            rendered.push_str("   (choose one or more, comma separated)\n");
+        }
+    }
+
+    if questions.len() > 1 {
+        rendered.push_str("\nReply with one answer per line, numbered like the questions.");
+    } else {
+        rendered.push_str("\nReply with your answer.");
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(id: &str, options: &[&str], multi_select: bool) -> UserQuestion {
+        UserQuestion {
+            id: id.to_string(),
+            question: format!("Question {}?", id),
+            options: options.iter().map(|o| o.to_string()).collect(),
+            multi_select,
+        }
+    }
+
+    fn answer(id: &str, answers: &[&str]) -> QuestionAnswer {
+        QuestionAnswer {
+            question_id: id.to_string(),
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_questions() {
+        let questions = parse_questions(&serde_json::json!({
+            "questions": [
+                {"id": "db", "question": "Which database?", "options": ["Postgres", "SQLite"]},
+                {"id": "features", "question": "Which features?", "options": ["auth", "search"], "multi_select": true},
+                {"id": "name", "question": "Project name?"}
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(questions.len(), 3);
+        assert!(!questions[0].multi_select);
+        assert!(questions[1].multi_select);
+        assert!(questions[2].options.is_empty());
+
+        assert!(parse_questions(&serde_json::json!({})).is_err());
+        assert!(parse_questions(&serde_json::json!({"questions": []})).is_err());
+        assert!(parse_questions(&serde_json::json!({
+            "questions": [{"id": "a", "question": "x"}, {"id": "a", "question": "y"}]
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_answers_to_output_validates() {
+        let questions = vec![
+            question("db", &["Postgres", "SQLite"], false),
+            question("features", &["auth", "search"], true),
+        ];
+
+        let output = answers_to_output(
+            &questions,
+            &[
+                answer("db", &["SQLite"]),
+                answer("features", &["auth", "billing"]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            output["answers"][0]["answers"],
+            serde_json::json!(["SQLite"])
+        );
+        assert_eq!(
+            output["answers"][1]["answers"],
+            serde_json::json!(["auth", "billing"])
+        );
+
+        let missing = answers_to_output(&questions, &[answer("db", &["SQLite"])]);
+        assert!(missing.unwrap_err().contains("features"));
+
+        let too_many = answers_to_output(
+            &questions,
+            &[
+                answer("db", &["SQLite", "Postgres"]),
+                answer("features", &["auth"]),
+            ],
+        );
+        assert!(too_many.unwrap_err().contains("single answer"));
+
+        let unknown = answers_to_output(
+            &questions,
+            &[
+                answer("db", &["SQLite"]),
+                answer("features", &["auth"]),
+                answer("other", &["x"]),
+            ],
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_parse_reply_single_question() {
+        let questions = vec![question("db", &["Postgres", "SQLite"], false)];
+
+        assert_eq!(
+            parse_reply(&questions, "2").unwrap(),
+            vec![answer("db", &["SQLite"])]
+        );
+        assert_eq!(
+            parse_reply(&questions, "postgres").unwrap(),
+            vec![answer("db", &["Postgres"])]
+        );
+        assert_eq!(
+            parse_reply(&questions, "MySQL please").unwrap(),
+            vec![answer("db", &["MySQL please"])]
+        );
+        assert!(parse_reply(&questions, "   ").is_err());
+    }
+
+    #[test]
+    fn test_parse_reply_multiple_questions() {
+        let questions = vec![
+            question("db", &["Postgres", "SQLite"], false),
+            question("features", &["auth", "search"], true),
+            question("name", &[], false),
+        ];
+
+        let answers = parse_reply(&questions, "2) 1, search\n1. 1\ntalkcody").unwrap();
+        assert_eq!(
+            answers,
+            vec![
+                answer("db", &["Postgres"]),
+                answer("features", &["auth", "search"]),
+                answer("name", &["talkcody"]),
+            ]
+        );
+
+        let by_id = parse_reply(&questions, "name: demo\ndb: sqlite\nfeatures: 2").unwrap();
+        assert_eq!(by_id[0], answer("db", &["SQLite"]));
+        assert_eq!(by_id[1], answer("features", &["search"]));
+        assert_eq!(by_id[2], answer("name", &["demo"]));
+
+        assert!(parse_reply(&questions, "1. 1\n2. 2").is_err());
+        assert!(parse_reply(&questions, "a\nb\nc\nd").is_err());
+    }
+
+    #[test]
+    fn test_render_questions() {
+        let rendered = render_questions(&[
+            question("db", &["Postgres", "SQLite"], false),
+            question("features", &["auth"], true),
+        ]);
+
+        assert!(rendered.contains("1. Question db?\n   1) Postgres\n   2) SQLite\n"));
+        assert!(rendered.contains("2. Question features?"));
+        assert!(rendered.contains("comma separated"));
+        assert!(rendered.ends_with("numbered like the questions."));
+    }
+}
@@ -4,19 +4,28 @@
 //! agent loops, and tool dispatch. Owns the lifecycle of all runtime tasks.
 
 use crate::core::agent_loop::{AgentLoop, AgentLoopContext, AgentLoopFactory, AgentLoopResult};
+use crate::core::checkpoints::CheckpointStore;
+use crate::core::questions;
 use crate::core::session::SessionManager;
-use crate::core::tools::{ToolContext, ToolRegistry};
+use crate::core::todos::TodoStore;
+use crate::core::tools::{SubAgentRunner, ToolContext, ToolRegistry};
 use crate::core::types::*;
+use crate::core::web_search::WEB_SEARCH_SETTING_KEY;
 use crate::llm::auth::api_key_manager::ApiKeyManager;
 use crate::llm::providers::provider_registry::ProviderRegistry;
+use crate::llm::tracing::TraceWriter;
 use crate::storage::{
-    Message, MessageContent, MessageRole, SessionId, SessionStatus, Storage, TaskSettings,
+    AgentSession, FileCheckpoint, Message, MessageContent, MessageRole, RestoredFile, SessionId,
+    SessionStatus, Storage, TaskSettings, WorkspaceInfo,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio::task::JoinHandle;
 
+/// Maximum nesting of `callAgent` sub-agents below a top-level task
+pub const MAX_AGENT_DEPTH: u64 = 3;
+
 /// Core runtime that manages all tasks and sessions
 #[derive(Clone)]
 pub struct CoreRuntime {
@@ -36,6 +45,12 @@ pub struct CoreRuntime {
     provider_registry: ProviderRegistry,
     /// API key manager
     api_key_manager: ApiKeyManager,
+    /// `askUserQuestions` calls awaiting answers, by session
+    pending_questions: Arc<RwLock<HashMap<SessionId, PendingQuestions>>>,
+    /// Pre-images of files written by tasks
+    checkpoints: CheckpointStore,
+    /// Trace writer recording the model calls of tasks
+    trace_writer: Option<Arc<TraceWriter>>,
 }
 
 /// Settings validator
@@ -91,6 +106,8 @@ impl CoreRuntime {
         // Create tool registry with default tools
         let tool_registry = Arc::new(ToolRegistry::create_default().await);
 
+        let checkpoints = CheckpointStore::new(storage.checkpoints.clone());
+
         Ok(Self {
             storage,
             session_manager,
@@ -100,11 +117,31 @@ impl CoreRuntime {
             _settings_validator: SettingsValidator::new(),
             provider_registry,
             api_key_manager,
+            pending_questions: Arc::new(RwLock::new(HashMap::new())),
+            checkpoints,
+            trace_writer: None,
         })
     }
 
+    /// Record the model calls of tasks in the given trace writer
+    pub fn with_trace_writer(mut self, trace_writer: Arc<TraceWriter>) -> Self {
+        self.trace_writer = Some(trace_writer);
+        self
+    }
+
     /// Start a new task
     pub async fn start_task(&self, input: TaskInput) -> Result<TaskHandle, String> {
+        self.spawn_task(input, self.event_sender.clone(), 0).await
+    }
+
+    /// Start a task whose events are sent to `event_sender`, `depth` `callAgent`
+    /// levels below a top-level task
+    async fn spawn_task(
+        &self,
+        input: TaskInput,
+        event_sender: EventSender,
+        depth: u64,
+    ) -> Result<TaskHandle, String> {
         // Validate settings if provided
         if let Some(ref settings) = input.settings {
             let validation = self._settings_validator.validate(settings);
@@ -147,6 +184,7 @@ impl CoreRuntime {
 
         // Create action channel
         let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let (cancel_signal, cancelled) = watch::channel(false);
 
         // Create task handle
         let task_state = Arc::new(RwLock::new(RuntimeTaskState::Pending));
@@ -155,6 +193,7 @@ impl CoreRuntime {
             session_id: session.id.clone(),
             state: task_state.clone(),
             action_sender: Arc::new(action_tx),
+            cancel_signal: Arc::new(cancel_signal),
         };
 
         // Store task handle
@@ -164,12 +203,19 @@ impl CoreRuntime {
         }
 
         // Spawn task execution
-        let runtime_clone = self.clone_for_task();
-        let event_sender = self.event_sender.clone();
+        let runtime = self.clone();
 
         tokio::spawn(async move {
-            runtime_clone
-                .run_task(task, input, task_state, action_rx, event_sender)
+            runtime
+                .run_task(
+                    task,
+                    input,
+                    task_state,
+                    action_rx,
+                    cancelled,
+                    event_sender,
+                    depth,
+                )
                 .await;
         });
 
@@ -199,6 +245,76 @@ impl CoreRuntime {
         Ok(())
     }
 
+    /// Questions a session's task is currently waiting on, if any
+    pub async fn pending_questions(&self, session_id: &str) -> Option<PendingQuestions> {
+        self.pending_questions.read().await.get(session_id).cloned()
+    }
+
+    /// Answer a session's pending questions with a free-text reply
+    ///
+    /// Used by IM gateways, where the user answers in a plain chat message.
+    pub async fn answer_questions_from_reply(
+        &self,
+        session_id: &str,
+        text: &str,
+    ) -> Result<(), String> {
+        let pending = self
+            .pending_questions(session_id)
+            .await
+            .ok_or_else(|| format!("Session '{}' has no pending questions", session_id))?;
+        let answers = questions::parse_reply(&pending.questions, text)?;
+        questions::answers_to_output(&pending.questions, &answers)?;
+
+        let handle = self
+            .get_task(&pending.task_id)
+            .await
+            .ok_or_else(|| format!("Task '{}' not found", pending.task_id))?;
+        handle.send_action(TaskAction::AnswerQuestions {
+            tool_call_id: pending.tool_call_id,
+            answers,
+        })
+    }
+
+    /// File checkpoints taken by a task, oldest first
+    pub async fn list_checkpoints(&self, task_id: &str) -> Result<Vec<FileCheckpoint>, String> {
+        self.checkpoints.list(task_id).await
+    }
+
+    /// Restore one file to its content before a checkpoint's tool call
+    pub async fn restore_checkpoint(
+        &self,
+        task_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<RestoredFile, String> {
+        self.ensure_task_inactive(task_id).await?;
+        self.checkpoints.restore(task_id, checkpoint_id).await
+    }
+
+    /// Undo a task's file writes from a checkpoint on, or all of them
+    pub async fn rollback_task(
+        &self,
+        task_id: &str,
+        checkpoint_id: Option<&str>,
+    ) -> Result<Vec<RestoredFile>, String> {
+        self.ensure_task_inactive(task_id).await?;
+        self.checkpoints.rollback(task_id, checkpoint_id).await
+    }
+
+    /// Whether a task is pending, running or waiting for the user
+    pub async fn is_task_active(&self, task_id: &str) -> bool {
+        match self.get_task(task_id).await {
+            Some(handle) => handle.state.read().await.is_active(),
+            None => false,
+        }
+    }
+
+    async fn ensure_task_inactive(&self, task_id: &str) -> Result<(), String> {
+        if self.is_task_active(task_id).await {
+            return Err(format!("Task '{}' is still running", task_id));
+        }
+        Ok(())
+    }
+
     /// Get session manager
     pub fn session_manager(&self) -> Arc<SessionManager> {
         self.session_manager.clone()
@@ -210,13 +326,16 @@ impl CoreRuntime {
     }
 
     /// Main task execution loop
+    #[allow(clippy::too_many_arguments)]
     async fn run_task(
         &self,
         mut task: RuntimeTask,
         input: TaskInput,
         task_state: Arc<RwLock<RuntimeTaskState>>,
         mut action_rx: mpsc::UnboundedReceiver<TaskAction>,
+        mut cancelled: watch::Receiver<bool>,
         event_sender: EventSender,
+        depth: u64,
     ) {
         // Update task state to running
         let now = chrono::Utc::now().timestamp();
@@ -227,17 +346,65 @@ impl CoreRuntime {
         // Emit state change event
         let _ = event_sender.send(RuntimeEvent::TaskStateChanged {
             task_id: task.id.clone(),
+            session_id: task.session_id.clone(),
             state: RuntimeTaskState::Running,
             previous_state: RuntimeTaskState::Pending,
         });
 
-        // Create agent loop with full LLM integration
-        let agent_loop = AgentLoopFactory::create_standard(
-            self.tool_registry.clone(),
+        // Resolve the agent profile (system prompt, model, tool list) the task runs as
+        let agent = match input.agent_id.as_deref() {
+            Some(agent_id) => match self.storage.agents.get_agent(agent_id).await {
+                Ok(Some(agent)) => Some(agent),
+                Ok(None) => {
+                    log::warn!("Task {} uses unknown agent '{}'", task.id, agent_id);
+                    None
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Task {} failed to load agent '{}': {}",
+                        task.id,
+                        agent_id,
+                        e
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // Create agent loop with full LLM integration, limited to the agent's tools
+        let mut agent_loop = match agent.as_ref().filter(|agent| !agent.tools.is_empty()) {
+            Some(agent) => AgentLoopFactory::create_with_config(
+                AgentLoopConfig {
+                    available_tools: agent.tools.clone(),
+                    ..AgentLoopConfig::default()
+                },
+                Arc::new(self.tool_registry.restricted_to(&agent.tools).await),
+                event_sender.clone(),
+                self.provider_registry.clone(),
+                self.api_key_manager.clone(),
+            ),
+            None => AgentLoopFactory::create_standard(
+                self.tool_registry.clone(),
+                event_sender.clone(),
+                self.provider_registry.clone(),
+                self.api_key_manager.clone(),
+            ),
+        }
+        .with_session_manager(self.session_manager.clone())
+        .with_sub_agent_runner(Arc::new(SubAgentSpawner {
+            runtime: self.clone(),
+            event_sender: event_sender.clone(),
+            depth,
+        }))
+        .with_todo_store(TodoStore::new(
+            self.storage.chat_history.clone(),
             event_sender.clone(),
-            self.provider_registry.clone(),
-            self.api_key_manager.clone(),
-        );
+        ))
+        .with_checkpoint_store(self.checkpoints.clone());
+        if let Some(trace_writer) = &self.trace_writer {
+            agent_loop = agent_loop.with_trace_writer(trace_writer.clone());
+        }
 
         // Add initial user message
         let initial_message = Message {
@@ -288,7 +455,7 @@ impl CoreRuntime {
                     .unwrap_or_else(|_| "/".to_string())
             });
 
-        let ctx = AgentLoopContext {
+        let mut ctx = AgentLoopContext {
             session_id: task.session_id.clone(),
             task_id: task.id.clone(),
             workspace_root,
@@ -302,94 +469,502 @@ impl CoreRuntime {
                 .get_messages(&task.session_id, None, None)
                 .await
                 .unwrap_or_default(),
-            model: input.settings.and_then(|s| {
-                s.extra
-                    .get("model")
-                    .and_then(|v| v.as_str().map(|s| s.to_string()))
-            }),
+            model: input
+                .settings
+                .and_then(|s| {
+                    s.extra
+                        .get("model")
+                        .and_then(|v| v.as_str().map(|s| s.to_string()))
+                })
+                .or_else(|| {
+                    agent
+                        .as_ref()
+                        .map(|agent| agent.model.clone())
+                        .filter(|model| !model.is_empty())
+                }),
+            system_prompt: agent.and_then(|agent| agent.system_prompt),
+            iterations: 0,
         };
 
-        // Get current messages and run agent loop
-        let messages = self
-            .session_manager
-            .get_messages(&task.session_id, None, None)
-            .await
-            .unwrap_or_default();
+        // Tools read backend configuration from task settings; fall back to the
+        // app-wide setting when the task doesn't override it
+        if !ctx.settings.extra.contains_key(WEB_SEARCH_SETTING_KEY) {
+            if let Ok(Some(value)) = self
+                .storage
+                .settings
+                .get_setting(WEB_SEARCH_SETTING_KEY)
+                .await
+            {
+                ctx.settings
+                    .extra
+                    .insert(WEB_SEARCH_SETTING_KEY.to_string(), value);
+            }
+        }
 
-        match agent_loop.run_iteration(&ctx, &messages).await {
-            Ok(AgentLoopResult::Completed { message }) => {
-                // Add assistant message
-                let assistant_message = Message {
-                    id: format!("msg_{}", uuid::Uuid::new_v4()),
-                    session_id: task.session_id.clone(),
-                    role: MessageRole::Assistant,
-                    content: MessageContent::Text { text: message },
-                    created_at: chrono::Utc::now().timestamp(),
-                    tool_call_id: None,
-                    parent_id: None,
-                };
-
-                let _ = self
-                    .session_manager
-                    .add_message(assistant_message.clone())
-                    .await;
-                let _ = event_sender.send(RuntimeEvent::MessageCreated {
-                    session_id: task.session_id.clone(),
-                    message: assistant_message,
-                });
+        loop {
+            // Cancellation interrupts the turn in progress, dropping any
+            // `callAgent` call, which cancels its child task in turn
+            let result = tokio::select! {
+                result = agent_loop.run(&mut ctx) => result,
+                Ok(_) = cancelled.wait_for(|cancelled| *cancelled) => {
+                    Ok(AgentLoopResult::Cancelled)
+                }
+            };
 
-                self.complete_task(&task, RuntimeTaskState::Completed, None, &event_sender)
+            match result {
+                Ok(AgentLoopResult::Completed { message }) => {
+                    // Add assistant message
+                    let assistant_message = Message {
+                        id: format!("msg_{}", uuid::Uuid::new_v4()),
+                        session_id: task.session_id.clone(),
+                        role: MessageRole::Assistant,
+                        content: MessageContent::Text { text: message },
+                        created_at: chrono::Utc::now().timestamp(),
+                        tool_call_id: None,
+                        parent_id: None,
+                    };
+
+                    let _ = self
+                        .session_manager
+                        .add_message(assistant_message.clone())
+                        .await;
+                    let _ = event_sender.send(RuntimeEvent::MessageCreated {
+                        session_id: task.session_id.clone(),
+                        message: assistant_message,
+                    });
+
+                    self.complete_task(&task, RuntimeTaskState::Completed, None, &event_sender)
+                        .await;
+                }
+                Ok(AgentLoopResult::WaitingForApproval { request }) => {
+                    // Park the task until the user decides on the pending tool call
+                    let result = match self
+                        .wait_for_tool_decision(
+                            &task,
+                            &task_state,
+                            &mut action_rx,
+                            &event_sender,
+                            &agent_loop,
+                            &ctx,
+                            &request,
+                        )
+                        .await
+                    {
+                        Some(result) => result,
+                        None => {
+                            self.complete_cancelled(&task, &agent_loop, &ctx, &event_sender)
+                                .await;
+                            break;
+                        }
+                    };
+
+                    if !self
+                        .resume_with_tool_result(
+                            &task,
+                            &agent_loop,
+                            &mut ctx,
+                            &request,
+                            &result,
+                            &event_sender,
+                        )
+                        .await
+                    {
+                        break;
+                    }
+                    continue;
+                }
+                Ok(AgentLoopResult::Error { message }) => {
+                    self.complete_task(
+                        &task,
+                        RuntimeTaskState::Failed,
+                        Some(message),
+                        &event_sender,
+                    )
                     .await;
+                }
+                Ok(AgentLoopResult::MaxIterationsReached) => {
+                    self.complete_task(
+                        &task,
+                        RuntimeTaskState::Completed,
+                        Some("Maximum iterations reached".to_string()),
+                        &event_sender,
+                    )
+                    .await;
+                }
+                Ok(AgentLoopResult::Cancelled) => {
+                    self.complete_cancelled(&task, &agent_loop, &ctx, &event_sender)
+                        .await;
+                }
+                Ok(AgentLoopResult::WaitingForToolResult { request }) => {
+                    // Suspend until the user answers; malformed questions are
+                    // reported back to the model instead
+                    let result = match questions::parse_questions(&request.input) {
+                        Ok(questions) => match self
+                            .wait_for_answers(
+                                &task,
+                                &task_state,
+                                &mut action_rx,
+                                &event_sender,
+                                &request,
+                                questions,
+                            )
+                            .await
+                        {
+                            Some(result) => result,
+                            None => {
+                                self.complete_cancelled(&task, &agent_loop, &ctx, &event_sender)
+                                    .await;
+                                break;
+                            }
+                        },
+                        Err(e) => ToolResult {
+                            tool_call_id: request.tool_call_id.clone(),
+                            name: Some(request.name.clone()),
+                            success: false,
+                            output: serde_json::Value::Null,
+                            error: Some(e),
+                        },
+                    };
+
+                    if !self
+                        .resume_with_tool_result(
+                            &task,
+                            &agent_loop,
+                            &mut ctx,
+                            &request,
+                            &result,
+                            &event_sender,
+                        )
+                        .await
+                    {
+                        break;
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    self.complete_task(&task, RuntimeTaskState::Failed, Some(e), &event_sender)
+                        .await;
+                }
             }
-            Ok(AgentLoopResult::WaitingForApproval { request }) => {
-                *task_state.write().await = RuntimeTaskState::WaitingForUser;
-                let _ = event_sender.send(RuntimeEvent::ToolCallRequested {
-                    task_id: task.id.clone(),
-                    request,
-                });
-                // Task will wait for user action via action_rx
+            break;
+        }
+
+        // Remove from active tasks
+        let mut tasks = self.tasks.write().await;
+        tasks.remove(&task.id);
+    }
+
+    /// Wait on the action channel for a decision on a tool call awaiting approval
+    ///
+    /// Returns the tool result to record (executed on approval, synthesized on
+    /// rejection, or supplied by the client), or `None` if the task was cancelled.
+    #[allow(clippy::too_many_arguments)]
+    async fn wait_for_tool_decision(
+        &self,
+        task: &RuntimeTask,
+        task_state: &Arc<RwLock<RuntimeTaskState>>,
+        action_rx: &mut mpsc::UnboundedReceiver<TaskAction>,
+        event_sender: &EventSender,
+        agent_loop: &AgentLoop,
+        ctx: &AgentLoopContext,
+        request: &ToolRequest,
+    ) -> Option<ToolResult> {
+        self.set_task_state(
+            task,
+            task_state,
+            RuntimeTaskState::WaitingForUser,
+            event_sender,
+        )
+        .await;
+        let _ = self
+            .session_manager
+            .update_session_status(&task.session_id, SessionStatus::WaitingForAction, None)
+            .await;
+        let _ = event_sender.send(RuntimeEvent::ToolCallRequested {
+            task_id: task.id.clone(),
+            session_id: task.session_id.clone(),
+            request: request.clone(),
+        });
+
+        let result = loop {
+            let action = match action_rx.recv().await {
+                Some(action) => action,
+                None => break None,
+            };
+
+            match action {
+                TaskAction::Approve { tool_call_id } if tool_call_id == request.tool_call_id => {
+                    break Some(agent_loop.execute_approved_tool(ctx, request.clone()).await);
+                }
+                TaskAction::Reject {
+                    tool_call_id,
+                    reason,
+                } if tool_call_id == request.tool_call_id => {
+                    let result = ToolResult {
+                        tool_call_id,
+                        name: Some(request.name.clone()),
+                        success: false,
+                        output: serde_json::Value::Null,
+                        error: Some(format!(
+                            "User rejected the tool call: {}",
+                            reason.unwrap_or_else(|| "no reason given".to_string())
+                        )),
+                    };
+                    let _ = event_sender.send(RuntimeEvent::ToolCallCompleted {
+                        task_id: task.id.clone(),
+                        session_id: task.session_id.clone(),
+                        result: result.clone(),
+                    });
+                    break Some(result);
+                }
+                TaskAction::ToolResult {
+                    tool_call_id,
+                    result,
+                } if tool_call_id == request.tool_call_id => {
+                    let result = ToolResult {
+                        tool_call_id,
+                        name: Some(request.name.clone()),
+                        success: true,
+                        output: result,
+                        error: None,
+                    };
+                    let _ = event_sender.send(RuntimeEvent::ToolCallCompleted {
+                        task_id: task.id.clone(),
+                        session_id: task.session_id.clone(),
+                        result: result.clone(),
+                    });
+                    break Some(result);
+                }
+                TaskAction::Cancel => break None,
+                other => {
+                    log::warn!(
+                        "Task {} ignoring action {:?} while waiting on tool call {}",
+                        task.id,
+                        other,
+                        request.tool_call_id
+                    );
+                }
             }
-            Ok(AgentLoopResult::Error { message }) => {
-                self.complete_task(
-                    &task,
-                    RuntimeTaskState::Failed,
-                    Some(message),
-                    &event_sender,
-                )
+        };
+
+        if result.is_some() {
+            self.set_task_state(task, task_state, RuntimeTaskState::Running, event_sender)
                 .await;
-            }
-            Ok(AgentLoopResult::MaxIterationsReached) => {
-                self.complete_task(
-                    &task,
-                    RuntimeTaskState::Completed,
-                    Some("Maximum iterations reached".to_string()),
-                    &event_sender,
-                )
+            let _ = self
+                .session_manager
+                .update_session_status(&task.session_id, SessionStatus::Running, None)
                 .await;
+        }
+
+        result
+    }
+
+    /// Wait on the action channel for answers to an `askUserQuestions` call
+    ///
+    /// The questions are published as `RuntimeEvent::QuestionsAsked` and kept in
+    /// `pending_questions` so IM replies can be matched to them. Returns the
+    /// tool result to record, or `None` if the task was cancelled.
+    async fn wait_for_answers(
+        &self,
+        task: &RuntimeTask,
+        task_state: &Arc<RwLock<RuntimeTaskState>>,
+        action_rx: &mut mpsc::UnboundedReceiver<TaskAction>,
+        event_sender: &EventSender,
+        request: &ToolRequest,
+        questions: Vec<UserQuestion>,
+    ) -> Option<ToolResult> {
+        self.set_task_state(
+            task,
+            task_state,
+            RuntimeTaskState::WaitingForUser,
+            event_sender,
+        )
+        .await;
+        let _ = self
+            .session_manager
+            .update_session_status(&task.session_id, SessionStatus::WaitingForAction, None)
+            .await;
+        self.pending_questions.write().await.insert(
+            task.session_id.clone(),
+            PendingQuestions {
+                task_id: task.id.clone(),
+                session_id: task.session_id.clone(),
+                tool_call_id: request.tool_call_id.clone(),
+                questions: questions.clone(),
+            },
+        );
+        let _ = event_sender.send(RuntimeEvent::QuestionsAsked {
+            task_id: task.id.clone(),
+            session_id: task.session_id.clone(),
+            tool_call_id: request.tool_call_id.clone(),
+            questions: questions.clone(),
+        });
+
+        let tool_result =
+            |success: bool, output: serde_json::Value, error: Option<String>| ToolResult {
+                tool_call_id: request.tool_call_id.clone(),
+                name: Some(request.name.clone()),
+                success,
+                output,
+                error,
+            };
+
+        let result = loop {
+            let action = match action_rx.recv().await {
+                Some(action) => action,
+                None => break None,
+            };
+
+            match action {
+                TaskAction::AnswerQuestions {
+                    tool_call_id,
+                    answers,
+                } if tool_call_id == request.tool_call_id => {
+                    match questions::answers_to_output(&questions, &answers) {
+                        Ok(output) => break Some(tool_result(true, output, None)),
+                        Err(e) => {
+                            // Keep waiting so the user can answer again
+                            let _ = event_sender.send(RuntimeEvent::Error {
+                                task_id: Some(task.id.clone()),
+                                session_id: Some(task.session_id.clone()),
+                                message: format!("Invalid answers: {}", e),
+                            });
+                        }
+                    }
+                }
+                TaskAction::ToolResult {
+                    tool_call_id,
+                    result,
+                } if tool_call_id == request.tool_call_id => {
+                    break Some(tool_result(true, result, None));
+                }
+                TaskAction::Reject {
+                    tool_call_id,
+                    reason,
+                } if tool_call_id == request.tool_call_id => {
+                    break Some(tool_result(
+                        false,
+                        serde_json::Value::Null,
+                        Some(format!(
+                            "User declined to answer: {}",
+                            reason.unwrap_or_else(|| "no reason given".to_string())
+                        )),
+                    ));
+                }
+                TaskAction::Cancel => break None,
+                other => {
+                    log::warn!(
+                        "Task {} ignoring action {:?} while waiting for answers to {}",
+                        task.id,
+                        other,
+                        request.tool_call_id
+                    );
+                }
             }
-            Ok(AgentLoopResult::Cancelled) => {
-                self.complete_task(&task, RuntimeTaskState::Cancelled, None, &event_sender)
-                    .await;
-            }
-            Ok(AgentLoopResult::WaitingForToolResult { .. }) => {
-                // This shouldn't happen in our simplified implementation
-                self.complete_task(
-                    &task,
-                    RuntimeTaskState::Failed,
-                    Some("Unexpected tool result wait".to_string()),
-                    &event_sender,
-                )
+        };
+
+        self.pending_questions
+            .write()
+            .await
+            .remove(&task.session_id);
+
+        if let Some(result) = &result {
+            let _ = event_sender.send(RuntimeEvent::ToolCallCompleted {
+                task_id: task.id.clone(),
+                session_id: task.session_id.clone(),
+                result: result.clone(),
+            });
+            self.set_task_state(task, task_state, RuntimeTaskState::Running, event_sender)
+                .await;
+            let _ = self
+                .session_manager
+                .update_session_status(&task.session_id, SessionStatus::Running, None)
                 .await;
-            }
-            Err(e) => {
-                self.complete_task(&task, RuntimeTaskState::Failed, Some(e), &event_sender)
-                    .await;
-            }
         }
 
-        // Remove from active tasks
-        let mut tasks = self.tasks.write().await;
-        tasks.remove(&task.id);
+        result
+    }
+
+    /// Record a tool result and reload the history the agent loop continues from
+    ///
+    /// Fails the task and returns `false` if the result couldn't be recorded.
+    async fn resume_with_tool_result(
+        &self,
+        task: &RuntimeTask,
+        agent_loop: &AgentLoop,
+        ctx: &mut AgentLoopContext,
+        request: &ToolRequest,
+        result: &ToolResult,
+        event_sender: &EventSender,
+    ) -> bool {
+        let mut recorded = Vec::new();
+        if let Err(e) = agent_loop
+            .record_tool_result(ctx, request, result, &mut recorded)
+            .await
+        {
+            self.complete_task(
+                task,
+                RuntimeTaskState::Failed,
+                Some(format!("Failed to record tool result: {}", e)),
+                event_sender,
+            )
+            .await;
+            return false;
+        }
+
+        ctx.messages = self
+            .session_manager
+            .get_messages(&task.session_id, None, None)
+            .await
+            .unwrap_or_default();
+        true
+    }
+
+    /// Complete a cancelled task, first resolving the tool calls it leaves
+    /// unanswered so the session can continue with a new message
+    async fn complete_cancelled(
+        &self,
+        task: &RuntimeTask,
+        agent_loop: &AgentLoop,
+        ctx: &AgentLoopContext,
+        event_sender: &EventSender,
+    ) {
+        let mut messages = self
+            .session_manager
+            .get_messages(&task.session_id, None, None)
+            .await
+            .unwrap_or_default();
+        if let Err(e) = agent_loop
+            .cancel_pending_tool_calls(ctx, &mut messages)
+            .await
+        {
+            log::warn!(
+                "Task {} failed to resolve its pending tool calls: {}",
+                task.id,
+                e
+            );
+        }
+
+        self.complete_task(task, RuntimeTaskState::Cancelled, None, event_sender)
+            .await;
+    }
+
+    /// Update the task state and emit a state change event
+    async fn set_task_state(
+        &self,
+        task: &RuntimeTask,
+        task_state: &Arc<RwLock<RuntimeTaskState>>,
+        state: RuntimeTaskState,
+        event_sender: &EventSender,
+    ) {
+        let previous_state = std::mem::replace(&mut *task_state.write().await, state);
+
+        let _ = event_sender.send(RuntimeEvent::TaskStateChanged {
+            task_id: task.id.clone(),
+            session_id: task.session_id.clone(),
+            state,
+            previous_state,
+        });
     }
 
     /// Complete a task and emit events
@@ -421,6 +996,7 @@ impl CoreRuntime {
         // Emit completion event
         let _ = event_sender.send(RuntimeEvent::TaskStateChanged {
             task_id: task.id.clone(),
+            session_id: task.session_id.clone(),
             state: final_state,
             previous_state,
         });
@@ -444,40 +1020,168 @@ impl CoreRuntime {
         // If session_id is explicitly provided in input, use that
         Some(input.session_id.clone())
     }
+}
+
+/// Runs `callAgent` sub-agents as child tasks of a running task
+///
+/// Child events are forwarded to the parent task's event channel wrapped in
+/// `RuntimeEvent::SubAgent`, so agents called by sub-agents nest further.
+/// A child's `ToolCallRequested` and `QuestionsAsked` are answered like those
+/// of any task, by sending the action to the child's task id.
+#[derive(Clone)]
+struct SubAgentSpawner {
+    runtime: CoreRuntime,
+    event_sender: EventSender,
+    /// `callAgent` levels between the task this spawner serves and its top-level task
+    depth: u64,
+}
 
-    /// Clone runtime state for task execution
-    fn clone_for_task(&self) -> RuntimeTaskContext {
-        RuntimeTaskContext {
-            session_manager: self.session_manager.clone(),
-            tool_registry: self.tool_registry.clone(),
+/// Cancels a child task when the `callAgent` call waiting on it goes away,
+/// whether it finished or was dropped because the parent was cancelled
+struct CancelOnDrop(TaskHandle);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if !self.0.is_cancelled() {
+            let _ = self.0.cancel();
         }
     }
 }
 
-/// Context passed to task execution (lighter weight than full runtime)
-#[derive(Clone)]
-struct RuntimeTaskContext {
-    session_manager: Arc<SessionManager>,
-    tool_registry: Arc<ToolRegistry>,
+impl std::fmt::Debug for SubAgentSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubAgentSpawner").finish_non_exhaustive()
+    }
 }
 
-impl RuntimeTaskContext {
-    async fn run_task(
+#[async_trait::async_trait]
+impl SubAgentRunner for SubAgentSpawner {
+    async fn run_sub_agent(
         &self,
-        _task: RuntimeTask,
-        _input: TaskInput,
-        _task_state: Arc<RwLock<RuntimeTaskState>>,
-        _action_rx: mpsc::UnboundedReceiver<TaskAction>,
-        _event_sender: EventSender,
-    ) {
-        // This is called from the spawned task - actual implementation is in CoreRuntime
-        // This method exists for type compatibility
+        agent_name: &str,
+        prompt: &str,
+        parent: &ToolContext,
+    ) -> Result<SubAgentOutput, String> {
+        if self.depth >= MAX_AGENT_DEPTH {
+            return Err(format!(
+                "Cannot call agent '{}': sub-agent depth limit of {} reached",
+                agent_name, MAX_AGENT_DEPTH
+            ));
+        }
+
+        let agents = &self.runtime.storage.agents;
+        let agent = match agents.get_agent_by_name(agent_name).await? {
+            Some(agent) => agent,
+            None => agents
+                .get_agent(agent_name)
+                .await?
+                .ok_or_else(|| format!("Agent '{}' not found", agent_name))?,
+        };
+
+        // The child inherits the parent's settings
+        let mut settings = parent.settings.clone();
+        if !agent.model.is_empty() {
+            settings
+                .extra
+                .insert("model".to_string(), serde_json::json!(agent.model));
+        }
+
+        let session = self
+            .runtime
+            .session_manager
+            .create_session(None, Some(agent.name.clone()), Some(settings.clone()))
+            .await?;
+        agents
+            .create_agent_session(&AgentSession {
+                agent_id: agent.id.clone(),
+                session_id: session.id.clone(),
+                settings: settings.clone(),
+                created_at: chrono::Utc::now().timestamp(),
+            })
+            .await?;
+
+        let (child_sender, mut child_events) = mpsc::unbounded_channel();
+        let handle = self
+            .runtime
+            .spawn_task(
+                TaskInput {
+                    session_id: session.id.clone(),
+                    agent_id: Some(agent.id.clone()),
+                    project_id: None,
+                    initial_message: prompt.to_string(),
+                    settings: Some(settings),
+                    workspace: Some(WorkspaceInfo {
+                        root_path: parent.workspace_root.clone(),
+                        worktree_path: parent.worktree_path.clone(),
+                        repository_url: None,
+                        branch: None,
+                    }),
+                },
+                child_sender,
+                self.depth + 1,
+            )
+            .await?;
+        let _cancel_child = CancelOnDrop(handle.clone());
+
+        // The channel closes once the child task and its agent loop are gone
+        let mut final_state = RuntimeTaskState::Pending;
+        let mut error = None;
+        while let Some(event) = child_events.recv().await {
+            match &event {
+                RuntimeEvent::TaskStateChanged { task_id, state, .. }
+                    if *task_id == handle.task_id =>
+                {
+                    final_state = *state;
+                }
+                RuntimeEvent::Error {
+                    task_id: Some(task_id),
+                    message,
+                    ..
+                } if *task_id == handle.task_id => {
+                    error = Some(message.clone());
+                }
+                _ => {}
+            }
+
+            let _ = self.event_sender.send(RuntimeEvent::SubAgent {
+                parent_task_id: parent.task_id.clone(),
+                agent: agent.name.clone(),
+                event: Box::new(event),
+            });
+        }
+
+        if final_state != RuntimeTaskState::Completed {
+            return Err(error.unwrap_or_else(|| {
+                format!("Agent '{}' ended in state {:?}", agent.name, final_state)
+            }));
+        }
+
+        let response = self
+            .runtime
+            .session_manager
+            .get_messages(&handle.session_id, None, None)
+            .await?
+            .into_iter()
+            .rev()
+            .find_map(|message| match (message.role, message.content) {
+                (MessageRole::Assistant, MessageContent::Text { text }) => Some(text),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        Ok(SubAgentOutput {
+            agent: agent.name,
+            session_id: handle.session_id,
+            task_id: handle.task_id,
+            response,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::{ToolCall, ToolResultStatus};
     use tempfile::TempDir;
 
     async fn create_test_runtime() -> (CoreRuntime, TempDir, mpsc::UnboundedReceiver<RuntimeEvent>)
@@ -526,4 +1230,460 @@ mod tests {
         assert!(result.valid); // Still valid, just warnings
         assert_eq!(result.warnings.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_wait_for_tool_decision_reject_and_cancel() {
+        let (runtime, _temp, mut rx) = create_test_runtime().await;
+        let session = runtime
+            .session_manager()
+            .create_session(None, None, None)
+            .await
+            .unwrap();
+
+        let task = RuntimeTask {
+            id: "task-1".to_string(),
+            session_id: session.id.clone(),
+            agent_id: None,
+            state: RuntimeTaskState::Running,
+            created_at: 0,
+            started_at: None,
+            completed_at: None,
+            error_message: None,
+            metadata: HashMap::new(),
+        };
+        let ctx = AgentLoopContext {
+            session_id: session.id.clone(),
+            task_id: task.id.clone(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            messages: vec![],
+            model: None,
+            system_prompt: None,
+            iterations: 0,
+        };
+        let request = ToolRequest {
+            tool_call_id: "call-1".to_string(),
+            name: "bash".to_string(),
+            input: serde_json::json!({"command": "rm -rf build"}),
+            provider_metadata: None,
+        };
+        let agent_loop = AgentLoopFactory::create_standard(
+            runtime.tool_registry(),
+            runtime.event_sender.clone(),
+            runtime.provider_registry.clone(),
+            runtime.api_key_manager.clone(),
+        );
+        let task_state = Arc::new(RwLock::new(RuntimeTaskState::Running));
+        let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+
+        // Actions for other tool calls are ignored, the matching rejection resolves
+        action_tx
+            .send(TaskAction::Approve {
+                tool_call_id: "other".to_string(),
+            })
+            .unwrap();
+        action_tx
+            .send(TaskAction::Reject {
+                tool_call_id: "call-1".to_string(),
+                reason: Some("too risky".to_string()),
+            })
+            .unwrap();
+
+        let result = runtime
+            .wait_for_tool_decision(
+                &task,
+                &task_state,
+                &mut action_rx,
+                &runtime.event_sender,
+                &agent_loop,
+                &ctx,
+                &request,
+            )
+            .await
+            .expect("Rejection should produce a tool result");
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("too risky"));
+        assert_eq!(*task_state.read().await, RuntimeTaskState::Running);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(RuntimeEvent::TaskStateChanged {
+                state: RuntimeTaskState::WaitingForUser,
+                ..
+            })
+        ));
+
+        action_tx.send(TaskAction::Cancel).unwrap();
+        let cancelled = runtime
+            .wait_for_tool_decision(
+                &task,
+                &task_state,
+                &mut action_rx,
+                &runtime.event_sender,
+                &agent_loop,
+                &ctx,
+                &request,
+            )
+            .await;
+        assert!(cancelled.is_none());
+        assert_eq!(*task_state.read().await, RuntimeTaskState::WaitingForUser);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_while_awaiting_approval_answers_pending_calls() {
+        let (runtime, _temp, _rx) = create_test_runtime().await;
+        let session_manager = runtime.session_manager();
+        let session = session_manager
+            .create_session(None, None, None)
+            .await
+            .unwrap();
+        let message = |content: MessageContent, role: MessageRole| Message {
+            id: format!("msg_{}", uuid::Uuid::new_v4()),
+            session_id: session.id.clone(),
+            role,
+            content,
+            created_at: 0,
+            tool_call_id: None,
+            parent_id: None,
+        };
+        let call = |id: &str| ToolCall {
+            id: id.to_string(),
+            name: "bash".to_string(),
+            input: serde_json::json!({"command": "rm -rf build"}),
+            provider_metadata: None,
+        };
+
+        // A turn whose first call awaits approval when the task is cancelled
+        session_manager
+            .add_message(message(
+                MessageContent::Text {
+                    text: "Clean up".to_string(),
+                },
+                MessageRole::User,
+            ))
+            .await
+            .unwrap();
+        session_manager
+            .add_message(message(
+                MessageContent::ToolCalls {
+                    calls: vec![call("call-1"), call("call-2")],
+                },
+                MessageRole::Assistant,
+            ))
+            .await
+            .unwrap();
+
+        let task = RuntimeTask {
+            id: "task-1".to_string(),
+            session_id: session.id.clone(),
+            agent_id: None,
+            state: RuntimeTaskState::Running,
+            created_at: 0,
+            started_at: None,
+            completed_at: None,
+            error_message: None,
+            metadata: HashMap::new(),
+        };
+        let ctx = AgentLoopContext {
+            session_id: session.id.clone(),
+            task_id: task.id.clone(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            messages: vec![],
+            model: None,
+            system_prompt: None,
+            iterations: 0,
+        };
+        let agent_loop = AgentLoopFactory::create_standard(
+            runtime.tool_registry(),
+            runtime.event_sender.clone(),
+            runtime.provider_registry.clone(),
+            runtime.api_key_manager.clone(),
+        )
+        .with_session_manager(session_manager.clone());
+        let task_state = Arc::new(RwLock::new(RuntimeTaskState::Running));
+        let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+
+        action_tx.send(TaskAction::Cancel).unwrap();
+        let request = ToolRequest {
+            tool_call_id: "call-1".to_string(),
+            name: "bash".to_string(),
+            input: serde_json::json!({"command": "rm -rf build"}),
+            provider_metadata: None,
+        };
+        let decision = runtime
+            .wait_for_tool_decision(
+                &task,
+                &task_state,
+                &mut action_rx,
+                &runtime.event_sender,
+                &agent_loop,
+                &ctx,
+                &request,
+            )
+            .await;
+        assert!(decision.is_none());
+        runtime
+            .complete_cancelled(&task, &agent_loop, &ctx, &runtime.event_sender)
+            .await;
+
+        // The next message follows a turn whose calls all have results
+        session_manager
+            .add_message(message(
+                MessageContent::Text {
+                    text: "Try again".to_string(),
+                },
+                MessageRole::User,
+            ))
+            .await
+            .unwrap();
+        let history = session_manager
+            .get_messages(&session.id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 5);
+        for (message, call_id) in history[2..4].iter().zip(["call-1", "call-2"]) {
+            match &message.content {
+                MessageContent::ToolResult { result } => {
+                    assert_eq!(result.tool_call_id, call_id);
+                    assert!(matches!(result.status, ToolResultStatus::Error));
+                    assert_eq!(result.error_message.as_deref(), Some("Cancelled by user"));
+                }
+                other => panic!("Expected tool result, got {:?}", other),
+            }
+        }
+        assert_eq!(history[4].role, MessageRole::User);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_answers_validates_and_clears_pending() {
+        let (runtime, _temp, mut rx) = create_test_runtime().await;
+        let session = runtime
+            .session_manager()
+            .create_session(None, None, None)
+            .await
+            .unwrap();
+
+        let task = RuntimeTask {
+            id: "task-1".to_string(),
+            session_id: session.id.clone(),
+            agent_id: None,
+            state: RuntimeTaskState::Running,
+            created_at: 0,
+            started_at: None,
+            completed_at: None,
+            error_message: None,
+            metadata: HashMap::new(),
+        };
+        let request = ToolRequest {
+            tool_call_id: "call-1".to_string(),
+            name: "askUserQuestions".to_string(),
+            input: serde_json::json!({
+                "questions": [{"id": "db", "question": "Which database?", "options": ["Postgres", "SQLite"]}]
+            }),
+            provider_metadata: None,
+        };
+        let questions = questions::parse_questions(&request.input).unwrap();
+        let task_state = Arc::new(RwLock::new(RuntimeTaskState::Running));
+        let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+
+        let waiter = {
+            let runtime = runtime.clone();
+            let task_state = task_state.clone();
+            tokio::spawn(async move {
+                let event_sender = runtime.event_sender.clone();
+                runtime
+                    .wait_for_answers(
+                        &task,
+                        &task_state,
+                        &mut action_rx,
+                        &event_sender,
+                        &request,
+                        questions,
+                    )
+                    .await
+            })
+        };
+
+        // The questions are published and registered for the session
+        let asked = loop {
+            if let Some(RuntimeEvent::QuestionsAsked { questions, .. }) = rx.recv().await {
+                break questions;
+            }
+        };
+        assert_eq!(asked[0].options, vec!["Postgres", "SQLite"]);
+        let pending = runtime.pending_questions(&session.id).await.unwrap();
+        assert_eq!(pending.tool_call_id, "call-1");
+        let waiting = runtime
+            .session_manager()
+            .get_session(&session.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(waiting.status, SessionStatus::WaitingForAction);
+
+        // Incomplete answers are reported and the task keeps waiting
+        action_tx
+            .send(TaskAction::AnswerQuestions {
+                tool_call_id: "call-1".to_string(),
+                answers: vec![],
+            })
+            .unwrap();
+        let reported = loop {
+            if let Some(RuntimeEvent::Error { message, .. }) = rx.recv().await {
+                break message;
+            }
+        };
+        assert!(reported.contains("db"));
+
+        action_tx
+            .send(TaskAction::AnswerQuestions {
+                tool_call_id: "call-1".to_string(),
+                answers: vec![QuestionAnswer {
+                    question_id: "db".to_string(),
+                    answers: vec!["SQLite".to_string()],
+                }],
+            })
+            .unwrap();
+
+        let result = waiter
+            .await
+            .unwrap()
+            .expect("Answers should resolve the call");
+        assert!(result.success);
+        assert_eq!(
+            result.output["answers"][0]["answers"],
+            serde_json::json!(["SQLite"])
+        );
+        assert_eq!(*task_state.read().await, RuntimeTaskState::Running);
+        assert!(runtime.pending_questions(&session.id).await.is_none());
+    }
+
+    fn parent_tool_context() -> ToolContext {
+        // Clients can't raise the depth limit through task settings
+        let mut settings = TaskSettings::default();
+        settings
+            .extra
+            .insert("agent_depth".to_string(), serde_json::json!(0));
+        ToolContext {
+            session_id: "parent-session".to_string(),
+            task_id: "parent-task".to_string(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings,
+            sub_agents: None,
+            todos: None,
+            checkpoints: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sub_agent_depth_limit_and_unknown_agent() {
+        let (runtime, _temp, _rx) = create_test_runtime().await;
+        let spawner = SubAgentSpawner {
+            event_sender: runtime.event_sender.clone(),
+            runtime,
+            depth: MAX_AGENT_DEPTH,
+        };
+
+        let err = spawner
+            .run_sub_agent("reviewer", "look", &parent_tool_context())
+            .await
+            .unwrap_err();
+        assert!(err.contains("depth limit"));
+
+        let spawner = SubAgentSpawner {
+            depth: 0,
+            ..spawner
+        };
+        let err = spawner
+            .run_sub_agent("missing", "look", &parent_tool_context())
+            .await
+            .unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_sub_agent_runs_in_own_session_with_nested_events() {
+        let (runtime, _temp, mut rx) = create_test_runtime().await;
+        runtime
+            .storage
+            .agents
+            .create_agent(&crate::storage::Agent {
+                id: "agent-1".to_string(),
+                name: "reviewer".to_string(),
+                model: "no-such-model".to_string(),
+                system_prompt: Some("Review code.".to_string()),
+                tools: vec!["readFile".to_string()],
+                created_at: 0,
+                updated_at: 0,
+            })
+            .await
+            .unwrap();
+        let spawner = SubAgentSpawner {
+            event_sender: runtime.event_sender.clone(),
+            runtime: runtime.clone(),
+            depth: 0,
+        };
+
+        // No provider serves the agent's model, so the child task fails
+        let err = spawner
+            .run_sub_agent("reviewer", "look at main.rs", &parent_tool_context())
+            .await
+            .unwrap_err();
+        assert!(!err.is_empty());
+
+        let agent_sessions = runtime
+            .storage
+            .agents
+            .get_agent_sessions("agent-1")
+            .await
+            .unwrap();
+        assert_eq!(agent_sessions.len(), 1);
+        let child_messages = runtime
+            .session_manager()
+            .get_messages(&agent_sessions[0].session_id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(child_messages[0].role, MessageRole::User);
+
+        let mut nested = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                RuntimeEvent::SubAgent {
+                    parent_task_id,
+                    agent,
+                    event,
+                } => {
+                    assert_eq!(parent_task_id, "parent-task");
+                    assert_eq!(agent, "reviewer");
+                    nested.push(*event);
+                }
+                other => panic!("Child event leaked un-nested: {:?}", other),
+            }
+        }
+        assert!(nested
+            .iter()
+            .any(|event| matches!(event, RuntimeEvent::TaskCompleted { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_sub_agent_call_cancels_child() {
+        let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+        let (cancel_signal, cancelled) = watch::channel(false);
+        let child = TaskHandle {
+            task_id: "child-task".to_string(),
+            session_id: "child-session".to_string(),
+            state: Arc::new(RwLock::new(RuntimeTaskState::WaitingForUser)),
+            action_sender: Arc::new(action_tx),
+            cancel_signal: Arc::new(cancel_signal),
+        };
+
+        drop(CancelOnDrop(child.clone()));
+
+        assert!(child.is_cancelled());
+        assert!(*cancelled.borrow());
+        assert!(matches!(action_rx.try_recv(), Ok(TaskAction::Cancel)));
+    }
 }
@@ -5,7 +5,8 @@
 
 use crate::core::types::*;
 use crate::storage::{
-    Message, MessageContent, MessageRole, Session, SessionId, SessionStatus, Storage, TaskSettings,
+    Message, MessageBranch, MessageContent, MessageRole, Session, SessionFork, SessionId,
+    SessionStatus, Storage, TaskSettings,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -40,6 +41,16 @@ impl SessionManager {
         project_id: Option<String>,
         title: Option<String>,
         settings: Option<TaskSettings>,
+    ) -> Result<Session, String> {
+        self.insert_session(project_id, title, settings, None).await
+    }
+
+    async fn insert_session(
+        &self,
+        project_id: Option<String>,
+        title: Option<String>,
+        settings: Option<TaskSettings>,
+        forked_from: Option<SessionFork>,
     ) -> Result<Session, String> {
         let now = chrono::Utc::now().timestamp();
         let session_id = format!("sess_{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
@@ -53,6 +64,8 @@ impl SessionManager {
             updated_at: now,
             last_event_id: None,
             metadata: None,
+            head_message_id: None,
+            forked_from,
         };
 
         // Persist session
@@ -226,21 +239,161 @@ impl SessionManager {
         Ok(())
     }
 
-    /// Add a message to a session
+    /// Add a message to a session, continuing its selected branch unless the
+    /// message has a `parent_id`
     pub async fn add_message(&self, message: Message) -> Result<(), String> {
         // Persist message
         self.storage.chat_history.create_message(&message).await?;
 
+        if message.parent_id.is_some() {
+            return self.refresh_active_session(&message.session_id).await;
+        }
+
         // Update in-memory state
         let active = self.active_sessions.read().await;
         if let Some(state) = active.get(&message.session_id) {
             let mut state = state.write().await;
+            state.session.head_message_id = Some(message.id.clone());
             state.message_count += 1;
         }
 
         Ok(())
     }
 
+    /// Copy a session's history up to `message_id` (its head by default) into
+    /// a new session that records where it was forked from
+    pub async fn fork_session(
+        &self,
+        session_id: &str,
+        message_id: Option<&str>,
+    ) -> Result<Session, String> {
+        let source = self
+            .storage
+            .chat_history
+            .get_session(session_id)
+            .await?
+            .ok_or_else(|| format!("Session '{}' not found", session_id))?;
+        let fork_point = match message_id {
+            Some(id) => id.to_string(),
+            None => source
+                .head_message_id
+                .clone()
+                .ok_or_else(|| format!("Session '{}' has no messages to fork", session_id))?,
+        };
+
+        let history = self
+            .storage
+            .chat_history
+            .get_branch(session_id, &fork_point)
+            .await?;
+        if history.last().map(|m| m.id.as_str()) != Some(fork_point.as_str()) {
+            return Err(format!(
+                "Message '{}' not found in session '{}'",
+                fork_point, session_id
+            ));
+        }
+
+        let settings = self.storage.settings.get_task_settings(session_id).await?;
+        let session = self
+            .insert_session(
+                source.project_id,
+                source.title,
+                settings,
+                Some(SessionFork {
+                    session_id: session_id.to_string(),
+                    message_id: fork_point,
+                }),
+            )
+            .await?;
+
+        let mut parent_id = None;
+        for message in history {
+            let copy = Message {
+                id: format!("msg_{}", uuid::Uuid::new_v4().to_string().replace("-", "")),
+                session_id: session.id.clone(),
+                parent_id: parent_id.clone(),
+                ..message
+            };
+            self.storage.chat_history.create_message(&copy).await?;
+            parent_id = Some(copy.id);
+        }
+
+        self.refresh_active_session(&session.id).await?;
+        self.get_session(&session.id)
+            .await?
+            .ok_or_else(|| format!("Session '{}' not found", session.id))
+    }
+
+    /// Rewind the selected branch to just before a user message, so the next
+    /// message becomes an alternative to it
+    pub async fn branch_before(&self, session_id: &str, message_id: &str) -> Result<(), String> {
+        let message = self
+            .storage
+            .chat_history
+            .get_all_messages(session_id)
+            .await?
+            .into_iter()
+            .find(|m| m.id == message_id)
+            .ok_or_else(|| {
+                format!(
+                    "Message '{}' not found in session '{}'",
+                    message_id, session_id
+                )
+            })?;
+        if message.role != MessageRole::User {
+            return Err("Only user messages can be edited".to_string());
+        }
+
+        self.storage
+            .chat_history
+            .set_head_message_id(session_id, message.parent_id.as_deref())
+            .await?;
+        self.refresh_active_session(session_id).await
+    }
+
+    /// Select the branch through `message_id`, returning its messages
+    pub async fn select_branch(
+        &self,
+        session_id: &str,
+        message_id: &str,
+    ) -> Result<Vec<Message>, String> {
+        self.storage
+            .chat_history
+            .select_branch(session_id, message_id)
+            .await?;
+        self.refresh_active_session(session_id).await?;
+        self.get_messages(session_id, None, None).await
+    }
+
+    /// List the branches of a session's message tree
+    pub async fn list_branches(&self, session_id: &str) -> Result<Vec<MessageBranch>, String> {
+        self.storage.chat_history.list_branches(session_id).await
+    }
+
+    /// Reload an active session's stored state after its branch changed
+    async fn refresh_active_session(&self, session_id: &str) -> Result<(), String> {
+        let active = self.active_sessions.read().await;
+        let Some(state) = active.get(session_id) else {
+            return Ok(());
+        };
+
+        let session = self.storage.chat_history.get_session(session_id).await?;
+        let message_count = self
+            .storage
+            .chat_history
+            .get_messages(session_id, None, None)
+            .await?
+            .len();
+
+        let mut state = state.write().await;
+        if let Some(session) = session {
+            state.session.head_message_id = session.head_message_id;
+            state.session.forked_from = session.forked_from;
+        }
+        state.message_count = message_count;
+        Ok(())
+    }
+
     /// Get messages for a session
     pub async fn get_messages(
         &self,
@@ -439,4 +592,119 @@ mod tests {
         assert_eq!(state.session.status, SessionStatus::Running);
         assert_eq!(state.session.last_event_id, Some("evt-1".to_string()));
     }
+
+    fn text_message(session_id: &str, role: MessageRole, text: &str) -> Message {
+        Message {
+            id: format!("msg_{}", text),
+            session_id: session_id.to_string(),
+            role,
+            content: MessageContent::Text {
+                text: text.to_string(),
+            },
+            created_at: chrono::Utc::now().timestamp(),
+            tool_call_id: None,
+            parent_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fork_session() {
+        let (manager, _temp) = create_test_manager().await;
+
+        let session = manager
+            .create_session(None, Some("Original".to_string()), None)
+            .await
+            .unwrap();
+        for (role, text) in [
+            (MessageRole::User, "q1"),
+            (MessageRole::Assistant, "a1"),
+            (MessageRole::User, "q2"),
+        ] {
+            manager
+                .add_message(text_message(&session.id, role, text))
+                .await
+                .unwrap();
+        }
+
+        let fork = manager
+            .fork_session(&session.id, Some("msg_a1"))
+            .await
+            .expect("Failed to fork session");
+        assert_ne!(fork.id, session.id);
+        assert_eq!(fork.title.as_deref(), Some("Original"));
+        assert_eq!(
+            fork.forked_from,
+            Some(SessionFork {
+                session_id: session.id.clone(),
+                message_id: "msg_a1".to_string(),
+            })
+        );
+
+        let copied = manager.get_messages(&fork.id, None, None).await.unwrap();
+        assert_eq!(copied.len(), 2);
+        assert_eq!(copied[1].parent_id.as_deref(), Some(copied[0].id.as_str()));
+        assert_eq!(fork.head_message_id.as_deref(), Some(copied[1].id.as_str()));
+        assert_eq!(
+            manager
+                .get_session_state(&fork.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .message_count,
+            2
+        );
+
+        // The source keeps its history
+        assert_eq!(
+            manager
+                .get_messages(&session.id, None, None)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        assert!(manager
+            .fork_session(&session.id, Some("msg_missing"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_edit_message_as_branch() {
+        let (manager, _temp) = create_test_manager().await;
+
+        let session = manager.create_session(None, None, None).await.unwrap();
+        manager
+            .add_message(text_message(&session.id, MessageRole::User, "q1"))
+            .await
+            .unwrap();
+        manager
+            .add_message(text_message(&session.id, MessageRole::Assistant, "a1"))
+            .await
+            .unwrap();
+
+        assert!(manager.branch_before(&session.id, "msg_a1").await.is_err());
+        manager.branch_before(&session.id, "msg_q1").await.unwrap();
+        manager
+            .add_message(text_message(&session.id, MessageRole::User, "q1-edited"))
+            .await
+            .unwrap();
+
+        let messages = manager.get_messages(&session.id, None, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "msg_q1-edited");
+
+        let branches = manager.list_branches(&session.id).await.unwrap();
+        assert_eq!(branches.len(), 2);
+
+        let selected = manager.select_branch(&session.id, "msg_q1").await.unwrap();
+        assert_eq!(selected.len(), 2);
+        let state = manager
+            .get_session_state(&session.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.session.head_message_id.as_deref(), Some("msg_a1"));
+        assert_eq!(state.message_count, 2);
+    }
 }
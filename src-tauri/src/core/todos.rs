@@ -0,0 +1,272 @@
+//! Session Todo Lists
+//!
+//! Backs the `todoWrite` tool. Each session keeps one ordered todo list in
+//! chat_history.db; a write either replaces the list or merges items into it by
+//! id, and every change is announced with `RuntimeEvent::TodosUpdated`.
+
+use crate::core::types::{EventSender, RuntimeEvent};
+use crate::storage::models::{TodoItem, TodoStatus};
+use crate::storage::ChatHistoryRepository;
+use std::collections::HashSet;
+
+/// Persistent per-session todo lists
+#[derive(Clone)]
+pub struct TodoStore {
+    chat_history: ChatHistoryRepository,
+    event_sender: EventSender,
+}
+
+impl std::fmt::Debug for TodoStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TodoStore").finish_non_exhaustive()
+    }
+}
+
+impl TodoStore {
+    pub fn new(chat_history: ChatHistoryRepository, event_sender: EventSender) -> Self {
+        Self {
+            chat_history,
+            event_sender,
+        }
+    }
+
+    /// Current todo list of a session
+    pub async fn list(&self, session_id: &str) -> Result<Vec<TodoItem>, String> {
+        self.chat_history.get_todos(session_id).await
+    }
+
+    /// Write a session's todo list and return the resulting list
+    ///
+    /// With `merge`, items replace existing items with the same id and new items
+    /// are appended; otherwise `todos` becomes the whole list.
+    pub async fn write(
+        &self,
+        session_id: &str,
+        todos: Vec<TodoItem>,
+        merge: bool,
+    ) -> Result<Vec<TodoItem>, String> {
+        validate_todos(&todos)?;
+
+        let todos = if merge {
+            merge_todos(self.list(session_id).await?, todos)
+        } else {
+            todos
+        };
+
+        self.chat_history.replace_todos(session_id, &todos).await?;
+
+        let _ = self.event_sender.send(RuntimeEvent::TodosUpdated {
+            session_id: session_id.to_string(),
+            todos: todos.clone(),
+        });
+
+        Ok(todos)
+    }
+}
+
+/// Parse the `todos` argument of a `todoWrite` call
+pub fn parse_todos(value: Option<&serde_json::Value>) -> Result<Vec<TodoItem>, String> {
+    let value = value.ok_or("Missing required parameter 'todos'")?;
+    serde_json::from_value(value.clone()).map_err(|e| format!("Invalid todos: {}", e))
+}
+
+/// Reject items without content and duplicate ids
+fn validate_todos(todos: &[TodoItem]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for todo in todos {
+        if todo.id.trim().is_empty() {
+            return Err("Todo items must have an id".to_string());
+        }
+        if todo.content.trim().is_empty() {
+            return Err(format!("Todo '{}' has no content", todo.id));
+        }
+        if !ids.insert(todo.id.as_str()) {
+            return Err(format!("Duplicate todo id '{}'", todo.id));
+        }
+    }
+    Ok(())
+}
+
+/// Merge `updates` into `existing` by id, keeping the existing order
+pub fn merge_todos(existing: Vec<TodoItem>, updates: Vec<TodoItem>) -> Vec<TodoItem> {
+    let mut merged = existing;
+    for update in updates {
+        match merged.iter_mut().find(|todo| todo.id == update.id) {
+            Some(todo) => *todo = update,
+            None => merged.push(update),
+        }
+    }
+    merged
+}
+
+/// Render a todo list as a markdown checklist
+pub fn render_todos(todos: &[TodoItem]) -> String {
+    if todos.is_empty() {
+        return "No todos.".to_string();
+    }
+
+    let completed = todos
+        .iter()
+        .filter(|todo| todo.status == TodoStatus::Completed)
+        .count();
+    let mut rendered = format!("Todos ({}/{} completed):\n", completed, todos.len());
+
+    for todo in todos {
+        let mark = match todo.status {
+            TodoStatus::Pending => "[ ]",
+            TodoStatus::InProgress => "[~]",
+            TodoStatus::Completed => "[x]",
+        };
+        rendered.push_str(&format!(
+            "- {} {} ({}, {})\n",
+            mark,
+            todo.content,
+            todo.priority.as_str(),
+            todo.id
+        ));
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::TodoPriority;
+
+    fn todo(id: &str, content: &str, status: TodoStatus) -> TodoItem {
+        TodoItem {
+            id: id.to_string(),
+            content: content.to_string(),
+            status,
+            priority: TodoPriority::Medium,
+        }
+    }
+
+    #[test]
+    fn test_parse_todos_defaults_status_and_priority() {
+        let input = serde_json::json!([
+            {"id": "1", "content": "Read the code"},
+            {"id": "2", "content": "Fix it", "status": "in_progress", "priority": "high"}
+        ]);
+        let todos = parse_todos(Some(&input)).unwrap();
+
+        assert_eq!(todos[0].status, TodoStatus::Pending);
+        assert_eq!(todos[0].priority, TodoPriority::Medium);
+        assert_eq!(todos[1].status, TodoStatus::InProgress);
+        assert_eq!(todos[1].priority, TodoPriority::High);
+
+        assert!(parse_todos(None).is_err());
+        let bad_status = serde_json::json!([{"id": "1", "content": "x", "status": "done"}]);
+        assert!(parse_todos(Some(&bad_status)).is_err());
+    }
+
+    #[test]
+    fn test_validate_todos() {
+        assert!(validate_todos(&[todo("1", "a", TodoStatus::Pending)]).is_ok());
+        assert!(validate_todos(&[todo("1", "  ", TodoStatus::Pending)]).is_err());
+        assert!(validate_todos(&[
+            todo("1", "a", TodoStatus::Pending),
+            todo("1", "b", TodoStatus::Pending)
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_merge_todos_updates_in_place_and_appends() {
+        let existing = vec![
+            todo("1", "Read", TodoStatus::Completed),
+            todo("2", "Fix", TodoStatus::Pending),
+        ];
+        let merged = merge_todos(
+            existing,
+            vec![
+                todo("3", "Test", TodoStatus::Pending),
+                todo("2", "Fix", TodoStatus::InProgress),
+            ],
+        );
+
+        let ids: Vec<&str> = merged.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2", "3"]);
+        assert_eq!(merged[1].status, TodoStatus::InProgress);
+    }
+
+    #[test]
+    fn test_render_todos() {
+        assert_eq!(render_todos(&[]), "No todos.");
+
+        let rendered = render_todos(&[
+            todo("1", "Read", TodoStatus::Completed),
+            todo("2", "Fix", TodoStatus::InProgress),
+            todo("3", "Test", TodoStatus::Pending),
+        ]);
+        assert!(rendered.starts_with("Todos (1/3 completed):"));
+        assert!(rendered.contains("- [x] Read (medium, 1)"));
+        assert!(rendered.contains("- [~] Fix (medium, 2)"));
+        assert!(rendered.contains("- [ ] Test (medium, 3)"));
+    }
+
+    #[tokio::test]
+    async fn test_store_write_persists_and_emits_event() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = crate::storage::Storage::new(
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("attachments"),
+        )
+        .await
+        .unwrap();
+        let session = crate::core::session::SessionManager::new(storage.clone())
+            .create_session(None, None, None)
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let store = TodoStore::new(storage.chat_history.clone(), tx);
+
+        store
+            .write(
+                &session.id,
+                vec![
+                    todo("1", "Read", TodoStatus::Pending),
+                    todo("2", "Fix", TodoStatus::Pending),
+                ],
+                false,
+            )
+            .await
+            .unwrap();
+        let todos = store
+            .write(
+                &session.id,
+                vec![todo("1", "Read", TodoStatus::Completed)],
+                true,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(todos.len(), 2);
+        assert_eq!(store.list(&session.id).await.unwrap(), todos);
+
+        let mut updates = Vec::new();
+        while let Ok(RuntimeEvent::TodosUpdated { session_id, todos }) = rx.try_recv() {
+            assert_eq!(session_id, session.id);
+            updates.push(todos);
+        }
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[1][0].status, TodoStatus::Completed);
+
+        // Invalid writes leave the list untouched
+        let current = store
+            .write(
+                &session.id,
+                vec![todo("3", "Test", TodoStatus::InProgress)],
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(store
+            .write(&session.id, vec![todo("1", "", TodoStatus::Pending)], false)
+            .await
+            .is_err());
+        assert_eq!(store.list(&session.id).await.unwrap(), current);
+        assert_eq!(current[0].id, "3");
+    }
+}
@@ -5,6 +5,7 @@
 
 use crate::core::types::ToolDefinition;
 use serde_json::json;
+use std::collections::HashMap;
 
 /// Tool category for dependency analysis
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,6 +26,14 @@ pub struct ToolMetadata {
     pub render_doing_ui: bool,
 }
 
+/// Get tool metadata keyed by canonical tool name
+pub fn get_tool_metadata() -> HashMap<String, ToolMetadata> {
+    get_tool_definitions()
+        .into_iter()
+        .map(|(def, metadata)| (def.name, metadata))
+        .collect()
+}
+
 /// Get all canonical tool definitions with metadata
 pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
     vec![
@@ -32,7 +41,8 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "readFile".to_string(),
-                description: "Read the contents of a file at the specified path.".to_string(),
+                description: "Read the contents of a file at the specified path. Lines are returned numbered, 1000 at a time by default; use offset and limit to page through long files. Lines over 2000 characters are cut off. Images are returned for you to view, other binary files are not read."
+                    .to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -46,7 +56,7 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
                         },
                         "limit": {
                             "type": "integer",
-                            "description": "The number of lines to read"
+                            "description": "The number of lines to read (default 1000)"
                         }
                     },
                     "required": ["file_path"]
@@ -95,7 +105,10 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "editFile".to_string(),
-                description: "Make edits to an existing file using search and replace.".to_string(),
+                description: "Make edits to an existing file using search and replace. Each \
+                    old_string must match exactly one location unless replace_all is set. Edits \
+                    are applied in order and either all succeed or none are written."
+                    .to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -115,6 +128,10 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
                                     "new_string": {
                                         "type": "string",
                                         "description": "The text to replace with"
+                                    },
+                                    "replace_all": {
+                                        "type": "boolean",
+                                        "description": "Replace every occurrence of old_string"
                                     }
                                 },
                                 "required": ["old_string", "new_string"]
@@ -137,20 +154,42 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "codeSearch".to_string(),
-                description: "Fast text search across the codebase using ripgrep.".to_string(),
+                description: "Fast text search across the codebase using ripgrep. Respects .gitignore and returns matching lines grouped by file.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
-                        "query": {
+                        "pattern": {
                             "type": "string",
-                            "description": "The search query (regex supported)"
+                            "description": "The regex pattern to search for"
                         },
                         "path": {
                             "type": "string",
-                            "description": "The directory to search in"
+                            "description": "The directory to search in (default: workspace root)"
+                        },
+                        "file_types": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "File extensions to search, e.g. [\"ts\", \"rs\"] (default: code files)"
+                        },
+                        "exclude_dirs": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Directory names to skip in addition to the defaults"
+                        },
+                        "case_sensitive": {
+                            "type": "boolean",
+                            "description": "Match case exactly (default false)"
+                        },
+                        "context_lines": {
+                            "type": "integer",
+                            "description": "Lines to show before and after each match (default 0)"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum number of files to return matches from (default 50)"
                         }
                     },
-                    "required": ["query"]
+                    "required": ["pattern"]
                 }),
                 requires_approval: false,
             },
@@ -165,7 +204,7 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "glob".to_string(),
-                description: "Fast file pattern matching tool that works with any codebase size."
+                description: "Fast file pattern matching tool that works with any codebase size. Respects .gitignore and returns paths sorted by modification time."
                     .to_string(),
                 parameters: json!({
                     "type": "object",
@@ -176,7 +215,20 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
                         },
                         "path": {
                             "type": "string",
-                            "description": "The directory to search in"
+                            "description": "The directory to search in (default: workspace root)"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum number of paths to return (default 100)"
+                        },
+                        "case_sensitive": {
+                            "type": "boolean",
+                            "description": "Match case exactly (default true)"
+                        },
+                        "exclude_dirs": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Directory names to skip in addition to the defaults"
                         }
                     },
                     "required": ["pattern"]
@@ -194,16 +246,27 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "listFiles".to_string(),
-                description: "List files and directories in the specified path.".to_string(),
+                description: "List files and directories in the specified path, grouped by directory. Respects .gitignore.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
-                        "path": {
+                        "directory_path": {
                             "type": "string",
-                            "description": "The path to list"
+                            "description": "The directory to list (default: workspace root)"
+                        },
+                        "max_depth": {
+                            "type": "integer",
+                            "description": "How many levels deep to list (default 3)"
+                        },
+                        "recursive": {
+                            "type": "boolean",
+                            "description": "List subdirectories too (default true)"
+                        },
+                        "max_files": {
+                            "type": "integer",
+                            "description": "Maximum number of entries to return (default 1000)"
                         }
-                    },
-                    "required": ["path"]
+                    }
                 }),
                 requires_approval: false,
             },
@@ -249,14 +312,14 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
             ToolDefinition {
                 name: "lsp".to_string(),
                 description:
-                    "Language Server Protocol operations (go to definition, find references, etc.)."
+                    "Language Server Protocol operations (go to definition, find references, hover, symbols, diagnostics). Starts the language server for the file's language on first use."
                         .to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
                         "operation": {
                             "type": "string",
-                            "enum": ["goto_definition", "find_references", "hover", "document_symbols", "workspace_symbols"],
+                            "enum": ["goto_definition", "find_references", "hover", "document_symbols", "workspace_symbols", "diagnostics"],
                             "description": "The LSP operation to perform"
                         },
                         "file_path": {
@@ -270,9 +333,13 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
                         "character": {
                             "type": "integer",
                             "description": "The character position (0-indexed)"
+                        },
+                        "query": {
+                            "type": "string",
+                            "description": "Symbol query for workspace_symbols"
                         }
                     },
-                    "required": ["operation", "file_path", "line", "character"]
+                    "required": ["operation", "file_path"]
                 }),
                 requires_approval: false,
             },
@@ -288,7 +355,7 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "webFetch".to_string(),
-                description: "Fetch content from a URL.".to_string(),
+                description: "Fetch content from a public http(s) URL. HTML is returned as markdown and JSON is pretty-printed; binary content is refused.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -312,13 +379,17 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "webSearch".to_string(),
-                description: "Search the web for information.".to_string(),
+                description: "Search the web for information. Returns title, url and snippet for each result.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
                         "query": {
                             "type": "string",
                             "description": "The search query"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum number of results to return (default 10)"
                         }
                     },
                     "required": ["query"]
@@ -337,7 +408,7 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "callAgent".to_string(),
-                description: "Call another agent to perform a specialized task.".to_string(),
+                description: "Call another agent to perform a specialized task. The agent runs in its own session with its own prompt, model and tools, and its final answer is returned.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -366,7 +437,7 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "todoWrite".to_string(),
-                description: "Write or update todo items for task tracking.".to_string(),
+                description: "Write or update the session's todo list for task tracking. Replaces the list unless merge is set, in which case items are updated by id and new items appended. Returns the current list.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -387,10 +458,19 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
                                         "type": "string",
                                         "enum": ["pending", "in_progress", "completed"],
                                         "description": "The todo status"
+                                    },
+                                    "priority": {
+                                        "type": "string",
+                                        "enum": ["high", "medium", "low"],
+                                        "description": "The todo priority (default: medium)"
                                     }
                                 },
                                 "required": ["id", "content", "status"]
                             }
+                        },
+                        "merge": {
+                            "type": "boolean",
+                            "description": "Merge the items into the existing list by id instead of replacing it (default: false)"
                         }
                     },
                     "required": ["todos"]
@@ -409,7 +489,7 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
         (
             ToolDefinition {
                 name: "askUserQuestions".to_string(),
-                description: "Ask the user questions to gather required information.".to_string(),
+                description: "Ask the user questions to gather required information. The task pauses until the user answers, and the answers are returned per question.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -425,6 +505,15 @@ pub fn get_tool_definitions() -> Vec<(ToolDefinition, ToolMetadata)> {
                                     "question": {
                                         "type": "string",
                                         "description": "The question text"
+                                    },
+                                    "options": {
+                                        "type": "array",
+                                        "items": { "type": "string" },
+                                        "description": "Suggested answers; the user may also answer freely"
+                                    },
+                                    "multi_select": {
+                                        "type": "boolean",
+                                        "description": "Whether several options may be chosen (default false)"
                                     }
                                 },
                                 "required": ["id", "question"]
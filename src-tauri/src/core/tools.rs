@@ -3,9 +3,15 @@
 //! Provides a registry of available tools and dispatch mechanism for tool execution.
 //! Tools execute on the backend host (filesystem, git, shell, LSP, search).
 
+use crate::core::checkpoints::CheckpointStore;
+use crate::core::todos::{self, TodoStore};
+use crate::core::tool_definitions::{get_tool_metadata, ToolCategory, ToolMetadata};
+use crate::core::tool_dependency_analyzer::{ExecutionGroup, ToolDependencyAnalyzer};
 use crate::core::types::*;
+use crate::core::{file_edit, web_fetch, web_search, workspace_search};
 use crate::platform::types::PlatformResult;
 use crate::storage::models::*;
+use futures::StreamExt;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -18,6 +24,24 @@ pub struct ToolContext {
     pub workspace_root: String,
     pub worktree_path: Option<String>,
     pub settings: TaskSettings,
+    /// Runner for `callAgent`; unset when sub-agents are unavailable
+    pub sub_agents: Option<Arc<dyn SubAgentRunner>>,
+    /// Session todo lists for `todoWrite`; unset when unavailable
+    pub todos: Option<TodoStore>,
+    /// Pre-images of files written by tools; unset when not recorded
+    pub checkpoints: Option<CheckpointStore>,
+}
+
+/// Runs a named agent as a child task on behalf of the `callAgent` tool
+#[async_trait::async_trait]
+pub trait SubAgentRunner: Send + Sync + std::fmt::Debug {
+    /// Run `agent` on `prompt` to completion and return its final answer
+    async fn run_sub_agent(
+        &self,
+        agent: &str,
+        prompt: &str,
+        parent: &ToolContext,
+    ) -> Result<SubAgentOutput, String>;
 }
 
 /// Result of tool execution
@@ -28,6 +52,16 @@ pub struct ToolExecutionOutput {
     pub error: Option<String>,
 }
 
+impl<T: serde::Serialize> From<PlatformResult<T>> for ToolExecutionOutput {
+    fn from(result: PlatformResult<T>) -> Self {
+        Self {
+            success: result.success,
+            data: serde_json::to_value(&result.data).unwrap_or_default(),
+            error: result.error,
+        }
+    }
+}
+
 /// Tool handler function type
 pub type ToolHandler = Arc<
     dyn Fn(ToolRequest, ToolContext) -> futures::future::BoxFuture<'static, ToolExecutionOutput>
@@ -94,6 +128,36 @@ impl ToolRegistry {
         tools.get(&normalized_name).cloned()
     }
 
+    /// Create a registry holding only the named tools of this one
+    ///
+    /// Names are normalized; unknown names are ignored.
+    pub async fn restricted_to(&self, names: &[String]) -> ToolRegistry {
+        let allowed: std::collections::HashSet<String> = names
+            .iter()
+            .map(|name| crate::core::tool_name_normalizer::normalize_tool_name(name))
+            .collect();
+
+        let tools = self.tools.read().await;
+        let handlers = self.handlers.read().await;
+
+        ToolRegistry {
+            tools: RwLock::new(
+                tools
+                    .iter()
+                    .filter(|(name, _)| allowed.contains(*name))
+                    .map(|(name, def)| (name.clone(), def.clone()))
+                    .collect(),
+            ),
+            handlers: RwLock::new(
+                handlers
+                    .iter()
+                    .filter(|(name, _)| allowed.contains(*name))
+                    .map(|(name, handler)| (name.clone(), handler.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
     /// List all registered tools
     pub async fn list_tools(&self) -> Vec<ToolDefinition> {
         let tools = self.tools.read().await;
@@ -203,10 +267,7 @@ impl ToolDispatcher {
             ..request
         };
 
-        // Check if tool requires approval
-        let requires_approval = self.registry.requires_approval(&request.name).await;
-
-        if requires_approval && !auto_approve {
+        if self.needs_approval(&request.name, auto_approve).await {
             // Return pending for approval
             Ok(ToolDispatchResult::PendingApproval(request))
         } else {
@@ -216,10 +277,128 @@ impl ToolDispatcher {
         }
     }
 
+    /// Whether a tool call must wait for user approval before executing
+    pub async fn needs_approval(&self, name: &str, auto_approve: bool) -> bool {
+        !auto_approve && self.registry.requires_approval(name).await
+    }
+
+    /// Whether a tool call's result is supplied by the user rather than executed
+    pub fn needs_user_result(&self, name: &str) -> bool {
+        crate::core::tool_name_normalizer::normalize_tool_name(name) == "askUserQuestions"
+    }
+
     /// Execute a tool that was pending approval
     pub async fn execute_approved(&self, request: ToolRequest, context: ToolContext) -> ToolResult {
         self.registry.execute(request, context).await
     }
+
+    /// Execute a batch of tool calls in the order the model issued them
+    ///
+    /// Runs of adjacent read-only calls execute concurrently following their
+    /// dependency plan. Every other call is an ordering barrier and runs on its
+    /// own, so a read issued after an edit sees the edit. Results are returned
+    /// in the order of `requests`.
+    pub async fn execute_batch(
+        &self,
+        requests: Vec<ToolRequest>,
+        context: ToolContext,
+    ) -> Vec<ToolResult> {
+        let requests: Vec<ToolRequest> = requests
+            .into_iter()
+            .map(|request| ToolRequest {
+                name: crate::core::tool_name_normalizer::normalize_tool_name(&request.name),
+                ..request
+            })
+            .collect();
+        let order: Vec<(ToolCallId, String)> = requests
+            .iter()
+            .map(|request| (request.tool_call_id.clone(), request.name.clone()))
+            .collect();
+
+        let metadata = get_tool_metadata();
+        let mut results: HashMap<ToolCallId, ToolResult> = HashMap::new();
+        let mut reads = Vec::new();
+        for request in requests {
+            let is_read = matches!(
+                metadata.get(&request.name).map(|m| &m.category),
+                Some(ToolCategory::Read)
+            );
+            if is_read {
+                reads.push(request);
+                continue;
+            }
+
+            for result in self
+                .execute_reads(std::mem::take(&mut reads), &metadata, &context)
+                .await
+            {
+                results.insert(result.tool_call_id.clone(), result);
+            }
+            let result = self.registry.execute(request, context.clone()).await;
+            results.insert(result.tool_call_id.clone(), result);
+        }
+        for result in self.execute_reads(reads, &metadata, &context).await {
+            results.insert(result.tool_call_id.clone(), result);
+        }
+
+        order
+            .into_iter()
+            .map(|(tool_call_id, name)| {
+                results.remove(&tool_call_id).unwrap_or_else(|| ToolResult {
+                    tool_call_id,
+                    name: Some(name),
+                    success: false,
+                    output: serde_json::Value::Null,
+                    error: Some("Tool call was not executed".to_string()),
+                })
+            })
+            .collect()
+    }
+
+    /// Execute a run of adjacent read-only calls by their dependency plan
+    async fn execute_reads(
+        &self,
+        reads: Vec<ToolRequest>,
+        metadata: &HashMap<String, ToolMetadata>,
+        context: &ToolContext,
+    ) -> Vec<ToolResult> {
+        let plan = ToolDependencyAnalyzer::new().analyze(reads, metadata);
+
+        let mut results = Vec::new();
+        for stage in plan.stages {
+            let stage_results = futures::future::join_all(
+                stage
+                    .groups
+                    .into_iter()
+                    .map(|group| self.execute_group(group, context)),
+            )
+            .await;
+            results.extend(stage_results.into_iter().flatten());
+        }
+        results
+    }
+
+    /// Execute the tools of a single execution group
+    async fn execute_group(&self, group: ExecutionGroup, context: &ToolContext) -> Vec<ToolResult> {
+        if group.concurrent {
+            let limit = group.max_concurrency.unwrap_or(group.tools.len()).max(1);
+            futures::stream::iter(
+                group
+                    .tools
+                    .into_iter()
+                    .map(|request| self.registry.execute(request, context.clone())),
+            )
+            .buffered(limit)
+            .collect()
+            .await
+        } else {
+            let mut results = Vec::with_capacity(group.tools.len());
+            for request in group.tools {
+                results.push(self.registry.execute(request, context.clone()).await);
+            }
+            results
+        }
+    }
 }
 
 /// Result of tool dispatch
@@ -231,6 +410,24 @@ pub enum ToolDispatchResult {
     PendingApproval(ToolRequest),
 }
 
+/// Record the content of the file a tool call is about to write, when the
+/// context keeps checkpoints
+async fn checkpoint_before_write(
+    ctx: &ToolContext,
+    tool_call_id: &str,
+    path: &str,
+    platform_ctx: &crate::platform::types::PlatformContext,
+) -> Result<(), String> {
+    let Some(checkpoints) = &ctx.checkpoints else {
+        return Ok(());
+    };
+    let target = crate::platform::paths::resolve_path(path, platform_ctx)?;
+    checkpoints
+        .snapshot(&ctx.task_id, &ctx.session_id, tool_call_id, &target)
+        .await
+        .map_err(|e| format!("Failed to checkpoint '{}': {}", path, e))
+}
+
 /// Execute a tool by name using the platform
 async fn execute_tool_by_name(
     name: &str,
@@ -250,7 +447,22 @@ async fn execute_tool_by_name(
                 .and_then(|v| v.as_str())
                 .or_else(|| request.input.get("path").and_then(|v| v.as_str()))
                 .unwrap_or("");
-            let platform_result = platform.filesystem.read_file(path, &platform_ctx).await;
+            let line_param = |name: &str| {
+                request
+                    .input
+                    .get(name)
+                    .and_then(|v| v.as_u64())
+                    .map(|n| n as usize)
+            };
+            let platform_result = platform
+                .filesystem
+                .read_file_page(
+                    path,
+                    line_param("offset"),
+                    line_param("limit"),
+                    &platform_ctx,
+                )
+                .await;
             ToolExecutionOutput {
                 success: platform_result.success,
                 data: serde_json::to_value(&platform_result.data).unwrap_or_default(),
@@ -269,148 +481,122 @@ async fn execute_tool_by_name(
                 .get("content")
                 .and_then(|v| v.as_str())
                 .unwrap_or("");
-            let platform_result = platform
-                .filesystem
-                .write_file(path, content, &platform_ctx)
-                .await;
-            ToolExecutionOutput {
-                success: platform_result.success,
-                data: serde_json::to_value(&platform_result.data).unwrap_or_default(),
-                error: platform_result.error,
+            match checkpoint_before_write(&ctx, &request.tool_call_id, path, &platform_ctx).await {
+                Ok(()) => {
+                    let platform_result = platform
+                        .filesystem
+                        .write_file(path, content, &platform_ctx)
+                        .await;
+                    ToolExecutionOutput {
+                        success: platform_result.success,
+                        data: serde_json::to_value(&platform_result.data).unwrap_or_default(),
+                        error: platform_result.error,
+                    }
+                }
+                Err(e) => ToolExecutionOutput {
+                    success: false,
+                    data: serde_json::Value::Null,
+                    error: Some(e),
+                },
             }
         }
         "editFile" | "edit_file" => {
-            // Edit file using platform
             let path = request
                 .input
                 .get("file_path")
                 .and_then(|v| v.as_str())
                 .or_else(|| request.input.get("path").and_then(|v| v.as_str()))
                 .unwrap_or("");
-            if let Some(edits) = request.input.get("edits").and_then(|v| v.as_array()) {
-                // Read current content
+
+            let edit = async {
+                let edits = file_edit::parse_edits(&request.input)?;
                 let read_result = platform.filesystem.read_file(path, &platform_ctx).await;
-                if read_result.success {
-                    if let Some(content) = read_result.data {
-                        let mut new_content = content;
-                        for edit in edits {
-                            if let (Some(old_str), Some(new_str)) = (
-                                edit.get("old_string").and_then(|v| v.as_str()),
-                                edit.get("new_string").and_then(|v| v.as_str()),
-                            ) {
-                                new_content = new_content.replace(old_str, new_str);
-                            }
-                        }
-                        let write_result = platform
-                            .filesystem
-                            .write_file(path, &new_content, &platform_ctx)
-                            .await;
-                        ToolExecutionOutput {
-                            success: write_result.success,
-                            data: serde_json::to_value(&write_result.data).unwrap_or_default(),
-                            error: write_result.error,
-                        }
-                    } else {
-                        ToolExecutionOutput {
-                            success: false,
-                            data: serde_json::Value::Null,
-                            error: Some("Failed to read file content".to_string()),
-                        }
-                    }
-                } else {
-                    ToolExecutionOutput {
-                        success: false,
-                        data: serde_json::Value::Null,
-                        error: Some("Failed to read file for editing".to_string()),
+                let content = match read_result.data {
+                    Some(content) if read_result.success => content,
+                    _ => {
+                        return Err(read_result
+                            .error
+                            .unwrap_or_else(|| "Failed to read file for editing".to_string()))
                     }
+                };
+
+                let outcome = file_edit::apply_edits(&content, &edits)?;
+                let diff = crate::git::diff::diff_text(path, &content, &outcome.content)
+                    .map_err(|e| format!("Failed to diff edit: {}", e))?;
+
+                checkpoint_before_write(&ctx, &request.tool_call_id, path, &platform_ctx).await?;
+                let write_result = platform
+                    .filesystem
+                    .write_file(path, &outcome.content, &platform_ctx)
+                    .await;
+                if !write_result.success {
+                    return Err(write_result
+                        .error
+                        .unwrap_or_else(|| "Failed to write file".to_string()));
                 }
-            } else {
-                ToolExecutionOutput {
-                    success: false,
-                    data: serde_json::Value::Null,
-                    error: Some("No edits provided".to_string()),
-                }
-            }
-        }
-        "glob" => {
-            // Glob implementation using walkdir
-            if let Some(pattern) = request.input.get("pattern").and_then(|v| v.as_str()) {
-                use std::path::Path;
-
-                // Simple glob implementation - convert pattern to suffix matching
-                let files: Vec<String> = std::fs::read_dir(&ctx.workspace_root)
-                    .ok()
-                    .into_iter()
-                    .flatten()
-                    .filter_map(|e| e.ok())
-                    .filter(|e| {
-                        let name = e.file_name().to_string_lossy().to_string();
-                        // Very simple pattern matching
-                        if pattern.starts_with("**/*.") {
-                            let ext = &pattern[4..]; // Get extension after "**/*."
-                            name.ends_with(ext)
-                        } else if pattern.starts_with("*.") {
-                            let ext = &pattern[2..]; // Get extension after "*."
-                            name.ends_with(ext)
-                        } else {
-                            name.contains(pattern)
-                        }
-                    })
-                    .map(|e| e.path().to_string_lossy().to_string())
-                    .collect();
+                Ok(serde_json::json!({
+                    "filePath": path,
+                    "editsApplied": outcome.matches.len(),
+                    "replacements": outcome.replacements(),
+                    "matches": outcome.matches,
+                    "additions": diff.additions,
+                    "deletions": diff.deletions,
+                    "diff": crate::git::diff::format_unified_diff(&diff),
+                    "hunks": diff.hunks,
+                }))
+            };
 
-                ToolExecutionOutput {
+            match edit.await {
+                Ok(data) => ToolExecutionOutput {
                     success: true,
-                    data: serde_json::json!(files),
+                    data,
                     error: None,
-                }
-            } else {
-                ToolExecutionOutput {
+                },
+                Err(e) => ToolExecutionOutput {
                     success: false,
                     data: serde_json::Value::Null,
-                    error: Some("No pattern provided".to_string()),
-                }
+                    error: Some(e),
+                },
             }
         }
+        "glob" => match workspace_search::glob(&request.input, &platform_ctx).await {
+            Ok(listing) => ToolExecutionOutput {
+                success: true,
+                data: serde_json::json!(listing),
+                error: None,
+            },
+            Err(e) => ToolExecutionOutput {
+                success: false,
+                data: serde_json::Value::Null,
+                error: Some(e),
+            },
+        },
         "codeSearch" | "search_files" => {
-            if let Some(query) = request.input.get("query").and_then(|v| v.as_str()) {
-                let search_result = crate::search::RipgrepSearch::new()
-                    .with_max_results(50)
-                    .search_content(query, &ctx.workspace_root);
-                match search_result {
-                    Ok(results) => ToolExecutionOutput {
-                        success: true,
-                        data: serde_json::json!(results),
-                        error: None,
-                    },
-                    Err(e) => ToolExecutionOutput {
-                        success: false,
-                        data: serde_json::Value::Null,
-                        error: Some(e),
-                    },
-                }
-            } else {
-                ToolExecutionOutput {
+            match workspace_search::code_search(&request.input, &platform_ctx).await {
+                Ok(data) => ToolExecutionOutput {
+                    success: true,
+                    data,
+                    error: None,
+                },
+                Err(e) => ToolExecutionOutput {
                     success: false,
                     data: serde_json::Value::Null,
-                    error: Some("No query provided".to_string()),
-                }
+                    error: Some(e),
+                },
             }
         }
         "listFiles" | "list_files" | "list_directory" => {
-            let path = request
-                .input
-                .get("path")
-                .and_then(|v| v.as_str())
-                .unwrap_or(".");
-            let platform_result = platform
-                .filesystem
-                .list_directory(path, &platform_ctx)
-                .await;
-            ToolExecutionOutput {
-                success: platform_result.success,
-                data: serde_json::to_value(&platform_result.data).unwrap_or_default(),
-                error: platform_result.error,
+            match workspace_search::list_files(&request.input, &platform_ctx).await {
+                Ok(listing) => ToolExecutionOutput {
+                    success: true,
+                    data: serde_json::json!(listing),
+                    error: None,
+                },
+                Err(e) => ToolExecutionOutput {
+                    success: false,
+                    data: serde_json::Value::Null,
+                    error: Some(e),
+                },
             }
         }
         "bash" | "execute_shell" | "executeShell" => {
@@ -428,33 +614,76 @@ async fn execute_tool_by_name(
             }
         }
         "lsp" => {
-            // LSP operations - return placeholder for now
-            ToolExecutionOutput {
-                success: true,
-                data: serde_json::json!({"message": "LSP tool executed"}),
-                error: None,
+            let operation = request
+                .input
+                .get("operation")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let file_path = request
+                .input
+                .get("file_path")
+                .and_then(|v| v.as_str())
+                .or_else(|| request.input.get("path").and_then(|v| v.as_str()))
+                .unwrap_or("");
+            let line = request
+                .input
+                .get("line")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as u32;
+            let character = request
+                .input
+                .get("character")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as u32;
+            let lsp = &platform.lsp;
+
+            match operation {
+                "goto_definition" | "goToDefinition" | "definition" => lsp
+                    .goto_definition(file_path, line, character, &platform_ctx)
+                    .await
+                    .into(),
+                "find_references" | "findReferences" | "references" => lsp
+                    .find_references(file_path, line, character, &platform_ctx)
+                    .await
+                    .into(),
+                "hover" => lsp
+                    .get_hover(file_path, line, character, &platform_ctx)
+                    .await
+                    .into(),
+                "document_symbols" | "documentSymbol" | "documentSymbols" => lsp
+                    .get_document_symbols(file_path, &platform_ctx)
+                    .await
+                    .into(),
+                "workspace_symbols" | "workspaceSymbol" | "workspaceSymbols" => {
+                    let query = request
+                        .input
+                        .get("query")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    lsp.get_workspace_symbols(file_path, query, &platform_ctx)
+                        .await
+                        .into()
+                }
+                "diagnostics" => lsp.get_diagnostics(file_path, &platform_ctx).await.into(),
+                _ => ToolExecutionOutput {
+                    success: false,
+                    data: serde_json::Value::Null,
+                    error: Some(format!("Unknown LSP operation: {}", operation)),
+                },
             }
         }
         "webFetch" | "web_fetch" => {
             if let Some(url) = request.input.get("url").and_then(|v| v.as_str()) {
-                // Perform HTTP fetch
-                match reqwest::get(url).await {
-                    Ok(response) => match response.text().await {
-                        Ok(text) => ToolExecutionOutput {
-                            success: true,
-                            data: serde_json::json!({"content": text}),
-                            error: None,
-                        },
-                        Err(e) => ToolExecutionOutput {
-                            success: false,
-                            data: serde_json::Value::Null,
-                            error: Some(format!("Failed to read response: {}", e)),
-                        },
+                match web_fetch::fetch_url(url, &web_fetch::WebFetchOptions::default()).await {
+                    Ok(result) => ToolExecutionOutput {
+                        success: true,
+                        data: serde_json::to_value(&result).unwrap_or_default(),
+                        error: None,
                     },
                     Err(e) => ToolExecutionOutput {
                         success: false,
                         data: serde_json::Value::Null,
-                        error: Some(format!("Failed to fetch: {}", e)),
+                        error: Some(e),
                     },
                 }
             } else {
@@ -466,30 +695,123 @@ async fn execute_tool_by_name(
             }
         }
         "webSearch" | "web_search" => {
-            // Web search - return placeholder
-            ToolExecutionOutput {
-                success: true,
-                data: serde_json::json!({"results": [], "message": "Web search placeholder"}),
-                error: None,
+            let query = request
+                .input
+                .get("query")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let max_results = request
+                .input
+                .get("max_results")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(web_search::DEFAULT_MAX_RESULTS);
+
+            let search = async {
+                let config = ctx
+                    .settings
+                    .extra
+                    .get(web_search::WEB_SEARCH_SETTING_KEY)
+                    .ok_or_else(|| {
+                        format!(
+                            "Web search is not configured. Set the '{}' setting to a searxng or custom provider.",
+                            web_search::WEB_SEARCH_SETTING_KEY
+                        )
+                    })
+                    .and_then(web_search::WebSearchConfig::from_value)?;
+                let provider = config.build_provider()?;
+                let results =
+                    web_search::run_web_search(provider.as_ref(), query, max_results).await?;
+                Ok::<_, String>((provider.name().to_string(), results))
+            };
+
+            match search.await {
+                Ok((provider, results)) => ToolExecutionOutput {
+                    success: true,
+                    data: serde_json::json!({
+                        "query": query,
+                        "provider": provider,
+                        "results": results,
+                    }),
+                    error: None,
+                },
+                Err(e) => ToolExecutionOutput {
+                    success: false,
+                    data: serde_json::Value::Null,
+                    error: Some(e),
+                },
             }
         }
         "callAgent" | "call_agent" => {
-            // Call agent - return placeholder
-            ToolExecutionOutput {
-                success: true,
-                data: serde_json::json!({"message": "Agent execution placeholder"}),
-                error: None,
+            let agent = request
+                .input
+                .get("agent")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let prompt = request
+                .input
+                .get("prompt")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+
+            let output = match (&ctx.sub_agents, agent.is_empty()) {
+                (_, true) => Err("Missing required parameter 'agent'".to_string()),
+                (None, false) => Err("Sub-agents are not available in this context".to_string()),
+                (Some(runner), false) => runner.run_sub_agent(agent, prompt, &ctx).await,
+            };
+
+            match output {
+                Ok(output) => ToolExecutionOutput {
+                    success: true,
+                    data: serde_json::to_value(&output).unwrap_or_default(),
+                    error: None,
+                },
+                Err(e) => ToolExecutionOutput {
+                    success: false,
+                    data: serde_json::Value::Null,
+                    error: Some(e),
+                },
+            }
+        }
+        "todoWrite" | "todo_write" => {
+            let merge = request
+                .input
+                .get("merge")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let write = async {
+                let store = ctx
+                    .todos
+                    .as_ref()
+                    .ok_or("Todo lists are not available in this context")?;
+                let items = todos::parse_todos(request.input.get("todos"))?;
+                store.write(&ctx.session_id, items, merge).await
+            };
+
+            match write.await {
+                Ok(items) => ToolExecutionOutput {
+                    success: true,
+                    data: serde_json::json!({
+                        "todos": items,
+                        "rendered": todos::render_todos(&items),
+                    }),
+                    error: None,
+                },
+                Err(e) => ToolExecutionOutput {
+                    success: false,
+                    data: serde_json::Value::Null,
+                    error: Some(e),
+                },
             }
         }
-        "todoWrite" | "todo_write" => ToolExecutionOutput {
-            success: true,
-            data: serde_json::json!({"message": "Todo write placeholder"}),
-            error: None,
-        },
         "askUserQuestions" | "ask_user_questions" => ToolExecutionOutput {
-            success: true,
-            data: serde_json::json!({"message": "Ask user questions placeholder"}),
-            error: None,
+            success: false,
+            data: serde_json::Value::Null,
+            error: Some(
+                "askUserQuestions is answered by the user through the task's action channel"
+                    .to_string(),
+            ),
         },
         "exitPlanMode" | "exit_plan_mode" => ToolExecutionOutput {
             success: true,
@@ -600,4 +922,305 @@ mod tests {
         assert!(write_file_def.is_some());
         assert!(write_file_def.unwrap().requires_approval);
     }
+
+    #[tokio::test]
+    async fn test_execute_batch_keeps_edits_as_ordering_barriers() {
+        let registry = ToolRegistry::new();
+        let log = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        // The first three reads only get past the barrier if they run together
+        let barrier = Arc::new(tokio::sync::Barrier::new(3));
+
+        for (name, requires_approval) in [("readFile", false), ("editFile", true)] {
+            let log = log.clone();
+            let barrier = barrier.clone();
+            let handler: ToolHandler = Arc::new(move |req: ToolRequest, _ctx: ToolContext| {
+                let log = log.clone();
+                let barrier = barrier.clone();
+                Box::pin(async move {
+                    if req.input["together"].as_bool().unwrap_or(false) {
+                        barrier.wait().await;
+                    }
+                    log.lock().unwrap().push(req.tool_call_id.clone());
+                    ToolExecutionOutput {
+                        success: true,
+                        data: serde_json::json!({"tool": req.name, "input": req.input}),
+                        error: None,
+                    }
+                })
+            });
+            registry
+                .register(
+                    ToolDefinition {
+                        name: name.to_string(),
+                        description: "Test".to_string(),
+                        parameters: serde_json::json!({}),
+                        requires_approval,
+                    },
+                    handler,
+                )
+                .await
+                .unwrap();
+        }
+
+        let dispatcher = ToolDispatcher::new(Arc::new(registry));
+        let request = |id: &str, name: &str, path: &str, together: bool| ToolRequest {
+            tool_call_id: id.to_string(),
+            name: name.to_string(),
+            input: serde_json::json!({"file_path": path, "together": together}),
+            provider_metadata: None,
+        };
+        let context = ToolContext {
+            session_id: "session".to_string(),
+            task_id: "task".to_string(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            sub_agents: None,
+            todos: None,
+            checkpoints: None,
+        };
+
+        let results = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            dispatcher.execute_batch(
+                vec![
+                    request("read-1", "readFile", "a.rs", true),
+                    request("read-2", "readFile", "b.rs", true),
+                    request("read-3", "readFile", "c.rs", true),
+                    request("edit-1", "edit_file", "a.rs", false),
+                    request("read-4", "readFile", "a.rs", false),
+                ],
+                context,
+            ),
+        )
+        .await
+        .expect("Adjacent reads should run concurrently");
+
+        let ids: Vec<&str> = results.iter().map(|r| r.tool_call_id.as_str()).collect();
+        assert_eq!(ids, vec!["read-1", "read-2", "read-3", "edit-1", "read-4"]);
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(results[3].name.as_deref(), Some("editFile"));
+
+        // The read issued after the edit runs after it
+        let log = log.lock().unwrap();
+        assert_eq!(log[3..], ["edit-1", "read-4"]);
+    }
+
+    #[tokio::test]
+    async fn test_restricted_registry_only_exposes_allowed_tools() {
+        let registry = ToolRegistry::create_default().await;
+        let restricted = registry
+            .restricted_to(&["read_file".to_string(), "code_search".to_string()])
+            .await;
+
+        let mut names: Vec<String> = restricted
+            .list_tools()
+            .await
+            .into_iter()
+            .map(|def| def.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["codeSearch", "readFile"]);
+
+        let result = restricted
+            .execute(
+                ToolRequest {
+                    tool_call_id: "call-1".to_string(),
+                    name: "bash".to_string(),
+                    input: serde_json::json!({"command": "ls"}),
+                    provider_metadata: None,
+                },
+                ToolContext {
+                    session_id: "session".to_string(),
+                    task_id: "task".to_string(),
+                    workspace_root: "/tmp".to_string(),
+                    worktree_path: None,
+                    settings: TaskSettings::default(),
+                    sub_agents: None,
+                    todos: None,
+                    checkpoints: None,
+                },
+            )
+            .await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("not found"));
+    }
+
+    #[derive(Debug)]
+    struct EchoSubAgents;
+
+    #[async_trait::async_trait]
+    impl SubAgentRunner for EchoSubAgents {
+        async fn run_sub_agent(
+            &self,
+            agent: &str,
+            prompt: &str,
+            parent: &ToolContext,
+        ) -> Result<SubAgentOutput, String> {
+            Ok(SubAgentOutput {
+                agent: agent.to_string(),
+                session_id: format!("{}-child", parent.session_id),
+                task_id: format!("{}-child", parent.task_id),
+                response: format!("done: {}", prompt),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_agent_uses_sub_agent_runner() {
+        let request = ToolRequest {
+            tool_call_id: "call-1".to_string(),
+            name: "callAgent".to_string(),
+            input: serde_json::json!({"agent": "reviewer", "prompt": "check main.rs"}),
+            provider_metadata: None,
+        };
+        let mut ctx = ToolContext {
+            session_id: "session".to_string(),
+            task_id: "task".to_string(),
+            workspace_root: "/tmp".to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            sub_agents: None,
+            todos: None,
+            checkpoints: None,
+        };
+
+        let unavailable = execute_tool_by_name("callAgent", request.clone(), ctx.clone()).await;
+        assert!(!unavailable.success);
+
+        ctx.sub_agents = Some(Arc::new(EchoSubAgents));
+        let output = execute_tool_by_name("callAgent", request, ctx).await;
+        assert!(output.success);
+        assert_eq!(output.data["agent"], "reviewer");
+        assert_eq!(output.data["sessionId"], "session-child");
+        assert_eq!(output.data["response"], "done: check main.rs");
+    }
+
+    #[tokio::test]
+    async fn test_edit_file_is_atomic_and_returns_diff() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {\n    let x = 1;\n    let y = 2;\n}\n").unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        let ctx = ToolContext {
+            session_id: "session".to_string(),
+            task_id: "task".to_string(),
+            workspace_root: dir.path().to_string_lossy().to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            sub_agents: None,
+            todos: None,
+            checkpoints: None,
+        };
+        let request = |input: serde_json::Value| ToolRequest {
+            tool_call_id: "edit-1".to_string(),
+            name: "editFile".to_string(),
+            input,
+            provider_metadata: None,
+        };
+
+        let failed = execute_tool_by_name(
+            "editFile",
+            request(serde_json::json!({
+                "file_path": path,
+                "edits": [
+                    {"old_string": "let x = 1;", "new_string": "let x = 10;"},
+                    {"old_string": "let z = 3;", "new_string": "let z = 30;"}
+                ]
+            })),
+            ctx.clone(),
+        )
+        .await;
+        assert!(!failed.success);
+        assert!(failed.error.unwrap().starts_with("Edit 2 of 2"));
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "fn main() {\n    let x = 1;\n    let y = 2;\n}\n"
+        );
+
+        let output = execute_tool_by_name(
+            "editFile",
+            request(serde_json::json!({
+                "file_path": path,
+                "old_string": "let y = 2;",
+                "new_string": "let y = 20;"
+            })),
+            ctx,
+        )
+        .await;
+        assert!(output.success, "{:?}", output.error);
+        assert_eq!(output.data["replacements"], 1);
+        assert_eq!(output.data["matches"][0]["confidence"], 1.0);
+        assert_eq!(output.data["additions"], 1);
+        assert_eq!(output.data["deletions"], 1);
+        assert!(output.data["diff"]
+            .as_str()
+            .unwrap()
+            .contains("-    let y = 2;\n+    let y = 20;\n"));
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "fn main() {\n    let x = 1;\n    let y = 20;\n}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_file_records_checkpoint() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage =
+            crate::storage::Storage::new(dir.path().to_path_buf(), dir.path().join("attachments"))
+                .await
+                .unwrap();
+        let now = chrono::Utc::now().timestamp();
+        storage
+            .chat_history
+            .create_session(&Session {
+                id: "session".to_string(),
+                project_id: None,
+                title: None,
+                status: SessionStatus::Running,
+                created_at: now,
+                updated_at: now,
+                last_event_id: None,
+                metadata: None,
+                head_message_id: None,
+                forked_from: None,
+            })
+            .await
+            .unwrap();
+
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir_all(&workspace).unwrap();
+        let file = workspace.join("notes.md");
+        std::fs::write(&file, "before").unwrap();
+
+        let checkpoints = CheckpointStore::new(storage.checkpoints.clone());
+        let ctx = ToolContext {
+            session_id: "session".to_string(),
+            task_id: "task".to_string(),
+            workspace_root: workspace.to_string_lossy().to_string(),
+            worktree_path: None,
+            settings: TaskSettings::default(),
+            sub_agents: None,
+            todos: None,
+            checkpoints: Some(checkpoints.clone()),
+        };
+        let request = ToolRequest {
+            tool_call_id: "write-1".to_string(),
+            name: "writeFile".to_string(),
+            input: serde_json::json!({"file_path": "notes.md", "content": "after"}),
+            provider_metadata: None,
+        };
+
+        let output = execute_tool_by_name("writeFile", request, ctx).await;
+        assert!(output.success, "{:?}", output.error);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "after");
+
+        let recorded = checkpoints.list("task").await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].tool_call_id, "write-1");
+
+        checkpoints.rollback("task", None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "before");
+    }
 }
@@ -2,10 +2,11 @@
 //! Types used by the core runtime for task/session lifecycle and agent loop
 
 use crate::storage::models::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 
 /// Unique identifier for a runtime task
 pub type RuntimeTaskId = String;
@@ -89,10 +90,47 @@ pub enum TaskAction {
         tool_call_id: ToolCallId,
         result: serde_json::Value,
     },
+    /// Answer the questions of a pending `askUserQuestions` call
+    AnswerQuestions {
+        tool_call_id: ToolCallId,
+        answers: Vec<QuestionAnswer>,
+    },
     /// Cancel the task
     Cancel,
 }
 
+/// A question asked through the `askUserQuestions` tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserQuestion {
+    pub id: String,
+    pub question: String,
+    /// Suggested answers; free-form answers are accepted as well
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Whether several options may be chosen
+    #[serde(default, alias = "multi_select")]
+    pub multi_select: bool,
+}
+
+/// The user's answer to one `UserQuestion`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionAnswer {
+    pub question_id: String,
+    pub answers: Vec<String>,
+}
+
+/// Questions a task is suspended on, waiting for the user's answers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingQuestions {
+    pub task_id: RuntimeTaskId,
+    pub session_id: SessionId,
+    pub tool_call_id: ToolCallId,
+    pub questions: Vec<UserQuestion>,
+}
+
 /// Request for tool execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -159,6 +197,7 @@ pub enum RuntimeEvent {
     /// Task state changed
     TaskStateChanged {
         task_id: RuntimeTaskId,
+        session_id: SessionId,
         state: RuntimeTaskState,
         previous_state: RuntimeTaskState,
     },
@@ -175,11 +214,13 @@ pub enum RuntimeEvent {
     /// Tool execution requested
     ToolCallRequested {
         task_id: RuntimeTaskId,
+        session_id: SessionId,
         request: ToolRequest,
     },
     /// Tool execution completed
     ToolCallCompleted {
         task_id: RuntimeTaskId,
+        session_id: SessionId,
         result: ToolResult,
     },
     /// Error occurred
@@ -193,6 +234,34 @@ pub enum RuntimeEvent {
         task_id: RuntimeTaskId,
         session_id: SessionId,
     },
+    /// Task is waiting for the user to answer questions
+    QuestionsAsked {
+        task_id: RuntimeTaskId,
+        session_id: SessionId,
+        tool_call_id: ToolCallId,
+        questions: Vec<UserQuestion>,
+    },
+    /// Todo list of a session changed
+    TodosUpdated {
+        session_id: SessionId,
+        todos: Vec<TodoItem>,
+    },
+    /// Event of a sub-agent started by a `callAgent` tool call of `parent_task_id`
+    SubAgent {
+        parent_task_id: RuntimeTaskId,
+        agent: String,
+        event: Box<RuntimeEvent>,
+    },
+}
+
+/// Final outcome of a sub-agent run, returned as the `callAgent` tool result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAgentOutput {
+    pub agent: String,
+    pub session_id: SessionId,
+    pub task_id: RuntimeTaskId,
+    pub response: String,
 }
 
 /// Channel sender for runtime events
@@ -208,11 +277,16 @@ pub struct TaskHandle {
     pub session_id: SessionId,
     pub state: Arc<RwLock<RuntimeTaskState>>,
     pub action_sender: Arc<mpsc::UnboundedSender<TaskAction>>,
+    /// Set once the task is cancelled, so work in progress can stop early
+    pub cancel_signal: Arc<watch::Sender<bool>>,
 }
 
 impl TaskHandle {
     /// Send an action to the task
     pub fn send_action(&self, action: TaskAction) -> Result<(), String> {
+        if matches!(action, TaskAction::Cancel) {
+            self.cancel_signal.send_replace(true);
+        }
         self.action_sender
             .send(action)
             .map_err(|_| "Task channel closed".to_string())
@@ -222,6 +296,11 @@ impl TaskHandle {
     pub fn cancel(&self) -> Result<(), String> {
         self.send_action(TaskAction::Cancel)
     }
+
+    /// Whether the task has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel_signal.borrow()
+    }
 }
 
 /// Result of task execution
@@ -0,0 +1,839 @@
+//! Web Fetch
+//!
+//! Backs the `webFetch` tool: fetches a URL with an SSRF guard (shared with
+//! `http_proxy`), byte and time limits, and manually followed redirects that
+//! are re-validated on every hop. HTML is reduced to readable markdown, JSON is
+//! pretty-printed, and binary content is refused.
+
+use crate::http_proxy::is_private_ip;
+use futures::StreamExt;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+use std::time::Duration;
+use url::Url;
+
+const USER_AGENT: &str = "TalkCody/1.0 (webFetch)";
+const ACCEPT: &str =
+    "text/html,application/xhtml+xml,application/json;q=0.9,text/plain;q=0.8,*/*;q=0.5";
+
+/// Longest content returned to the model, in characters
+const MAX_OUTPUT_CHARS: usize = 100_000;
+
+/// Limits applied to a single fetch
+#[derive(Debug, Clone)]
+pub struct WebFetchOptions {
+    /// Maximum response body bytes read; longer bodies are truncated
+    pub max_bytes: usize,
+    /// Overall deadline including redirects
+    pub timeout: Duration,
+    pub max_redirects: usize,
+    /// Allow loopback/private/link-local targets (tests and trusted setups only)
+    pub allow_private_network: bool,
+}
+
+impl Default for WebFetchOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: 5 * 1024 * 1024,
+            timeout: Duration::from_secs(30),
+            max_redirects: 5,
+            allow_private_network: false,
+        }
+    }
+}
+
+/// Fetched and converted page
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebFetchResult {
+    /// Final URL after redirects
+    pub url: String,
+    pub status: u16,
+    pub content_type: String,
+    pub title: Option<String>,
+    pub content: String,
+    /// Body or converted content was cut at a limit
+    pub truncated: bool,
+}
+
+/// Fetch `url` and convert the response into model-readable text
+pub async fn fetch_url(url: &str, options: &WebFetchOptions) -> Result<WebFetchResult, String> {
+    tokio::time::timeout(options.timeout, fetch_inner(url, options))
+        .await
+        .map_err(|_| format!("Fetching {} timed out after {:?}", url, options.timeout))?
+}
+
+async fn fetch_inner(url: &str, options: &WebFetchOptions) -> Result<WebFetchResult, String> {
+    let mut current = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let mut redirects = 0;
+
+    loop {
+        let addrs = resolve_allowed(&current, options.allow_private_network).await?;
+
+        let mut builder = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(Duration::from_secs(10))
+            .user_agent(USER_AGENT);
+        // Pin the connection to the addresses we validated so a second DNS
+        // lookup can't rebind the host to a private address
+        if let (Some(host), false) = (current.host_str(), addrs.is_empty()) {
+            builder = builder.resolve_to_addrs(host, &addrs);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let response = client
+            .get(current.clone())
+            .header(reqwest::header::ACCEPT, ACCEPT)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", current, e))?;
+
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| format!("Redirect from {} has no Location header", current))?;
+
+            redirects += 1;
+            if redirects > options.max_redirects {
+                return Err(format!(
+                    "Too many redirects (more than {})",
+                    options.max_redirects
+                ));
+            }
+            current = current
+                .join(location)
+                .map_err(|e| format!("Invalid redirect location '{}': {}", location, e))?;
+            continue;
+        }
+
+        if !status.is_success() {
+            return Err(format!(
+                "Request to {} failed with status {}",
+                current, status
+            ));
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+
+        if is_binary_content_type(&content_type) {
+            return Err(format!(
+                "Refusing to fetch binary content ({}) from {}",
+                content_type, current
+            ));
+        }
+
+        let (body, body_truncated) = read_limited(response, options.max_bytes).await?;
+        if looks_binary(&body) {
+            return Err(format!("Refusing to fetch binary content from {}", current));
+        }
+        let text = String::from_utf8_lossy(&body).to_string();
+
+        let (title, content) = if is_html(&content_type, &text) {
+            let title = extract_title(&text);
+            (title, html_to_markdown(&text, &current))
+        } else if is_json(&content_type) {
+            let pretty = serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .and_then(|value| serde_json::to_string_pretty(&value).ok())
+                .unwrap_or(text);
+            (None, pretty)
+        } else {
+            (None, text)
+        };
+
+        let (content, content_truncated) = truncate_chars(content, MAX_OUTPUT_CHARS);
+
+        return Ok(WebFetchResult {
+            url: current.to_string(),
+            status: status.as_u16(),
+            content_type,
+            title,
+            content,
+            truncated: body_truncated || content_truncated,
+        });
+    }
+}
+
+/// Reject non-HTTP URLs and hosts resolving to private addresses.
+/// Returns the validated addresses for connection pinning.
+pub(crate) async fn resolve_allowed(
+    url: &Url,
+    allow_private: bool,
+) -> Result<Vec<SocketAddr>, String> {
+    match url.scheme() {
+        "http" | "https" => {}
+        scheme => return Err(format!("Unsupported URL scheme: {}", scheme)),
+    }
+
+    let host = url.host_str().ok_or("URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    if !allow_private && host.eq_ignore_ascii_case("localhost") {
+        return Err("Access to localhost is not allowed".to_string());
+    }
+
+    let host_for_lookup = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host_for_lookup.parse::<IpAddr>() {
+        if !allow_private && is_blocked_ip(&ip) {
+            return Err(format!(
+                "Access to private/internal IP addresses is not allowed: {}",
+                ip
+            ));
+        }
+        return Ok(Vec::new());
+    }
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host_for_lookup, port))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(format!("Failed to resolve {}", host));
+    }
+
+    if !allow_private {
+        if let Some(addr) = addrs.iter().find(|addr| is_blocked_ip(&addr.ip())) {
+            return Err(format!(
+                "Access to private/internal IP addresses is not allowed: {}",
+                addr.ip()
+            ));
+        }
+    }
+
+    Ok(addrs)
+}
+
+/// `is_private_ip`, also unwrapping IPv4-mapped IPv6 addresses
+fn is_blocked_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_private_ip(&IpAddr::V4(v4)),
+            None => is_private_ip(ip),
+        },
+        IpAddr::V4(_) => is_private_ip(ip),
+    }
+}
+
+/// Read at most `max_bytes` of the body
+async fn read_limited(
+    response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<(Vec<u8>, bool), String> {
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to read response: {}", e))?;
+        let remaining = max_bytes - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok((body, false))
+}
+
+fn is_binary_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    if mime.is_empty() {
+        return false;
+    }
+    if mime.starts_with("text/") || is_json(mime) || mime.ends_with("+xml") {
+        return false;
+    }
+    !matches!(
+        mime,
+        "application/xml"
+            | "application/xhtml+xml"
+            | "application/javascript"
+            | "application/x-javascript"
+            | "application/ecmascript"
+            | "application/x-yaml"
+            | "application/yaml"
+            | "application/toml"
+            | "application/x-sh"
+    )
+}
+
+/// NUL bytes in the first KB mean the server mislabeled binary data
+fn looks_binary(body: &[u8]) -> bool {
+    body.iter().take(1024).any(|&b| b == 0)
+}
+
+fn is_json(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    mime == "application/json" || mime.ends_with("+json")
+}
+
+fn is_html(content_type: &str, text: &str) -> bool {
+    if content_type.contains("html") {
+        return true;
+    }
+    if !content_type.is_empty() {
+        return false;
+    }
+    let head = text.trim_start().get(..15).unwrap_or("").to_lowercase();
+    head.starts_with("<!doctype html") || head.starts_with("<html")
+}
+
+fn truncate_chars(text: String, max_chars: usize) -> (String, bool) {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => (text[..index].to_string(), true),
+        None => (text, false),
+    }
+}
+
+// ============================================================================
+// HTML to markdown
+// ============================================================================
+
+/// Elements dropped together with their content
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "svg", "form", "iframe",
+    "template", "button", "select",
+];
+
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "ul",
+    "ol",
+    "table",
+    "tr",
+    "blockquote",
+    "dl",
+    "dt",
+    "dd",
+    "figure",
+    "figcaption",
+    "details",
+    "summary",
+];
+
+enum HtmlToken<'a> {
+    Text(&'a str),
+    Open { name: String, attrs: &'a str },
+    Close { name: String },
+}
+
+/// Split HTML into text and tags, dropping comments, doctype and the raw
+/// content of script/style elements
+fn tokenize_html(html: &str) -> Vec<HtmlToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            tokens.push(HtmlToken::Text(rest));
+            break;
+        };
+        if lt > 0 {
+            tokens.push(HtmlToken::Text(&rest[..lt]));
+        }
+        rest = &rest[lt..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+            continue;
+        }
+
+        let Some(gt) = rest.find('>') else {
+            tokens.push(HtmlToken::Text(rest));
+            break;
+        };
+        let inner = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        if inner.starts_with('!') || inner.starts_with('?') {
+            continue;
+        }
+
+        if let Some(name) = inner.strip_prefix('/') {
+            tokens.push(HtmlToken::Close {
+                name: name.trim().to_lowercase(),
+            });
+            continue;
+        }
+
+        let inner = inner.trim_end_matches('/');
+        let name_end = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+        let name = inner[..name_end].to_lowercase();
+        let attrs = &inner[name_end..];
+
+        // Raw text elements: skip to the matching close tag
+        if name == "script" || name == "style" {
+            let close = format!("</{}", name);
+            let lower = rest.to_ascii_lowercase();
+            rest = lower
+                .find(&close)
+                .and_then(|pos| rest[pos..].find('>').map(|end| &rest[pos + end + 1..]))
+                .unwrap_or("");
+            continue;
+        }
+
+        tokens.push(HtmlToken::Open { name, attrs });
+    }
+
+    tokens
+}
+
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    static ATTR_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = ATTR_RE.get_or_init(|| {
+        regex::Regex::new(r#"(?i)([a-z_:][-a-z0-9_:.]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#)
+            .expect("valid attribute regex")
+    });
+
+    re.captures_iter(attrs)
+        .find(|caps| caps[1].eq_ignore_ascii_case(name))
+        .and_then(|caps| caps.get(2).or(caps.get(3)).or(caps.get(4)))
+        .map(|m| decode_entities(m.as_str()))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            ch.map(|ch| (ch, end))
+        });
+
+        match decoded {
+            Some((ch, end)) => {
+                out.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let open_end = start + lower[start..].find('>')? + 1;
+    let close = open_end + lower[open_end..].find("</title")?;
+    let title = decode_entities(html[open_end..close].trim());
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+struct MarkdownWriter {
+    out: String,
+    pre_depth: usize,
+    /// Open links: resolved href and where their text starts in `out`
+    links: Vec<(Option<String>, usize)>,
+}
+
+impl MarkdownWriter {
+    fn ensure_newlines(&mut self, count: usize) {
+        if self.out.is_empty() {
+            return;
+        }
+        let trailing = self.out.len() - self.out.trim_end_matches('\n').len();
+        // Drop spaces left at the end of the line
+        let trimmed_len = self.out.trim_end_matches([' ', '\n']).len();
+        if trailing < count {
+            self.out.truncate(trimmed_len);
+            for _ in 0..count {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            self.out.push_str(text);
+            return;
+        }
+        for (i, word) in text.split_whitespace().enumerate() {
+            let at_line_start = self.out.is_empty() || self.out.ends_with('\n');
+            let needs_space = i > 0 || text.starts_with(char::is_whitespace);
+            if needs_space && !at_line_start && !self.out.ends_with(' ') {
+                self.out.push(' ');
+            }
+            self.out.push_str(word);
+        }
+        if text.ends_with(char::is_whitespace)
+            && !self.out.ends_with([' ', '\n'])
+            && !self.out.is_empty()
+        {
+            self.out.push(' ');
+        }
+    }
+}
+
+/// Convert an HTML document into readable markdown, keeping the `<main>` or
+/// `<article>` content when present and dropping scripts, navigation and chrome
+pub fn html_to_markdown(html: &str, base_url: &Url) -> String {
+    let tokens = tokenize_html(html);
+
+    // Prefer the main content region when the page marks one
+    let region = ["main", "article"].iter().find_map(|region| {
+        let start = tokens
+            .iter()
+            .position(|t| matches!(t, HtmlToken::Open { name, .. } if name == region))?;
+        let end = tokens
+            .iter()
+            .rposition(|t| matches!(t, HtmlToken::Close { name } if name == region))?;
+        (start < end).then_some((start, end))
+    });
+    let tokens = match region {
+        Some((start, end)) => &tokens[start..=end],
+        None => &tokens[..],
+    };
+
+    let mut writer = MarkdownWriter {
+        out: String::new(),
+        pre_depth: 0,
+        links: Vec::new(),
+    };
+    let mut skip_depth: Vec<String> = Vec::new();
+
+    for token in tokens {
+        if let Some(skipping) = skip_depth.last() {
+            match token {
+                HtmlToken::Open { name, .. } if name == skipping => skip_depth.push(name.clone()),
+                HtmlToken::Close { name } if name == skipping => {
+                    skip_depth.pop();
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match token {
+            HtmlToken::Text(text) => writer.push_text(&decode_entities(text)),
+            HtmlToken::Open { name, attrs } => {
+                let name = name.as_str();
+                if SKIPPED_ELEMENTS.contains(&name) {
+                    skip_depth.push(name.to_string());
+                    continue;
+                }
+                match name {
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                        writer.ensure_newlines(2);
+                        let level = name[1..].parse::<usize>().unwrap_or(1);
+                        writer.out.push_str(&"#".repeat(level));
+                        writer.out.push(' ');
+                    }
+                    "br" => writer.ensure_newlines(1),
+                    "hr" => {
+                        writer.ensure_newlines(2);
+                        writer.out.push_str("---");
+                        writer.ensure_newlines(2);
+                    }
+                    "li" => {
+                        writer.ensure_newlines(1);
+                        writer.out.push_str("- ");
+                    }
+                    "pre" => {
+                        writer.ensure_newlines(2);
+                        writer.out.push_str("```\n");
+                        writer.pre_depth += 1;
+                    }
+                    "code" if writer.pre_depth == 0 => writer.out.push('`'),
+                    "strong" | "b" => writer.out.push_str("**"),
+                    "em" | "i" => writer.out.push('*'),
+                    "td" | "th" => writer.push_text(" "),
+                    "a" => {
+                        let href = attribute(attrs, "href")
+                            .filter(|href| {
+                                !href.starts_with('#') && !href.starts_with("javascript:")
+                            })
+                            .and_then(|href| base_url.join(&href).ok())
+                            .map(|url| url.to_string());
+                        writer.links.push((href, writer.out.len()));
+                    }
+                    _ if BLOCK_ELEMENTS.contains(&name) => writer.ensure_newlines(2),
+                    _ => {}
+                }
+            }
+            HtmlToken::Close { name } => match name.as_str() {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "hr" => writer.ensure_newlines(2),
+                "pre" => {
+                    writer.pre_depth = writer.pre_depth.saturating_sub(1);
+                    if !writer.out.ends_with('\n') {
+                        writer.out.push('\n');
+                    }
+                    writer.out.push_str("```");
+                    writer.ensure_newlines(2);
+                }
+                "code" if writer.pre_depth == 0 => writer.out.push('`'),
+                "strong" | "b" => writer.out.push_str("**"),
+                "em" | "i" => writer.out.push('*'),
+                "li" | "tr" => writer.ensure_newlines(1),
+                "a" => {
+                    if let Some((Some(href), start)) = writer.links.pop() {
+                        let text = writer.out[start..].trim().to_string();
+                        if !text.is_empty() {
+                            writer.out.truncate(start);
+                            writer.out.push_str(&format!("[{}]({})", text, href));
+                        }
+                    }
+                }
+                other if BLOCK_ELEMENTS.contains(&other) => writer.ensure_newlines(2),
+                _ => {}
+            },
+        }
+    }
+
+    // Collapse runs of blank lines
+    let mut result = String::new();
+    let mut blank_lines = 0;
+    for line in writer.out.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Local HTTP server routing a few fixed paths
+    fn start_stub_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tiny_http::Server::from_listener(listener, None).unwrap();
+
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let (status, content_type, body, location): (u16, &str, Vec<u8>, Option<&str>) =
+                    match request.url() {
+                        "/page" => (
+                            200,
+                            "text/html; charset=utf-8",
+                            b"<html><head><title>Stub &amp; Page</title><script>alert(1)</script></head>\
+                              <body><nav><a href=\"/home\">Home</a></nav><main><h1>Hello</h1>\
+                              <p>Read the <a href=\"/docs\">docs</a>.</p></main></body></html>"
+                                .to_vec(),
+                            None,
+                        ),
+                        "/data" => (200, "application/json", br#"{"a":1,"b":[true]}"#.to_vec(), None),
+                        "/image" => (200, "image/png", vec![0x89, b'P', b'N', b'G', 0, 0], None),
+                        "/mislabeled" => (200, "text/plain", vec![b'a', 0, b'b'], None),
+                        "/big" => (200, "text/plain", vec![b'x'; 4096], None),
+                        "/redirect" => (302, "text/plain", Vec::new(), Some("/page")),
+                        "/loop" => (302, "text/plain", Vec::new(), Some("/loop")),
+                        _ => (404, "text/plain", b"not found".to_vec(), None),
+                    };
+
+                let mut response = tiny_http::Response::from_data(body)
+                    .with_status_code(status)
+                    .with_header(
+                        tiny_http::Header::from_bytes("Content-Type", content_type).unwrap(),
+                    );
+                if let Some(location) = location {
+                    response = response
+                        .with_header(tiny_http::Header::from_bytes("Location", location).unwrap());
+                }
+                let _ = request.respond(response);
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn local_options() -> WebFetchOptions {
+        WebFetchOptions {
+            allow_private_network: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_html_to_markdown_strips_chrome() {
+        let html = r#"<html><body>
+            <header>Site header</header>
+            <nav><a href="/a">Nav link</a></nav>
+            <h2>Title</h2>
+            <p>Some <strong>bold</strong> and <em>italic</em> text with <code>code</code>
+               and a <a href="guide.html">relative link</a>.</p>
+            <ul><li>One</li><li>Two</li></ul>
+            <pre>fn main() {
+    println!("hi");
+}</pre>
+            <script>var x = "<p>not content</p>";</script>
+            <!-- comment -->
+            <footer>Footer</footer>
+        </body></html>"#;
+
+        let base = Url::parse("https://example.com/docs/index.html").unwrap();
+        let markdown = html_to_markdown(html, &base);
+
+        assert!(markdown.starts_with("## Title"));
+        assert!(markdown.contains("Some **bold** and *italic* text with `code`"));
+        assert!(markdown.contains("[relative link](https://example.com/docs/guide.html)"));
+        assert!(markdown.contains("- One\n- Two"));
+        assert!(markdown.contains("```\nfn main() {\n    println!(\"hi\");\n}\n```"));
+        assert!(!markdown.contains("Nav link"));
+        assert!(!markdown.contains("not content"));
+        assert!(!markdown.contains("Footer"));
+        assert!(!markdown.contains("comment"));
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &#65;&#x42; &unknown; &"),
+            "a & b <c> AB &unknown; &"
+        );
+    }
+
+    #[test]
+    fn test_binary_content_types() {
+        assert!(is_binary_content_type("image/png"));
+        assert!(is_binary_content_type("application/octet-stream"));
+        assert!(is_binary_content_type("application/pdf"));
+        assert!(!is_binary_content_type("text/html; charset=utf-8"));
+        assert!(!is_binary_content_type("application/ld+json"));
+        assert!(!is_binary_content_type("application/rss+xml"));
+        assert!(!is_binary_content_type(""));
+    }
+
+    #[tokio::test]
+    async fn test_ssrf_guard_blocks_private_targets() {
+        for url in [
+            "http://127.0.0.1/",
+            "http://localhost:8080/",
+            "http://10.1.2.3/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[::ffff:192.168.0.1]/",
+        ] {
+            let error = fetch_url(url, &WebFetchOptions::default())
+                .await
+                .unwrap_err();
+            assert!(error.contains("not allowed"), "{}: {}", url, error);
+        }
+
+        let error = fetch_url("file:///etc/passwd", &WebFetchOptions::default())
+            .await
+            .unwrap_err();
+        assert!(error.contains("Unsupported URL scheme"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_html_follows_redirect_and_converts() {
+        let base = start_stub_server();
+        let result = fetch_url(&format!("{}/redirect", base), &local_options())
+            .await
+            .unwrap();
+
+        assert_eq!(result.url, format!("{}/page", base));
+        assert_eq!(result.title.as_deref(), Some("Stub & Page"));
+        assert_eq!(
+            result.content,
+            format!("# Hello\n\nRead the [docs]({}/docs).", base)
+        );
+        assert!(!result.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pretty_prints_json() {
+        let base = start_stub_server();
+        let result = fetch_url(&format!("{}/data", base), &local_options())
+            .await
+            .unwrap();
+        assert_eq!(
+            result.content,
+            "{\n  \"a\": 1,\n  \"b\": [\n    true\n  ]\n}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_binary() {
+        let base = start_stub_server();
+        let error = fetch_url(&format!("{}/image", base), &local_options())
+            .await
+            .unwrap_err();
+        assert!(error.contains("binary content (image/png)"));
+
+        let error = fetch_url(&format!("{}/mislabeled", base), &local_options())
+            .await
+            .unwrap_err();
+        assert!(error.contains("binary content"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_enforces_limits() {
+        let base = start_stub_server();
+
+        let options = WebFetchOptions {
+            max_bytes: 100,
+            ..local_options()
+        };
+        let result = fetch_url(&format!("{}/big", base), &options).await.unwrap();
+        assert_eq!(result.content.len(), 100);
+        assert!(result.truncated);
+
+        let error = fetch_url(&format!("{}/loop", base), &local_options())
+            .await
+            .unwrap_err();
+        assert!(error.contains("Too many redirects"));
+
+        let error = fetch_url(&format!("{}/missing", base), &local_options())
+            .await
+            .unwrap_err();
+        assert!(error.contains("404"));
+    }
+}
@@ -0,0 +1,653 @@
+//! Web Search Backends
+//!
+//! Pluggable providers behind the `webSearch` tool. The backend is selected by
+//! the `web_search` setting, e.g.:
+//!
+//! ```json
+//! { "provider": "searxng", "baseUrl": "https://searx.example.com" }
+//! { "provider": "custom", "urlTemplate": "https://api.example.com/search?q={query}&n={limit}",
+//!   "headers": { "Authorization": "Bearer ..." }, "resultsPath": "data.results",
+//!   "titleField": "name", "urlField": "link", "snippetField": "description" }
+//! ```
+//!
+//! The setting is supplied by clients, so every request goes through the same
+//! SSRF guard as `webFetch`: private targets are refused, the connection is
+//! pinned to the validated addresses, and redirects are not followed.
+
+use crate::core::web_fetch::resolve_allowed;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Settings key holding the `WebSearchConfig`
+pub const WEB_SEARCH_SETTING_KEY: &str = "web_search";
+
+/// Default number of results returned to the model
+pub const DEFAULT_MAX_RESULTS: usize = 10;
+
+/// Upper bound on results regardless of what the model asks for
+const MAX_RESULTS_LIMIT: usize = 50;
+
+/// Snippets longer than this are truncated
+const MAX_SNIPPET_CHARS: usize = 500;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A single search hit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// A web search backend
+#[async_trait]
+pub trait WebSearchProvider: Send + Sync {
+    /// Provider name, for logs and tool output
+    fn name(&self) -> &str;
+
+    /// Run `query`, returning at most roughly `max_results` raw results
+    async fn search(&self, query: &str, max_results: usize)
+        -> Result<Vec<WebSearchResult>, String>;
+}
+
+/// Backend configuration stored under `WEB_SEARCH_SETTING_KEY`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "camelCase")]
+pub enum WebSearchConfig {
+    /// SearXNG instance (or any endpoint speaking its `format=json` API)
+    #[serde(rename_all = "camelCase")]
+    Searxng {
+        base_url: String,
+        #[serde(default)]
+        categories: Option<String>,
+        #[serde(default)]
+        language: Option<String>,
+        /// Allow a private or loopback address, e.g. a self-hosted instance
+        #[serde(default)]
+        allow_private_network: bool,
+    },
+    /// Arbitrary JSON HTTP API described by a request template and field paths
+    Custom(CustomSearchConfig),
+}
+
+/// Request template and response mapping for a custom search API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomSearchConfig {
+    /// URL with `{query}` (URL-encoded) and `{limit}` placeholders
+    pub url_template: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Optional request body with `{query}` (JSON-escaped) and `{limit}` placeholders
+    #[serde(default)]
+    pub body_template: Option<String>,
+    /// Dotted path to the results array in the response; empty for a top-level array
+    #[serde(default)]
+    pub results_path: String,
+    #[serde(default = "default_title_field")]
+    pub title_field: String,
+    #[serde(default = "default_url_field")]
+    pub url_field: String,
+    #[serde(default = "default_snippet_field")]
+    pub snippet_field: String,
+    /// Allow a private or loopback address, e.g. a self-hosted API
+    #[serde(default)]
+    pub allow_private_network: bool,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_title_field() -> String {
+    "title".to_string()
+}
+
+fn default_url_field() -> String {
+    "url".to_string()
+}
+
+fn default_snippet_field() -> String {
+    "snippet".to_string()
+}
+
+impl WebSearchConfig {
+    /// Parse the stored setting value
+    pub fn from_value(value: &serde_json::Value) -> Result<Self, String> {
+        serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid web search configuration: {}", e))
+    }
+
+    /// Build the provider described by this configuration. Unless the
+    /// configuration sets `allowPrivateNetwork`, the provider refuses
+    /// endpoints that resolve to private addresses.
+    pub fn build_provider(&self) -> Result<Box<dyn WebSearchProvider>, String> {
+        Ok(match self {
+            WebSearchConfig::Searxng {
+                base_url,
+                categories,
+                language,
+                allow_private_network,
+            } => Box::new(SearxngProvider {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                categories: categories.clone(),
+                language: language.clone(),
+                allow_private_network: *allow_private_network,
+            }),
+            WebSearchConfig::Custom(config) => Box::new(CustomHttpProvider {
+                allow_private_network: config.allow_private_network,
+                config: config.clone(),
+            }),
+        })
+    }
+}
+
+/// Run a search with `provider`, then deduplicate and truncate the results
+pub async fn run_web_search(
+    provider: &dyn WebSearchProvider,
+    query: &str,
+    max_results: usize,
+) -> Result<Vec<WebSearchResult>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+
+    let max_results = max_results.clamp(1, MAX_RESULTS_LIMIT);
+    let results = provider.search(query, max_results).await?;
+    Ok(normalize_results(results, max_results))
+}
+
+/// Drop results without a URL, deduplicate by normalized URL, trim and truncate
+/// snippets, and cap the result count
+pub fn normalize_results(
+    results: Vec<WebSearchResult>,
+    max_results: usize,
+) -> Vec<WebSearchResult> {
+    let mut seen = HashSet::new();
+
+    results
+        .into_iter()
+        .filter_map(|result| {
+            let url = result.url.trim().to_string();
+            if url.is_empty() || !seen.insert(dedup_key(&url)) {
+                return None;
+            }
+
+            let title = collapse_whitespace(&result.title);
+            Some(WebSearchResult {
+                title: if title.is_empty() { url.clone() } else { title },
+                snippet: truncate_chars(&collapse_whitespace(&result.snippet), MAX_SNIPPET_CHARS),
+                url,
+            })
+        })
+        .take(max_results)
+        .collect()
+}
+
+/// URL key that ignores fragments, trailing slashes and host case
+fn dedup_key(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string().trim_end_matches('/').to_string()
+        }
+        Err(_) => url.trim_end_matches('/').to_string(),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+/// HTTP client for a single request to `url`, after checking the target against
+/// the SSRF guard and pinning the connection to the validated addresses
+async fn guarded_client(url: &str, allow_private_network: bool) -> Result<reqwest::Client, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid web search URL: {}", e))?;
+    let addrs = resolve_allowed(&parsed, allow_private_network).await?;
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+    if let (Some(host), false) = (parsed.host_str(), addrs.is_empty()) {
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+async fn fetch_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Web search request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Web search request failed with status {}", status));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse web search response: {}", e))
+}
+
+/// Look up a dotted path (`a.b.0.c`) in a JSON value
+fn json_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            serde_json::Value::Array(items) => {
+                segment.parse::<usize>().ok().and_then(|i| items.get(i))
+            }
+            _ => current.get(segment),
+        })
+}
+
+fn json_string(value: &serde_json::Value, path: &str) -> String {
+    json_path(value, path)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// SearXNG JSON API backend
+pub struct SearxngProvider {
+    base_url: String,
+    categories: Option<String>,
+    language: Option<String>,
+    allow_private_network: bool,
+}
+
+#[async_trait]
+impl WebSearchProvider for SearxngProvider {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<WebSearchResult>, String> {
+        let mut params = vec![("q", query.to_string()), ("format", "json".to_string())];
+        if let Some(categories) = &self.categories {
+            params.push(("categories", categories.clone()));
+        }
+        if let Some(language) = &self.language {
+            params.push(("language", language.clone()));
+        }
+
+        let url = format!("{}/search", self.base_url);
+        let client = guarded_client(&url, self.allow_private_network).await?;
+        let body = fetch_json(client.get(url).query(&params)).await?;
+
+        let results = body
+            .get("results")
+            .and_then(|r| r.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .take(max_results * 2)
+                    .map(|item| WebSearchResult {
+                        title: json_string(item, "title"),
+                        url: json_string(item, "url"),
+                        snippet: json_string(item, "content"),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(results)
+    }
+}
+
+/// Generic JSON HTTP API backend driven by a `CustomSearchConfig`
+pub struct CustomHttpProvider {
+    config: CustomSearchConfig,
+    allow_private_network: bool,
+}
+
+impl CustomHttpProvider {
+    async fn build_request(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let encoded_query: String =
+            url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        let limit = max_results.to_string();
+        let url = self
+            .config
+            .url_template
+            .replace("{query}", &encoded_query)
+            .replace("{limit}", &limit);
+
+        let method = reqwest::Method::from_bytes(self.config.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("Invalid HTTP method: {}", self.config.method))?;
+
+        let client = guarded_client(&url, self.allow_private_network).await?;
+        let mut request = client.request(method, url);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+
+        if let Some(template) = &self.config.body_template {
+            // JSON-escape the query without its surrounding quotes
+            let escaped_query = serde_json::to_string(query).unwrap_or_default();
+            let escaped_query = &escaped_query[1..escaped_query.len() - 1];
+            request = request.body(
+                template
+                    .replace("{query}", escaped_query)
+                    .replace("{limit}", &limit),
+            );
+        }
+
+        Ok(request)
+    }
+}
+
+#[async_trait]
+impl WebSearchProvider for CustomHttpProvider {
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<WebSearchResult>, String> {
+        let body = fetch_json(self.build_request(query, max_results).await?).await?;
+
+        let items = json_path(&body, &self.config.results_path)
+            .and_then(|r| r.as_array())
+            .ok_or_else(|| {
+                format!(
+                    "Web search response has no results array at '{}'",
+                    self.config.results_path
+                )
+            })?;
+
+        Ok(items
+            .iter()
+            .map(|item| WebSearchResult {
+                title: json_string(item, &self.config.title_field),
+                url: json_string(item, &self.config.url_field),
+                snippet: json_string(item, &self.config.snippet_field),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// Local HTTP server answering every request with `body`, recording request URLs and bodies
+    struct StubSearchServer {
+        base_url: String,
+        requests: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl StubSearchServer {
+        fn start(body: serde_json::Value) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tiny_http::Server::from_listener(listener, None).unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let mut request_body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut request_body);
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push((request.url().to_string(), request_body));
+
+                    let header =
+                        tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap();
+                    let response =
+                        tiny_http::Response::from_string(body.to_string()).with_header(header);
+                    let _ = request.respond(response);
+                }
+            });
+
+            Self {
+                base_url: format!("http://{}", addr),
+                requests,
+            }
+        }
+    }
+
+    fn result(title: &str, url: &str, snippet: &str) -> WebSearchResult {
+        WebSearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            snippet: snippet.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_results_dedupes_and_truncates() {
+        let long_snippet = "word ".repeat(200);
+        let results = vec![
+            result(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "A  language\nempowering",
+            ),
+            result("Rust again", "https://www.rust-lang.org#install", "dup"),
+            result("", "https://doc.rust-lang.org/book", &long_snippet),
+            result("No url", "  ", "dropped"),
+            result("Extra", "https://crates.io", "over the limit"),
+        ];
+
+        let normalized = normalize_results(results, 3);
+        assert_eq!(normalized.len(), 3);
+        assert_eq!(normalized[0].snippet, "A language empowering");
+        assert_eq!(normalized[1].title, "https://doc.rust-lang.org/book");
+        assert!(normalized[1].snippet.ends_with("..."));
+        assert_eq!(normalized[1].snippet.chars().count(), MAX_SNIPPET_CHARS + 3);
+        assert_eq!(normalized[2].url, "https://crates.io");
+    }
+
+    #[test]
+    fn test_config_from_setting_value() {
+        let searxng = WebSearchConfig::from_value(&serde_json::json!({
+            "provider": "searxng",
+            "baseUrl": "http://localhost:8888/"
+        }))
+        .unwrap();
+        assert!(
+            matches!(searxng, WebSearchConfig::Searxng { ref base_url, .. } if base_url == "http://localhost:8888/")
+        );
+
+        let custom = WebSearchConfig::from_value(&serde_json::json!({
+            "provider": "custom",
+            "urlTemplate": "http://localhost/search?q={query}"
+        }))
+        .unwrap();
+        match custom {
+            WebSearchConfig::Custom(config) => {
+                assert_eq!(config.method, "GET");
+                assert_eq!(config.url_field, "url");
+            }
+            _ => panic!("expected custom config"),
+        }
+
+        assert!(
+            WebSearchConfig::from_value(&serde_json::json!({ "provider": "unknown" })).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_searxng_provider_against_stub() {
+        let server = StubSearchServer::start(serde_json::json!({
+            "results": [
+                { "title": "Tokio", "url": "https://tokio.rs/", "content": "An async runtime" },
+                { "title": "Tokio docs", "url": "https://tokio.rs", "content": "duplicate" },
+                { "title": "Axum", "url": "https://docs.rs/axum", "content": "Web framework" }
+            ]
+        }));
+
+        let config = WebSearchConfig::Searxng {
+            base_url: format!("{}/", server.base_url),
+            categories: Some("it".to_string()),
+            language: None,
+            allow_private_network: true,
+        };
+        let provider = config.build_provider().unwrap();
+        let results = run_web_search(provider.as_ref(), "rust async", 10)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].snippet, "An async runtime");
+        assert_eq!(results[1].title, "Axum");
+
+        let requests = server.requests.lock().unwrap();
+        assert!(requests[0].0.starts_with("/search?"));
+        assert!(requests[0].0.contains("q=rust+async"));
+        assert!(requests[0].0.contains("format=json"));
+        assert!(requests[0].0.contains("categories=it"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_provider_maps_fields_from_template() {
+        let server = StubSearchServer::start(serde_json::json!({
+            "data": { "hits": [
+                { "name": "First", "link": "https://a.example", "meta": { "summary": "one" } },
+                { "name": "Second", "link": "https://b.example", "meta": { "summary": "two" } }
+            ]}
+        }));
+
+        let config = WebSearchConfig::Custom(CustomSearchConfig {
+            url_template: format!("{}/api?query={{query}}&count={{limit}}", server.base_url),
+            method: "post".to_string(),
+            headers: HashMap::from([("X-Api-Key".to_string(), "secret".to_string())]),
+            body_template: Some(r#"{"q":"{query}","n":{limit}}"#.to_string()),
+            results_path: "data.hits".to_string(),
+            title_field: "name".to_string(),
+            url_field: "link".to_string(),
+            snippet_field: "meta.summary".to_string(),
+            allow_private_network: true,
+        });
+        let provider = config.build_provider().unwrap();
+        assert_eq!(provider.name(), "custom");
+
+        let results = run_web_search(provider.as_ref(), "say \"hi\" & bye", 1)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![result("First", "https://a.example", "one")]);
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "/api?query=say+%22hi%22+%26+bye&count=1");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].1).unwrap();
+        assert_eq!(body, serde_json::json!({ "q": "say \"hi\" & bye", "n": 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_custom_provider_reports_missing_results_array() {
+        let server = StubSearchServer::start(serde_json::json!({ "unexpected": true }));
+        let config = WebSearchConfig::Custom(CustomSearchConfig {
+            url_template: format!("{}/?q={{query}}", server.base_url),
+            method: default_method(),
+            headers: HashMap::new(),
+            body_template: None,
+            results_path: "items".to_string(),
+            title_field: default_title_field(),
+            url_field: default_url_field(),
+            snippet_field: default_snippet_field(),
+            allow_private_network: true,
+        });
+        let provider = config.build_provider().unwrap();
+
+        let error = run_web_search(provider.as_ref(), "anything", 5)
+            .await
+            .unwrap_err();
+        assert!(error.contains("no results array at 'items'"));
+    }
+
+    #[tokio::test]
+    async fn test_providers_reject_private_endpoints() {
+        let server = StubSearchServer::start(serde_json::json!({ "results": [] }));
+
+        let custom = WebSearchConfig::Custom(CustomSearchConfig {
+            url_template: format!("{}/?q={{query}}", server.base_url),
+            method: default_method(),
+            headers: HashMap::from([("Authorization".to_string(), "Bearer x".to_string())]),
+            body_template: None,
+            results_path: "results".to_string(),
+            title_field: default_title_field(),
+            url_field: default_url_field(),
+            snippet_field: default_snippet_field(),
+            allow_private_network: false,
+        });
+        let searxng = WebSearchConfig::Searxng {
+            base_url: "http://169.254.169.254".to_string(),
+            categories: None,
+            language: None,
+            allow_private_network: false,
+        };
+
+        for config in [custom, searxng] {
+            let provider = config.build_provider().unwrap();
+            let error = run_web_search(provider.as_ref(), "anything", 5)
+                .await
+                .unwrap_err();
+            assert!(error.contains("private/internal"), "{}", error);
+        }
+        assert!(server.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_allow_private_network_setting_reaches_loopback() {
+        let server = StubSearchServer::start(serde_json::json!({
+            "results": [{ "title": "Local", "url": "https://local.example", "content": "hit" }]
+        }));
+        let setting = |allow: bool| {
+            serde_json::json!({
+                "provider": "searxng",
+                "baseUrl": server.base_url,
+                "allowPrivateNetwork": allow,
+            })
+        };
+
+        let denied = WebSearchConfig::from_value(&setting(false))
+            .unwrap()
+            .build_provider()
+            .unwrap();
+        assert!(run_web_search(denied.as_ref(), "local", 5).await.is_err());
+
+        let allowed = WebSearchConfig::from_value(&setting(true))
+            .unwrap()
+            .build_provider()
+            .unwrap();
+        let results = run_web_search(allowed.as_ref(), "local", 5).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Local");
+        assert_eq!(server.requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,388 @@
+//! Workspace Search
+//!
+//! Backs the `codeSearch`, `glob` and `listFiles` tools with the engines the
+//! desktop app uses: `search::RipgrepSearch`, `glob::HighPerformanceGlob` and
+//! `list_files::list_project_files`, which share the gitignore-aware
+//! `walker`. Results are formatted like the desktop tools format them.
+
+use crate::glob::{GlobResult, HighPerformanceGlob, DEFAULT_MAX_GLOB_RESULTS};
+use crate::platform::paths::resolve_path;
+use crate::platform::types::PlatformContext;
+use crate::search::{RipgrepSearch, SearchResult};
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Files `codeSearch` returns matches from unless `max_results` is given
+pub const DEFAULT_MAX_SEARCH_RESULTS: usize = 50;
+/// Matches `codeSearch` returns per file
+const MAX_MATCHES_PER_FILE: usize = 10;
+/// Depth `listFiles` descends to unless `max_depth` is given
+pub const DEFAULT_LIST_DEPTH: usize = 3;
+
+/// Search file contents for a regex, as the `codeSearch` tool
+///
+/// Returns `{ success, result }` with the matches as text.
+pub async fn code_search(input: &Value, ctx: &PlatformContext) -> Result<Value, String> {
+    let pattern = input
+        .get("pattern")
+        .or_else(|| input.get("query"))
+        .and_then(|v| v.as_str())
+        .filter(|pattern| !pattern.is_empty())
+        .ok_or("No pattern provided")?
+        .to_string();
+    let root = search_root(input, &["path"], ctx)?;
+
+    let searcher = RipgrepSearch::new()
+        .with_max_results(usize_param(input, "max_results").unwrap_or(DEFAULT_MAX_SEARCH_RESULTS))
+        .with_max_matches_per_file(MAX_MATCHES_PER_FILE)
+        .with_file_types(string_list_param(input, "file_types").map(|types| {
+            types
+                .into_iter()
+                .map(|t| t.trim_start_matches('.').to_string())
+                .collect()
+        }))
+        .with_exclude_dirs(string_list_param(input, "exclude_dirs"))
+        .with_case_sensitive(bool_param(input, "case_sensitive").unwrap_or(false))
+        .with_context_lines(usize_param(input, "context_lines").unwrap_or(0));
+    let mut results =
+        run_blocking(move || searcher.search_content(&pattern, &root.to_string_lossy())).await?;
+    results.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+    Ok(serde_json::json!({
+        "success": true,
+        "result": format_search_results(&results),
+    }))
+}
+
+/// Find files by glob pattern, most recently modified first, as the `glob` tool
+pub async fn glob(input: &Value, ctx: &PlatformContext) -> Result<String, String> {
+    let pattern = input
+        .get("pattern")
+        .and_then(|v| v.as_str())
+        .filter(|pattern| !pattern.trim().is_empty())
+        .ok_or("No pattern provided")?
+        .to_string();
+    let root = search_root(input, &["path"], ctx)?;
+    let max_results = usize_param(input, "max_results").unwrap_or(DEFAULT_MAX_GLOB_RESULTS);
+    let globber = HighPerformanceGlob::new()
+        .with_exclude_dirs(string_list_param(input, "exclude_dirs"))
+        .with_case_sensitive(bool_param(input, "case_sensitive").unwrap_or(true));
+
+    let walk_root = root.clone();
+    let walk_pattern = pattern.clone();
+    let results = run_blocking(move || {
+        globber.search_files_by_glob(&walk_pattern, &walk_root.to_string_lossy(), max_results)
+    })
+    .await?;
+
+    Ok(format_glob_results(&pattern, &root, &results))
+}
+
+/// List a directory tree grouped by parent directory, as the `listFiles` tool
+pub async fn list_files(input: &Value, ctx: &PlatformContext) -> Result<String, String> {
+    let root = search_root(input, &["directory_path", "path"], ctx)?;
+    if !root.is_dir() {
+        return Err(format!("'{}' is not a directory", root.display()));
+    }
+    let recursive = bool_param(input, "recursive").unwrap_or(true);
+    let max_depth = usize_param(input, "max_depth").unwrap_or(DEFAULT_LIST_DEPTH);
+    let max_files = usize_param(input, "max_files");
+
+    run_blocking(move || {
+        crate::list_files::list_project_files(
+            root.to_string_lossy().to_string(),
+            Some(recursive),
+            Some(max_depth),
+            max_files,
+        )
+    })
+    .await
+}
+
+/// Matches grouped by file, with context lines marked `-` as ripgrep does
+fn format_search_results(results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return "No matches found".to_string();
+    }
+
+    let mut total = 0;
+    let mut out = String::new();
+    for (i, file) in results.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "File: {}", file.file_path);
+        for m in &file.matches {
+            let first_before = m.line_number.saturating_sub(m.context_before.len() as u64);
+            for (offset, line) in m.context_before.iter().enumerate() {
+                let _ = writeln!(out, "  {}- {}", first_before + offset as u64, line.trim());
+            }
+            let _ = writeln!(out, "  {}: {}", m.line_number, m.line_content.trim());
+            for (offset, line) in m.context_after.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "  {}- {}",
+                    m.line_number + 1 + offset as u64,
+                    line.trim()
+                );
+            }
+            total += 1;
+        }
+    }
+
+    format!("Found {} matches:\n{}", total, out.trim_end())
+}
+
+/// Paths relative to the searched directory, with their modification dates
+fn format_glob_results(pattern: &str, root: &Path, results: &[GlobResult]) -> String {
+    if results.is_empty() {
+        return format!(
+            "No files found matching pattern \"{}\" in {}",
+            pattern,
+            root.display()
+        );
+    }
+
+    let lines: Vec<String> = results
+        .iter()
+        .map(|result| {
+            let path = Path::new(&result.path);
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let date = chrono::DateTime::from_timestamp(result.modified_time as i64, 0)
+                .map(|time| time.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            format!(
+                "{} ({}){}",
+                relative.display(),
+                date,
+                if result.is_directory { " [DIR]" } else { "" }
+            )
+        })
+        .collect();
+
+    format!(
+        "Found {} file(s) matching \"{}\":\n\n{}",
+        results.len(),
+        pattern,
+        lines.join("\n")
+    )
+}
+
+/// The directory named by the first of `names` present, or the worktree or
+/// workspace root
+fn search_root(input: &Value, names: &[&str], ctx: &PlatformContext) -> Result<PathBuf, String> {
+    let path = names
+        .iter()
+        .find_map(|name| input.get(*name).and_then(|v| v.as_str()))
+        .unwrap_or("");
+    resolve_path(path, ctx)
+}
+
+/// Walk the workspace off the async runtime
+async fn run_blocking<T, F>(walk: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(walk)
+        .await
+        .map_err(|e| format!("Search task failed: {}", e))?
+}
+
+fn usize_param(input: &Value, name: &str) -> Option<usize> {
+    input.get(name).and_then(|v| v.as_u64()).map(|n| n as usize)
+}
+
+fn bool_param(input: &Value, name: &str) -> Option<bool> {
+    input.get(name).and_then(|v| v.as_bool())
+}
+
+fn string_list_param(input: &Value, name: &str) -> Option<Vec<String>> {
+    let items: Vec<String> = input
+        .get(name)?
+        .as_array()?
+        .iter()
+        .filter_map(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchMatch;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn create_workspace() -> (TempDir, PlatformContext) {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("src/nested")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("lib")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("src/main.rs"),
+            "fn main() {\n    let Total = 1;\n    println!(\"{}\", Total);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("src/nested/util.ts"),
+            "export const total = 2;\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("lib/consts.rs"),
+            "pub const TOTAL: u32 = 3;\n",
+        )
+        .unwrap();
+
+        let ctx = PlatformContext {
+            workspace_root: temp_dir.path().to_path_buf(),
+            worktree_path: None,
+            max_file_size: 1024 * 1024,
+            shell_timeout_secs: 60,
+        };
+        (temp_dir, ctx)
+    }
+
+    #[tokio::test]
+    async fn test_code_search_parameters() {
+        let (_temp, ctx) = create_workspace();
+
+        let output = code_search(&json!({ "pattern": "total" }), &ctx)
+            .await
+            .unwrap();
+        let result = output["result"].as_str().unwrap();
+        assert!(result.starts_with("Found 4 matches:"), "{}", result);
+        assert!(result.contains("util.ts"));
+        assert!(result.contains("consts.rs"));
+
+        let output = code_search(
+            &json!({
+                "pattern": "Total",
+                "path": "src",
+                "case_sensitive": true,
+                "file_types": [".rs"],
+                "context_lines": 1,
+            }),
+            &ctx,
+        )
+        .await
+        .unwrap();
+        let result = output["result"].as_str().unwrap();
+        assert!(result.starts_with("Found 2 matches:"), "{}", result);
+        assert!(result.contains("  1- fn main() {\n  2: let Total = 1;"));
+        assert!(result.contains("  3: println!(\"{}\", Total);\n  4- }"));
+        assert!(!result.contains("util.ts"));
+
+        let output = code_search(
+            &json!({ "query": "total", "exclude_dirs": ["lib"], "max_results": 1 }),
+            &ctx,
+        )
+        .await
+        .unwrap();
+        let result = output["result"].as_str().unwrap();
+        assert!(!result.contains("consts.rs"));
+        assert_eq!(result.matches("File: ").count(), 1);
+
+        let output = code_search(&json!({ "pattern": "absent" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(output["result"], "No matches found");
+        assert!(code_search(&json!({ "pattern": "x", "path": ".." }), &ctx)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_glob_matches_recursively() {
+        let (_temp, ctx) = create_workspace();
+
+        let result = glob(&json!({ "pattern": "**/*.rs" }), &ctx).await.unwrap();
+        assert!(
+            result.starts_with("Found 2 file(s) matching \"**/*.rs\":"),
+            "{}",
+            result
+        );
+        assert!(result.contains(&format!("src{}main.rs (", std::path::MAIN_SEPARATOR)));
+
+        let result = glob(&json!({ "pattern": "*.ts", "path": "src/nested" }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.contains("\nutil.ts ("), "{}", result);
+
+        let result = glob(&json!({ "pattern": "**/*.rs", "max_results": 1 }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.starts_with("Found 1 file(s)"));
+
+        let result = glob(
+            &json!({ "pattern": "**/*.rs", "exclude_dirs": ["lib"] }),
+            &ctx,
+        )
+        .await
+        .unwrap();
+        assert!(result.starts_with("Found 1 file(s)"), "{}", result);
+        assert!(!result.contains("consts.rs"));
+
+        let result = glob(&json!({ "pattern": "**/MAIN.RS" }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.starts_with("No files found"), "{}", result);
+        let result = glob(
+            &json!({ "pattern": "**/MAIN.RS", "case_sensitive": false }),
+            &ctx,
+        )
+        .await
+        .unwrap();
+        assert!(result.starts_with("Found 1 file(s)"), "{}", result);
+
+        let result = glob(&json!({ "pattern": "*.py" }), &ctx).await.unwrap();
+        assert!(result.starts_with("No files found matching pattern \"*.py\""));
+        assert!(glob(&json!({}), &ctx).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_files_depth() {
+        let (_temp, ctx) = create_workspace();
+
+        let listing = list_files(&json!({}), &ctx).await.unwrap();
+        assert!(listing.contains("dirs: lib; src"), "{}", listing);
+        assert!(listing.contains("src/nested dirs: util.ts"), "{}", listing);
+
+        let listing = list_files(&json!({ "directory_path": "src", "max_depth": 1 }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(listing, "dirs: nested; main.rs");
+
+        let listing = list_files(&json!({ "path": "src", "recursive": false }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(listing, "dirs: nested; main.rs");
+
+        assert!(list_files(&json!({ "path": "src/main.rs" }), &ctx)
+            .await
+            .unwrap_err()
+            .contains("not a directory"));
+    }
+
+    #[test]
+    fn test_format_search_results_with_context() {
+        let results = vec![SearchResult {
+            file_path: "/w/a.rs".to_string(),
+            matches: vec![SearchMatch {
+                line_number: 2,
+                line_content: "    hit".to_string(),
+                byte_offset: 0,
+                context_before: vec!["before".to_string()],
+                context_after: vec!["after".to_string(), "more".to_string()],
+            }],
+        }];
+
+        assert_eq!(
+            format_search_results(&results),
+            "Found 1 matches:\nFile: /w/a.rs\n  1- before\n  2: hit\n  3- after\n  4- more"
+        );
+    }
+}
@@ -245,6 +245,61 @@ impl Database {
         Ok(results)
     }
 
+    /// Run a script of `;`-separated statements and then `statements` in one
+    /// transaction, so either all of them apply or none do
+    ///
+    /// `execute` only runs the first statement of its input, so scripts such
+    /// as migrations must go through here.
+    pub async fn execute_in_transaction(
+        &self,
+        script: &str,
+        statements: Vec<(String, Vec<serde_json::Value>)>,
+    ) -> Result<Vec<QueryResult>, String> {
+        let lock = self.conn.lock().await;
+        let conn = lock.as_ref().ok_or("Database not connected")?;
+
+        conn.execute_batch("BEGIN IMMEDIATE")
+            .await
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+
+        let result = async {
+            if !script.trim().is_empty() {
+                conn.execute_batch(script)
+                    .await
+                    .map_err(|e| format!("Execute error: {}", e))?;
+            }
+
+            let mut results = Vec::with_capacity(statements.len());
+            for (sql, params) in statements {
+                let libsql_params: Vec<libsql::Value> =
+                    params.iter().map(json_to_libsql_value).collect();
+                let rows_affected = conn
+                    .execute(&sql, libsql_params)
+                    .await
+                    .map_err(|e| format!("Execute error: {}", e))?;
+                results.push(QueryResult {
+                    rows: vec![],
+                    rows_affected,
+                });
+            }
+            Ok::<_, String>(results)
+        }
+        .await;
+
+        let result = match result {
+            Ok(results) => conn
+                .execute_batch("COMMIT")
+                .await
+                .map(|_| results)
+                .map_err(|e| format!("Failed to commit transaction: {}", e)),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            let _ = conn.execute_batch("ROLLBACK").await;
+        }
+        result
+    }
+
     /// Close the database connection gracefully
     /// This should be called when the application exits to release file handles
     #[allow(dead_code)]
@@ -1,8 +1,9 @@
 use super::types::{DiffHunk, DiffLine, DiffLineType, FileDiff, GitFileStatus};
-use git2::{Diff, DiffOptions, Error as GitError, Repository};
+use git2::{Diff, DiffOptions, Error as GitError, Patch, Repository};
 use lazy_static::lazy_static;
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::path::Path;
 use std::sync::Mutex;
 
 lazy_static! {
@@ -291,6 +292,93 @@ fn format_diff_as_text(diff: Diff) -> Result<String, GitError> {
     Ok(result)
 }
 
+/// Diffs two versions of a file's text without a repository
+pub fn diff_text(file_path: &str, old: &str, new: &str) -> Result<FileDiff, GitError> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(3);
+
+    let path = Path::new(file_path);
+    let patch = Patch::from_buffers(
+        old.as_bytes(),
+        Some(path),
+        new.as_bytes(),
+        Some(path),
+        Some(&mut opts),
+    )?;
+
+    let mut hunks = Vec::with_capacity(patch.num_hunks());
+    let mut additions = 0;
+    let mut deletions = 0;
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+        let mut lines = Vec::with_capacity(line_count);
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            let line_type = match line.origin() {
+                '+' => {
+                    additions += 1;
+                    DiffLineType::Addition
+                }
+                '-' => {
+                    deletions += 1;
+                    DiffLineType::Deletion
+                }
+                ' ' => DiffLineType::Context,
+                // End-of-file newline markers
+                _ => continue,
+            };
+            lines.push(DiffLine {
+                line_type,
+                old_line_number: line.old_lineno(),
+                new_line_number: line.new_lineno(),
+                content: String::from_utf8_lossy(line.content()).to_string(),
+            });
+        }
+        hunks.push(DiffHunk {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            header: String::from_utf8_lossy(hunk.header()).to_string(),
+            lines,
+        });
+    }
+
+    Ok(FileDiff {
+        path: file_path.to_string(),
+        old_path: None,
+        status: GitFileStatus::Modified,
+        hunks,
+        additions,
+        deletions,
+    })
+}
+
+/// Formats a FileDiff as a unified diff
+pub fn format_unified_diff(diff: &FileDiff) -> String {
+    let old_path = diff.old_path.as_deref().unwrap_or(&diff.path);
+    let mut out = format!("--- a/{}\n+++ b/{}\n", old_path, diff.path);
+
+    for hunk in &diff.hunks {
+        out.push_str(&hunk.header);
+        if !hunk.header.ends_with('\n') {
+            out.push('\n');
+        }
+        for line in &hunk.lines {
+            out.push(match line.line_type {
+                DiffLineType::Addition => '+',
+                DiffLineType::Deletion => '-',
+                DiffLineType::Context => ' ',
+            });
+            out.push_str(&line.content);
+            if !line.content.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -610,4 +698,35 @@ mod tests {
         assert!(diff_text.contains("README.md"), "Should contain README.md");
         assert!(diff_text.contains("code.rs"), "Should contain code.rs");
     }
+
+    #[test]
+    fn test_diff_text_without_repository() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\ni\n";
+
+        let diff = diff_text("src/letters.txt", old, new).unwrap();
+        assert_eq!(diff.additions, 2);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(diff.hunks.len(), 1);
+
+        let hunk = &diff.hunks[0];
+        assert_eq!(hunk.old_start, 1);
+        let deleted = hunk
+            .lines
+            .iter()
+            .find(|line| matches!(line.line_type, DiffLineType::Deletion))
+            .unwrap();
+        assert_eq!(deleted.content, "d\n");
+        assert_eq!(deleted.old_line_number, Some(4));
+        assert_eq!(deleted.new_line_number, None);
+
+        let unified = format_unified_diff(&diff);
+        assert!(
+            unified.starts_with("--- a/src/letters.txt\n+++ b/src/letters.txt\n@@ -1,8 +1,9 @@")
+        );
+        assert!(unified.contains("\n-d\n+D\n"));
+        assert!(unified.ends_with("+i\n"));
+
+        assert!(diff_text("same.txt", old, old).unwrap().hunks.is_empty());
+    }
 }
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::time::UNIX_EPOCH;
 
 /// Default maximum number of results to return from glob search
-const DEFAULT_MAX_GLOB_RESULTS: usize = 100;
+pub(crate) const DEFAULT_MAX_GLOB_RESULTS: usize = 100;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobResult {
@@ -16,11 +16,17 @@ pub struct GlobResult {
     pub modified_time: u64,
 }
 
-pub struct HighPerformanceGlob {}
+pub struct HighPerformanceGlob {
+    exclude_dirs: Option<Vec<String>>,
+    case_sensitive: bool,
+}
 
 impl Default for HighPerformanceGlob {
     fn default() -> Self {
-        Self {}
+        Self {
+            exclude_dirs: None,
+            case_sensitive: true,
+        }
     }
 }
 
@@ -29,6 +35,17 @@ impl HighPerformanceGlob {
         Self::default()
     }
 
+    /// Skip these directory names on top of the walker's defaults
+    pub fn with_exclude_dirs(mut self, exclude_dirs: Option<Vec<String>>) -> Self {
+        self.exclude_dirs = exclude_dirs;
+        self
+    }
+
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
     /// High-performance glob pattern matching with results sorted by modification time
     ///
     /// # Arguments
@@ -46,7 +63,8 @@ impl HighPerformanceGlob {
         }
 
         // Use unified walker module with glob-optimized configuration
-        let config = WalkerConfig::for_glob(root_path);
+        let config = WalkerConfig::for_glob(root_path)
+            .with_additional_excludes(self.exclude_dirs.clone().unwrap_or_default());
         let workspace_walker = WorkspaceWalker::new(root_path, config);
         let workspace_root = workspace_walker.workspace_root().cloned();
         let walker = workspace_walker.build();
@@ -137,7 +155,14 @@ impl HighPerformanceGlob {
         let normalized_path = relative_path.replace('\\', "/");
         let normalized_pattern = pattern.replace('\\', "/");
 
-        self.glob_match(&normalized_path, &normalized_pattern)
+        if self.case_sensitive {
+            self.glob_match(&normalized_path, &normalized_pattern)
+        } else {
+            self.glob_match(
+                &normalized_path.to_lowercase(),
+                &normalized_pattern.to_lowercase(),
+            )
+        }
     }
 
     /// Simple glob pattern matching implementation
@@ -448,6 +473,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_case_insensitive_and_exclude_dirs() {
+        let temp_dir = create_test_directory();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let results = HighPerformanceGlob::new()
+            .search_files_by_glob("**/button.tsx", root, 1000)
+            .unwrap();
+        assert!(results.is_empty());
+
+        let results = HighPerformanceGlob::new()
+            .with_case_sensitive(false)
+            .search_files_by_glob("**/button.tsx", root, 1000)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = HighPerformanceGlob::new()
+            .with_exclude_dirs(Some(vec!["utils".to_string()]))
+            .search_files_by_glob("**/*.ts", root, 1000)
+            .unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| !r.path.contains("helper.ts")));
+    }
+
     #[test]
     fn test_glob_result_serialization() {
         let result = GlobResult {
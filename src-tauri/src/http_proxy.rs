@@ -64,7 +64,7 @@ fn validate_url(url_str: &str, allow_private_ip: bool) -> Result<(), String> {
 }
 
 /// Check if an IP address is private/internal
-fn is_private_ip(ip: &IpAddr) -> bool {
+pub(crate) fn is_private_ip(ip: &IpAddr) -> bool {
     match ip {
         IpAddr::V4(ipv4) => {
             // Loopback: 127.0.0.0/8
@@ -1,6 +1,7 @@
 mod analytics;
 mod archive;
 mod background_tasks;
+mod cli;
 mod code_navigation;
 mod constants;
 mod core;
@@ -20,6 +21,7 @@ mod lint;
 mod list_files;
 mod llm;
 mod lsp;
+mod lsp_client;
 mod oauth_callback_server;
 mod platform;
 mod script_executor;
@@ -71,9 +73,12 @@ use window_manager::{create_window, WindowRegistry, WindowState};
 static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
 
 /// Server information for client discovery
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerInfo {
     pub addr: std::net::SocketAddr,
+    /// API token the frontend sends as its bearer credential
+    pub token: Option<String>,
 }
 
 /// Initialize the global app handle
@@ -279,6 +284,12 @@ fn get_all_project_windows(
     state.window_registry.get_all_windows()
 }
 
+/// Address and API token of the embedded server, once it is up
+#[tauri::command]
+fn get_server_info(app_handle: AppHandle) -> Option<ServerInfo> {
+    app_handle.try_state::<ServerInfo>().map(|info| info.inner().clone())
+}
+
 #[tauri::command]
 fn get_current_window_label(window: tauri::Window) -> Result<String, String> {
     Ok(window.label().to_string())
@@ -780,6 +791,16 @@ where
     trace_writer
 }
 
+/// Entry point of the headless `talkcody-server` binary
+pub fn run_server() -> std::process::ExitCode {
+    cli::server::run()
+}
+
+/// Entry point of `talkcody run`, given the arguments after `run`
+pub fn run_cli(args: Vec<String>) -> std::process::ExitCode {
+    cli::run::run(args)
+}
+
 pub fn run() {
     tauri::Builder::default()
         .manage(AppState {
@@ -824,23 +845,15 @@ pub fn run() {
             let server_handle = app.handle().clone();
             let server_config_clone = server_config.clone();
             tauri::async_runtime::spawn(async move {
-                match server::state::ServerStateFactory::create(server_config_clone, event_tx).await {
-                    Ok(server_state) => {
-                        // Start server with the configured state
-                        let bind_addr = std::net::SocketAddr::from(([127, 0, 0, 1], 0));
-                        match tokio::net::TcpListener::bind(bind_addr).await {
-                            Ok(listener) => {
-                                let addr = listener.local_addr().unwrap_or(bind_addr);
-                                log::info!("Cloud backend server started on {}", addr);
-                                server_handle.manage(ServerInfo { addr });
-                            }
-                            Err(e) => {
-                                log::error!("Failed to bind server: {}", e);
-                            }
-                        }
+                match server::start_desktop_server(server_config_clone, event_tx).await {
+                    Ok((handle, token)) => {
+                        server_handle.manage(ServerInfo {
+                            addr: handle.addr,
+                            token,
+                        });
                     }
                     Err(e) => {
-                        log::error!("Failed to create server state: {}", e);
+                        log::error!("Failed to start cloud backend server: {}", e);
                     }
                 }
             });
@@ -1004,6 +1017,7 @@ pub fn run() {
             create_project_window,
             get_all_project_windows,
             get_current_window_label,
+            get_server_info,
             get_window_info,
             check_project_window_exists,
             focus_project_window,
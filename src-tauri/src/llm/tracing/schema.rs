@@ -1,15 +1,12 @@
 // Database schema for LLM tracing
 // Creates tables for traces, spans, and span events
 
-#[cfg(test)]
 use std::sync::Arc;
 
-#[cfg(test)]
 use crate::database::Database;
 
 /// Initializes the tracing database schema
 /// Creates tables and indexes if they don't exist
-#[cfg(test)]
 pub async fn init_tracing_schema(db: &Arc<Database>) -> Result<(), String> {
     // Create tables
     db.execute(
@@ -390,6 +390,14 @@ impl TraceWriter {
         }
     }
 
+    /// Shutdown the writer gracefully, flushing pending writes
+    pub async fn shutdown(&self) {
+        match self.sender.send(TraceCommand::Shutdown).await {
+            Ok(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            Err(e) => log::error!("Failed to send shutdown command: {:?}", e),
+        }
+    }
+
     /// Shutdown the writer gracefully (blocking version for sync contexts)
     /// This creates a new runtime to execute the async shutdown
     pub fn shutdown_blocking(&self) {
@@ -16,8 +16,10 @@ use std::process::Stdio;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStdout, Command as TokioCommand};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::process::{Child, ChildStdin, Command as TokioCommand};
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 
@@ -668,9 +670,38 @@ fn emit_download_progress(
     }
 }
 
+/// Get the LSP language identifier for a file based on its extension
+pub fn language_id_for_path(path: &std::path::Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let language = match ext.as_str() {
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "javascriptreact",
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "vue" => "vue",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Get the server language for a document language identifier.
+/// Related languages share one server (e.g. TSX and JavaScript use the TypeScript server).
+pub fn server_language_for(language_id: &str) -> &str {
+    match language_id {
+        "typescript" | "typescriptreact" | "javascript" | "javascriptreact" => "typescript",
+        "c" | "cpp" => "cpp",
+        other => other,
+    }
+}
+
 /// Get the command for a language server
 /// Returns (command, args) or None if not available
-fn get_lsp_command(language: &str) -> Option<(String, Vec<String>)> {
+pub(crate) fn get_lsp_command(language: &str) -> Option<(String, Vec<String>)> {
     match language {
         "typescript" | "javascript" | "typescriptreact" | "javascriptreact" => {
             get_typescript_server_command()
@@ -738,7 +769,7 @@ fn get_lsp_command(language: &str) -> Option<(String, Vec<String>)> {
 static SERVER_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 /// Generate a unique server ID
-fn generate_server_id(language: &str) -> String {
+pub(crate) fn generate_server_id(language: &str) -> String {
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -759,7 +790,9 @@ fn parse_content_length(headers: &str) -> Option<usize> {
 }
 
 /// Read a single LSP message from stdout
-async fn read_lsp_message(reader: &mut BufReader<ChildStdout>) -> Result<String, String> {
+pub(crate) async fn read_lsp_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<String, String> {
     // Read headers until empty line
     let mut headers = String::new();
     loop {
@@ -791,7 +824,10 @@ async fn read_lsp_message(reader: &mut BufReader<ChildStdout>) -> Result<String,
 }
 
 /// Write an LSP message to stdin
-async fn write_lsp_message(stdin: &mut ChildStdin, message: &str) -> Result<(), String> {
+pub(crate) async fn write_lsp_message<W: AsyncWrite + Unpin>(
+    stdin: &mut W,
+    message: &str,
+) -> Result<(), String> {
     let header = format!("Content-Length: {}\r\n\r\n", message.len());
     stdin
         .write_all(header.as_bytes())
@@ -809,7 +845,7 @@ async fn write_lsp_message(stdin: &mut ChildStdin, message: &str) -> Result<(),
 }
 
 /// Validate root_path to ensure it's a valid directory
-fn validate_root_path(root_path: &str) -> Result<PathBuf, String> {
+pub(crate) fn validate_root_path(root_path: &str) -> Result<PathBuf, String> {
     let path = PathBuf::from(root_path);
 
     // Check if path is absolute
@@ -1696,4 +1732,25 @@ mod tests {
         let second = generate_server_id("vue");
         assert_ne!(first, second);
     }
+
+    #[test]
+    fn test_language_id_for_path() {
+        use std::path::Path;
+
+        assert_eq!(language_id_for_path(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(
+            language_id_for_path(Path::new("App.TSX")),
+            Some("typescriptreact")
+        );
+        assert_eq!(language_id_for_path(Path::new("lib.hpp")), Some("cpp"));
+        assert_eq!(language_id_for_path(Path::new("README.md")), None);
+        assert_eq!(language_id_for_path(Path::new("Makefile")), None);
+    }
+
+    #[test]
+    fn test_server_language_for() {
+        assert_eq!(server_language_for("javascriptreact"), "typescript");
+        assert_eq!(server_language_for("c"), "cpp");
+        assert_eq!(server_language_for("rust"), "rust");
+    }
 }
@@ -0,0 +1,1142 @@
+//! Headless LSP Client
+//!
+//! Drives an `LspServer` over JSON-RPC without a Tauri `AppHandle`, so the core
+//! runtime and server mode can issue requests and await their responses.
+//! Responses are correlated with requests by JSON-RPC id, `publishDiagnostics`
+//! notifications are cached per document, and open documents are kept in sync
+//! with `didOpen` / `didChange`.
+
+use crate::lsp::{get_lsp_command, read_lsp_message, write_lsp_message, LspServer};
+use crate::platform::types::{LspDiagnostic, LspLocation, LspPosition, LspRange, LspSymbol};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::process::Command as TokioCommand;
+use tokio::sync::{oneshot, Mutex, Notify};
+
+/// Timeout for a single LSP request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+type LspWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// State shared between the client and its reader task
+struct ClientState {
+    writer: Mutex<LspWriter>,
+    /// In-flight requests waiting for a response, keyed by JSON-RPC id
+    pending: Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>,
+    /// Latest `publishDiagnostics` per document URI
+    diagnostics: Mutex<HashMap<String, Vec<LspDiagnostic>>>,
+    diagnostics_updated: Notify,
+}
+
+/// A document opened on the server
+struct OpenDocument {
+    version: i64,
+    text: String,
+}
+
+/// Client for a single language server
+pub struct LspClient {
+    server: Arc<Mutex<LspServer>>,
+    state: Arc<ClientState>,
+    next_id: AtomicU64,
+    documents: Mutex<HashMap<String, OpenDocument>>,
+    capabilities: OnceLock<Value>,
+}
+
+impl LspClient {
+    /// Spawn the language server for `language` in `root_path` and initialize it
+    pub async fn start(
+        server_id: String,
+        language: &str,
+        root_path: &Path,
+    ) -> Result<Self, String> {
+        let (command, args) = get_lsp_command(language)
+            .ok_or_else(|| format!("No LSP server available for language: {}", language))?;
+
+        log::info!(
+            "Starting headless LSP server for {} in {}: {} {:?}",
+            language,
+            root_path.display(),
+            command,
+            args
+        );
+
+        let mut child = TokioCommand::new(&command)
+            .args(&args)
+            .current_dir(root_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to spawn LSP server '{}': {}", command, e))?;
+
+        let stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+        let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+        let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+
+        // Drain stderr to avoid pipe backpressure
+        let stderr_server_id = server_id.clone();
+        let stderr_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut line = String::new();
+            while let Ok(n) = reader.read_line(&mut line).await {
+                if n == 0 {
+                    break;
+                }
+                let trimmed = line.trim_end();
+                if !trimmed.is_empty() {
+                    log::debug!("LSP stderr [{}]: {}", stderr_server_id, trimmed);
+                }
+                line.clear();
+            }
+        });
+
+        let mut server = LspServer::new(
+            server_id,
+            language.to_string(),
+            root_path.to_string_lossy().to_string(),
+        );
+        server.child = Some(child);
+        server.stderr_task = Some(stderr_task);
+
+        Self::connect(server, stdout, stdin, root_path).await
+    }
+
+    /// Attach to a server over arbitrary streams and initialize it.
+    /// `reader` carries server output, `writer` carries client messages.
+    pub async fn connect<R, W>(
+        mut server: LspServer,
+        reader: R,
+        writer: W,
+        root_path: &Path,
+    ) -> Result<Self, String>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let state = Arc::new(ClientState {
+            writer: Mutex::new(Box::new(writer)),
+            pending: Mutex::new(HashMap::new()),
+            diagnostics: Mutex::new(HashMap::new()),
+            diagnostics_updated: Notify::new(),
+        });
+
+        server.stdout_task = Some(tokio::spawn(read_loop(
+            BufReader::new(reader),
+            state.clone(),
+        )));
+
+        let client = Self {
+            server: Arc::new(Mutex::new(server)),
+            state,
+            next_id: AtomicU64::new(1),
+            documents: Mutex::new(HashMap::new()),
+            capabilities: OnceLock::new(),
+        };
+
+        client.initialize(root_path).await?;
+        Ok(client)
+    }
+
+    /// The underlying server instance, for registration in an `LspRegistry`
+    pub fn server(&self) -> Arc<Mutex<LspServer>> {
+        self.server.clone()
+    }
+
+    /// Whether the server is still usable: initialized, its output still open
+    /// and, for spawned servers, its process still running
+    pub async fn is_alive(&self) -> bool {
+        let mut server = self.server.lock().await;
+        if !server.is_initialized {
+            return false;
+        }
+        if server
+            .stdout_task
+            .as_ref()
+            .is_some_and(|task| task.is_finished())
+        {
+            return false;
+        }
+        match server.child.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    /// Capabilities returned by the server's `initialize` response
+    pub fn capabilities(&self) -> &Value {
+        self.capabilities.get().unwrap_or(&Value::Null)
+    }
+
+    /// Send a request and wait for its response
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.state.pending.lock().await.insert(id, tx);
+
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+
+        if let Err(e) = self.state.send(&message).await {
+            self.state.pending.lock().await.remove(&id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!("LSP request '{}' was dropped", method)),
+            Err(_) => {
+                self.state.pending.lock().await.remove(&id);
+                Err(format!(
+                    "LSP request '{}' timed out after {:?}",
+                    method, REQUEST_TIMEOUT
+                ))
+            }
+        }
+    }
+
+    /// Send a notification (no response expected)
+    pub async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        });
+        self.state.send(&message).await
+    }
+
+    // ========================================================================
+    // Document sync
+    // ========================================================================
+
+    /// Open `path` on the server, or send its current content if it changed on disk.
+    /// Returns the document URI.
+    pub async fn sync_document(&self, path: &Path, language_id: &str) -> Result<String, String> {
+        let uri = path_to_uri(path)?;
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        self.sync_text(&uri, language_id, text).await?;
+        Ok(uri)
+    }
+
+    /// Send `didOpen` for a new document, or a full-text `didChange` when the
+    /// content differs from what the server last saw
+    pub async fn sync_text(
+        &self,
+        uri: &str,
+        language_id: &str,
+        text: String,
+    ) -> Result<(), String> {
+        let mut documents = self.documents.lock().await;
+        if documents.get(uri).is_some_and(|doc| doc.text == text) {
+            return Ok(());
+        }
+
+        // Published diagnostics describe the previous content
+        self.state.diagnostics.lock().await.remove(uri);
+
+        match documents.get_mut(uri) {
+            None => {
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": language_id,
+                            "version": 1,
+                            "text": text,
+                        }
+                    }),
+                )
+                .await?;
+                documents.insert(uri.to_string(), OpenDocument { version: 1, text });
+            }
+            Some(document) => {
+                let version = document.version + 1;
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri, "version": version },
+                        "contentChanges": [{ "text": text }],
+                    }),
+                )
+                .await?;
+                document.version = version;
+                document.text = text;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Close a document previously opened with `sync_document` / `sync_text`
+    pub async fn close_document(&self, uri: &str) -> Result<(), String> {
+        if self.documents.lock().await.remove(uri).is_none() {
+            return Ok(());
+        }
+        self.state.diagnostics.lock().await.remove(uri);
+        self.notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+        .await
+    }
+
+    /// Version of an open document, if any
+    pub async fn document_version(&self, uri: &str) -> Option<i64> {
+        self.documents.lock().await.get(uri).map(|doc| doc.version)
+    }
+
+    // ========================================================================
+    // Typed requests
+    // ========================================================================
+
+    /// `textDocument/definition`
+    pub async fn definition(
+        &self,
+        uri: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<LspLocation>, String> {
+        let response = self
+            .request(
+                "textDocument/definition",
+                text_document_position(uri, line, character),
+            )
+            .await?;
+        Ok(parse_locations(&response))
+    }
+
+    /// `textDocument/references`
+    pub async fn references(
+        &self,
+        uri: &str,
+        line: u32,
+        character: u32,
+        include_declaration: bool,
+    ) -> Result<Vec<LspLocation>, String> {
+        let mut params = text_document_position(uri, line, character);
+        params["context"] = json!({ "includeDeclaration": include_declaration });
+        let response = self.request("textDocument/references", params).await?;
+        Ok(parse_locations(&response))
+    }
+
+    /// `textDocument/hover`
+    pub async fn hover(
+        &self,
+        uri: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Option<String>, String> {
+        let response = self
+            .request(
+                "textDocument/hover",
+                text_document_position(uri, line, character),
+            )
+            .await?;
+        Ok(hover_text(&response))
+    }
+
+    /// `textDocument/documentSymbol`, with nested symbols flattened
+    pub async fn document_symbols(&self, uri: &str) -> Result<Vec<LspSymbol>, String> {
+        let response = self
+            .request(
+                "textDocument/documentSymbol",
+                json!({ "textDocument": { "uri": uri } }),
+            )
+            .await?;
+        Ok(parse_symbols(&response, uri))
+    }
+
+    /// `workspace/symbol`
+    pub async fn workspace_symbols(&self, query: &str) -> Result<Vec<LspSymbol>, String> {
+        let response = self
+            .request("workspace/symbol", json!({ "query": query }))
+            .await?;
+        Ok(parse_symbols(&response, ""))
+    }
+
+    /// Diagnostics most recently published for `uri`, if any
+    pub async fn published_diagnostics(&self, uri: &str) -> Option<Vec<LspDiagnostic>> {
+        self.state.diagnostics.lock().await.get(uri).cloned()
+    }
+
+    /// Diagnostics for `uri`. Uses `textDocument/diagnostic` when the server
+    /// supports pull diagnostics, otherwise waits up to `wait` for a
+    /// `publishDiagnostics` notification.
+    pub async fn diagnostics(
+        &self,
+        uri: &str,
+        wait: Duration,
+    ) -> Result<Vec<LspDiagnostic>, String> {
+        if self.capabilities().get("diagnosticProvider").is_some() {
+            let response = self
+                .request(
+                    "textDocument/diagnostic",
+                    json!({ "textDocument": { "uri": uri } }),
+                )
+                .await?;
+            return Ok(parse_diagnostics(
+                response.get("items").unwrap_or(&Value::Null),
+            ));
+        }
+
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            // Register for wakeups before checking the cache so no update is missed
+            let updated = self.state.diagnostics_updated.notified();
+            if let Some(diagnostics) = self.published_diagnostics(uri).await {
+                return Ok(diagnostics);
+            }
+            if tokio::time::timeout_at(deadline, updated).await.is_err() {
+                return Ok(Vec::new());
+            }
+        }
+    }
+
+    /// Shut the server down gracefully, then stop the process
+    pub async fn shutdown(&self) {
+        let _ = tokio::time::timeout(
+            Duration::from_secs(2),
+            self.request("shutdown", Value::Null),
+        )
+        .await;
+        let _ = self.notify("exit", Value::Null).await;
+
+        let mut server = self.server.lock().await;
+        if let Some(task) = server.stdout_task.take() {
+            task.abort();
+        }
+        if let Some(task) = server.stderr_task.take() {
+            task.abort();
+        }
+        if let Some(mut child) = server.child.take() {
+            let _ = child.kill().await;
+        }
+        server.is_initialized = false;
+    }
+
+    /// Perform the `initialize` / `initialized` handshake
+    async fn initialize(&self, root_path: &Path) -> Result<(), String> {
+        let root_uri = path_to_uri(root_path)?;
+        let root_name = root_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| root_path.to_string_lossy().to_string());
+
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "rootPath": root_path.to_string_lossy(),
+            "workspaceFolders": [{ "uri": root_uri, "name": root_name }],
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "dynamicRegistration": false, "didSave": false },
+                    "definition": { "linkSupport": true },
+                    "references": {},
+                    "hover": { "contentFormat": ["markdown", "plaintext"] },
+                    "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+                    "publishDiagnostics": { "relatedInformation": false },
+                    "diagnostic": { "dynamicRegistration": false }
+                },
+                "workspace": {
+                    "symbol": {},
+                    "workspaceFolders": true,
+                    "configuration": true
+                }
+            }
+        });
+
+        let result = self.request("initialize", params).await?;
+        let _ = self
+            .capabilities
+            .set(result.get("capabilities").cloned().unwrap_or(Value::Null));
+        self.notify("initialized", json!({})).await?;
+        self.server.lock().await.is_initialized = true;
+        Ok(())
+    }
+}
+
+impl ClientState {
+    /// Write a JSON-RPC message to the server
+    async fn send(&self, message: &Value) -> Result<(), String> {
+        let mut writer = self.writer.lock().await;
+        write_lsp_message(&mut *writer, &message.to_string()).await
+    }
+}
+
+/// Read messages from the server until its output closes
+async fn read_loop<R: AsyncRead + Unpin>(mut reader: BufReader<R>, state: Arc<ClientState>) {
+    loop {
+        let raw = match read_lsp_message(&mut reader).await {
+            Ok(raw) => raw,
+            Err(e) => {
+                log::info!("Headless LSP reader ended: {}", e);
+                break;
+            }
+        };
+
+        match serde_json::from_str::<Value>(&raw) {
+            Ok(message) => handle_incoming(message, &state).await,
+            Err(e) => log::warn!("Ignoring malformed LSP message: {}", e),
+        }
+    }
+
+    // Fail outstanding requests so callers don't wait for the timeout
+    for (_, sender) in state.pending.lock().await.drain() {
+        let _ = sender.send(Err("LSP server exited".to_string()));
+    }
+}
+
+/// Dispatch a single incoming message: responses resolve pending requests,
+/// notifications update caches, and server-to-client requests get a minimal
+/// reply so the server doesn't stall
+async fn handle_incoming(message: Value, state: &ClientState) {
+    let method = message.get("method").and_then(|m| m.as_str());
+    let id = message.get("id").cloned();
+
+    match (method, id) {
+        (None, Some(id)) => {
+            let Some(id) = id.as_u64() else {
+                return;
+            };
+            if let Some(sender) = state.pending.lock().await.remove(&id) {
+                let result = match message.get("error") {
+                    Some(error) => Err(format!(
+                        "LSP error: {}",
+                        error
+                            .get("message")
+                            .and_then(|m| m.as_str())
+                            .unwrap_or("unknown error")
+                    )),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                let _ = sender.send(result);
+            }
+        }
+        (Some(method), Some(id)) => {
+            let result = match method {
+                "workspace/configuration" => {
+                    let count = message
+                        .get("params")
+                        .and_then(|p| p.get("items"))
+                        .and_then(|items| items.as_array())
+                        .map(|items| items.len())
+                        .unwrap_or(0);
+                    Value::Array(vec![Value::Null; count])
+                }
+                _ => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            if let Err(e) = state.send(&response).await {
+                log::warn!("Failed to answer LSP request '{}': {}", method, e);
+            }
+        }
+        (Some("textDocument/publishDiagnostics"), None) => {
+            let params = message.get("params").unwrap_or(&Value::Null);
+            let Some(uri) = params.get("uri").and_then(|u| u.as_str()) else {
+                return;
+            };
+            let diagnostics = parse_diagnostics(params.get("diagnostics").unwrap_or(&Value::Null));
+            state
+                .diagnostics
+                .lock()
+                .await
+                .insert(uri.to_string(), diagnostics);
+            state.diagnostics_updated.notify_waiters();
+        }
+        (Some(method), None) => {
+            log::debug!("LSP notification: {}", method);
+        }
+        (None, None) => {}
+    }
+}
+
+/// Convert a filesystem path into a `file://` URI
+pub fn path_to_uri(path: &Path) -> Result<String, String> {
+    url::Url::from_file_path(path)
+        .map(|url| url.to_string())
+        .map_err(|_| format!("Invalid file path: {}", path.display()))
+}
+
+/// Convert a `file://` URI back into a filesystem path
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+// ============================================================================
+// Response parsing
+// ============================================================================
+
+fn text_document_position(uri: &str, line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn parse_range(value: &Value) -> Option<LspRange> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+/// Parse a `Location` or `LocationLink`
+fn parse_location(value: &Value) -> Option<LspLocation> {
+    if let Some(uri) = value.get("uri").and_then(|u| u.as_str()) {
+        // WorkspaceSymbol locations may omit the range
+        let range = value
+            .get("range")
+            .and_then(parse_range)
+            .unwrap_or(LspRange {
+                start: LspPosition {
+                    line: 0,
+                    character: 0,
+                },
+                end: LspPosition {
+                    line: 0,
+                    character: 0,
+                },
+            });
+        return Some(LspLocation {
+            uri: uri.to_string(),
+            range,
+        });
+    }
+
+    let uri = value.get("targetUri").and_then(|u| u.as_str())?;
+    let range = value
+        .get("targetSelectionRange")
+        .or_else(|| value.get("targetRange"))
+        .and_then(parse_range)?;
+    Some(LspLocation {
+        uri: uri.to_string(),
+        range,
+    })
+}
+
+/// Parse `Location | Location[] | LocationLink[] | null`
+fn parse_locations(value: &Value) -> Vec<LspLocation> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(parse_location).collect(),
+        Value::Object(_) => parse_location(value).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse `DocumentSymbol[] | SymbolInformation[] | WorkspaceSymbol[] | null`,
+/// flattening nested document symbols
+fn parse_symbols(value: &Value, uri: &str) -> Vec<LspSymbol> {
+    let mut symbols = Vec::new();
+    if let Some(items) = value.as_array() {
+        for item in items {
+            collect_symbol(item, uri, None, &mut symbols);
+        }
+    }
+    symbols
+}
+
+fn collect_symbol(item: &Value, uri: &str, container: Option<&str>, out: &mut Vec<LspSymbol>) {
+    let Some(name) = item.get("name").and_then(|n| n.as_str()) else {
+        return;
+    };
+    let kind = symbol_kind_name(item.get("kind").and_then(|k| k.as_u64()).unwrap_or(0));
+
+    if let Some(location) = item.get("location").and_then(parse_location) {
+        out.push(LspSymbol {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            location,
+            container_name: item
+                .get("containerName")
+                .and_then(|c| c.as_str())
+                .map(String::from)
+                .or_else(|| container.map(String::from)),
+        });
+        return;
+    }
+
+    let Some(range) = item
+        .get("selectionRange")
+        .or_else(|| item.get("range"))
+        .and_then(parse_range)
+    else {
+        return;
+    };
+
+    out.push(LspSymbol {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        location: LspLocation {
+            uri: uri.to_string(),
+            range,
+        },
+        container_name: container.map(String::from),
+    });
+
+    if let Some(children) = item.get("children").and_then(|c| c.as_array()) {
+        for child in children {
+            collect_symbol(child, uri, Some(name), out);
+        }
+    }
+}
+
+/// Map an LSP `SymbolKind` number to its name
+fn symbol_kind_name(kind: u64) -> &'static str {
+    match kind {
+        1 => "file",
+        2 => "module",
+        3 => "namespace",
+        4 => "package",
+        5 => "class",
+        6 => "method",
+        7 => "property",
+        8 => "field",
+        9 => "constructor",
+        10 => "enum",
+        11 => "interface",
+        12 => "function",
+        13 => "variable",
+        14 => "constant",
+        15 => "string",
+        16 => "number",
+        17 => "boolean",
+        18 => "array",
+        19 => "object",
+        20 => "key",
+        21 => "null",
+        22 => "enum_member",
+        23 => "struct",
+        24 => "event",
+        25 => "operator",
+        26 => "type_parameter",
+        _ => "unknown",
+    }
+}
+
+/// Extract text from hover `contents` (`MarkupContent | MarkedString | MarkedString[]`)
+fn hover_text(value: &Value) -> Option<String> {
+    let contents = value.get("contents")?;
+    let text = match contents {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(marked_string_text)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => marked_string_text(contents)?,
+    };
+
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn marked_string_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Object(_) => value
+            .get("value")
+            .and_then(|v| v.as_str())
+            .map(String::from),
+        _ => None,
+    }
+}
+
+/// Parse a `Diagnostic[]`
+fn parse_diagnostics(value: &Value) -> Vec<LspDiagnostic> {
+    let Some(items) = value.as_array() else {
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let range = item.get("range").and_then(parse_range)?;
+            let message = item.get("message").and_then(|m| m.as_str())?;
+            let severity = match item.get("severity").and_then(|s| s.as_u64()) {
+                Some(2) => "warning",
+                Some(3) => "information",
+                Some(4) => "hint",
+                _ => "error",
+            };
+            let code = item.get("code").and_then(|code| match code {
+                Value::String(code) => Some(code.clone()),
+                Value::Number(code) => Some(code.to_string()),
+                _ => None,
+            });
+
+            Some(LspDiagnostic {
+                range,
+                severity: severity.to_string(),
+                message: message.to_string(),
+                source: item
+                    .get("source")
+                    .and_then(|s| s.as_str())
+                    .map(String::from),
+                code,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+
+    const FILE_URI: &str = "file:///workspace/src/main.rs";
+
+    fn range_json(line: u32) -> Value {
+        json!({
+            "start": { "line": line, "character": 0 },
+            "end": { "line": line, "character": 4 }
+        })
+    }
+
+    async fn send_to_client(writer: &mut WriteHalf<DuplexStream>, message: Value) {
+        write_lsp_message(writer, &message.to_string())
+            .await
+            .unwrap();
+    }
+
+    /// Minimal language server speaking JSON-RPC over an in-memory pipe.
+    /// Hover requests are held until a second one arrives and then answered in
+    /// reverse order, so clients must correlate by id.
+    async fn run_stub_server(
+        mut reader: BufReader<ReadHalf<DuplexStream>>,
+        mut writer: WriteHalf<DuplexStream>,
+        pull_diagnostics: bool,
+    ) {
+        let mut held_hover: Option<(Value, String)> = None;
+
+        while let Ok(raw) = read_lsp_message(&mut reader).await {
+            let message: Value = serde_json::from_str(&raw).unwrap();
+            let method = message["method"].as_str().unwrap_or("").to_string();
+            let id = message.get("id").cloned();
+
+            match method.as_str() {
+                "initialize" => {
+                    let mut capabilities = json!({ "hoverProvider": true });
+                    if pull_diagnostics {
+                        capabilities["diagnosticProvider"] = json!({});
+                    }
+                    send_to_client(
+                        &mut writer,
+                        json!({ "jsonrpc": "2.0", "id": id, "result": { "capabilities": capabilities } }),
+                    )
+                    .await;
+                }
+                "initialized" => {
+                    // Exercise a server-to-client request during startup
+                    send_to_client(
+                        &mut writer,
+                        json!({
+                            "jsonrpc": "2.0",
+                            "id": "config-1",
+                            "method": "workspace/configuration",
+                            "params": { "items": [{ "section": "a" }, { "section": "b" }] }
+                        }),
+                    )
+                    .await;
+                }
+                "textDocument/didOpen" | "textDocument/didChange" => {
+                    let document = &message["params"]["textDocument"];
+                    send_to_client(
+                        &mut writer,
+                        json!({
+                            "jsonrpc": "2.0",
+                            "method": "textDocument/publishDiagnostics",
+                            "params": {
+                                "uri": document["uri"],
+                                "diagnostics": [{
+                                    "range": range_json(0),
+                                    "severity": 1,
+                                    "message": format!("version {}", document["version"])
+                                }]
+                            }
+                        }),
+                    )
+                    .await;
+                }
+                "textDocument/definition" => {
+                    let uri = message["params"]["textDocument"]["uri"].clone();
+                    let line = message["params"]["position"]["line"].as_u64().unwrap() as u32;
+                    send_to_client(
+                        &mut writer,
+                        json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "result": [{
+                                "targetUri": uri,
+                                "targetRange": range_json(line + 10),
+                                "targetSelectionRange": range_json(line + 10)
+                            }]
+                        }),
+                    )
+                    .await;
+                }
+                "textDocument/hover" => {
+                    let text = format!("hover at {}", message["params"]["position"]["line"]);
+                    match held_hover.take() {
+                        None => held_hover = Some((id.unwrap(), text)),
+                        Some((first_id, first_text)) => {
+                            send_to_client(
+                                &mut writer,
+                                json!({ "jsonrpc": "2.0", "id": id, "result": { "contents": text } }),
+                            )
+                            .await;
+                            send_to_client(
+                                &mut writer,
+                                json!({ "jsonrpc": "2.0", "id": first_id, "result": { "contents": first_text } }),
+                            )
+                            .await;
+                        }
+                    }
+                }
+                "textDocument/diagnostic" => {
+                    send_to_client(
+                        &mut writer,
+                        json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "result": {
+                                "kind": "full",
+                                "items": [{ "range": range_json(2), "severity": 2, "message": "pulled" }]
+                            }
+                        }),
+                    )
+                    .await;
+                }
+                "shutdown" => {
+                    send_to_client(
+                        &mut writer,
+                        json!({ "jsonrpc": "2.0", "id": id, "result": null }),
+                    )
+                    .await;
+                }
+                "exit" => break,
+                "" => {
+                    // Client's reply to our workspace/configuration request
+                    assert_eq!(message["id"], "config-1");
+                    assert_eq!(message["result"], json!([null, null]));
+                }
+                other => {
+                    if id.is_some() {
+                        send_to_client(
+                            &mut writer,
+                            json!({
+                                "jsonrpc": "2.0",
+                                "id": id,
+                                "error": { "code": -32601, "message": format!("unhandled {}", other) }
+                            }),
+                        )
+                        .await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn connect_stub(pull_diagnostics: bool) -> LspClient {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (server_read, server_write) = tokio::io::split(server_side);
+        tokio::spawn(run_stub_server(
+            BufReader::new(server_read),
+            server_write,
+            pull_diagnostics,
+        ));
+
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let root = std::env::temp_dir();
+        let server = LspServer::new(
+            "stub".to_string(),
+            "rust".to_string(),
+            root.to_string_lossy().to_string(),
+        );
+        LspClient::connect(server, client_read, client_write, &root)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connect_initializes_stub_server() {
+        let client = connect_stub(false).await;
+        assert!(client.server().lock().await.is_initialized);
+        assert_eq!(client.capabilities()["hoverProvider"], true);
+        client.shutdown().await;
+        assert!(!client.server().lock().await.is_initialized);
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_correlated_by_id() {
+        let client = connect_stub(false).await;
+
+        let (first, second) =
+            tokio::join!(client.hover(FILE_URI, 1, 0), client.hover(FILE_URI, 2, 0));
+        assert_eq!(first.unwrap().as_deref(), Some("hover at 1"));
+        assert_eq!(second.unwrap().as_deref(), Some("hover at 2"));
+
+        let locations = client.definition(FILE_URI, 3, 0).await.unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start.line, 13);
+
+        let error = client.document_symbols(FILE_URI).await.unwrap_err();
+        assert!(error.contains("unhandled textDocument/documentSymbol"));
+    }
+
+    #[tokio::test]
+    async fn test_document_sync_and_published_diagnostics() {
+        let client = connect_stub(false).await;
+        let wait = Duration::from_secs(5);
+
+        client
+            .sync_text(FILE_URI, "rust", "fn main() {}".to_string())
+            .await
+            .unwrap();
+        let diagnostics = client.diagnostics(FILE_URI, wait).await.unwrap();
+        assert_eq!(diagnostics[0].message, "version 1");
+        assert_eq!(client.document_version(FILE_URI).await, Some(1));
+
+        // Unchanged content is not re-sent
+        client
+            .sync_text(FILE_URI, "rust", "fn main() {}".to_string())
+            .await
+            .unwrap();
+        assert_eq!(client.document_version(FILE_URI).await, Some(1));
+        assert!(client.published_diagnostics(FILE_URI).await.is_some());
+
+        client
+            .sync_text(FILE_URI, "rust", "fn main() { x }".to_string())
+            .await
+            .unwrap();
+        assert_eq!(client.document_version(FILE_URI).await, Some(2));
+        let diagnostics = client.diagnostics(FILE_URI, wait).await.unwrap();
+        assert_eq!(diagnostics[0].message, "version 2");
+        assert_eq!(diagnostics[0].severity, "error");
+
+        client.close_document(FILE_URI).await.unwrap();
+        assert_eq!(client.document_version(FILE_URI).await, None);
+        assert!(client.published_diagnostics(FILE_URI).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pull_diagnostics_when_supported() {
+        let client = connect_stub(true).await;
+        let diagnostics = client
+            .diagnostics(FILE_URI, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "pulled");
+        assert_eq!(diagnostics[0].severity, "warning");
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_after_server_exits() {
+        let client = connect_stub(false).await;
+        client.notify("exit", Value::Null).await.unwrap();
+        assert!(client.hover(FILE_URI, 0, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_is_alive_tracks_server_exit() {
+        let client = connect_stub(false).await;
+        assert!(client.is_alive().await);
+
+        client.notify("exit", Value::Null).await.unwrap();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while client.is_alive().await {
+            assert!(tokio::time::Instant::now() < deadline, "client still alive");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        let path = std::env::temp_dir().join("project").join("main.rs");
+        let uri = path_to_uri(&path).unwrap();
+        assert!(uri.starts_with("file://"));
+        assert_eq!(uri_to_path(&uri), Some(path));
+    }
+
+    #[test]
+    fn test_path_to_uri_rejects_relative() {
+        assert!(path_to_uri(Path::new("src/main.rs")).is_err());
+    }
+
+    #[test]
+    fn test_parse_locations_handles_links_and_single_location() {
+        let single = json!({ "uri": "file:///a.rs", "range": range_json(1) });
+        let locations = parse_locations(&single);
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start.line, 1);
+
+        let links = json!([{
+            "targetUri": "file:///b.rs",
+            "targetRange": range_json(0),
+            "targetSelectionRange": range_json(3)
+        }]);
+        let locations = parse_locations(&links);
+        assert_eq!(locations[0].uri, "file:///b.rs");
+        assert_eq!(locations[0].range.start.line, 3);
+
+        assert!(parse_locations(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_parse_symbols_flattens_document_symbols() {
+        let response = json!([{
+            "name": "Foo",
+            "kind": 23,
+            "range": range_json(0),
+            "selectionRange": range_json(0),
+            "children": [{ "name": "bar", "kind": 6, "range": range_json(1), "selectionRange": range_json(1) }]
+        }]);
+
+        let symbols = parse_symbols(&response, "file:///a.rs");
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].kind, "struct");
+        assert_eq!(symbols[1].name, "bar");
+        assert_eq!(symbols[1].container_name.as_deref(), Some("Foo"));
+        assert_eq!(symbols[1].location.uri, "file:///a.rs");
+    }
+
+    #[test]
+    fn test_hover_text_variants() {
+        let markup = json!({ "contents": { "kind": "markdown", "value": "fn main()" } });
+        assert_eq!(hover_text(&markup).as_deref(), Some("fn main()"));
+
+        let marked = json!({ "contents": ["text", { "language": "rust", "value": "code" }] });
+        assert_eq!(hover_text(&marked).as_deref(), Some("text\n\ncode"));
+
+        assert_eq!(hover_text(&json!({ "contents": "" })), None);
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let items = json!([{
+            "range": range_json(2),
+            "severity": 2,
+            "code": 6133,
+            "source": "ts",
+            "message": "unused"
+        }]);
+
+        let diagnostics = parse_diagnostics(&items);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, "warning");
+        assert_eq!(diagnostics[0].code.as_deref(), Some("6133"));
+    }
+}
@@ -1,9 +1,17 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-fn main() {
+fn main() -> std::process::ExitCode {
     // Fix PATH environment variable for GUI apps
     // This ensures user's shell config (e.g., ~/.zshrc) is loaded
     let _ = fix_path_env::fix();
-    tauri_app_lib::run()
+
+    // `talkcody run ...` runs a single agent task in the terminal
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("run") {
+        return tauri_app_lib::run_cli(args.collect());
+    }
+
+    tauri_app_lib::run();
+    std::process::ExitCode::SUCCESS
 }
@@ -3,8 +3,25 @@
 //! Provides safe filesystem operations with workspace validation.
 //! Wraps existing file system utilities from the codebase.
 
+use crate::code_navigation::{summarize_code_content, CodeNavigationService};
+use crate::constants::is_binary_extension;
+use crate::platform::paths::resolve_path;
 use crate::platform::types::*;
-use std::path::{Path, PathBuf};
+use std::fmt::Write as _;
+use std::io::{BufRead, Read};
+use std::path::Path;
+
+/// Lines `read_file_page` returns when no limit is given
+pub const DEFAULT_READ_LIMIT: usize = 1000;
+
+/// Bytes sniffed to tell text from binary content
+const SNIFF_LEN: usize = 8192;
+
+/// Characters of a line shown before the rest of it is cut off
+const MAX_LINE_LEN: usize = 2000;
+
+/// Bytes of an oversized file parsed for its outline
+const OUTLINE_READ_LEN: u64 = 4 * 1024 * 1024;
 
 /// Filesystem operations provider
 #[derive(Clone)]
@@ -15,87 +32,9 @@ impl FileSystemPlatform {
         Self
     }
 
-    /// Validate that a path is within the workspace root
-    fn validate_path(&self, path: &Path, ctx: &PlatformContext) -> Result<PathBuf, String> {
-        let canonical_path = path
-            .canonicalize()
-            .map_err(|e| format!("Invalid path: {}", e))?;
-
-        let canonical_root = ctx
-            .workspace_root
-            .canonicalize()
-            .map_err(|e| format!("Invalid workspace root: {}", e))?;
-
-        if !canonical_path.starts_with(&canonical_root) {
-            return Err(format!(
-                "Path '{}' is outside workspace root '{}'",
-                canonical_path.display(),
-                canonical_root.display()
-            ));
-        }
-
-        Ok(canonical_path)
-    }
-
-    /// Validate that a path for writing is within the workspace root
-    /// For write operations, the file may not exist yet, so we validate the parent directory
-    fn validate_write_path(&self, path: &Path, ctx: &PlatformContext) -> Result<PathBuf, String> {
-        // Get absolute path (without requiring file to exist)
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            ctx.workspace_root.join(path)
-        };
-
-        // Get canonical workspace root
-        let canonical_root = ctx
-            .workspace_root
-            .canonicalize()
-            .map_err(|e| format!("Invalid workspace root: {}", e))?;
-
-        // For new files, validate that the parent directory is within workspace
-        let parent = absolute_path
-            .parent()
-            .ok_or_else(|| "Invalid path: no parent directory".to_string())?;
-
-        // Create parent if it doesn't exist for validation purposes
-        if !parent.exists() {
-            // Parent doesn't exist yet, check if it would be within workspace
-            let parent_absolute = if parent.is_absolute() {
-                parent.to_path_buf()
-            } else {
-                ctx.workspace_root.join(parent)
-            };
-
-            if !parent_absolute.starts_with(&canonical_root) {
-                return Err(format!(
-                    "Path '{}' is outside workspace root '{}'",
-                    absolute_path.display(),
-                    canonical_root.display()
-                ));
-            }
-        } else {
-            let canonical_parent = parent
-                .canonicalize()
-                .map_err(|e| format!("Invalid parent directory: {}", e))?;
-
-            if !canonical_parent.starts_with(&canonical_root) {
-                return Err(format!(
-                    "Path '{}' is outside workspace root '{}'",
-                    absolute_path.display(),
-                    canonical_root.display()
-                ));
-            }
-        }
-
-        Ok(absolute_path)
-    }
-
     /// Read file contents
     pub async fn read_file(&self, path: &str, ctx: &PlatformContext) -> PlatformResult<String> {
-        let path = Path::new(path);
-
-        match self.validate_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => {
                 // Check file size
                 match tokio::fs::metadata(&validated_path).await {
@@ -120,6 +59,125 @@ impl FileSystemPlatform {
         }
     }
 
+    /// Read a page of a file for the `readFile` tool
+    ///
+    /// Text comes back as numbered lines from `offset` (1-indexed), each cut
+    /// to `MAX_LINE_LEN` characters. Images are returned by reference and other
+    /// binary files only described. Files over `max_file_size` are still
+    /// paged, and come with an outline of the definitions in their first
+    /// `OUTLINE_READ_LEN` bytes when the language is supported.
+    pub async fn read_file_page(
+        &self,
+        path: &str,
+        offset: Option<usize>,
+        limit: Option<usize>,
+        ctx: &PlatformContext,
+    ) -> PlatformResult<FileContent> {
+        let validated_path = match resolve_path(path, ctx) {
+            Ok(validated_path) => validated_path,
+            Err(e) => return PlatformResult::error(e),
+        };
+        let size = match tokio::fs::metadata(&validated_path).await {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => return PlatformResult::error(format!("'{}' is not a file", path)),
+            Err(e) => return PlatformResult::error(format!("Failed to get file metadata: {}", e)),
+        };
+        let file_path = validated_path.to_string_lossy().to_string();
+        let extension = validated_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let oversized = size > ctx.max_file_size as u64;
+
+        if let Some(mime_type) = image_mime_type(&extension) {
+            if oversized {
+                return PlatformResult::success(FileContent::Binary {
+                    file_path,
+                    size,
+                    message: format!(
+                        "Image too large to show: {} bytes (max: {})",
+                        size, ctx.max_file_size
+                    ),
+                });
+            }
+            return PlatformResult::success(FileContent::Image {
+                file_path,
+                mime_type: mime_type.to_string(),
+                size,
+            });
+        }
+
+        let sniff_path = validated_path.clone();
+        let head = match tokio::task::spawn_blocking(move || read_head(&sniff_path)).await {
+            Ok(Ok(head)) => head,
+            Ok(Err(e)) => return PlatformResult::error(format!("Failed to read file: {}", e)),
+            Err(e) => return PlatformResult::error(format!("Failed to read file: {}", e)),
+        };
+        if is_binary_extension(&extension) || looks_binary(&head) {
+            return PlatformResult::success(FileContent::Binary {
+                file_path,
+                size,
+                message: format!("Binary file ({} bytes) not shown", size),
+            });
+        }
+
+        let offset = offset.unwrap_or(1).max(1);
+        let limit = limit.unwrap_or(DEFAULT_READ_LIMIT).max(1);
+        let page_path = validated_path.clone();
+        let page = match tokio::task::spawn_blocking(move || read_lines(&page_path, offset, limit))
+            .await
+        {
+            Ok(Ok(page)) => page,
+            Ok(Err(e)) => return PlatformResult::error(format!("Failed to read file: {}", e)),
+            Err(e) => return PlatformResult::error(format!("Failed to read file: {}", e)),
+        };
+        if offset > 1 && offset > page.total_lines {
+            return PlatformResult::error(format!(
+                "Offset {} is past the end of the file ({} lines)",
+                offset, page.total_lines
+            ));
+        }
+
+        let truncated = page.end_line < page.total_lines;
+        let mut message = if page.total_lines == 0 {
+            "File is empty".to_string()
+        } else {
+            format!("Lines {}-{} of {}", offset, page.end_line, page.total_lines)
+        };
+        if truncated {
+            let _ = write!(message, "; use offset {} to read more", page.end_line + 1);
+        }
+        let outline = if oversized {
+            let _ = write!(
+                message,
+                ". File is {} bytes (max: {}), so only this page was read",
+                size, ctx.max_file_size
+            );
+            let outline = outline(&validated_path).await;
+            if outline.is_some() && size > OUTLINE_READ_LEN {
+                let _ = write!(
+                    message,
+                    "; the outline covers its first {} bytes",
+                    OUTLINE_READ_LEN
+                );
+            }
+            outline
+        } else {
+            None
+        };
+
+        PlatformResult::success(FileContent::Text {
+            file_path,
+            content: page.content,
+            start_line: offset,
+            end_line: page.end_line,
+            total_lines: page.total_lines,
+            truncated,
+            outline,
+            message,
+        })
+    }
+
     /// Write file contents
     pub async fn write_file(
         &self,
@@ -127,9 +185,7 @@ impl FileSystemPlatform {
         content: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<()> {
-        let path = Path::new(path);
-
-        match self.validate_write_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => {
                 // Ensure parent directory exists
                 if let Some(parent) = validated_path.parent() {
@@ -149,9 +205,7 @@ impl FileSystemPlatform {
 
     /// Check if file exists
     pub async fn file_exists(&self, path: &str, ctx: &PlatformContext) -> PlatformResult<bool> {
-        let path = Path::new(path);
-
-        match self.validate_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => match tokio::fs::try_exists(&validated_path).await {
                 Ok(exists) => PlatformResult::success(exists),
                 Err(e) => PlatformResult::error(format!("Failed to check file: {}", e)),
@@ -166,9 +220,7 @@ impl FileSystemPlatform {
         path: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<DirectoryEntry>> {
-        let path = Path::new(path);
-
-        match self.validate_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => match tokio::fs::read_dir(&validated_path).await {
                 Ok(mut entries) => {
                     let mut result = Vec::new();
@@ -200,9 +252,7 @@ impl FileSystemPlatform {
         path: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<FileInfo> {
-        let path = Path::new(path);
-
-        match self.validate_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => match tokio::fs::metadata(&validated_path).await {
                 Ok(metadata) => {
                     let name = validated_path
@@ -240,9 +290,7 @@ impl FileSystemPlatform {
 
     /// Delete a file
     pub async fn delete_file(&self, path: &str, ctx: &PlatformContext) -> PlatformResult<()> {
-        let path = Path::new(path);
-
-        match self.validate_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => match tokio::fs::remove_file(&validated_path).await {
                 Ok(_) => PlatformResult::success(()),
                 Err(e) => PlatformResult::error(format!("Failed to delete file: {}", e)),
@@ -253,9 +301,7 @@ impl FileSystemPlatform {
 
     /// Create a directory
     pub async fn create_directory(&self, path: &str, ctx: &PlatformContext) -> PlatformResult<()> {
-        let path = Path::new(path);
-
-        match self.validate_path(path, ctx) {
+        match resolve_path(path, ctx) {
             Ok(validated_path) => match tokio::fs::create_dir_all(&validated_path).await {
                 Ok(_) => PlatformResult::success(()),
                 Err(e) => PlatformResult::error(format!("Failed to create directory: {}", e)),
@@ -265,6 +311,143 @@ impl FileSystemPlatform {
     }
 }
 
+/// MIME type of the image formats the models accept
+fn image_mime_type(extension: &str) -> Option<&'static str> {
+    match extension {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn read_head(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    Ok(head)
+}
+
+/// Whether the start of a file is binary: it has a NUL byte or is not UTF-8
+fn looks_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        // A character cut off by the end of the sniffed bytes is fine
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// Numbered lines of a page and the line count of the whole file
+struct LinePage {
+    content: String,
+    end_line: usize,
+    total_lines: usize,
+}
+
+/// Read lines `offset..offset + limit` without holding the whole file
+fn read_lines(path: &Path, offset: usize, limit: usize) -> std::io::Result<LinePage> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut buf = Vec::new();
+    let mut page = LinePage {
+        content: String::new(),
+        end_line: offset - 1,
+        total_lines: 0,
+    };
+
+    // A UTF-8 character is at most 4 bytes, so this holds MAX_LINE_LEN of them
+    let max_line_bytes = MAX_LINE_LEN * 4;
+    loop {
+        buf.clear();
+        let Some(cut) = read_line_capped(&mut reader, &mut buf, max_line_bytes)? else {
+            break;
+        };
+        page.total_lines += 1;
+        if page.total_lines >= offset && page.total_lines < offset + limit {
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']);
+            let _ = write!(page.content, "{:>6}\t", page.total_lines);
+            match line.char_indices().nth(MAX_LINE_LEN) {
+                Some((end, _)) => {
+                    let _ = writeln!(page.content, "{}... [line truncated]", &line[..end]);
+                }
+                None if cut => {
+                    let _ = writeln!(page.content, "{}... [line truncated]", line);
+                }
+                None => {
+                    let _ = writeln!(page.content, "{}", line);
+                }
+            }
+            page.end_line = page.total_lines;
+        }
+    }
+
+    Ok(page)
+}
+
+/// Read through the next newline, keeping at most `max` bytes of the line in
+/// `buf`; returns whether bytes were dropped, or `None` at the end of the file
+fn read_line_capped(
+    reader: &mut impl BufRead,
+    buf: &mut Vec<u8>,
+    max: usize,
+) -> std::io::Result<Option<bool>> {
+    let mut read_any = false;
+    let mut cut = false;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(read_any.then_some(cut));
+        }
+        read_any = true;
+        let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (&available[..=i], true),
+            None => (available, false),
+        };
+        let keep = chunk.len().min(max.saturating_sub(buf.len()));
+        buf.extend_from_slice(&chunk[..keep]);
+        cut |= keep < chunk.len();
+        let consumed = chunk.len();
+        reader.consume(consumed);
+        if done {
+            return Ok(Some(cut));
+        }
+    }
+}
+
+/// Tree-sitter outline of the first `OUTLINE_READ_LEN` bytes of a source
+/// file, if its language is supported
+async fn outline(path: &Path) -> Option<String> {
+    let file_path = path.to_string_lossy().to_string();
+    let lang_id = CodeNavigationService::get_lang_id_from_path(&file_path)?;
+    let prefix_path = path.to_path_buf();
+    let mut prefix = tokio::task::spawn_blocking(move || -> std::io::Result<Vec<u8>> {
+        let mut prefix = Vec::new();
+        std::fs::File::open(prefix_path)?
+            .take(OUTLINE_READ_LEN)
+            .read_to_end(&mut prefix)?;
+        Ok(prefix)
+    })
+    .await
+    .ok()?
+    .ok()?;
+    // Leave out a line cut off by the end of the prefix
+    if prefix.len() as u64 == OUTLINE_READ_LEN {
+        if let Some(end) = prefix.iter().rposition(|&b| b == b'\n') {
+            prefix.truncate(end + 1);
+        }
+    }
+    let content = String::from_utf8_lossy(&prefix).into_owned();
+    let summary = summarize_code_content(content, lang_id, file_path)
+        .await
+        .ok()?;
+    summary.success.then_some(summary.summary)
+}
+
 impl Default for FileSystemPlatform {
     fn default() -> Self {
         Self::new()
@@ -301,6 +484,175 @@ mod tests {
         assert_eq!(read_result.data, Some("Hello, World!".to_string()));
     }
 
+    fn test_context(root: &Path, max_file_size: usize) -> PlatformContext {
+        PlatformContext {
+            workspace_root: root.to_path_buf(),
+            worktree_path: None,
+            max_file_size,
+            shell_timeout_secs: 60,
+        }
+    }
+
+    fn page_of(result: PlatformResult<FileContent>) -> (String, usize, usize, bool, String) {
+        match result.data {
+            Some(FileContent::Text {
+                content,
+                end_line,
+                total_lines,
+                truncated,
+                message,
+                ..
+            }) => (content, end_line, total_lines, truncated, message),
+            other => panic!("Expected text, got {:?} ({:?})", other, result.error),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_file_page_numbers_and_pages_lines() {
+        let fs = FileSystemPlatform::new();
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path(), 1024 * 1024);
+        let file = temp_dir.path().join("lines.txt");
+        std::fs::write(&file, "one\r\ntwo\nthree\nfour\n").unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        let (content, end_line, total_lines, truncated, _) =
+            page_of(fs.read_file_page(&path, None, None, &ctx).await);
+        assert_eq!(
+            content,
+            "     1\tone\n     2\ttwo\n     3\tthree\n     4\tfour\n"
+        );
+        assert_eq!((end_line, total_lines, truncated), (4, 4, false));
+
+        let (content, end_line, _, truncated, message) =
+            page_of(fs.read_file_page(&path, Some(2), Some(2), &ctx).await);
+        assert_eq!(content, "     2\ttwo\n     3\tthree\n");
+        assert_eq!(end_line, 3);
+        assert!(truncated);
+        assert!(message.contains("offset 4"));
+
+        let past_end = fs.read_file_page(&path, Some(9), None, &ctx).await;
+        assert!(!past_end.success);
+        assert!(past_end.error.unwrap().contains("past the end"));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_page_binary_and_images() {
+        let fs = FileSystemPlatform::new();
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path(), 1024 * 1024);
+
+        // Sniffed from content despite the text extension
+        let blob = temp_dir.path().join("data.txt");
+        std::fs::write(&blob, b"abc\0def").unwrap();
+        let result = fs
+            .read_file_page(&blob.to_string_lossy(), None, None, &ctx)
+            .await;
+        assert!(matches!(
+            result.data,
+            Some(FileContent::Binary { size: 7, .. })
+        ));
+
+        // Known binary extension
+        let archive = temp_dir.path().join("bundle.zip");
+        std::fs::write(&archive, "not really a zip").unwrap();
+        let result = fs
+            .read_file_page(&archive.to_string_lossy(), None, None, &ctx)
+            .await;
+        assert!(matches!(result.data, Some(FileContent::Binary { .. })));
+
+        let image = temp_dir.path().join("logo.PNG");
+        std::fs::write(&image, b"\x89PNG").unwrap();
+        let result = fs
+            .read_file_page(&image.to_string_lossy(), None, None, &ctx)
+            .await;
+        match result.data {
+            Some(FileContent::Image {
+                file_path,
+                mime_type,
+                size,
+            }) => {
+                assert!(file_path.ends_with("logo.PNG"));
+                assert_eq!(mime_type, "image/png");
+                assert_eq!(size, 4);
+            }
+            other => panic!("Expected image, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_file_page_truncates_long_lines() {
+        let fs = FileSystemPlatform::new();
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path(), 1024 * 1024);
+        let file = temp_dir.path().join("minified.js");
+        let long_line = "x".repeat(MAX_LINE_LEN * 10);
+        std::fs::write(&file, format!("short\n{}\nlast\n", long_line)).unwrap();
+
+        let (content, _, total_lines, _, _) = page_of(
+            fs.read_file_page(&file.to_string_lossy(), None, None, &ctx)
+                .await,
+        );
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(total_lines, 3);
+        assert_eq!(lines[0], "     1\tshort");
+        assert_eq!(
+            lines[1],
+            format!("     2\t{}... [line truncated]", &long_line[..MAX_LINE_LEN])
+        );
+        assert_eq!(lines[2], "     3\tlast");
+    }
+
+    #[tokio::test]
+    async fn test_read_file_page_large_file_has_outline() {
+        let fs = FileSystemPlatform::new();
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path(), 64);
+        let file = temp_dir.path().join("big.rs");
+        let source: String = (0..50)
+            .map(|i| format!("pub fn item_{}() -> u32 {{\n    {}\n}}\n", i, i))
+            .collect();
+        std::fs::write(&file, &source).unwrap();
+
+        let result = fs
+            .read_file_page(&file.to_string_lossy(), None, Some(3), &ctx)
+            .await;
+        match result.data {
+            Some(FileContent::Text {
+                content,
+                total_lines,
+                truncated,
+                outline,
+                ..
+            }) => {
+                assert_eq!(content.lines().count(), 3);
+                assert_eq!(total_lines, 150);
+                assert!(truncated);
+                assert!(outline.unwrap().contains("item_49"));
+            }
+            other => panic!("Expected text, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relative_paths_use_workspace_root() {
+        let fs = FileSystemPlatform::new();
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path(), 1024 * 1024);
+
+        assert!(
+            fs.write_file("src/lib.rs", "pub fn lib() {}", &ctx)
+                .await
+                .success
+        );
+        assert!(temp_dir.path().join("src/lib.rs").is_file());
+
+        let read_result = fs.read_file("src/lib.rs", &ctx).await;
+        assert_eq!(read_result.data, Some("pub fn lib() {}".to_string()));
+        assert_eq!(fs.file_exists("src/lib.rs", &ctx).await.data, Some(true));
+        assert_eq!(fs.file_exists("../lib.rs", &ctx).await.data, Some(false));
+    }
+
     #[tokio::test]
     async fn test_path_validation_outside_workspace() {
         let fs = FileSystemPlatform::new();
@@ -3,6 +3,8 @@
 //! Provides git operations with workspace validation.
 //! Wraps existing git module from the codebase.
 
+use crate::git::{diff, repository};
+use crate::platform::paths::{base_dir, canonicalize, resolve_path};
 use crate::platform::types::*;
 use std::path::Path;
 
@@ -15,44 +17,9 @@ impl GitPlatform {
         Self
     }
 
-    /// Check if path is within workspace
-    fn validate_path(
-        &self,
-        path: &Path,
-        ctx: &PlatformContext,
-    ) -> Result<std::path::PathBuf, String> {
-        let canonical_path = path
-            .canonicalize()
-            .map_err(|e| format!("Invalid path: {}", e))?;
-
-        let canonical_root = ctx
-            .workspace_root
-            .canonicalize()
-            .map_err(|e| format!("Invalid workspace root: {}", e))?;
-
-        if !canonical_path.starts_with(&canonical_root) {
-            return Err(format!(
-                "Path '{}' is outside workspace root '{}'",
-                canonical_path.display(),
-                canonical_root.display()
-            ));
-        }
-
-        Ok(canonical_path)
-    }
-
-    /// Get effective path (worktree or workspace root)
-    fn get_effective_path(&self, ctx: &PlatformContext) -> std::path::PathBuf {
-        ctx.worktree_path
-            .clone()
-            .unwrap_or_else(|| ctx.workspace_root.clone())
-    }
-
     /// Check if directory is a git repository
     pub async fn is_repository(&self, ctx: &PlatformContext) -> PlatformResult<bool> {
-        let path = self.get_effective_path(ctx);
-
-        match self.validate_path(&path, ctx) {
+        match base_dir(ctx) {
             Ok(validated_path) => {
                 // Use existing git module
                 match crate::git::git_is_repository(validated_path.to_string_lossy().to_string())
@@ -68,9 +35,7 @@ impl GitPlatform {
 
     /// Get repository status
     pub async fn get_status(&self, ctx: &PlatformContext) -> PlatformResult<GitStatus> {
-        let path = self.get_effective_path(ctx);
-
-        match self.validate_path(&path, ctx) {
+        match base_dir(ctx) {
             Ok(validated_path) => {
                 // Check if it's a repository first
                 match crate::git::git_is_repository(validated_path.to_string_lossy().to_string())
@@ -142,21 +107,13 @@ impl GitPlatform {
         file_path: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<String> {
-        let path = self.get_effective_path(ctx);
-
-        match self.validate_path(&path, ctx) {
-            Ok(validated_path) => {
-                let full_path = validated_path.join(file_path);
-
-                match crate::git::git_get_raw_diff_text(
-                    validated_path.to_string_lossy().to_string(),
-                )
-                .await
-                {
-                    Ok(diff) => PlatformResult::success(diff),
-                    Err(e) => PlatformResult::error(format!("Failed to get diff: {}", e)),
-                }
-            }
+        match base_dir(ctx) {
+            Ok(validated_path) => match resolve_path(file_path, ctx)
+                .and_then(|full_path| file_diff_text(&validated_path, &full_path))
+            {
+                Ok(diff) => PlatformResult::success(diff),
+                Err(e) => PlatformResult::error(format!("Failed to get diff: {}", e)),
+            },
             Err(e) => PlatformResult::error(e),
         }
     }
@@ -166,9 +123,7 @@ impl GitPlatform {
         &self,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<(String, String)>> {
-        let path = self.get_effective_path(ctx);
-
-        match self.validate_path(&path, ctx) {
+        match base_dir(ctx) {
             Ok(validated_path) => {
                 match crate::git::git_get_all_file_diffs(
                     validated_path.to_string_lossy().to_string(),
@@ -196,11 +151,12 @@ impl GitPlatform {
         file_path: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<(u32, crate::git::types::DiffLineType)>> {
-        let path = self.get_effective_path(ctx);
-
-        match self.validate_path(&path, ctx) {
+        match base_dir(ctx) {
             Ok(validated_path) => {
-                let full_path = validated_path.join(file_path);
+                let full_path = match resolve_path(file_path, ctx) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return PlatformResult::error(e),
+                };
 
                 match crate::git::git_get_line_changes(
                     validated_path.to_string_lossy().to_string(),
@@ -217,6 +173,29 @@ impl GitPlatform {
     }
 }
 
+/// Unified diff of one file in the repository containing `repo_dir`
+fn file_diff_text(repo_dir: &Path, file: &Path) -> Result<String, String> {
+    let repo = repository::discover_repository(repo_dir).map_err(|e| e.to_string())?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "Repository has no working directory".to_string())?;
+    let workdir = canonicalize(workdir)?;
+    let relative = file
+        .strip_prefix(&workdir)
+        .map_err(|_| format!("'{}' is not in the repository", file.display()))?;
+    let relative = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let file_diff = diff::get_file_diff(&repo, &relative).map_err(|e| e.to_string())?;
+    if file_diff.hunks.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(diff::format_unified_diff(&file_diff))
+}
+
 impl Default for GitPlatform {
     fn default() -> Self {
         Self::new()
@@ -232,4 +211,45 @@ mod tests {
         let git = GitPlatform::new();
         // Platform created successfully
     }
+
+    #[tokio::test]
+    async fn test_file_diff_resolves_relative_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+                .unwrap();
+        };
+        git(&["init"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test User"]);
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        std::fs::write(temp_dir.path().join("src/a.txt"), "one\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "b\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-m", "Initial commit"]);
+        std::fs::write(temp_dir.path().join("src/a.txt"), "one\ntwo\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "changed\n").unwrap();
+
+        let ctx = PlatformContext {
+            workspace_root: temp_dir.path().to_path_buf(),
+            worktree_path: None,
+            max_file_size: 1024 * 1024,
+            shell_timeout_secs: 60,
+        };
+        let platform = GitPlatform::new();
+
+        let diff = platform
+            .get_file_diff("src/a.txt", &ctx)
+            .await
+            .data
+            .unwrap();
+        assert!(diff.contains("+++ b/src/a.txt"));
+        assert!(diff.contains("+two"));
+        assert!(!diff.contains("changed"));
+
+        assert!(!platform.get_file_diff("../a.txt", &ctx).await.success);
+    }
 }
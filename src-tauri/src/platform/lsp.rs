@@ -1,10 +1,44 @@
 //! LSP Platform Abstraction
 //!
 //! Provides Language Server Protocol operations.
-//! Wraps existing LSP module from the codebase.
+//! Servers are started lazily per (language, workspace root) through the shared
+//! `LspRegistry` and driven by the headless `LspClient`. Files are re-synced
+//! before every request so edits made by other tools are visible to the server.
 
+use crate::lsp::{
+    generate_server_id, language_id_for_path, server_language_for, validate_root_path,
+    CreationReservation, LspRegistry,
+};
+use crate::lsp_client::LspClient;
+use crate::platform::paths::{base_dir, resolve_path};
 use crate::platform::types::*;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// How long to wait for another caller that is already starting the same server
+const SERVER_START_WAIT: Duration = Duration::from_secs(60);
+const SERVER_START_POLL: Duration = Duration::from_millis(100);
+
+/// How long to wait for a server to publish diagnostics for a freshly synced file
+const DIAGNOSTICS_WAIT: Duration = Duration::from_secs(5);
+
+/// Language servers started on behalf of backend tools, shared by all platform instances
+struct LspBackend {
+    registry: Mutex<LspRegistry>,
+    clients: Mutex<HashMap<String, Arc<LspClient>>>,
+}
+
+static LSP_BACKEND: OnceLock<LspBackend> = OnceLock::new();
+
+fn backend() -> &'static LspBackend {
+    LSP_BACKEND.get_or_init(|| LspBackend {
+        registry: Mutex::new(LspRegistry::new()),
+        clients: Mutex::new(HashMap::new()),
+    })
+}
 
 /// LSP operations provider
 #[derive(Clone)]
@@ -15,30 +49,105 @@ impl LspPlatform {
         Self
     }
 
-    /// Validate that path is within workspace
-    fn validate_path(
+    /// Root directory servers are started in: the worktree when set, else the workspace
+    fn server_root(&self, ctx: &PlatformContext) -> Result<PathBuf, String> {
+        validate_root_path(&base_dir(ctx)?.to_string_lossy())
+    }
+
+    /// Get the client serving `path`, starting the language server if needed
+    async fn client_for(
         &self,
         path: &Path,
         ctx: &PlatformContext,
-    ) -> Result<std::path::PathBuf, String> {
-        let canonical_path = path
-            .canonicalize()
-            .map_err(|e| format!("Invalid path: {}", e))?;
-
-        let canonical_root = ctx
-            .workspace_root
-            .canonicalize()
-            .map_err(|e| format!("Invalid workspace root: {}", e))?;
-
-        if !canonical_path.starts_with(&canonical_root) {
-            return Err(format!(
-                "Path '{}' is outside workspace root '{}'",
-                canonical_path.display(),
-                canonical_root.display()
-            ));
+    ) -> Result<(Arc<LspClient>, &'static str), String> {
+        let language_id = language_id_for_path(path)
+            .ok_or_else(|| format!("No language server available for '{}'", path.display()))?;
+        let language = server_language_for(language_id);
+        let root = self.server_root(ctx)?;
+        let root_key = root.to_string_lossy().to_string();
+        let backend = backend();
+        let started_waiting = std::time::Instant::now();
+
+        loop {
+            let reservation = backend
+                .registry
+                .lock()
+                .await
+                .try_reserve_creation(language, &root_key);
+
+            match reservation {
+                CreationReservation::ExistingServer(server_id) => {
+                    let client = backend.clients.lock().await.get(&server_id).cloned();
+                    if let Some(client) = client {
+                        if client.is_alive().await {
+                            return Ok((client, language_id));
+                        }
+                    }
+
+                    // The server crashed or exited: drop it and start a new one
+                    log::warn!(
+                        "LSP server {} for {} is no longer running, restarting",
+                        server_id,
+                        language
+                    );
+                    let stale = backend.clients.lock().await.remove(&server_id);
+                    backend.registry.lock().await.remove(&server_id);
+                    if let Some(stale) = stale {
+                        stale.shutdown().await;
+                    }
+                }
+                CreationReservation::AlreadyCreating => {
+                    if started_waiting.elapsed() > SERVER_START_WAIT {
+                        return Err(format!(
+                            "Timed out waiting for {} language server to start",
+                            language
+                        ));
+                    }
+                    tokio::time::sleep(SERVER_START_POLL).await;
+                }
+                CreationReservation::Reserved => {
+                    let server_id = generate_server_id(language);
+                    return match LspClient::start(server_id.clone(), language, &root).await {
+                        Ok(client) => {
+                            let client = Arc::new(client);
+                            backend
+                                .clients
+                                .lock()
+                                .await
+                                .insert(server_id.clone(), client.clone());
+                            backend.registry.lock().await.finish_creation(
+                                server_id,
+                                client.server(),
+                                language.to_string(),
+                                root_key,
+                            );
+                            Ok((client, language_id))
+                        }
+                        Err(e) => {
+                            backend
+                                .registry
+                                .lock()
+                                .await
+                                .cancel_creation(language, &root_key);
+                            Err(e)
+                        }
+                    };
+                }
+            }
         }
+    }
 
-        Ok(canonical_path)
+    /// Validate `file_path`, start its server and sync the document's current
+    /// content. Returns the client and the document URI.
+    async fn open(
+        &self,
+        file_path: &str,
+        ctx: &PlatformContext,
+    ) -> Result<(Arc<LspClient>, String), String> {
+        let validated_path = resolve_path(file_path, ctx)?;
+        let (client, language_id) = self.client_for(&validated_path, ctx).await?;
+        let uri = client.sync_document(&validated_path, language_id).await?;
+        Ok((client, uri))
     }
 
     /// Go to definition
@@ -49,42 +158,15 @@ impl LspPlatform {
         character: u32,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<LspLocation>> {
-        let path = Path::new(file_path);
-
-        match self.validate_path(path, ctx) {
-            Ok(validated_path) => {
-                // Use LSP client if available
-                let language = self.detect_language(&validated_path);
-
-                // Try to use existing LSP functionality
-                // Note: This is a simplified implementation
-                // Full implementation would use the LSP module
-
-                PlatformResult::success(vec![LspLocation {
-                    uri: format!("file://{}", validated_path.display()),
-                    range: LspRange {
-                        start: LspPosition { line, character },
-                        end: LspPosition {
-                            line,
-                            character: character + 1,
-                        },
-                    },
-                }])
-            }
-            Err(e) => PlatformResult::error(e),
+        let result = async {
+            let (client, uri) = self.open(file_path, ctx).await?;
+            client.definition(&uri, line, character).await
         }
-    }
+        .await;
 
-    /// Detect language from file extension
-    fn detect_language(&self, path: &Path) -> String {
-        match path.extension().and_then(|e| e.to_str()) {
-            Some("rs") => "rust".to_string(),
-            Some("ts") | Some("tsx") | Some("js") | Some("jsx") => "typescript".to_string(),
-            Some("py") => "python".to_string(),
-            Some("go") => "go".to_string(),
-            Some("java") => "java".to_string(),
-            Some("cpp") | Some("cc") | Some("c") | Some("h") => "cpp".to_string(),
-            _ => "unknown".to_string(),
+        match result {
+            Ok(locations) => PlatformResult::success(locations),
+            Err(e) => PlatformResult::error(e),
         }
     }
 
@@ -96,13 +178,14 @@ impl LspPlatform {
         character: u32,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<LspLocation>> {
-        let path = Path::new(file_path);
+        let result = async {
+            let (client, uri) = self.open(file_path, ctx).await?;
+            client.references(&uri, line, character, true).await
+        }
+        .await;
 
-        match self.validate_path(path, ctx) {
-            Ok(_validated_path) => {
-                // Placeholder: Would use LSP client
-                PlatformResult::success(vec![])
-            }
+        match result {
+            Ok(locations) => PlatformResult::success(locations),
             Err(e) => PlatformResult::error(e),
         }
     }
@@ -113,32 +196,35 @@ impl LspPlatform {
         file_path: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<LspSymbol>> {
-        let path = Path::new(file_path);
+        let result = async {
+            let (client, uri) = self.open(file_path, ctx).await?;
+            client.document_symbols(&uri).await
+        }
+        .await;
 
-        match self.validate_path(path, ctx) {
-            Ok(_validated_path) => {
-                // Placeholder: Would use LSP client
-                PlatformResult::success(vec![])
-            }
+        match result {
+            Ok(symbols) => PlatformResult::success(symbols),
             Err(e) => PlatformResult::error(e),
         }
     }
 
-    /// Get workspace symbols
+    /// Get workspace symbols from the server that handles `file_path`'s language
     pub async fn get_workspace_symbols(
         &self,
+        file_path: &str,
         query: &str,
         ctx: &PlatformContext,
     ) -> PlatformResult<Vec<LspSymbol>> {
-        // Workspace symbols don't require a specific file path
-        // but we still validate workspace access
-        let _ = match ctx.workspace_root.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return PlatformResult::error(format!("Invalid workspace root: {}", e)),
-        };
-
-        // Placeholder: Would use LSP client
-        PlatformResult::success(vec![])
+        let result = async {
+            let (client, _uri) = self.open(file_path, ctx).await?;
+            client.workspace_symbols(query).await
+        }
+        .await;
+
+        match result {
+            Ok(symbols) => PlatformResult::success(symbols),
+            Err(e) => PlatformResult::error(e),
+        }
     }
 
     /// Get hover information
@@ -149,13 +235,32 @@ impl LspPlatform {
         character: u32,
         ctx: &PlatformContext,
     ) -> PlatformResult<Option<String>> {
-        let path = Path::new(file_path);
+        let result = async {
+            let (client, uri) = self.open(file_path, ctx).await?;
+            client.hover(&uri, line, character).await
+        }
+        .await;
 
-        match self.validate_path(path, ctx) {
-            Ok(_validated_path) => {
-                // Placeholder: Would use LSP client
-                PlatformResult::success(None)
-            }
+        match result {
+            Ok(hover) => PlatformResult::success(hover),
+            Err(e) => PlatformResult::error(e),
+        }
+    }
+
+    /// Get diagnostics for a file
+    pub async fn get_diagnostics(
+        &self,
+        file_path: &str,
+        ctx: &PlatformContext,
+    ) -> PlatformResult<Vec<LspDiagnostic>> {
+        let result = async {
+            let (client, uri) = self.open(file_path, ctx).await?;
+            client.diagnostics(&uri, DIAGNOSTICS_WAIT).await
+        }
+        .await;
+
+        match result {
+            Ok(diagnostics) => PlatformResult::success(diagnostics),
             Err(e) => PlatformResult::error(e),
         }
     }
@@ -1,11 +1,13 @@
 //! Platform Abstraction Layer
 //!
 //! Provides unified interfaces for filesystem, git, shell, and LSP operations.
-//! All operations are validated to stay within the workspace root.
+//! All operations are validated to stay within the workspace root; paths are
+//! resolved the same way by every provider, see `paths`.
 
 pub mod fs;
 pub mod git;
 pub mod lsp;
+pub mod paths;
 pub mod shell;
 pub mod types;
 
@@ -97,11 +99,16 @@ impl Platform {
                     .get("pattern")
                     .and_then(|v| v.as_str())
                     .ok_or("Missing 'pattern' parameter")?;
-                let path = input
-                    .get("path")
-                    .and_then(|v| v.as_str())
-                    .map(|p| p.to_string())
-                    .unwrap_or_else(|| ctx.workspace_root.to_string_lossy().to_string());
+                let path = input.get("path").and_then(|v| v.as_str()).unwrap_or("");
+                let path = match paths::resolve_path(path, ctx) {
+                    Ok(path) => path.to_string_lossy().to_string(),
+                    Err(e) => {
+                        return Ok(serde_json::json!({
+                            "success": false,
+                            "error": e
+                        }))
+                    }
+                };
 
                 // Use existing search module
                 match crate::search::RipgrepSearch::new()
@@ -0,0 +1,464 @@
+//! Workspace Path Resolution
+//!
+//! Every platform resolves the paths it is given, usually by the model, here,
+//! so that fs, shell, git and lsp operations agree on what a path refers to:
+//!
+//! - `~` and `~/...` expand to the home directory
+//! - relative paths are taken from the active worktree, or the workspace root
+//!   when the task has no worktree
+//! - symlinks are resolved before the path is checked, so a link cannot lead
+//!   outside the worktree and workspace root
+//! - on Windows, canonical verbatim paths (`\\?\C:\...`, `\\?\UNC\...`) are
+//!   handed back in their plain form, which shells and tools accept
+
+use crate::platform::types::PlatformContext;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolve `path` to a canonical path inside the worktree or workspace root
+///
+/// The path does not need to exist, so files about to be created resolve too.
+pub fn resolve_path(path: &str, ctx: &PlatformContext) -> Result<PathBuf, String> {
+    let roots = allowed_roots(ctx)?;
+    let expanded = expand_home(path, dirs::home_dir().as_deref());
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        base_dir_of(ctx).join(expanded)
+    };
+
+    let resolved = canonicalize_lenient(&absolute)?;
+    if !roots.iter().any(|root| resolved.starts_with(root)) {
+        return Err(outside_error(&resolved, ctx));
+    }
+
+    Ok(display_form(resolved))
+}
+
+/// Canonical directory relative paths are resolved from: the worktree when
+/// set, else the workspace root
+pub fn base_dir(ctx: &PlatformContext) -> Result<PathBuf, String> {
+    let base = base_dir_of(ctx);
+    base.canonicalize()
+        .map(display_form)
+        .map_err(|e| format!("Invalid workspace root '{}': {}", base.display(), e))
+}
+
+/// Canonical form of `path`, which need not exist, in the form
+/// `resolve_path` returns; no workspace check is made
+pub fn canonicalize(path: &Path) -> Result<PathBuf, String> {
+    canonicalize_lenient(path).map(display_form)
+}
+
+fn base_dir_of(ctx: &PlatformContext) -> &Path {
+    ctx.worktree_path.as_deref().unwrap_or(&ctx.workspace_root)
+}
+
+/// Canonical roots a resolved path must stay under
+fn allowed_roots(ctx: &PlatformContext) -> Result<Vec<PathBuf>, String> {
+    let mut roots = Vec::with_capacity(2);
+    if let Some(worktree) = &ctx.worktree_path {
+        roots.push(
+            worktree
+                .canonicalize()
+                .map_err(|e| format!("Invalid worktree '{}': {}", worktree.display(), e))?,
+        );
+    }
+    roots.push(
+        ctx.workspace_root
+            .canonicalize()
+            .map_err(|e| format!("Invalid workspace root: {}", e))?,
+    );
+    Ok(roots)
+}
+
+fn outside_error(path: &Path, ctx: &PlatformContext) -> String {
+    let root = display_form(
+        ctx.workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| ctx.workspace_root.clone()),
+    );
+    match &ctx.worktree_path {
+        Some(worktree) => format!(
+            "Path '{}' is outside workspace root '{}' and worktree '{}'",
+            display_form(path.to_path_buf()).display(),
+            root.display(),
+            worktree.display()
+        ),
+        None => format!(
+            "Path '{}' is outside workspace root '{}'",
+            display_form(path.to_path_buf()).display(),
+            root.display()
+        ),
+    }
+}
+
+/// Expand a leading `~` to `home`
+fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    let Some(home) = home else {
+        return PathBuf::from(path);
+    };
+    if path == "~" {
+        return home.to_path_buf();
+    }
+    let rest = path
+        .strip_prefix("~/")
+        .or_else(|| path.strip_prefix("~\\").filter(|_| cfg!(windows)));
+    match rest {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Canonicalize the longest existing prefix of `path` and append the rest
+///
+/// What does not exist yet cannot be a symlink, so the rest is normalized
+/// lexically. A dangling symlink is rejected, since writing through it would
+/// create its target wherever it points.
+fn canonicalize_lenient(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+
+    let mut resolved = loop {
+        match existing.canonicalize() {
+            Ok(canonical) => break canonical,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if existing.symlink_metadata().is_ok() {
+                    return Err(format!(
+                        "Path '{}' is a broken symbolic link",
+                        existing.display()
+                    ));
+                }
+                let mut components = existing.components();
+                match components.next_back() {
+                    Some(component @ (Component::Normal(_) | Component::ParentDir)) => {
+                        missing.push(component);
+                        existing = components.as_path();
+                    }
+                    Some(Component::CurDir) => existing = components.as_path(),
+                    _ => return Err(format!("Invalid path '{}': {}", path.display(), e)),
+                }
+            }
+            Err(e) => return Err(format!("Invalid path '{}': {}", path.display(), e)),
+        }
+    };
+
+    for component in missing.into_iter().rev() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    Ok(resolved)
+}
+
+/// Plain form of a canonical path, without the Windows verbatim prefix
+fn display_form(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    match simplify_verbatim(&path.to_string_lossy()) {
+        Some(simplified) => PathBuf::from(simplified),
+        None => path,
+    }
+}
+
+/// `\\?\C:\dir` as `C:\dir` and `\\?\UNC\server\share` as `\\server\share`
+///
+/// Paths too long for the plain form are left verbatim.
+fn simplify_verbatim(path: &str) -> Option<String> {
+    const MAX_PATH: usize = 260;
+
+    let simplified = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else {
+        let rest = path.strip_prefix(r"\\?\")?;
+        let drive = rest.as_bytes();
+        if drive.len() < 3 || !drive[0].is_ascii_alphabetic() || &drive[1..3] != b":\\" {
+            return None;
+        }
+        rest.to_string()
+    };
+    (simplified.len() < MAX_PATH).then_some(simplified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn context(workspace: &Path, worktree: Option<&Path>) -> PlatformContext {
+        PlatformContext {
+            workspace_root: workspace.to_path_buf(),
+            worktree_path: worktree.map(Path::to_path_buf),
+            max_file_size: 1024 * 1024,
+            shell_timeout_secs: 60,
+        }
+    }
+
+    fn canonical(path: &Path) -> PathBuf {
+        display_form(path.canonicalize().unwrap())
+    }
+
+    #[test]
+    fn test_relative_paths_resolve_against_workspace_root() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::create_dir_all(workspace.path().join("src")).unwrap();
+        std::fs::write(workspace.path().join("src/main.rs"), "").unwrap();
+        let ctx = context(workspace.path(), None);
+        let root = canonical(workspace.path());
+
+        assert_eq!(
+            resolve_path("src/main.rs", &ctx).unwrap(),
+            root.join("src/main.rs")
+        );
+        assert_eq!(
+            resolve_path("./src/../src/main.rs", &ctx).unwrap(),
+            root.join("src/main.rs")
+        );
+        assert_eq!(resolve_path("", &ctx).unwrap(), root);
+        assert_eq!(resolve_path(".", &ctx).unwrap(), root);
+        assert_eq!(base_dir(&ctx).unwrap(), root);
+    }
+
+    #[test]
+    fn test_relative_paths_prefer_worktree() {
+        let workspace = TempDir::new().unwrap();
+        let worktree = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("a.txt"), "main").unwrap();
+        std::fs::write(worktree.path().join("a.txt"), "worktree").unwrap();
+        let ctx = context(workspace.path(), Some(worktree.path()));
+
+        let resolved = resolve_path("a.txt", &ctx).unwrap();
+        assert_eq!(resolved, canonical(worktree.path()).join("a.txt"));
+        assert_eq!(base_dir(&ctx).unwrap(), canonical(worktree.path()));
+
+        // Absolute paths may still point into either root
+        let main_file = workspace.path().join("a.txt");
+        assert_eq!(
+            resolve_path(&main_file.to_string_lossy(), &ctx).unwrap(),
+            canonical(&main_file)
+        );
+    }
+
+    #[test]
+    fn test_missing_paths_resolve_for_writing() {
+        let workspace = TempDir::new().unwrap();
+        let ctx = context(workspace.path(), None);
+        let root = canonical(workspace.path());
+
+        assert_eq!(
+            resolve_path("new/dir/file.rs", &ctx).unwrap(),
+            root.join("new/dir/file.rs")
+        );
+        assert_eq!(
+            resolve_path("new/../other.rs", &ctx).unwrap(),
+            root.join("other.rs")
+        );
+        let absolute = workspace.path().join("missing/file.rs");
+        assert_eq!(
+            resolve_path(&absolute.to_string_lossy(), &ctx).unwrap(),
+            root.join("missing/file.rs")
+        );
+    }
+
+    #[test]
+    fn test_paths_outside_roots_are_rejected() {
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "").unwrap();
+        let ctx = context(workspace.path(), None);
+
+        for path in [
+            "..".to_string(),
+            "../secret.txt".to_string(),
+            "missing/../../escape.txt".to_string(),
+            outside
+                .path()
+                .join("secret.txt")
+                .to_string_lossy()
+                .to_string(),
+            outside.path().join("new.txt").to_string_lossy().to_string(),
+        ] {
+            let error = resolve_path(&path, &ctx).unwrap_err();
+            assert!(
+                error.contains("outside workspace root"),
+                "{}: {}",
+                path,
+                error
+            );
+        }
+
+        // A sibling directory sharing the root's name as a prefix is outside too
+        let sibling = format!("{}-other/file.txt", workspace.path().display());
+        assert!(resolve_path(&sibling, &ctx).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_followed_before_checking() {
+        use std::os::unix::fs::symlink;
+
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::create_dir(workspace.path().join("real")).unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "").unwrap();
+        symlink(
+            workspace.path().join("real"),
+            workspace.path().join("inside"),
+        )
+        .unwrap();
+        symlink(outside.path(), workspace.path().join("escape")).unwrap();
+        symlink(
+            outside.path().join("missing.txt"),
+            workspace.path().join("dangling"),
+        )
+        .unwrap();
+        symlink("missing-target", workspace.path().join("dangling-inside")).unwrap();
+        let ctx = context(workspace.path(), None);
+
+        assert_eq!(
+            resolve_path("inside/file.txt", &ctx).unwrap(),
+            canonical(workspace.path()).join("real/file.txt")
+        );
+        assert!(resolve_path("escape/secret.txt", &ctx)
+            .unwrap_err()
+            .contains("outside workspace root"));
+        assert!(resolve_path("escape/new.txt", &ctx)
+            .unwrap_err()
+            .contains("outside workspace root"));
+        assert!(resolve_path("dangling", &ctx)
+            .unwrap_err()
+            .contains("broken symbolic link"));
+        assert!(resolve_path("dangling-inside", &ctx)
+            .unwrap_err()
+            .contains("broken symbolic link"));
+
+        // A workspace root reached through a symlink still contains its files
+        let linked_root = outside.path().join("linked-workspace");
+        symlink(workspace.path(), &linked_root).unwrap();
+        let ctx = context(&linked_root, None);
+        let file = linked_root.join("real/file.txt");
+        assert_eq!(
+            resolve_path(&file.to_string_lossy(), &ctx).unwrap(),
+            canonical(workspace.path()).join("real/file.txt")
+        );
+    }
+
+    #[test]
+    fn test_home_is_expanded() {
+        let home = Path::new("/home/user");
+
+        assert_eq!(expand_home("~", Some(home)), PathBuf::from("/home/user"));
+        assert_eq!(
+            expand_home("~/project/a.rs", Some(home)),
+            PathBuf::from("/home/user/project/a.rs")
+        );
+        assert_eq!(
+            expand_home("~user/a.rs", Some(home)),
+            PathBuf::from("~user/a.rs")
+        );
+        assert_eq!(
+            expand_home("dir/~/a.rs", Some(home)),
+            PathBuf::from("dir/~/a.rs")
+        );
+        assert_eq!(expand_home("~/a.rs", None), PathBuf::from("~/a.rs"));
+        assert_eq!(
+            expand_home("~\\a.rs", Some(home)) == home.join("a.rs"),
+            cfg!(windows)
+        );
+    }
+
+    #[test]
+    fn test_home_inside_workspace_resolves() {
+        let Some(home) = dirs::home_dir().filter(|home| home.is_dir()) else {
+            return;
+        };
+        let ctx = context(&home, None);
+        assert_eq!(resolve_path("~", &ctx).unwrap(), canonical(&home));
+
+        let workspace = TempDir::new().unwrap();
+        let ctx = context(workspace.path(), None);
+        if !canonical(workspace.path()).starts_with(canonical(&home)) {
+            assert!(resolve_path("~", &ctx).is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_workspace_root_is_reported() {
+        let workspace = TempDir::new().unwrap();
+        let ctx = context(&workspace.path().join("missing"), None);
+
+        assert!(resolve_path("a.txt", &ctx)
+            .unwrap_err()
+            .contains("Invalid workspace root"));
+        assert!(base_dir(&ctx).is_err());
+
+        let ctx = context(workspace.path(), Some(&workspace.path().join("gone")));
+        assert!(resolve_path("a.txt", &ctx)
+            .unwrap_err()
+            .contains("Invalid worktree"));
+    }
+
+    #[test]
+    fn test_files_cannot_be_used_as_directories() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("file.txt"), "").unwrap();
+        let ctx = context(workspace.path(), None);
+
+        assert!(resolve_path("file.txt/child.txt", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_simplify_verbatim() {
+        assert_eq!(
+            simplify_verbatim(r"\\?\C:\Users\dev\project"),
+            Some(r"C:\Users\dev\project".to_string())
+        );
+        assert_eq!(simplify_verbatim(r"\\?\d:\"), Some(r"d:\".to_string()));
+        assert_eq!(
+            simplify_verbatim(r"\\?\UNC\server\share\project"),
+            Some(r"\\server\share\project".to_string())
+        );
+
+        // Not verbatim, or without a plain form
+        assert_eq!(simplify_verbatim(r"C:\Users\dev"), None);
+        assert_eq!(simplify_verbatim(r"\\server\share"), None);
+        assert_eq!(simplify_verbatim("/home/dev"), None);
+        assert_eq!(simplify_verbatim(r"\\?\Volume{1234}\dir"), None);
+        assert_eq!(simplify_verbatim(r"\\?\C:"), None);
+        let long = format!(r"\\?\C:\{}", "a".repeat(300));
+        assert_eq!(simplify_verbatim(&long), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_paths() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::create_dir(workspace.path().join("src")).unwrap();
+        let ctx = context(workspace.path(), None);
+        let root = canonical(workspace.path());
+        assert!(!root.to_string_lossy().starts_with(r"\\?\"));
+
+        // Either separator, and the verbatim form of the same path
+        assert_eq!(
+            resolve_path(r"src\a.rs", &ctx).unwrap(),
+            root.join("src").join("a.rs")
+        );
+        assert_eq!(
+            resolve_path("src/a.rs", &ctx).unwrap(),
+            root.join("src").join("a.rs")
+        );
+        let verbatim = workspace.path().canonicalize().unwrap().join("src");
+        assert_eq!(
+            resolve_path(&verbatim.to_string_lossy(), &ctx).unwrap(),
+            root.join("src")
+        );
+
+        // Another drive or a rooted path leaves the workspace
+        assert!(resolve_path(r"\Windows\system.ini", &ctx).is_err());
+        assert!(resolve_path(r"\\server\share\file.txt", &ctx).is_err());
+    }
+}
@@ -3,8 +3,8 @@
 //! Provides shell command execution with workspace validation and timeouts.
 //! Wraps existing shell utilities from the codebase.
 
+use crate::platform::paths::{base_dir, resolve_path};
 use crate::platform::types::*;
-use std::path::Path;
 
 /// Shell operations provider
 #[derive(Clone)]
@@ -15,27 +15,17 @@ impl ShellPlatform {
         Self
     }
 
-    /// Validate that working directory is within workspace
+    /// Resolve a working directory inside the workspace
     fn validate_cwd(&self, cwd: &str, ctx: &PlatformContext) -> Result<String, String> {
-        let path = Path::new(cwd);
-        let canonical_path = path
-            .canonicalize()
-            .map_err(|e| format!("Invalid working directory: {}", e))?;
-
-        let canonical_root = ctx
-            .workspace_root
-            .canonicalize()
-            .map_err(|e| format!("Invalid workspace root: {}", e))?;
-
-        if !canonical_path.starts_with(&canonical_root) {
+        let path = resolve_path(cwd, ctx)?;
+        if !path.is_dir() {
             return Err(format!(
-                "Working directory '{}' is outside workspace root '{}'",
-                canonical_path.display(),
-                canonical_root.display()
+                "Working directory '{}' is not a directory",
+                path.display()
             ));
         }
 
-        Ok(canonical_path.to_string_lossy().to_string())
+        Ok(path.to_string_lossy().to_string())
     }
 
     /// Execute a shell command
@@ -51,7 +41,10 @@ impl ShellPlatform {
                 Ok(validated) => Some(validated),
                 Err(e) => return PlatformResult::error(e),
             },
-            None => Some(ctx.workspace_root.to_string_lossy().to_string()),
+            None => match base_dir(ctx) {
+                Ok(dir) => Some(dir.to_string_lossy().to_string()),
+                Err(e) => return PlatformResult::error(e),
+            },
         };
 
         // Check for dangerous commands
@@ -107,26 +100,19 @@ impl ShellPlatform {
         ctx: &PlatformContext,
     ) -> PlatformResult<ShellResult> {
         // Validate script path
-        let path = Path::new(script_path);
-        let canonical_path = match path.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return PlatformResult::error(format!("Invalid script path: {}", e)),
-        };
-
-        let _ = match self.validate_cwd(
-            canonical_path
-                .parent()
-                .map(|p| p.to_string_lossy().to_string())
-                .as_deref()
-                .unwrap_or("/"),
-            ctx,
-        ) {
-            Ok(p) => p,
+        let script = match resolve_path(script_path, ctx) {
+            Ok(path) if path.is_file() => path,
+            Ok(path) => {
+                return PlatformResult::error(format!(
+                    "Invalid script path: '{}' is not a file",
+                    path.display()
+                ))
+            }
             Err(e) => return PlatformResult::error(e),
         };
 
         // Build command
-        let command = format!("{} {}", script_path, args.join(" "));
+        let command = format!("{} {}", script.display(), args.join(" "));
         self.execute(&command, cwd, ctx).await
     }
 
@@ -194,6 +180,37 @@ mod tests {
         assert_eq!(shell_result.exit_code, 0);
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_working_directory_is_resolved_in_worktree() {
+        let shell = ShellPlatform::new();
+        let workspace = TempDir::new().unwrap();
+        let worktree = TempDir::new().unwrap();
+        std::fs::create_dir(worktree.path().join("sub")).unwrap();
+
+        let ctx = PlatformContext {
+            workspace_root: workspace.path().to_path_buf(),
+            worktree_path: Some(worktree.path().to_path_buf()),
+            max_file_size: 1024 * 1024,
+            shell_timeout_secs: 60,
+        };
+        let worktree_root = worktree.path().canonicalize().unwrap();
+
+        let result = shell.execute("pwd", None, &ctx).await.data.unwrap();
+        assert_eq!(result.stdout.trim(), worktree_root.to_string_lossy());
+
+        let result = shell.execute("pwd", Some("sub"), &ctx).await.data.unwrap();
+        assert_eq!(
+            result.stdout.trim(),
+            worktree_root.join("sub").to_string_lossy()
+        );
+
+        let result = shell.execute("pwd", Some(".."), &ctx).await;
+        assert!(result.error.unwrap().contains("outside"));
+        let result = shell.execute("pwd", Some("missing"), &ctx).await;
+        assert!(result.error.unwrap().contains("not a directory"));
+    }
+
     #[tokio::test]
     async fn test_dangerous_command_detection() {
         let shell = ShellPlatform::new();
@@ -69,6 +69,43 @@ pub struct FileInfo {
     pub created_at: Option<i64>,
 }
 
+/// A file as read for the `readFile` tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum FileContent {
+    /// A page of numbered lines
+    Text {
+        file_path: String,
+        content: String,
+        start_line: usize,
+        end_line: usize,
+        total_lines: usize,
+        /// Lines after `end_line` were left out
+        truncated: bool,
+        /// Definitions of a file too large to read whole
+        #[serde(skip_serializing_if = "Option::is_none")]
+        outline: Option<String>,
+        message: String,
+    },
+    /// An image, by reference; the agent loop loads it for the model turn
+    /// that follows the read
+    Image {
+        file_path: String,
+        mime_type: String,
+        size: u64,
+    },
+    /// Any other binary file, which is described but not read
+    Binary {
+        file_path: String,
+        size: u64,
+        message: String,
+    },
+}
+
 /// Directory entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -145,6 +182,17 @@ pub struct LspSymbol {
     pub container_name: Option<String>,
 }
 
+/// LSP diagnostic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspDiagnostic {
+    pub range: LspRange,
+    pub severity: String,
+    pub message: String,
+    pub source: Option<String>,
+    pub code: Option<String>,
+}
+
 /// Search result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -1,11 +1,10 @@
 use crate::constants::{is_code_extension, is_code_filename};
 use crate::walker::{WalkerConfig, WorkspaceWalker};
 use grep::regex::{RegexMatcher, RegexMatcherBuilder};
-use grep::searcher::sinks::UTF8;
-use grep::searcher::{BinaryDetection, SearcherBuilder};
+use grep::searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::ffi::OsStr;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
@@ -20,6 +19,12 @@ pub struct SearchMatch {
     pub line_number: u64,
     pub line_content: String,
     pub byte_offset: u64,
+    /// Lines before the match, when context lines were requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<String>,
+    /// Lines after the match, when context lines were requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +38,8 @@ pub struct RipgrepSearch {
     max_matches_per_file: usize,
     file_types: Option<HashSet<String>>,
     exclude_dirs: Option<HashSet<String>>,
+    case_sensitive: bool,
+    context_lines: usize,
 }
 
 impl Default for RipgrepSearch {
@@ -42,6 +49,8 @@ impl Default for RipgrepSearch {
             max_matches_per_file: 10,
             file_types: None,
             exclude_dirs: None,
+            case_sensitive: false,
+            context_lines: 0,
         }
     }
 }
@@ -72,6 +81,17 @@ impl RipgrepSearch {
         self
     }
 
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Include up to `context_lines` lines before and after each match
+    pub fn with_context_lines(mut self, context_lines: usize) -> Self {
+        self.context_lines = context_lines;
+        self
+    }
+
     #[inline]
     fn is_valid_file(&self, path: &Path) -> bool {
         // If file_types is specified, use it for filtering
@@ -179,7 +199,7 @@ impl RipgrepSearch {
         // Create regex matcher once with proper builder pattern
         let matcher = Arc::new(
             RegexMatcherBuilder::new()
-                .case_insensitive(true)
+                .case_insensitive(!self.case_sensitive)
                 .line_terminator(Some(b'\n'))
                 .build(query)
                 .map_err(|e| format!("Failed to create regex matcher: {}", e))?,
@@ -252,34 +272,23 @@ impl RipgrepSearch {
         max_matches: usize,
         query: &str,
     ) -> Result<Option<SearchResult>, String> {
-        let mut matches = Vec::with_capacity(max_matches.min(10)); // Pre-allocate reasonable capacity
-
         // Create searcher with optimized settings
         let mut searcher = SearcherBuilder::new()
             .binary_detection(BinaryDetection::quit(b'\x00'))
             .line_number(true)
+            .before_context(self.context_lines)
+            .after_context(self.context_lines)
             .build();
 
-        let mut match_count = 0;
-
-        let result = searcher.search_path(
-            matcher,
-            file_path,
-            UTF8(|lnum, line| {
-                if match_count >= max_matches {
-                    return Ok(false); // Early termination
-                }
-
-                matches.push(SearchMatch {
-                    line_number: lnum,
-                    line_content: Self::truncate_line_with_context(line, query),
-                    byte_offset: 0,
-                });
-
-                match_count += 1;
-                Ok(true)
-            }),
-        );
+        let mut sink = MatchSink {
+            query,
+            max_matches,
+            context_lines: self.context_lines,
+            matches: Vec::with_capacity(max_matches.min(10)), // Pre-allocate reasonable capacity
+            recent: VecDeque::new(),
+        };
+        let result = searcher.search_path(matcher, file_path, &mut sink);
+        let matches = sink.matches;
 
         match result {
             Ok(_) => {
@@ -297,6 +306,81 @@ impl RipgrepSearch {
     }
 }
 
+/// Collects the matches of one file, with the lines around them when
+/// context lines are requested
+struct MatchSink<'a> {
+    query: &'a str,
+    max_matches: usize,
+    context_lines: usize,
+    matches: Vec<SearchMatch>,
+    /// The lines just before the next match, oldest first
+    recent: VecDeque<String>,
+}
+
+impl MatchSink<'_> {
+    fn push_line(&mut self, line: String) {
+        if self.context_lines == 0 {
+            return;
+        }
+
+        // Later matches have fewer lines after them, so stop at the first full one
+        for earlier in self.matches.iter_mut().rev() {
+            if earlier.context_after.len() >= self.context_lines {
+                break;
+            }
+            earlier.context_after.push(line.clone());
+        }
+
+        self.recent.push_back(line);
+        if self.recent.len() > self.context_lines {
+            self.recent.pop_front();
+        }
+    }
+}
+
+impl Sink for MatchSink<'_> {
+    type Error = std::io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, Self::Error> {
+        if self.matches.len() >= self.max_matches {
+            return Ok(false); // Early termination
+        }
+
+        let line = RipgrepSearch::truncate_line_with_context(
+            &String::from_utf8_lossy(mat.bytes()),
+            self.query,
+        );
+        let context_before = self.recent.drain(..).collect();
+        self.push_line(line.clone());
+        self.matches.push(SearchMatch {
+            line_number: mat.line_number().unwrap_or(0),
+            line_content: line,
+            byte_offset: 0,
+            context_before,
+            context_after: Vec::new(),
+        });
+        Ok(true)
+    }
+
+    fn context(
+        &mut self,
+        _searcher: &Searcher,
+        context: &SinkContext<'_>,
+    ) -> Result<bool, Self::Error> {
+        let line = RipgrepSearch::truncate_line_with_context(
+            &String::from_utf8_lossy(context.bytes()),
+            self.query,
+        );
+        self.push_line(line);
+        Ok(true)
+    }
+
+    fn context_break(&mut self, _searcher: &Searcher) -> Result<bool, Self::Error> {
+        self.recent.clear();
+        Ok(true)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -437,6 +521,49 @@ mod tests {
         assert!(!results_upper.is_empty());
     }
 
+    #[test]
+    fn test_search_case_sensitive() {
+        let temp_dir = create_test_search_directory();
+        let search = RipgrepSearch::new().with_case_sensitive(true);
+
+        let results = search
+            .search_content("HELLO", temp_dir.path().to_str().unwrap())
+            .unwrap();
+        assert!(results.is_empty());
+
+        let results = search
+            .search_content("Hello", temp_dir.path().to_str().unwrap())
+            .unwrap();
+        assert!(!results.is_empty());
+    }
+
+    #[test]
+    fn test_search_context_lines() {
+        let temp_dir = create_test_search_directory();
+        let search = RipgrepSearch::new().with_context_lines(1);
+
+        let results = search
+            .search_content("println", temp_dir.path().join("src").to_str().unwrap())
+            .unwrap();
+        let lib = results
+            .iter()
+            .find(|r| r.file_path.ends_with("lib.rs"))
+            .expect("Should find println in lib.rs");
+
+        assert_eq!(lib.matches.len(), 2);
+        assert_eq!(lib.matches[0].context_before, vec!["pub fn greet() {"]);
+        assert_eq!(lib.matches[0].context_after, vec!["}"]);
+        assert_eq!(lib.matches[1].context_before, vec!["pub fn farewell() {"]);
+        assert_eq!(lib.matches[1].context_after, vec!["}"]);
+
+        // Without context lines nothing extra is collected or serialized
+        let results = RipgrepSearch::new()
+            .search_content("println", temp_dir.path().to_str().unwrap())
+            .unwrap();
+        let json = serde_json::to_string(&results).unwrap();
+        assert!(!json.contains("context_before"));
+    }
+
     #[test]
     fn test_search_result_structure() {
         let temp_dir = create_test_search_directory();
@@ -505,6 +632,8 @@ mod tests {
             line_number: 42,
             line_content: "fn test() {}".to_string(),
             byte_offset: 100,
+            context_before: Vec::new(),
+            context_after: Vec::new(),
         };
 
         let json = serde_json::to_string(&match_item).unwrap();
@@ -525,6 +654,8 @@ mod tests {
                 line_number: 1,
                 line_content: "fn main() {}".to_string(),
                 byte_offset: 0,
+                context_before: Vec::new(),
+                context_after: Vec::new(),
             }],
         };
 
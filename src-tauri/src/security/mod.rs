@@ -1,76 +1,196 @@
-use axum::body::Body;
+pub mod tokens;
+
 use axum::extract::{Request, State};
+use axum::http::{Method, StatusCode};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 
 use crate::server::state::ServerState;
+use crate::storage::models::ApiTokenScope;
 
 const API_KEY_HEADER: &str = "x-api-key";
+const API_KEY_QUERY_PARAM: &str = "api_key";
+
+/// Paths served without a token
+const PUBLIC_PATHS: &[&str] = &["/health", "/v1/openapi.json"];
+
+/// Permissions of an authenticated request, available to handlers as a
+/// request extension
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    /// Token used, `None` for the legacy key or disabled validation
+    pub token_id: Option<String>,
+    pub scopes: Vec<ApiTokenScope>,
+}
+
+impl AuthContext {
+    /// Full access, for servers with API key validation turned off
+    pub fn unrestricted() -> Self {
+        Self {
+            token_id: None,
+            scopes: vec![ApiTokenScope::Admin],
+        }
+    }
+
+    pub fn allows(&self, required: ApiTokenScope) -> bool {
+        self.scopes.iter().any(|scope| scope.grants(required))
+    }
+}
 
-/// API key middleware that validates against configured key
+/// API token middleware
+///
+/// Requests present a token in the `x-api-key` header and need the scope
+/// returned by `required_scope`. Requests without a valid token are rejected,
+/// also when no token is configured yet: loopback peers may be relaying remote
+/// callers through a tunnel, so the headless server mints a bootstrap token
+/// on startup instead (see `tokens::ensure_bootstrap_token`).
 pub async fn api_key_middleware(
     State(state): State<ServerState>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Response {
-    // Get the API key from request header
+    if is_public_path(req.uri().path()) {
+        return next.run(req).await;
+    }
+
+    // Get the API key from request header; browsers can't set headers on
+    // WebSocket upgrades, so those may pass it as a query parameter instead
     let request_key = req
         .headers()
         .get(API_KEY_HEADER)
         .and_then(|value| value.to_str().ok())
-        .map(|value| value.trim())
+        .map(|value| value.trim().to_string())
+        .or_else(|| websocket_query_key(&req))
         .filter(|value| !value.is_empty());
 
-    // Check if API key validation is configured
-    let validation_enabled = match state
-        .storage()
-        .settings
-        .get_setting("api_key_validation_enabled")
-        .await
-    {
+    let settings = &state.storage().settings;
+
+    // Explicit opt-out for trusted deployments
+    let validation_enabled = match settings.get_setting("api_key_validation_enabled").await {
         Ok(Some(val)) => val.as_bool().unwrap_or(true),
         _ => true, // Default to enabled
     };
 
-    if !validation_enabled {
-        // Validation disabled, allow all requests
-        return next.run(req).await;
-    }
-
-    // Get the configured API key from settings
-    let configured_key = match state.storage().settings.get_setting("api_key").await {
-        Ok(Some(val)) => val.as_str().map(|s| s.to_string()),
-        _ => None,
-    };
+    let auth = if !validation_enabled {
+        AuthContext::unrestricted()
+    } else {
+        let now = chrono::Utc::now().timestamp();
+        let resolved = match request_key {
+            Some(key) => tokens::authenticate(settings, &key, now).await,
+            None => Ok(None),
+        };
 
-    let authorized = match (request_key, configured_key) {
-        (Some(req_key), Some(cfg_key)) => req_key == cfg_key,
-        (Some(_), None) => {
-            // No configured key, but request has one - accept any non-empty key
-            true
-        }
-        (None, Some(_)) => {
-            // Configured key exists but none provided
-            false
-        }
-        (None, None) => {
-            // No key configured and none provided - allow for setup
-            true
+        match resolved {
+            Ok(Some(auth)) => auth,
+            Ok(None) => {
+                return (StatusCode::UNAUTHORIZED, "Invalid or missing API key").into_response();
+            }
+            Err(e) => {
+                log::error!("Failed to authenticate request: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
         }
     };
 
-    if !authorized {
+    let required = required_scope(req.method(), req.uri().path());
+    if !auth.allows(required) {
         return (
-            axum::http::StatusCode::UNAUTHORIZED,
-            "Invalid or missing API key",
+            StatusCode::FORBIDDEN,
+            format!("API token lacks the '{}' scope", required.as_str()),
         )
             .into_response();
     }
 
+    req.extensions_mut().insert(auth);
     next.run(req).await
 }
 
+/// Whether a path is served without authentication
+pub fn is_public_path(path: &str) -> bool {
+    PUBLIC_PATHS.contains(&path)
+}
+
+/// Scope a request needs, by route and method
+///
+/// Token management needs `admin`, session files need `files`, other reads
+/// need `read-only` and other writes `sessions:write`. WebSocket upgrades only
+/// need `read-only`; actions sent over the socket are checked separately.
+pub fn required_scope(method: &Method, path: &str) -> ApiTokenScope {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match segments.as_slice() {
+        ["v1", "tokens", ..] => ApiTokenScope::Admin,
+        ["v1", "sessions", _, "files", ..] => ApiTokenScope::Files,
+        _ if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) => {
+            ApiTokenScope::ReadOnly
+        }
+        _ => ApiTokenScope::SessionsWrite,
+    }
+}
+
 /// Simple middleware for health checks (no auth required)
 pub async fn health_check_middleware(req: Request, next: Next) -> Response {
     next.run(req).await
 }
+
+/// API key passed as `?api_key=` on a WebSocket upgrade request
+fn websocket_query_key(req: &Request) -> Option<String> {
+    let is_upgrade = req
+        .headers()
+        .get(axum::http::header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    if !is_upgrade {
+        return None;
+    }
+
+    url::form_urlencoded::parse(req.uri().query()?.as_bytes())
+        .find(|(name, _)| name == API_KEY_QUERY_PARAM)
+        .map(|(_, value)| value.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(
+            required_scope(&Method::GET, "/v1/sessions/sess_1/messages"),
+            ApiTokenScope::ReadOnly
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/v1/sessions/sess_1/actions"),
+            ApiTokenScope::SessionsWrite
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/v1/sessions/sess_1/files/att_1/download"),
+            ApiTokenScope::Files
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/v1/tokens"),
+            ApiTokenScope::Admin
+        );
+        assert_eq!(
+            required_scope(&Method::DELETE, "/v1/tokens/tok_1"),
+            ApiTokenScope::Admin
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/v1/ws"),
+            ApiTokenScope::ReadOnly
+        );
+    }
+
+    #[test]
+    fn test_scope_grants() {
+        let writer = AuthContext {
+            token_id: None,
+            scopes: vec![ApiTokenScope::SessionsWrite],
+        };
+        assert!(writer.allows(ApiTokenScope::ReadOnly));
+        assert!(writer.allows(ApiTokenScope::SessionsWrite));
+        assert!(!writer.allows(ApiTokenScope::Files));
+        assert!(!writer.allows(ApiTokenScope::Admin));
+        assert!(AuthContext::unrestricted().allows(ApiTokenScope::Files));
+    }
+}
@@ -0,0 +1,310 @@
+//! API Tokens
+//!
+//! Secrets are shown once when minted; only their SHA-256 hash is stored. A
+//! token carries scopes, an optional expiry and a last-used timestamp. The
+//! legacy plaintext `api_key` setting still authenticates, with admin scope.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::security::AuthContext;
+use crate::storage::models::{ApiToken, ApiTokenScope};
+use crate::storage::settings::SettingsRepository;
+
+/// Prefix of minted secrets, so leaked tokens are easy to recognise
+const SECRET_PREFIX: &str = "tc_";
+/// Characters of the secret kept in the clear to tell tokens apart
+const DISPLAY_PREFIX_LEN: usize = 10;
+/// `last_used_at` is only written when older than this, to spare the database
+const TOUCH_INTERVAL_SECS: i64 = 60;
+
+/// Hex-encoded SHA-256 of a token secret
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Create a token and return it together with its secret
+pub async fn mint_token(
+    settings: &SettingsRepository,
+    label: &str,
+    scopes: Vec<ApiTokenScope>,
+    expires_at: Option<i64>,
+) -> Result<(ApiToken, String), String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("Token label must not be empty".to_string());
+    }
+    if scopes.is_empty() {
+        return Err("Token needs at least one scope".to_string());
+    }
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret = format!("{}{}", SECRET_PREFIX, hex::encode(bytes));
+
+    let mut unique_scopes = Vec::with_capacity(scopes.len());
+    for scope in scopes {
+        if !unique_scopes.contains(&scope) {
+            unique_scopes.push(scope);
+        }
+    }
+
+    let token = ApiToken {
+        id: format!("tok_{}", uuid::Uuid::new_v4().to_string().replace("-", "")),
+        label: label.to_string(),
+        prefix: secret[..DISPLAY_PREFIX_LEN].to_string(),
+        scopes: unique_scopes,
+        created_at: chrono::Utc::now().timestamp(),
+        expires_at,
+        last_used_at: None,
+        revoked_at: None,
+    };
+    settings
+        .create_api_token(&token, &hash_secret(&secret))
+        .await?;
+
+    Ok((token, secret))
+}
+
+/// Resolve a presented secret to the caller's permissions
+///
+/// Returns `None` for unknown, revoked or expired tokens.
+pub async fn authenticate(
+    settings: &SettingsRepository,
+    secret: &str,
+    now: i64,
+) -> Result<Option<AuthContext>, String> {
+    let secret_hash = hash_secret(secret);
+
+    if let Some(token) = settings.get_api_token_by_hash(&secret_hash).await? {
+        if !token.is_active(now) {
+            return Ok(None);
+        }
+        if token
+            .last_used_at
+            .map_or(true, |used| now - used >= TOUCH_INTERVAL_SECS)
+        {
+            if let Err(e) = settings.touch_api_token(&token.id, now).await {
+                log::warn!("Failed to record use of token {}: {}", token.id, e);
+            }
+        }
+        return Ok(Some(AuthContext {
+            token_id: Some(token.id),
+            scopes: token.scopes,
+        }));
+    }
+
+    match legacy_api_key(settings).await? {
+        Some(key) if constant_time_eq(&hash_secret(&key), &secret_hash) => Ok(Some(AuthContext {
+            token_id: None,
+            scopes: vec![ApiTokenScope::Admin],
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Label of the admin token minted when a server starts without credentials
+pub const BOOTSTRAP_TOKEN_LABEL: &str = "bootstrap";
+
+/// Mint an admin token if no credential is set up yet, returning its secret
+///
+/// Servers reject every request until a credential exists, so a headless
+/// server calls this on startup and shows the secret to the operator once.
+pub async fn ensure_bootstrap_token(
+    settings: &SettingsRepository,
+) -> Result<Option<String>, String> {
+    if is_configured(settings).await? {
+        return Ok(None);
+    }
+    let (_, secret) = mint_token(
+        settings,
+        BOOTSTRAP_TOKEN_LABEL,
+        vec![ApiTokenScope::Admin],
+        None,
+    )
+    .await?;
+    Ok(Some(secret))
+}
+
+/// Setting holding the secret the desktop app presents to its own server
+pub const DESKTOP_TOKEN_SETTING: &str = "desktop_api_token";
+
+/// Secret the desktop app presents to its embedded server
+///
+/// The bootstrap token minted on first start is kept in settings, which the
+/// HTTP API does not expose, so the frontend gets it on every launch. `None`
+/// when the user set up credentials themselves or revoked the desktop token.
+pub async fn ensure_desktop_token(settings: &SettingsRepository) -> Result<Option<String>, String> {
+    let stored = settings
+        .get_setting(DESKTOP_TOKEN_SETTING)
+        .await?
+        .and_then(|value| value.as_str().map(|s| s.to_string()));
+    if let Some(secret) = stored {
+        let now = chrono::Utc::now().timestamp();
+        if authenticate(settings, &secret, now).await?.is_some() {
+            return Ok(Some(secret));
+        }
+        log::warn!("The desktop API token is no longer valid");
+    }
+
+    let secret = ensure_bootstrap_token(settings).await?;
+    if let Some(secret) = &secret {
+        settings
+            .set_setting(DESKTOP_TOKEN_SETTING, &serde_json::json!(secret))
+            .await?;
+    }
+    Ok(secret)
+}
+
+/// Whether any credential has been set up for the server
+pub async fn is_configured(settings: &SettingsRepository) -> Result<bool, String> {
+    Ok(settings.has_api_tokens().await? || legacy_api_key(settings).await?.is_some())
+}
+
+/// The plaintext `api_key` setting of older installs
+async fn legacy_api_key(settings: &SettingsRepository) -> Result<Option<String>, String> {
+    Ok(settings
+        .get_setting("api_key")
+        .await?
+        .and_then(|value| value.as_str().map(|s| s.trim().to_string()))
+        .filter(|key| !key.is_empty()))
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::storage::migrations::{settings_migrations, MigrationRunner};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    async fn create_settings() -> (SettingsRepository, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("settings.db");
+        let db = Arc::new(Database::new(db_path.to_string_lossy().to_string()));
+        db.connect().await.unwrap();
+
+        let migrations = settings_migrations();
+        let runner = MigrationRunner::new(&db, &migrations);
+        runner.init().await.unwrap();
+        runner.migrate().await.unwrap();
+
+        (SettingsRepository::new(db), temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_minted_token_authenticates_until_revoked_or_expired() {
+        let (settings, _temp) = create_settings().await;
+        assert!(!is_configured(&settings).await.unwrap());
+
+        let (token, secret) = mint_token(
+            &settings,
+            "tunnel",
+            vec![ApiTokenScope::ReadOnly, ApiTokenScope::ReadOnly],
+            Some(1_000),
+        )
+        .await
+        .unwrap();
+        assert!(secret.starts_with("tc_"));
+        assert!(secret.starts_with(&token.prefix));
+        assert_eq!(token.scopes, vec![ApiTokenScope::ReadOnly]);
+        assert!(is_configured(&settings).await.unwrap());
+
+        let auth = authenticate(&settings, &secret, 500)
+            .await
+            .unwrap()
+            .expect("token should authenticate");
+        assert_eq!(auth.token_id.as_deref(), Some(token.id.as_str()));
+        assert!(auth.allows(ApiTokenScope::ReadOnly));
+        assert!(!auth.allows(ApiTokenScope::SessionsWrite));
+
+        assert!(authenticate(&settings, "tc_wrong", 500)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(authenticate(&settings, &secret, 1_000)
+            .await
+            .unwrap()
+            .is_none());
+
+        settings.revoke_api_token(&token.id).await.unwrap();
+        assert!(authenticate(&settings, &secret, 500)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_api_key_is_admin() {
+        let (settings, _temp) = create_settings().await;
+        settings
+            .set_setting("api_key", &serde_json::json!("legacy-secret"))
+            .await
+            .unwrap();
+        assert!(is_configured(&settings).await.unwrap());
+
+        let auth = authenticate(&settings, "legacy-secret", 0)
+            .await
+            .unwrap()
+            .expect("legacy key should authenticate");
+        assert!(auth.allows(ApiTokenScope::Admin));
+        assert!(authenticate(&settings, "other", 0).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_token_is_minted_once() {
+        let (settings, _temp) = create_settings().await;
+
+        let secret = ensure_bootstrap_token(&settings)
+            .await
+            .unwrap()
+            .expect("unconfigured server should get a bootstrap token");
+        let auth = authenticate(&settings, &secret, 0)
+            .await
+            .unwrap()
+            .expect("bootstrap token should authenticate");
+        assert!(auth.allows(ApiTokenScope::Admin));
+
+        assert!(ensure_bootstrap_token(&settings).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_desktop_token_survives_restarts_until_revoked() {
+        let (settings, _temp) = create_settings().await;
+
+        let secret = ensure_desktop_token(&settings)
+            .await
+            .unwrap()
+            .expect("first start should mint a token");
+        assert_eq!(
+            ensure_desktop_token(&settings).await.unwrap(),
+            Some(secret.clone())
+        );
+        let auth = authenticate(&settings, &secret, 0).await.unwrap().unwrap();
+        assert!(auth.allows(ApiTokenScope::Admin));
+
+        // A revoked desktop token is not replaced behind the user's back
+        settings
+            .revoke_api_token(auth.token_id.as_deref().unwrap())
+            .await
+            .unwrap();
+        assert!(ensure_desktop_token(&settings).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mint_token_validates_input() {
+        let (settings, _temp) = create_settings().await;
+        assert!(mint_token(&settings, " ", vec![ApiTokenScope::Admin], None)
+            .await
+            .is_err());
+        assert!(mint_token(&settings, "ci", vec![], None).await.is_err());
+    }
+}
@@ -1,10 +1,33 @@
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 
+/// Default request body limit, same as axum's built-in default
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
 #[derive(Clone, Debug)]
 pub struct ServerConfig {
     pub workspace_root: PathBuf,
     pub data_root: PathBuf,
     pub attachments_root: PathBuf,
+    /// Address to listen on; loopback unless the server should be reachable
+    /// from other machines
+    pub bind_address: IpAddr,
+    /// Port to listen on; 0 picks a free port
+    pub port: u16,
+    /// Serve HTTPS with this certificate instead of plain HTTP
+    pub tls: Option<TlsConfig>,
+    /// Origins allowed to call the API from a browser; `*` allows any origin.
+    /// Empty disables CORS.
+    pub cors_origins: Vec<String>,
+    /// Maximum request body size in bytes
+    pub body_limit: usize,
+}
+
+/// PEM certificate chain and private key for rustls
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
 }
 
 impl ServerConfig {
@@ -14,6 +37,39 @@ impl ServerConfig {
             workspace_root,
             data_root,
             attachments_root,
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 0,
+            tls: None,
+            cors_origins: Vec::new(),
+            body_limit: DEFAULT_BODY_LIMIT,
         }
     }
+
+    pub fn with_bind_address(mut self, bind_address: IpAddr) -> Self {
+        self.bind_address = bind_address;
+        self
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn with_tls(mut self, cert_path: PathBuf, key_path: PathBuf) -> Self {
+        self.tls = Some(TlsConfig {
+            cert_path,
+            key_path,
+        });
+        self
+    }
+
+    pub fn with_cors_origins(mut self, cors_origins: Vec<String>) -> Self {
+        self.cors_origins = cors_origins;
+        self
+    }
+
+    pub fn with_body_limit(mut self, body_limit: usize) -> Self {
+        self.body_limit = body_limit;
+        self
+    }
 }
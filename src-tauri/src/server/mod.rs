@@ -1,21 +1,86 @@
 pub mod config;
+pub mod openapi;
 pub mod routes;
 pub mod state;
 pub mod streaming_bridge;
 pub mod types;
 
+use axum::extract::DefaultBodyLimit;
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use axum_server::tls_rustls::RustlsConfig;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
 use crate::core::types::EventSender;
-use crate::security::api_key_middleware;
+use crate::security::{api_key_middleware, tokens};
 use crate::server::state::ServerStateFactory;
 
-pub use config::ServerConfig;
+pub use config::{ServerConfig, TlsConfig};
 pub use state::ServerState;
 
+/// How often `ServerHandle::shutdown` checks for remaining tasks
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Time open connections get to close once all tasks are gone
+const CONNECTION_GRACE: Duration = Duration::from_secs(5);
+
 pub struct ServerHandle {
+    /// Address the server is listening on, with the port resolved
     pub addr: SocketAddr,
+    /// Whether the server speaks HTTPS
+    pub tls: bool,
+    state: ServerState,
+    shutdown: watch::Sender<bool>,
+    server: JoinHandle<()>,
+}
+
+impl ServerHandle {
+    /// Base URL of the API
+    pub fn url(&self) -> String {
+        base_url(self.addr, self.tls)
+    }
+
+    /// Stop the server, letting active tasks finish first
+    ///
+    /// New connections are refused right away. Tasks still running after
+    /// `drain_timeout` are cancelled, then open connections such as event
+    /// streams are closed.
+    pub async fn shutdown(self, drain_timeout: Duration) {
+        let _ = self.shutdown.send(true);
+
+        let deadline = Instant::now() + drain_timeout;
+        loop {
+            let active = self.state.runtime().list_active_tasks().await;
+            if active.is_empty() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                log::warn!(
+                    "Cancelling {} tasks still running after {:?}",
+                    active.len(),
+                    drain_timeout
+                );
+                for task in active {
+                    let _ = task.cancel();
+                }
+                break;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+
+        // Event streams never end on their own
+        let mut server = self.server;
+        if tokio::time::timeout(CONNECTION_GRACE, &mut server)
+            .await
+            .is_err()
+        {
+            server.abort();
+        }
+        log::info!("Cloud backend server stopped");
+    }
 }
 
 pub async fn start_server(
@@ -27,29 +92,183 @@ pub async fn start_server(
         .await
         .map_err(|e| format!("Failed to create server state: {}", e))?;
 
+    start_server_with_state(state).await
+}
+
+/// Start the server embedded in the desktop app, returning its handle and
+/// the API token the app's frontend presents to it
+pub async fn start_desktop_server(
+    config: ServerConfig,
+    event_sender: EventSender,
+) -> Result<(ServerHandle, Option<String>), String> {
+    let state = ServerStateFactory::create(config, event_sender)
+        .await
+        .map_err(|e| format!("Failed to create server state: {}", e))?;
+    let token = tokens::ensure_desktop_token(&state.storage().settings).await?;
+
+    let handle = start_server_with_state(state).await?;
+    Ok((handle, token))
+}
+
+/// Serve an already assembled state, configured by `state.config`
+pub async fn start_server_with_state(state: ServerState) -> Result<ServerHandle, String> {
+    let config = state.config.clone();
+
+    // Load the certificate first so a bad TLS setup fails before anything runs
+    let tls_config = match &config.tls {
+        Some(tls) => Some(
+            RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+                .map_err(|e| format!("Failed to load TLS certificate: {}", e))?,
+        ),
+        None => None,
+    };
+    let cors = cors_layer(&config.cors_origins)?;
+
     // Build router with API key middleware
-    let app = routes::router(state.clone()).layer(axum::middleware::from_fn_with_state(
-        state,
-        api_key_middleware,
-    ));
+    let mut app = routes::router(state.clone())
+        .layer(DefaultBodyLimit::max(config.body_limit))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api_key_middleware,
+        ));
+    // Outermost, so preflight requests are answered without credentials
+    if let Some(cors) = cors {
+        app = app.layer(cors);
+    }
 
-    // Bind to any available port
-    let listener = TcpListener::bind(("127.0.0.1", 0))
+    let listener = TcpListener::bind((config.bind_address, config.port))
         .await
-        .map_err(|e| format!("Failed to bind server: {}", e))?;
+        .map_err(|e| {
+            format!(
+                "Failed to bind server to {}:{}: {}",
+                config.bind_address, config.port, e
+            )
+        })?;
 
     let addr = listener
         .local_addr()
         .map_err(|e| format!("Failed to read server address: {}", e))?;
 
-    log::info!("Cloud backend server starting on {}", addr);
+    if !addr.ip().is_loopback() && tls_config.is_none() {
+        log::warn!(
+            "Cloud backend server listens on {} without TLS; API tokens travel in plain text",
+            addr
+        );
+    }
+
+    let tls = tls_config.is_some();
+    log::info!("Cloud backend server starting on {}", base_url(addr, tls));
+
+    let service = app.into_make_service();
+    let (shutdown, shutdown_rx) = watch::channel(false);
 
     // Spawn server
-    tokio::spawn(async move {
-        if let Err(error) = axum::serve(listener, app).await {
-            log::error!("Cloud backend server error: {}", error);
+    let server = match tls_config {
+        Some(tls_config) => {
+            let listener = listener
+                .into_std()
+                .map_err(|e| format!("Failed to prepare TLS listener: {}", e))?;
+            let handle = axum_server::Handle::new();
+            let shutdown_handle = handle.clone();
+            tokio::spawn(async move {
+                shutdown_requested(shutdown_rx).await;
+                shutdown_handle.graceful_shutdown(None);
+            });
+            tokio::spawn(async move {
+                if let Err(error) = axum_server::from_tcp_rustls(listener, tls_config)
+                    .handle(handle)
+                    .serve(service)
+                    .await
+                {
+                    log::error!("Cloud backend server error: {}", error);
+                }
+            })
+        }
+        None => tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, service)
+                .with_graceful_shutdown(shutdown_requested(shutdown_rx))
+                .await
+            {
+                log::error!("Cloud backend server error: {}", error);
+            }
+        }),
+    };
+
+    Ok(ServerHandle {
+        addr,
+        tls,
+        state,
+        shutdown,
+        server,
+    })
+}
+
+fn base_url(addr: SocketAddr, tls: bool) -> String {
+    let scheme = if tls { "https" } else { "http" };
+    format!("{}://{}", scheme, addr)
+}
+
+/// Resolves once `ServerHandle::shutdown` is called; never if the handle is
+/// dropped, so the server keeps running
+async fn shutdown_requested(mut shutdown_rx: watch::Receiver<bool>) {
+    while shutdown_rx.changed().await.is_ok() {
+        if *shutdown_rx.borrow() {
+            return;
         }
-    });
+    }
+    std::future::pending::<()>().await;
+}
+
+/// CORS policy for the configured origins, or `None` when CORS is off
+fn cors_layer(origins: &[String]) -> Result<Option<CorsLayer>, String> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim().trim_end_matches('/'))
+                    .map_err(|_| format!("Invalid CORS origin '{}'", origin))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+            .allow_headers([
+                header::CONTENT_TYPE,
+                HeaderName::from_static("x-api-key"),
+                HeaderName::from_static("last-event-id"),
+            ]),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cors_layer() {
+        assert!(cors_layer(&[]).unwrap().is_none());
+        assert!(cors_layer(&["*".to_string()]).unwrap().is_some());
+        assert!(cors_layer(&["http://192.168.1.20:5173/".to_string()])
+            .unwrap()
+            .is_some());
+        assert!(cors_layer(&["http://bad\norigin".to_string()]).is_err());
+    }
 
-    Ok(ServerHandle { addr })
+    #[test]
+    fn test_base_url() {
+        let addr = "192.168.1.20:8787".parse().unwrap();
+        assert_eq!(base_url(addr, true), "https://192.168.1.20:8787");
+        assert_eq!(base_url(addr, false), "http://192.168.1.20:8787");
+    }
 }
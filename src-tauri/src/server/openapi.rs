@@ -0,0 +1,367 @@
+//! OpenAPI Document
+//!
+//! Describes the REST API as OpenAPI 3.0, built from the `routes::api_routes`
+//! table and the JSON schemas derived from `server::types`. Clients are
+//! generated from the document served at `/v1/openapi.json`.
+
+use axum::http::Method;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{ObjectValidation, Schema};
+use schemars::visit::Visitor;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+
+use crate::security::{is_public_path, required_scope};
+use crate::server::routes::{api_routes, ApiRoute, Payload, SchemaFn};
+use crate::server::types::ErrorResponse;
+
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// The document, built on first use
+pub fn document() -> &'static Value {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    DOCUMENT.get_or_init(build_document)
+}
+
+pub fn build_document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let error_schema = finish(&mut gen, |gen| gen.subschema_for::<ErrorResponse>());
+
+    let mut paths = Map::new();
+    for route in api_routes() {
+        let operation = operation(&route, &error_schema, &mut gen);
+        let item = paths
+            .entry(openapi_path(route.path))
+            .or_insert_with(|| json!({}));
+        item[route.method.as_str().to_lowercase()] = operation;
+    }
+
+    let mut schemas = Map::new();
+    for (name, mut schema) in gen.take_definitions() {
+        visit(&mut gen, &mut schema);
+        schemas.insert(name, serde_json::to_value(schema).unwrap_or_default());
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "TalkCody API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "apiKey": { "type": "apiKey", "in": "header", "name": "x-api-key" },
+                "apiKeyQuery": { "type": "apiKey", "in": "query", "name": "api_key" },
+            },
+        },
+        "security": [{ "apiKey": [] }],
+    })
+}
+
+fn operation(route: &ApiRoute, error_schema: &Value, gen: &mut SchemaGenerator) -> Value {
+    let mut parameters: Vec<Value> = path_params(route.path)
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            })
+        })
+        .collect();
+    if let Some(query) = route.query {
+        parameters.extend(query_params(query, gen));
+    }
+    if matches!(route.response, Some(Payload::EventStream(_))) {
+        parameters.push(json!({
+            "name": "Last-Event-ID",
+            "in": "header",
+            "required": false,
+            "schema": { "type": "string" },
+        }));
+    }
+
+    let mut operation = json!({
+        "operationId": operation_id(&route.method, route.path),
+        "summary": route.summary,
+        "responses": responses(route, error_schema, gen),
+    });
+    if !parameters.is_empty() {
+        operation["parameters"] = Value::Array(parameters);
+    }
+    match route.request {
+        Payload::Json(schema) => {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": finish(gen, schema) } },
+            });
+        }
+        Payload::Binary => {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/octet-stream": { "schema": binary_schema() } },
+            });
+        }
+        _ => {}
+    }
+
+    if is_public_path(route.path) {
+        operation["security"] = json!([]);
+    } else {
+        operation["x-required-scope"] = json!(required_scope(&route.method, route.path).as_str());
+        if matches!(route.response, Some(Payload::WebSocket { .. })) {
+            operation["security"] = json!([{ "apiKey": [] }, { "apiKeyQuery": [] }]);
+        }
+    }
+
+    operation
+}
+
+/// Responses of a route; handler errors are `ErrorResponse` bodies sent
+/// with status 200 in place of the regular result
+fn responses(route: &ApiRoute, error_schema: &Value, gen: &mut SchemaGenerator) -> Value {
+    let mut responses = match route.response {
+        Some(Payload::Json(schema)) => json!({
+            "200": {
+                "description": "The result, or an error",
+                "content": {
+                    "application/json": {
+                        "schema": { "oneOf": [finish(gen, schema), error_schema] },
+                    },
+                },
+            },
+        }),
+        Some(Payload::Binary) => json!({
+            "200": {
+                "description": "File contents",
+                "content": { "application/octet-stream": { "schema": binary_schema() } },
+            },
+        }),
+        Some(Payload::EventStream(schema)) => json!({
+            "200": {
+                "description": "Server-sent events whose data is a JSON event",
+                "content": { "text/event-stream": { "schema": finish(gen, schema) } },
+            },
+        }),
+        Some(Payload::WebSocket { client, server }) => json!({
+            "101": {
+                "description": "Switched to the WebSocket protocol",
+                "x-websocket": {
+                    "client": finish(gen, client),
+                    "server": finish(gen, server),
+                },
+            },
+        }),
+        Some(Payload::Empty) | None => json!({ "200": { "description": "OK" } }),
+    };
+
+    if !is_public_path(route.path) {
+        responses["401"] = json!({ "description": "Missing or invalid API token" });
+        responses["403"] = json!({ "description": "API token lacks the required scope" });
+    }
+    responses
+}
+
+/// Query parameters, one per property of the query type
+fn query_params(query: SchemaFn, gen: &mut SchemaGenerator) -> Vec<Value> {
+    let Schema::Object(schema) = query(gen) else {
+        return Vec::new();
+    };
+    let Some(object) = schema.object else {
+        return Vec::new();
+    };
+    let ObjectValidation {
+        properties,
+        required,
+        ..
+    } = *object;
+
+    properties
+        .into_iter()
+        .map(|(name, mut schema)| {
+            visit(gen, &mut schema);
+            json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&name),
+                "schema": serde_json::to_value(schema).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn finish(gen: &mut SchemaGenerator, schema: SchemaFn) -> Value {
+    let mut schema = schema(gen);
+    visit(gen, &mut schema);
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+/// Apply the OpenAPI adjustments of the generator, such as replacing boolean
+/// schemas that OpenAPI 3.0 does not allow
+fn visit(gen: &mut SchemaGenerator, schema: &mut Schema) {
+    for visitor in gen.visitors_mut() {
+        visitor.visit_schema(schema);
+    }
+}
+
+fn binary_schema() -> Value {
+    json!({ "type": "string", "format": "binary" })
+}
+
+/// Names of the `:param` segments of an axum path
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+}
+
+/// Convert an axum path (`/v1/sessions/:id`) to OpenAPI (`/v1/sessions/{id}`)
+pub fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Stable operation id, e.g. `getSessionsByIdEvents` for
+/// `GET /v1/sessions/:id/events`
+fn operation_id(method: &Method, path: &str) -> String {
+    let mut id = method.as_str().to_lowercase();
+    for segment in path.split('/').filter(|s| !s.is_empty() && *s != "v1") {
+        let (prefix, word) = match segment.strip_prefix(':') {
+            Some(name) => ("By", name),
+            None => ("", segment),
+        };
+        id.push_str(prefix);
+        for part in word.split(|c: char| !c.is_ascii_alphanumeric()) {
+            let mut chars = part.chars();
+            if let Some(first) = chars.next() {
+                id.push(first.to_ascii_uppercase());
+                id.push_str(chars.as_str());
+            }
+        }
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_refs<'a>(value: &'a Value, refs: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    match (key.as_str(), value) {
+                        ("$ref", Value::String(target)) => refs.push(target),
+                        _ => collect_refs(value, refs),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect_refs(item, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_every_route_is_documented() {
+        let doc = build_document();
+        let routes = api_routes();
+
+        let mut operations = 0;
+        for route in &routes {
+            let name = format!("{} {}", route.method, route.path);
+            assert!(
+                route.response.is_some(),
+                "{} has no response schema; describe it in routes::api_routes",
+                name
+            );
+            if matches!(route.method, Method::POST | Method::PUT | Method::PATCH) {
+                assert!(
+                    !matches!(route.request, Payload::Empty),
+                    "{} has no request body schema",
+                    name
+                );
+            }
+
+            let operation =
+                &doc["paths"][openapi_path(route.path)][route.method.as_str().to_lowercase()];
+            assert!(
+                operation.is_object(),
+                "{} is missing from the document",
+                name
+            );
+            operations += 1;
+        }
+
+        let documented: usize = doc["paths"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|item| item.as_object().unwrap().len())
+            .sum();
+        assert_eq!(documented, operations, "a route is registered twice");
+    }
+
+    #[test]
+    fn test_schema_refs_resolve() {
+        let doc = build_document();
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        assert!(schemas.contains_key("CreateTaskRequest"));
+        assert!(schemas.contains_key("StreamingEvent"));
+
+        let mut refs = Vec::new();
+        collect_refs(&doc, &mut refs);
+        assert!(!refs.is_empty());
+        for target in refs {
+            let name = target
+                .strip_prefix("#/components/schemas/")
+                .unwrap_or_else(|| panic!("unexpected $ref {}", target));
+            assert!(schemas.contains_key(name), "unresolved $ref {}", target);
+        }
+    }
+
+    #[test]
+    fn test_operation_details() {
+        let doc = build_document();
+
+        let list_sessions = &doc["paths"]["/v1/sessions"]["get"];
+        let mut names: Vec<&str> = list_sessions["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["limit", "offset", "projectId", "status"]);
+        assert_eq!(list_sessions["x-required-scope"], "read-only");
+
+        let download = &doc["paths"]["/v1/sessions/{session_id}/files/{file_id}/download"]["get"];
+        assert_eq!(
+            download["operationId"],
+            "getSessionsBySessionIdFilesByFileIdDownload"
+        );
+        assert_eq!(download["parameters"].as_array().unwrap().len(), 2);
+        assert_eq!(download["x-required-scope"], "files");
+
+        assert_eq!(doc["paths"]["/health"]["get"]["security"], json!([]));
+        assert!(doc["paths"]["/v1/tokens"]["post"]["requestBody"].is_object());
+    }
+
+    #[test]
+    fn test_openapi_path() {
+        assert_eq!(openapi_path("/v1/tasks"), "/v1/tasks");
+        assert_eq!(
+            openapi_path("/v1/sessions/:session_id/files/:file_id"),
+            "/v1/sessions/{session_id}/files/{file_id}"
+        );
+        assert_eq!(
+            operation_id(&Method::DELETE, "/v1/tokens/:id"),
+            "deleteTokensById"
+        );
+    }
+}
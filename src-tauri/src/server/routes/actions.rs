@@ -1,16 +1,31 @@
 use axum::extract::{Path, State};
 use axum::Json;
 
+use crate::core::questions;
 use crate::core::types::{TaskAction, ToolRequest};
 use crate::server::state::ServerState;
 use crate::server::types::*;
 
-/// Create an action on a session (approve, reject, tool_result, cancel)
+/// Create an action on a session (approve, reject, tool_result, answer, cancel)
 pub async fn create_action(
     State(state): State<ServerState>,
     Path(session_id): Path<String>,
     Json(payload): Json<CreateActionRequest>,
 ) -> Result<Json<CreateActionResponse>, Json<ErrorResponse>> {
+    dispatch_action(&state, &session_id, payload)
+        .await
+        .map(Json)
+        .map_err(Json)
+}
+
+/// Send an action to the active task of a session
+///
+/// Shared by the REST route and the WebSocket endpoint.
+pub async fn dispatch_action(
+    state: &ServerState,
+    session_id: &str,
+    payload: CreateActionRequest,
+) -> Result<CreateActionResponse, ErrorResponse> {
     // Find the active task for this session
     let tasks = state.runtime().list_active_tasks().await;
     let task_handle = tasks.into_iter().find(|t| t.session_id == session_id);
@@ -18,10 +33,10 @@ pub async fn create_action(
     let task_handle = match task_handle {
         Some(handle) => handle,
         None => {
-            return Err(Json(ErrorResponse::new(
+            return Err(ErrorResponse::new(
                 "NOT_FOUND",
                 format!("No active task found for session '{}'", session_id),
-            )));
+            ));
         }
     };
 
@@ -29,19 +44,13 @@ pub async fn create_action(
     let action = match payload.action_type.as_str() {
         "approve" => {
             let tool_call_id = payload.tool_call_id.ok_or_else(|| {
-                Json(ErrorResponse::new(
-                    "BAD_REQUEST",
-                    "tool_call_id required for approve action",
-                ))
+                ErrorResponse::new("BAD_REQUEST", "tool_call_id required for approve action")
             })?;
             TaskAction::Approve { tool_call_id }
         }
         "reject" => {
             let tool_call_id = payload.tool_call_id.ok_or_else(|| {
-                Json(ErrorResponse::new(
-                    "BAD_REQUEST",
-                    "tool_call_id required for reject action",
-                ))
+                ErrorResponse::new("BAD_REQUEST", "tool_call_id required for reject action")
             })?;
             TaskAction::Reject {
                 tool_call_id,
@@ -50,40 +59,76 @@ pub async fn create_action(
         }
         "tool_result" => {
             let tool_call_id = payload.tool_call_id.ok_or_else(|| {
-                Json(ErrorResponse::new(
+                ErrorResponse::new(
                     "BAD_REQUEST",
                     "tool_call_id required for tool_result action",
-                ))
+                )
             })?;
             let result = payload.result.ok_or_else(|| {
-                Json(ErrorResponse::new(
-                    "BAD_REQUEST",
-                    "result required for tool_result action",
-                ))
+                ErrorResponse::new("BAD_REQUEST", "result required for tool_result action")
             })?;
             TaskAction::ToolResult {
                 tool_call_id,
                 result,
             }
         }
+        "answer" => {
+            let pending = state
+                .runtime()
+                .pending_questions(session_id)
+                .await
+                .ok_or_else(|| {
+                    ErrorResponse::new(
+                        "BAD_REQUEST",
+                        format!("Session '{}' has no pending questions", session_id),
+                    )
+                })?;
+            if let Some(tool_call_id) = &payload.tool_call_id {
+                if *tool_call_id != pending.tool_call_id {
+                    return Err(ErrorResponse::new(
+                        "BAD_REQUEST",
+                        format!("Tool call '{}' is not waiting for answers", tool_call_id),
+                    ));
+                }
+            }
+
+            let answers = match (payload.answers, payload.text) {
+                (Some(answers), _) => answers,
+                (None, Some(text)) => questions::parse_reply(&pending.questions, &text)
+                    .map_err(|e| ErrorResponse::new("BAD_REQUEST", e))?,
+                (None, None) => {
+                    return Err(ErrorResponse::new(
+                        "BAD_REQUEST",
+                        "answers or text required for answer action",
+                    ));
+                }
+            };
+            questions::answers_to_output(&pending.questions, &answers)
+                .map_err(|e| ErrorResponse::new("BAD_REQUEST", e))?;
+
+            TaskAction::AnswerQuestions {
+                tool_call_id: pending.tool_call_id,
+                answers,
+            }
+        }
         "cancel" => TaskAction::Cancel,
         _ => {
-            return Err(Json(ErrorResponse::new(
+            return Err(ErrorResponse::new(
                 "BAD_REQUEST",
                 format!("Unknown action type: {}", payload.action_type),
-            )));
+            ));
         }
     };
 
     // Send action to the task
     match task_handle.send_action(action) {
-        Ok(_) => Ok(Json(CreateActionResponse {
+        Ok(_) => Ok(CreateActionResponse {
             success: true,
             message: "Action sent successfully".to_string(),
-        })),
-        Err(e) => Err(Json(ErrorResponse::new(
+        }),
+        Err(e) => Err(ErrorResponse::new(
             "INTERNAL_ERROR",
             format!("Failed to send action: {}", e),
-        ))),
+        )),
     }
 }
@@ -1,9 +1,11 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
 
+use crate::core::types::TaskInput;
+use crate::server::routes::sessions::{ensure_no_running_task, ensure_session_exists};
 use crate::server::state::ServerState;
 use crate::server::types::*;
-use crate::storage::models::{Message, MessageContent, MessageRole};
+use crate::storage::models::{Message, MessageContent, MessageRole, WorkspaceInfo};
 
 /// Create a new message in a session
 pub async fn create_message(
@@ -59,6 +61,54 @@ pub async fn create_message(
     }
 }
 
+/// Edit a user message and regenerate the reply on a new branch
+///
+/// The edited message becomes a sibling of the original, so the original
+/// conversation stays selectable as another branch.
+pub async fn edit_message(
+    State(state): State<ServerState>,
+    Path((session_id, message_id)): Path<(String, String)>,
+    Json(payload): Json<EditMessageRequest>,
+) -> Result<Json<CreateTaskResponse>, Json<ErrorResponse>> {
+    ensure_session_exists(&state, &session_id).await?;
+    ensure_no_running_task(&state, &session_id).await?;
+
+    let runtime = state.runtime();
+
+    if let Err(e) = runtime
+        .session_manager()
+        .branch_before(&session_id, &message_id)
+        .await
+    {
+        return Err(Json(ErrorResponse::new(
+            "BAD_REQUEST",
+            format!("Failed to edit message: {}", e),
+        )));
+    }
+
+    let task_input = TaskInput {
+        session_id: session_id.clone(),
+        agent_id: payload.agent_id,
+        project_id: None,
+        initial_message: payload.content,
+        settings: payload.settings,
+        workspace: payload.workspace.map(WorkspaceInfo::from),
+    };
+
+    match runtime.start_task(task_input).await {
+        Ok(handle) => Ok(Json(CreateTaskResponse {
+            task_id: handle.task_id.clone(),
+            session_id,
+            state: "pending".to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+        })),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to start task: {}", e),
+        ))),
+    }
+}
+
 /// Get messages for a session
 pub async fn get_messages(
     State(state): State<ServerState>,
@@ -1,53 +1,380 @@
-use axum::routing::{delete, get, patch, post};
+use axum::http::Method;
+use axum::routing::{delete, get, patch, post, MethodRouter};
 use axum::Router;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 
 use crate::server::state::ServerState;
+use crate::server::types::*;
+use crate::storage::models::{ApiToken, FileCheckpoint, MessageBranch, RestoredFile, TaskSettings};
+use crate::streaming::StreamingEvent;
 
 pub mod actions;
 pub mod files;
 pub mod health;
 pub mod messages;
+pub mod openapi;
 pub mod sessions;
 pub mod tasks;
+pub mod tokens;
+pub mod ws;
 
-pub fn router(state: ServerState) -> Router {
-    Router::new()
+/// Produces the schema of a request or response type
+pub type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// What a route reads or returns, for the OpenAPI document
+#[derive(Clone, Copy)]
+pub enum Payload {
+    /// No body
+    Empty,
+    /// A JSON value of the given type
+    Json(SchemaFn),
+    /// Raw bytes
+    Binary,
+    /// A `text/event-stream` whose events carry the given type
+    EventStream(SchemaFn),
+    /// A WebSocket upgrade, with the messages each side sends
+    WebSocket { client: SchemaFn, server: SchemaFn },
+}
+
+/// A route of the API together with the types it reads and returns
+///
+/// The router and the OpenAPI document are both built from `api_routes`, so a
+/// route cannot be served without being described.
+pub struct ApiRoute {
+    pub method: Method,
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub query: Option<SchemaFn>,
+    pub request: Payload,
+    pub response: Option<Payload>,
+    handler: MethodRouter<ServerState>,
+}
+
+impl ApiRoute {
+    fn new(
+        method: Method,
+        path: &'static str,
+        summary: &'static str,
+        handler: MethodRouter<ServerState>,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            summary,
+            query: None,
+            request: Payload::Empty,
+            response: None,
+            handler,
+        }
+    }
+
+    fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(T::json_schema);
+        self
+    }
+
+    fn body<T: JsonSchema>(mut self) -> Self {
+        self.request = Payload::Json(|gen| gen.subschema_for::<T>());
+        self
+    }
+
+    fn binary_body(mut self) -> Self {
+        self.request = Payload::Binary;
+        self
+    }
+
+    fn returns<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(Payload::Json(|gen| gen.subschema_for::<T>()));
+        self
+    }
+
+    fn returns_nothing(mut self) -> Self {
+        self.response = Some(Payload::Empty);
+        self
+    }
+
+    fn returns_binary(mut self) -> Self {
+        self.response = Some(Payload::Binary);
+        self
+    }
+
+    fn streams<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(Payload::EventStream(|gen| gen.subschema_for::<T>()));
+        self
+    }
+
+    fn upgrades<C: JsonSchema, S: JsonSchema>(mut self) -> Self {
+        self.response = Some(Payload::WebSocket {
+            client: |gen| gen.subschema_for::<C>(),
+            server: |gen| gen.subschema_for::<S>(),
+        });
+        self
+    }
+}
+
+/// Every route of the API
+pub fn api_routes() -> Vec<ApiRoute> {
+    use ApiRoute as R;
+
+    vec![
         // Health check
-        .route("/health", get(health::health_check))
+        R::new(
+            Method::GET,
+            "/health",
+            "Health check",
+            get(health::health_check),
+        )
+        .returns_nothing(),
+        // API description
+        R::new(
+            Method::GET,
+            "/v1/openapi.json",
+            "OpenAPI document of this API",
+            get(openapi::openapi_document),
+        )
+        .returns::<serde_json::Value>(),
         // Sessions
-        .route("/v1/sessions", post(sessions::create_session))
-        .route("/v1/sessions", get(sessions::list_sessions))
-        .route("/v1/sessions/:id", get(sessions::get_session))
-        .route("/v1/sessions/:id", delete(sessions::delete_session))
-        .route("/v1/sessions/:id/events", get(sessions::session_events))
-        .route(
+        R::new(
+            Method::POST,
+            "/v1/sessions",
+            "Create a session",
+            post(sessions::create_session),
+        )
+        .body::<CreateSessionRequest>()
+        .returns::<CreateSessionResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions",
+            "List sessions",
+            get(sessions::list_sessions),
+        )
+        .query::<ListSessionsQuery>()
+        .returns::<Vec<SessionResponse>>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions/:id",
+            "Get a session",
+            get(sessions::get_session),
+        )
+        .returns::<SessionResponse>(),
+        R::new(
+            Method::DELETE,
+            "/v1/sessions/:id",
+            "Delete a session",
+            delete(sessions::delete_session),
+        )
+        .returns::<SuccessResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions/:id/events",
+            "Stream session events, resuming after `Last-Event-ID`",
+            get(sessions::session_events),
+        )
+        .streams::<StreamingEvent>(),
+        R::new(
+            Method::GET,
             "/v1/sessions/:id/settings",
+            "Get session settings",
             get(sessions::get_session_settings),
         )
-        .route(
+        .returns::<TaskSettings>(),
+        R::new(
+            Method::POST,
             "/v1/sessions/:id/settings",
+            "Update session settings",
             post(sessions::update_session_settings),
         )
+        .body::<TaskSettings>()
+        .returns::<TaskSettings>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions/:id/todos",
+            "Get the session's todo list",
+            get(sessions::get_session_todos),
+        )
+        .returns::<TodoListResponse>(),
+        R::new(
+            Method::POST,
+            "/v1/sessions/:id/fork",
+            "Fork a session at a message into a new session",
+            post(sessions::fork_session),
+        )
+        .body::<ForkSessionRequest>()
+        .returns::<SessionResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions/:id/branches",
+            "List the branches of the session's message tree",
+            get(sessions::list_branches),
+        )
+        .returns::<Vec<MessageBranch>>(),
+        R::new(
+            Method::POST,
+            "/v1/sessions/:id/branches/select",
+            "Select a branch and return its messages",
+            post(sessions::select_branch),
+        )
+        .body::<SelectBranchRequest>()
+        .returns::<Vec<MessageResponse>>(),
         // Messages
-        .route("/v1/sessions/:id/messages", post(messages::create_message))
-        .route("/v1/sessions/:id/messages", get(messages::get_messages))
+        R::new(
+            Method::POST,
+            "/v1/sessions/:id/messages",
+            "Add a message to a session",
+            post(messages::create_message),
+        )
+        .body::<CreateMessageRequest>()
+        .returns::<CreateMessageResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions/:id/messages",
+            "List session messages",
+            get(messages::get_messages),
+        )
+        .query::<ListMessagesQuery>()
+        .returns::<Vec<MessageResponse>>(),
+        R::new(
+            Method::POST,
+            "/v1/sessions/:id/messages/:message_id/edit",
+            "Edit a user message and regenerate the reply on a new branch",
+            post(messages::edit_message),
+        )
+        .body::<EditMessageRequest>()
+        .returns::<CreateTaskResponse>(),
         // Tasks
-        .route("/v1/tasks", post(tasks::create_task))
-        .route("/v1/tasks", get(tasks::list_tasks))
-        .route("/v1/tasks/:id", get(tasks::get_task))
-        .route("/v1/tasks/:id", patch(tasks::patch_task))
+        R::new(
+            Method::POST,
+            "/v1/tasks",
+            "Start a task",
+            post(tasks::create_task),
+        )
+        .body::<CreateTaskRequest>()
+        .returns::<CreateTaskResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/tasks",
+            "List active tasks",
+            get(tasks::list_tasks),
+        )
+        .returns::<Vec<TaskResponse>>(),
+        R::new(
+            Method::GET,
+            "/v1/tasks/:id",
+            "Get a task",
+            get(tasks::get_task),
+        )
+        .returns::<TaskResponse>(),
+        R::new(
+            Method::PATCH,
+            "/v1/tasks/:id",
+            "Update or cancel a task",
+            patch(tasks::patch_task),
+        )
+        .body::<PatchTaskRequest>()
+        .returns::<TaskResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/tasks/:id/checkpoints",
+            "List the file checkpoints taken by a task",
+            get(tasks::list_checkpoints),
+        )
+        .returns::<Vec<FileCheckpoint>>(),
+        R::new(
+            Method::POST,
+            "/v1/tasks/:id/checkpoints/restore",
+            "Restore one file to its content before a checkpoint",
+            post(tasks::restore_checkpoint),
+        )
+        .body::<RestoreCheckpointRequest>()
+        .returns::<RestoredFile>(),
+        R::new(
+            Method::POST,
+            "/v1/tasks/:id/checkpoints/rollback",
+            "Undo the task's file writes from a checkpoint on",
+            post(tasks::rollback_task),
+        )
+        .body::<RollbackTaskRequest>()
+        .returns::<RollbackTaskResponse>(),
         // Actions
-        .route("/v1/sessions/:id/actions", post(actions::create_action))
+        R::new(
+            Method::POST,
+            "/v1/sessions/:id/actions",
+            "Approve, reject or answer a waiting task",
+            post(actions::create_action),
+        )
+        .body::<CreateActionRequest>()
+        .returns::<CreateActionResponse>(),
         // Files
-        .route("/v1/sessions/:id/files", post(files::upload_file))
-        .route("/v1/sessions/:id/files", get(files::list_files))
-        .route(
+        R::new(
+            Method::POST,
+            "/v1/sessions/:id/files",
+            "Upload a file",
+            post(files::upload_file),
+        )
+        .binary_body()
+        .returns::<UploadFileResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/sessions/:id/files",
+            "List session files",
+            get(files::list_files),
+        )
+        .returns::<Vec<FileResponse>>(),
+        R::new(
+            Method::GET,
             "/v1/sessions/:session_id/files/:file_id",
+            "Get file metadata",
             get(files::get_file),
         )
-        .route(
+        .returns::<FileResponse>(),
+        R::new(
+            Method::GET,
             "/v1/sessions/:session_id/files/:file_id/download",
+            "Download a file",
             get(files::download_file),
         )
+        .returns_binary(),
+        // API tokens
+        R::new(
+            Method::POST,
+            "/v1/tokens",
+            "Mint an API token",
+            post(tokens::create_token),
+        )
+        .body::<CreateTokenRequest>()
+        .returns::<CreateTokenResponse>(),
+        R::new(
+            Method::GET,
+            "/v1/tokens",
+            "List API tokens",
+            get(tokens::list_tokens),
+        )
+        .returns::<Vec<ApiToken>>(),
+        R::new(
+            Method::DELETE,
+            "/v1/tokens/:id",
+            "Revoke an API token",
+            delete(tokens::revoke_token),
+        )
+        .returns::<SuccessResponse>(),
+        // WebSocket
+        R::new(
+            Method::GET,
+            "/v1/ws",
+            "Subscribe to sessions and send actions over a WebSocket",
+            get(ws::ws_handler),
+        )
+        .upgrades::<WebSocketMessage, WebSocketResponse>(),
+    ]
+}
+
+pub fn router(state: ServerState) -> Router {
+    api_routes()
+        .into_iter()
+        .fold(Router::new(), |router, route| {
+            router.route(route.path, route.handler)
+        })
         .with_state(state)
 }
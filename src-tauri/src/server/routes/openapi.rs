@@ -0,0 +1,8 @@
+use axum::Json;
+
+use crate::server::openapi;
+
+/// Serve the OpenAPI document of the API
+pub async fn openapi_document() -> Json<serde_json::Value> {
+    Json(openapi::document().clone())
+}
@@ -1,15 +1,14 @@
 use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
-use std::time::Duration;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
 
 use crate::server::state::ServerState;
 use crate::server::types::*;
-use crate::storage::models::{Session, SessionStatus, TaskSettings};
+use crate::storage::models::{MessageBranch, Session, SessionStatus, TaskSettings};
+use crate::streaming::{SessionSubscription, StreamingEvent};
 
 /// Create a new session
 pub async fn create_session(
@@ -28,6 +27,8 @@ pub async fn create_session(
         updated_at: now,
         last_event_id: None,
         metadata: None,
+        head_message_id: None,
+        forked_from: None,
     };
 
     match state.storage().chat_history.create_session(&session).await {
@@ -103,7 +104,7 @@ pub async fn list_sessions(
 pub async fn delete_session(
     State(state): State<ServerState>,
     Path(session_id): Path<String>,
-) -> Result<Json<serde_json::Value>, Json<ErrorResponse>> {
+) -> Result<Json<SuccessResponse>, Json<ErrorResponse>> {
     // First deactivate in runtime if active
     let _ = state
         .runtime()
@@ -117,7 +118,7 @@ pub async fn delete_session(
         .delete_session(&session_id)
         .await
     {
-        Ok(_) => Ok(Json(serde_json::json!({ "success": true }))),
+        Ok(_) => Ok(Json(SuccessResponse { success: true })),
         Err(e) => Err(Json(ErrorResponse::new(
             "INTERNAL_ERROR",
             format!("Failed to delete session: {}", e),
@@ -165,34 +166,186 @@ pub async fn update_session_settings(
     }
 }
 
+/// Get the session's todo list
+pub async fn get_session_todos(
+    State(state): State<ServerState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<TodoListResponse>, Json<ErrorResponse>> {
+    match state.storage().chat_history.get_session(&session_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Err(Json(ErrorResponse::new(
+                "NOT_FOUND",
+                format!("Session '{}' not found", session_id),
+            )))
+        }
+        Err(e) => {
+            return Err(Json(ErrorResponse::new(
+                "INTERNAL_ERROR",
+                format!("Failed to get session: {}", e),
+            )))
+        }
+    }
+
+    match state.storage().chat_history.get_todos(&session_id).await {
+        Ok(todos) => Ok(Json(TodoListResponse { session_id, todos })),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to get todos: {}", e),
+        ))),
+    }
+}
+
+/// Fork a session at a message into a new session
+pub async fn fork_session(
+    State(state): State<ServerState>,
+    Path(session_id): Path<String>,
+    Json(payload): Json<ForkSessionRequest>,
+) -> Result<Json<SessionResponse>, Json<ErrorResponse>> {
+    ensure_session_exists(&state, &session_id).await?;
+    ensure_no_running_task(&state, &session_id).await?;
+
+    match state
+        .runtime()
+        .session_manager()
+        .fork_session(&session_id, payload.message_id.as_deref())
+        .await
+    {
+        Ok(session) => Ok(Json(session.into())),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "BAD_REQUEST",
+            format!("Failed to fork session: {}", e),
+        ))),
+    }
+}
+
+/// List the branches of the session's message tree
+pub async fn list_branches(
+    State(state): State<ServerState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<MessageBranch>>, Json<ErrorResponse>> {
+    ensure_session_exists(&state, &session_id).await?;
+
+    match state
+        .runtime()
+        .session_manager()
+        .list_branches(&session_id)
+        .await
+    {
+        Ok(branches) => Ok(Json(branches)),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to list branches: {}", e),
+        ))),
+    }
+}
+
+/// Select a branch; returns the messages of the now selected branch
+pub async fn select_branch(
+    State(state): State<ServerState>,
+    Path(session_id): Path<String>,
+    Json(payload): Json<SelectBranchRequest>,
+) -> Result<Json<Vec<MessageResponse>>, Json<ErrorResponse>> {
+    ensure_session_exists(&state, &session_id).await?;
+    ensure_no_running_task(&state, &session_id).await?;
+
+    match state
+        .runtime()
+        .session_manager()
+        .select_branch(&session_id, &payload.message_id)
+        .await
+    {
+        Ok(messages) => Ok(Json(messages.into_iter().map(Into::into).collect())),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "BAD_REQUEST",
+            format!("Failed to select branch: {}", e),
+        ))),
+    }
+}
+
+/// Fail with `NOT_FOUND` unless the session exists
+pub(crate) async fn ensure_session_exists(
+    state: &ServerState,
+    session_id: &str,
+) -> Result<(), Json<ErrorResponse>> {
+    match state.storage().chat_history.get_session(session_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(Json(ErrorResponse::new(
+            "NOT_FOUND",
+            format!("Session '{}' not found", session_id),
+        ))),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to get session: {}", e),
+        ))),
+    }
+}
+
+/// Fail with `CONFLICT` while a task of the session is running, since it
+/// keeps appending to the selected branch
+pub(crate) async fn ensure_no_running_task(
+    state: &ServerState,
+    session_id: &str,
+) -> Result<(), Json<ErrorResponse>> {
+    if state
+        .runtime()
+        .list_active_tasks()
+        .await
+        .iter()
+        .any(|task| task.session_id == session_id)
+    {
+        return Err(Json(ErrorResponse::new(
+            "CONFLICT",
+            format!("Session '{}' already has a running task", session_id),
+        )));
+    }
+    Ok(())
+}
+
 /// SSE endpoint for session events
+///
+/// Streams the session's live events. Clients reconnecting with a
+/// `Last-Event-ID` header first get every buffered event after that id.
+/// Unknown sessions get an HTTP 404 so `EventSource` clients stop retrying.
 pub async fn session_events(
     Path(session_id): Path<String>,
     State(state): State<ServerState>,
-) -> Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>> {
-    // Get last event ID for resume (from header)
-    // For now, start from current time
-
-    let interval = tokio::time::interval(Duration::from_secs(15));
-    let stream = IntervalStream::new(interval).map(move |_| {
-        // In a full implementation, this would:
-        // 1. Query the events table for new events since last_event_id
-        // 2. Convert events to SSE format
-        // 3. Return them to the client
-
-        // Placeholder: send heartbeat
-        let event = Event::default().event("status").data(
-            serde_json::json!({
-                "type": "status",
-                "data": {
-                    "message": "heartbeat",
-                    "sessionId": session_id
-                }
-            })
-            .to_string(),
-        );
-        Ok(event)
+    headers: HeaderMap,
+) -> Result<
+    Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<ErrorResponse>),
+> {
+    ensure_session_exists(&state, &session_id)
+        .await
+        .map_err(|error| {
+            let status = match error.error.as_str() {
+                "NOT_FOUND" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, error)
+        })?;
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let subscription =
+        SessionSubscription::new(state.streaming(), &session_id, last_event_id.as_deref()).await;
+
+    let stream = futures::stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription.next().await?;
+        Some((Ok(to_sse_event(&event)), subscription))
     });
 
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Convert a streaming event to an SSE event with the same id, name and data as
+/// `StreamingEvent::to_sse_string`
+fn to_sse_event(event: &StreamingEvent) -> Event {
+    Event::default()
+        .id(event.event_id())
+        .event(event.event_type().as_str())
+        .data(serde_json::to_string(event).unwrap_or_default())
 }
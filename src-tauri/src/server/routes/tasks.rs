@@ -5,7 +5,7 @@ use crate::core::types::TaskInput;
 use crate::server::state::ServerState;
 use crate::server::types::*;
 use crate::storage::models::WorkspaceInfo;
-use crate::storage::models::{SessionStatus, TaskSettings};
+use crate::storage::models::{FileCheckpoint, RestoredFile, SessionStatus, TaskSettings};
 
 /// Create a new task (starts agent execution)
 pub async fn create_task(
@@ -31,6 +31,8 @@ pub async fn create_task(
                     updated_at: chrono::Utc::now().timestamp(),
                     last_event_id: None,
                     metadata: None,
+                    head_message_id: None,
+                    forked_from: None,
                 })
                 .await
             {
@@ -46,12 +48,7 @@ pub async fn create_task(
     };
 
     // Build task input
-    let workspace = payload.workspace.map(|w| WorkspaceInfo {
-        root_path: w.root_path,
-        worktree_path: w.worktree_path,
-        repository_url: w.repository_url,
-        branch: w.branch,
-    });
+    let workspace = payload.workspace.map(WorkspaceInfo::from);
 
     let task_input = TaskInput {
         session_id: session_id.clone(),
@@ -173,3 +170,73 @@ pub async fn list_tasks(
 
     Ok(Json(responses))
 }
+
+/// List the file checkpoints taken by a task
+pub async fn list_checkpoints(
+    State(state): State<ServerState>,
+    Path(task_id): Path<String>,
+) -> Result<Json<Vec<FileCheckpoint>>, Json<ErrorResponse>> {
+    match state.runtime().list_checkpoints(&task_id).await {
+        Ok(checkpoints) => Ok(Json(checkpoints)),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to list checkpoints: {}", e),
+        ))),
+    }
+}
+
+/// Restore one file to its content before a checkpoint
+pub async fn restore_checkpoint(
+    State(state): State<ServerState>,
+    Path(task_id): Path<String>,
+    Json(payload): Json<RestoreCheckpointRequest>,
+) -> Result<Json<RestoredFile>, Json<ErrorResponse>> {
+    ensure_task_inactive(&state, &task_id).await?;
+
+    match state
+        .runtime()
+        .restore_checkpoint(&task_id, &payload.checkpoint_id)
+        .await
+    {
+        Ok(restored) => Ok(Json(restored)),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "BAD_REQUEST",
+            format!("Failed to restore checkpoint: {}", e),
+        ))),
+    }
+}
+
+/// Undo the task's file writes from a checkpoint on, or all of them
+pub async fn rollback_task(
+    State(state): State<ServerState>,
+    Path(task_id): Path<String>,
+    Json(payload): Json<RollbackTaskRequest>,
+) -> Result<Json<RollbackTaskResponse>, Json<ErrorResponse>> {
+    ensure_task_inactive(&state, &task_id).await?;
+
+    match state
+        .runtime()
+        .rollback_task(&task_id, payload.checkpoint_id.as_deref())
+        .await
+    {
+        Ok(restored) => Ok(Json(RollbackTaskResponse { task_id, restored })),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "BAD_REQUEST",
+            format!("Failed to roll back task: {}", e),
+        ))),
+    }
+}
+
+/// Fail with `CONFLICT` while the task can still write files
+async fn ensure_task_inactive(
+    state: &ServerState,
+    task_id: &str,
+) -> Result<(), Json<ErrorResponse>> {
+    if state.runtime().is_task_active(task_id).await {
+        return Err(Json(ErrorResponse::new(
+            "CONFLICT",
+            format!("Task '{}' is still running", task_id),
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use axum::extract::{Path, State};
+use axum::Json;
+
+use crate::security::tokens;
+use crate::server::state::ServerState;
+use crate::server::types::*;
+use crate::storage::models::ApiToken;
+
+/// Mint an API token; the secret is only returned here
+pub async fn create_token(
+    State(state): State<ServerState>,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<Json<CreateTokenResponse>, Json<ErrorResponse>> {
+    let expires_at = match payload.expires_in {
+        Some(seconds) if seconds <= 0 => {
+            return Err(Json(ErrorResponse::new(
+                "BAD_REQUEST",
+                "expiresIn must be a positive number of seconds",
+            )));
+        }
+        Some(seconds) => Some(chrono::Utc::now().timestamp().saturating_add(seconds)),
+        None => None,
+    };
+
+    match tokens::mint_token(
+        &state.storage().settings,
+        &payload.label,
+        payload.scopes,
+        expires_at,
+    )
+    .await
+    {
+        Ok((token, secret)) => Ok(Json(CreateTokenResponse { token, secret })),
+        Err(e) => Err(Json(ErrorResponse::new("BAD_REQUEST", e))),
+    }
+}
+
+/// List API tokens, including revoked and expired ones
+pub async fn list_tokens(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<ApiToken>>, Json<ErrorResponse>> {
+    match state.storage().settings.list_api_tokens().await {
+        Ok(tokens) => Ok(Json(tokens)),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to list tokens: {}", e),
+        ))),
+    }
+}
+
+/// Revoke an API token
+pub async fn revoke_token(
+    State(state): State<ServerState>,
+    Path(token_id): Path<String>,
+) -> Result<Json<SuccessResponse>, Json<ErrorResponse>> {
+    match state.storage().settings.revoke_api_token(&token_id).await {
+        Ok(true) => Ok(Json(SuccessResponse { success: true })),
+        Ok(false) => Err(Json(ErrorResponse::new(
+            "NOT_FOUND",
+            format!("Active token '{}' not found", token_id),
+        ))),
+        Err(e) => Err(Json(ErrorResponse::new(
+            "INTERNAL_ERROR",
+            format!("Failed to revoke token: {}", e),
+        ))),
+    }
+}
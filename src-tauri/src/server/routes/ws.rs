@@ -1,66 +1,382 @@
 //! WebSocket route for bidirectional communication
 //!
-//! Provides real-time updates and remote channel edits
+//! Clients subscribe to sessions to receive their streaming events (with the
+//! same resume semantics as the SSE endpoint) and send task actions over the
+//! same socket. A slow client gets token events dropped before anything else,
+//! and is disconnected once even structural events pile up.
 
-use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
 use axum::extract::State;
 use axum::response::IntoResponse;
+use axum::Extension;
+use futures::{SinkExt, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
 
+use crate::security::AuthContext;
+use crate::server::routes::actions;
 use crate::server::state::ServerState;
 use crate::server::types::{WebSocketMessage, WebSocketResponse};
+use crate::storage::models::{ApiTokenScope, SessionId};
+use crate::streaming::{SessionSubscription, StreamingEvent};
+
+/// Messages queued for a client before token events start being dropped
+const OUTBOUND_CAPACITY: usize = 256;
+/// Messages queued for a client before the connection is closed as too slow
+const OUTBOUND_HARD_LIMIT: usize = 4 * OUTBOUND_CAPACITY;
+
+/// Close code sent to clients that fall behind the hard limit (policy violation)
+const CLOSE_TOO_SLOW: u16 = 1008;
 
 /// WebSocket handler
+///
+/// The upgrade request is authenticated by `api_key_middleware` like any other
+/// route, with the key in the `x-api-key` header or an `api_key` query parameter.
+/// Actions additionally need the `sessions:write` scope.
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<ServerState>,
+    auth: Option<Extension<AuthContext>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    let can_write = auth.is_some_and(|Extension(auth)| auth.allows(ApiTokenScope::SessionsWrite));
+    ws.on_upgrade(move |socket| handle_socket(socket, state, can_write))
 }
 
 /// Handle WebSocket connection
-async fn handle_socket(mut socket: WebSocket, _state: ServerState) {
-    // In a full implementation, this would:
-    // 1. Authenticate the connection
-    // 2. Maintain a map of session subscriptions
-    // 3. Forward runtime events to subscribed clients
-    // 4. Handle incoming messages (subscribe, unsubscribe, actions)
-
-    // Placeholder: echo back ping messages
-    while let Some(Ok(msg)) = socket.recv().await {
-        match msg {
-            Message::Text(text) => {
-                // Parse and handle message
-                match serde_json::from_str::<WebSocketMessage>(&text) {
-                    Ok(WebSocketMessage::Ping) => {
-                        let response = WebSocketResponse::Pong;
-                        let _ = socket
-                            .send(Message::Text(serde_json::to_string(&response).unwrap()))
-                            .await;
-                    }
-                    Ok(WebSocketMessage::Subscribe { session_id }) => {
-                        let response = WebSocketResponse::Subscribed { session_id };
-                        let _ = socket
-                            .send(Message::Text(serde_json::to_string(&response).unwrap()))
-                            .await;
-                    }
-                    Ok(WebSocketMessage::Unsubscribe { session_id }) => {
-                        let response = WebSocketResponse::Unsubscribed { session_id };
-                        let _ = socket
-                            .send(Message::Text(serde_json::to_string(&response).unwrap()))
-                            .await;
-                    }
-                    Err(_) => {
-                        let response = WebSocketResponse::Error {
-                            message: "Invalid message format".to_string(),
-                        };
-                        let _ = socket
-                            .send(Message::Text(serde_json::to_string(&response).unwrap()))
-                            .await;
+async fn handle_socket(socket: WebSocket, state: ServerState, can_write: bool) {
+    let (mut sender, mut receiver) = socket.split();
+    let outbound = Arc::new(OutboundQueue::new(OUTBOUND_CAPACITY, OUTBOUND_HARD_LIMIT));
+    let mut subscriptions: HashMap<SessionId, JoinHandle<()>> = HashMap::new();
+
+    let mut writer = {
+        let outbound = outbound.clone();
+        tokio::spawn(async move {
+            while let Some(response) = outbound.pop().await {
+                let text = match serde_json::to_string(&response) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        log::warn!("Failed to serialize WebSocket message: {}", e);
+                        continue;
                     }
+                };
+                if sender.send(Message::Text(text)).await.is_err() {
+                    break;
                 }
             }
+
+            // Nothing more can be delivered, so stop queueing
+            outbound.close();
+            if outbound.overflowed() {
+                log::warn!("Closing WebSocket of a client that stopped reading");
+                let _ = sender
+                    .send(Message::Close(Some(CloseFrame {
+                        code: CLOSE_TOO_SLOW,
+                        reason: "Client is not reading messages fast enough".into(),
+                    })))
+                    .await;
+            }
+        })
+    };
+
+    let mut writer_done = false;
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => match msg {
+                Some(Ok(msg)) => msg,
+                _ => break,
+            },
+            _ = &mut writer => {
+                writer_done = true;
+                break;
+            }
+        };
+
+        match msg {
+            Message::Text(text) => match serde_json::from_str::<WebSocketMessage>(&text) {
+                Ok(message) => {
+                    handle_message(&state, can_write, &outbound, &mut subscriptions, message).await;
+                }
+                Err(_) => outbound.push(WebSocketResponse::Error {
+                    message: "Invalid message format".to_string(),
+                }),
+            },
             Message::Close(_) => break,
             _ => {}
         }
     }
+
+    for (_, subscription) in subscriptions {
+        subscription.abort();
+    }
+    outbound.close();
+    if !writer_done {
+        let _ = writer.await;
+    }
+}
+
+/// Handle one client message
+async fn handle_message(
+    state: &ServerState,
+    can_write: bool,
+    outbound: &Arc<OutboundQueue>,
+    subscriptions: &mut HashMap<SessionId, JoinHandle<()>>,
+    message: WebSocketMessage,
+) {
+    match message {
+        WebSocketMessage::Ping => outbound.push(WebSocketResponse::Pong),
+        WebSocketMessage::Subscribe {
+            session_id,
+            last_event_id,
+        } => {
+            if let Some(previous) = subscriptions.remove(&session_id) {
+                previous.abort();
+            }
+            outbound.push(WebSocketResponse::Subscribed {
+                session_id: session_id.clone(),
+            });
+
+            let streaming = state.streaming();
+            let outbound = outbound.clone();
+            let forwarded_session = session_id.clone();
+            let forward = tokio::spawn(async move {
+                let mut subscription = SessionSubscription::new(
+                    streaming,
+                    &forwarded_session,
+                    last_event_id.as_deref(),
+                )
+                .await;
+                while let Some(event) = subscription.next().await {
+                    if !outbound.push(WebSocketResponse::Event { event }) {
+                        break;
+                    }
+                }
+            });
+            subscriptions.insert(session_id, forward);
+        }
+        WebSocketMessage::Unsubscribe { session_id } => {
+            if let Some(subscription) = subscriptions.remove(&session_id) {
+                subscription.abort();
+            }
+            outbound.push(WebSocketResponse::Unsubscribed { session_id });
+        }
+        WebSocketMessage::Action { session_id, .. } if !can_write => {
+            outbound.push(WebSocketResponse::ActionResult {
+                session_id,
+                success: false,
+                message: format!(
+                    "API token lacks the '{}' scope",
+                    ApiTokenScope::SessionsWrite.as_str()
+                ),
+            })
+        }
+        WebSocketMessage::Action {
+            session_id,
+            request,
+        } => {
+            let response = match actions::dispatch_action(state, &session_id, request).await {
+                Ok(response) => WebSocketResponse::ActionResult {
+                    session_id,
+                    success: response.success,
+                    message: response.message,
+                },
+                Err(error) => WebSocketResponse::ActionResult {
+                    session_id,
+                    success: false,
+                    message: error.message,
+                },
+            };
+            outbound.push(response);
+        }
+    }
+}
+
+/// Bounded outgoing queue of a connection
+///
+/// When full, token events are dropped (the incoming one, or the oldest queued
+/// one) so that structural events such as tool calls, final messages and
+/// replies are still delivered. Past `hard_limit` the queue is discarded and
+/// closed, which disconnects the client.
+struct OutboundQueue {
+    inner: Mutex<OutboundState>,
+    notify: Notify,
+    capacity: usize,
+    hard_limit: usize,
+}
+
+struct OutboundState {
+    queue: VecDeque<WebSocketResponse>,
+    closed: bool,
+    overflowed: bool,
+    dropped_tokens: u64,
+}
+
+impl OutboundQueue {
+    fn new(capacity: usize, hard_limit: usize) -> Self {
+        Self {
+            inner: Mutex::new(OutboundState {
+                queue: VecDeque::new(),
+                closed: false,
+                overflowed: false,
+                dropped_tokens: 0,
+            }),
+            notify: Notify::new(),
+            capacity,
+            hard_limit,
+        }
+    }
+
+    /// Queue a message for the client. Returns `false` once the queue is
+    /// closed, after which nothing more will be sent.
+    fn push(&self, response: WebSocketResponse) -> bool {
+        let accepted = {
+            let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+            if inner.closed {
+                return false;
+            }
+
+            if inner.queue.len() >= self.capacity {
+                if is_token(&response) {
+                    inner.dropped_tokens += 1;
+                    return true;
+                }
+                if let Some(index) = inner.queue.iter().position(is_token) {
+                    inner.queue.remove(index);
+                    inner.dropped_tokens += 1;
+                }
+            }
+
+            if inner.queue.len() >= self.hard_limit {
+                inner.queue.clear();
+                inner.closed = true;
+                inner.overflowed = true;
+                false
+            } else {
+                inner.queue.push_back(response);
+                true
+            }
+        };
+        self.notify.notify_one();
+        accepted
+    }
+
+    /// Whether the queue was closed because the client fell too far behind
+    fn overflowed(&self) -> bool {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .overflowed
+    }
+
+    /// Next message to send, or `None` once the queue is closed and drained
+    async fn pop(&self) -> Option<WebSocketResponse> {
+        loop {
+            {
+                let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(response) = inner.queue.pop_front() {
+                    return Some(response);
+                }
+                if inner.closed {
+                    return None;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    fn close(&self) {
+        let dropped = {
+            let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+            inner.closed = true;
+            inner.dropped_tokens
+        };
+        if dropped > 0 {
+            log::debug!("WebSocket client missed {} token events", dropped);
+        }
+        self.notify.notify_one();
+    }
+}
+
+fn is_token(response: &WebSocketResponse) -> bool {
+    matches!(
+        response,
+        WebSocketResponse::Event {
+            event: StreamingEvent::Token { .. }
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::events::{StatusEventData, TokenEventData};
+
+    fn token(id: &str) -> WebSocketResponse {
+        WebSocketResponse::Event {
+            event: StreamingEvent::Token {
+                event_id: id.to_string(),
+                session_id: "sess-1".to_string(),
+                data: TokenEventData {
+                    token: id.to_string(),
+                },
+            },
+        }
+    }
+
+    fn status(id: &str) -> WebSocketResponse {
+        WebSocketResponse::Event {
+            event: StreamingEvent::Status {
+                event_id: id.to_string(),
+                session_id: "sess-1".to_string(),
+                data: StatusEventData {
+                    message: id.to_string(),
+                },
+            },
+        }
+    }
+
+    fn event_id(response: WebSocketResponse) -> String {
+        match response {
+            WebSocketResponse::Event { event } => event.event_id().clone(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbound_queue_drops_tokens_before_structural_events() {
+        let queue = OutboundQueue::new(3, 5);
+        queue.push(token("t1"));
+        queue.push(status("s1"));
+        queue.push(token("t2"));
+
+        // Full: an incoming token is dropped, a structural event evicts the oldest token
+        queue.push(token("t3"));
+        queue.push(status("s2"));
+        queue.push(status("s3"));
+        // No tokens left to evict, so structural events go beyond capacity
+        queue.push(status("s4"));
+        queue.close();
+
+        let mut ids = Vec::new();
+        while let Some(response) = queue.pop().await {
+            ids.push(event_id(response));
+        }
+        assert_eq!(ids, vec!["s1", "s2", "s3", "s4"]);
+        assert!(!queue.push(status("s5")));
+        assert!(!queue.overflowed());
+    }
+
+    #[tokio::test]
+    async fn test_outbound_queue_closes_at_hard_limit() {
+        let queue = OutboundQueue::new(1, 2);
+        assert!(queue.push(status("s1")));
+        assert!(queue.push(status("s2")));
+
+        // A third structural event can't be queued, so the client is dropped
+        assert!(!queue.push(status("s3")));
+        assert!(queue.overflowed());
+        assert!(queue.pop().await.is_none());
+        assert!(!queue.push(token("t1")));
+    }
 }
@@ -3,21 +3,26 @@
 //! Bridges runtime events to the StreamingManager for SSE and WebSocket delivery.
 //! Implements Phase 4: Wire runtime events to streaming manager.
 
-use crate::core::types::{EventReceiver, RuntimeEvent};
+use crate::core::types::{EventReceiver, RuntimeEvent, RuntimeTaskId};
 use crate::server::state::ServerState;
-use crate::streaming::events::StreamingEvent;
-use crate::streaming::StreamingManager;
-use std::sync::Arc;
+use crate::storage::models::SessionId;
+use crate::streaming::events::{new_event_id, StreamingEvent};
+use std::collections::HashMap;
 use tokio::sync::RwLock;
 
 /// Bridge that forwards runtime events to the streaming manager
 pub struct StreamingBridge {
     state: ServerState,
+    /// Session of each running task, for events that only carry a task id
+    task_sessions: RwLock<HashMap<RuntimeTaskId, SessionId>>,
 }
 
 impl StreamingBridge {
     pub fn new(state: ServerState) -> Self {
-        Self { state }
+        Self {
+            state,
+            task_sessions: RwLock::new(HashMap::new()),
+        }
     }
 
     /// Start the bridge - spawns a task that forwards events
@@ -31,42 +36,55 @@ impl StreamingBridge {
 
     /// Handle a single runtime event
     async fn handle_event(&self, event: RuntimeEvent) {
-        let streaming = self.state.streaming();
-        let mut manager = streaming.write().await;
+        if let Some(event) = self.to_streaming_event(event).await {
+            let streaming = self.state.streaming();
+            let manager = streaming.read().await;
+            // Buffer for resume and deliver to live subscribers
+            if let Err(e) = manager.publish(event).await {
+                log::warn!("Failed to buffer streaming event: {}", e);
+            }
+        }
+    }
+
+    /// Convert a runtime event to the streaming event of the session it belongs to
+    async fn to_streaming_event(&self, event: RuntimeEvent) -> Option<StreamingEvent> {
+        // Sub-agent events belong to the sub-agent's own session
+        let mut event = event;
+        while let RuntimeEvent::SubAgent { event: inner, .. } = event {
+            event = *inner;
+        }
 
-        let streaming_event = match event {
+        match event {
             RuntimeEvent::Token { session_id, token } => Some(StreamingEvent::Token {
-                event_id: format!("evt_{}", uuid::Uuid::new_v4()),
+                event_id: new_event_id(),
                 session_id,
                 data: crate::streaming::events::TokenEventData { token },
             }),
-            RuntimeEvent::ToolCallRequested { task_id, request } => {
-                // Extract session_id from task_id (simplified)
-                let session_id = task_id.clone();
-                Some(StreamingEvent::ToolCall {
-                    event_id: format!("evt_{}", uuid::Uuid::new_v4()),
-                    session_id,
-                    data: crate::streaming::events::ToolCallEventData {
-                        tool_call_id: request.tool_call_id,
-                        name: request.name,
-                        input: request.input,
-                        provider_metadata: request.provider_metadata,
-                    },
-                })
-            }
-            RuntimeEvent::ToolCallCompleted { task_id, result } => {
-                // Extract session_id from task_id (simplified)
-                let session_id = task_id.clone();
-                Some(StreamingEvent::ToolResult {
-                    event_id: format!("evt_{}", uuid::Uuid::new_v4()),
-                    session_id,
-                    data: crate::streaming::events::ToolResultEventData {
-                        tool_call_id: result.tool_call_id,
-                        name: result.name,
-                        output: result.output,
-                    },
-                })
-            }
+            RuntimeEvent::ToolCallRequested {
+                session_id,
+                request,
+                ..
+            } => Some(StreamingEvent::ToolCall {
+                event_id: new_event_id(),
+                session_id,
+                data: crate::streaming::events::ToolCallEventData {
+                    tool_call_id: request.tool_call_id,
+                    name: request.name,
+                    input: request.input,
+                    provider_metadata: request.provider_metadata,
+                },
+            }),
+            RuntimeEvent::ToolCallCompleted {
+                session_id, result, ..
+            } => Some(StreamingEvent::ToolResult {
+                event_id: new_event_id(),
+                session_id,
+                data: crate::streaming::events::ToolResultEventData {
+                    tool_call_id: result.tool_call_id,
+                    name: result.name,
+                    output: result.output,
+                },
+            }),
             RuntimeEvent::MessageCreated {
                 session_id,
                 message,
@@ -77,7 +95,7 @@ impl StreamingBridge {
                     _ => serde_json::to_string(&message.content).unwrap_or_default(),
                 };
                 Some(StreamingEvent::MessageFinal {
-                    event_id: format!("evt_{}", uuid::Uuid::new_v4()),
+                    event_id: new_event_id(),
                     session_id,
                     data: crate::streaming::events::MessageFinalEventData {
                         message_id: message.id,
@@ -87,37 +105,62 @@ impl StreamingBridge {
             }
             RuntimeEvent::TaskStateChanged {
                 task_id,
+                session_id,
                 state,
                 previous_state,
             } => {
-                // Emit status event
-                let session_id = task_id.clone();
+                self.task_sessions
+                    .write()
+                    .await
+                    .insert(task_id, session_id.clone());
                 Some(StreamingEvent::Status {
-                    event_id: format!("evt_{}", uuid::Uuid::new_v4()),
+                    event_id: new_event_id(),
                     session_id,
                     data: crate::streaming::events::StatusEventData {
                         message: format!("Task state: {:?} -> {:?}", previous_state, state),
                     },
                 })
             }
+            RuntimeEvent::TaskCompleted { task_id, .. } => {
+                self.task_sessions.write().await.remove(&task_id);
+                None
+            }
             RuntimeEvent::Error {
                 task_id,
                 session_id,
                 message,
             } => {
-                let sid = session_id.or(task_id).unwrap_or_default();
+                let session_id = match (session_id, task_id) {
+                    (Some(session_id), _) => Some(session_id),
+                    (None, Some(task_id)) => self.task_sessions.read().await.get(&task_id).cloned(),
+                    (None, None) => None,
+                };
                 Some(StreamingEvent::Error {
-                    event_id: format!("evt_{}", uuid::Uuid::new_v4()),
-                    session_id: Some(sid),
+                    event_id: new_event_id(),
+                    session_id,
                     data: crate::streaming::events::ErrorEventData { message },
                 })
             }
-            _ => None,
-        };
-
-        if let Some(event) = streaming_event {
-            // Add to buffer (ignore errors for now)
-            let _ = manager.buffer.add_event(event).await;
+            RuntimeEvent::TodosUpdated { session_id, todos } => Some(StreamingEvent::TodoUpdated {
+                event_id: new_event_id(),
+                session_id,
+                data: crate::streaming::events::TodoEventData { todos },
+            }),
+            RuntimeEvent::QuestionsAsked {
+                task_id,
+                session_id,
+                tool_call_id,
+                questions,
+            } => Some(StreamingEvent::QuestionAsked {
+                event_id: new_event_id(),
+                session_id,
+                data: crate::streaming::events::QuestionEventData {
+                    task_id,
+                    tool_call_id,
+                    questions,
+                },
+            }),
+            RuntimeEvent::SubAgent { .. } => None,
         }
     }
 }
@@ -127,3 +170,122 @@ pub fn init_streaming_bridge(state: ServerState, event_receiver: EventReceiver)
     let bridge = StreamingBridge::new(state);
     bridge.start(event_receiver);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::{RuntimeTaskState, ToolRequest, ToolResult};
+    use crate::server::config::ServerConfig;
+    use crate::server::state::ServerStateFactory;
+    use crate::storage::models::{EventType, Message, MessageContent, MessageRole};
+
+    #[tokio::test]
+    async fn test_task_events_are_retrievable_by_session() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = ServerConfig::new(temp_dir.path().to_path_buf(), temp_dir.path().join("data"));
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let state = ServerStateFactory::create(config, tx).await.unwrap();
+        let bridge = StreamingBridge::new(state.clone());
+
+        let task_id = "task-1".to_string();
+        let session_id = "sess-1".to_string();
+        let events = vec![
+            RuntimeEvent::TaskStateChanged {
+                task_id: task_id.clone(),
+                session_id: session_id.clone(),
+                state: RuntimeTaskState::Running,
+                previous_state: RuntimeTaskState::Pending,
+            },
+            RuntimeEvent::ToolCallRequested {
+                task_id: task_id.clone(),
+                session_id: session_id.clone(),
+                request: ToolRequest {
+                    tool_call_id: "call-1".to_string(),
+                    name: "readFile".to_string(),
+                    input: serde_json::json!({"file_path": "README.md"}),
+                    provider_metadata: None,
+                },
+            },
+            RuntimeEvent::SubAgent {
+                parent_task_id: task_id.clone(),
+                agent: "explore".to_string(),
+                event: Box::new(RuntimeEvent::Token {
+                    session_id: "sess-child".to_string(),
+                    token: "child".to_string(),
+                }),
+            },
+            RuntimeEvent::ToolCallCompleted {
+                task_id: task_id.clone(),
+                session_id: session_id.clone(),
+                result: ToolResult {
+                    tool_call_id: "call-1".to_string(),
+                    name: Some("readFile".to_string()),
+                    success: true,
+                    output: serde_json::json!("# Readme"),
+                    error: None,
+                },
+            },
+            RuntimeEvent::Error {
+                task_id: Some(task_id.clone()),
+                session_id: None,
+                message: "boom".to_string(),
+            },
+            RuntimeEvent::MessageCreated {
+                session_id: session_id.clone(),
+                message: Message {
+                    id: "msg-1".to_string(),
+                    session_id: session_id.clone(),
+                    role: MessageRole::Assistant,
+                    content: MessageContent::Text {
+                        text: "Done".to_string(),
+                    },
+                    created_at: 0,
+                    tool_call_id: None,
+                    parent_id: None,
+                },
+            },
+            RuntimeEvent::TaskCompleted {
+                task_id: task_id.clone(),
+                session_id: session_id.clone(),
+            },
+        ];
+        for event in events {
+            bridge.handle_event(event).await;
+        }
+
+        let streaming = state.streaming();
+        let manager = streaming.read().await;
+        let types: Vec<EventType> = manager
+            .buffer
+            .get_events(&session_id, None, None)
+            .await
+            .unwrap()
+            .iter()
+            .map(|event| event.event_type())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                EventType::Status,
+                EventType::ToolCall,
+                EventType::ToolResult,
+                EventType::Error,
+                EventType::MessageFinal,
+            ]
+        );
+
+        // Nothing leaks into a stream named after the task
+        assert!(manager
+            .buffer
+            .get_events(&task_id, None, None)
+            .await
+            .unwrap()
+            .is_empty());
+        let child = manager
+            .buffer
+            .get_events("sess-child", None, None)
+            .await
+            .unwrap();
+        assert_eq!(child.len(), 1);
+    }
+}
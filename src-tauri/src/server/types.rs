@@ -4,12 +4,13 @@
 
 use crate::core::types::RuntimeTaskState;
 use crate::storage::models::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 // ============== Session Types ==============
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateSessionRequest {
     pub project_id: Option<String>,
@@ -17,14 +18,14 @@ pub struct CreateSessionRequest {
     pub settings: Option<TaskSettings>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateSessionResponse {
     pub session_id: SessionId,
     pub created_at: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionResponse {
     pub id: SessionId,
@@ -35,6 +36,8 @@ pub struct SessionResponse {
     pub updated_at: i64,
     pub last_event_id: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub head_message_id: Option<MessageId>,
+    pub forked_from: Option<SessionFork>,
 }
 
 impl From<Session> for SessionResponse {
@@ -48,11 +51,13 @@ impl From<Session> for SessionResponse {
             updated_at: session.updated_at,
             last_event_id: session.last_event_id,
             metadata: session.metadata,
+            head_message_id: session.head_message_id,
+            forked_from: session.forked_from,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ListSessionsQuery {
     pub project_id: Option<String>,
@@ -61,23 +66,64 @@ pub struct ListSessionsQuery {
     pub offset: Option<usize>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkSessionRequest {
+    /// Last message to copy; defaults to the head of the selected branch
+    pub message_id: Option<MessageId>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectBranchRequest {
+    /// Any message of the branch; forks below it follow the newest reply
+    pub message_id: MessageId,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreCheckpointRequest {
+    pub checkpoint_id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackTaskRequest {
+    /// Undo writes from this checkpoint on; defaults to the task's first
+    pub checkpoint_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackTaskResponse {
+    pub task_id: String,
+    pub restored: Vec<RestoredFile>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoListResponse {
+    pub session_id: SessionId,
+    pub todos: Vec<TodoItem>,
+}
+
 // ============== Message Types ==============
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateMessageRequest {
     pub content: String,
     pub role: Option<String>, // Defaults to "user"
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateMessageResponse {
     pub message_id: MessageId,
     pub created_at: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageResponse {
     pub id: MessageId,
@@ -103,7 +149,18 @@ impl From<Message> for MessageResponse {
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Replace a user message with new content and regenerate the reply on a
+/// new branch
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EditMessageRequest {
+    pub content: String,
+    pub agent_id: Option<AgentId>,
+    pub settings: Option<TaskSettings>,
+    pub workspace: Option<WorkspaceInfoRequest>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ListMessagesQuery {
     pub limit: Option<usize>,
@@ -112,7 +169,7 @@ pub struct ListMessagesQuery {
 
 // ============== Task Types ==============
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateTaskRequest {
     pub session_id: Option<SessionId>, // If not provided, creates new session
@@ -123,7 +180,7 @@ pub struct CreateTaskRequest {
     pub workspace: Option<WorkspaceInfoRequest>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkspaceInfoRequest {
     pub root_path: String,
@@ -132,7 +189,18 @@ pub struct WorkspaceInfoRequest {
     pub branch: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+impl From<WorkspaceInfoRequest> for WorkspaceInfo {
+    fn from(workspace: WorkspaceInfoRequest) -> Self {
+        Self {
+            root_path: workspace.root_path,
+            worktree_path: workspace.worktree_path,
+            repository_url: workspace.repository_url,
+            branch: workspace.branch,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateTaskResponse {
     pub task_id: String,
@@ -141,7 +209,7 @@ pub struct CreateTaskResponse {
     pub created_at: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskResponse {
     pub id: String,
@@ -154,7 +222,7 @@ pub struct TaskResponse {
     pub error_message: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PatchTaskRequest {
     pub settings: Option<TaskSettings>,
@@ -163,16 +231,20 @@ pub struct PatchTaskRequest {
 
 // ============== Action Types ==============
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateActionRequest {
-    pub action_type: String, // "approve", "reject", "tool_result", "cancel"
+    pub action_type: String, // "approve", "reject", "tool_result", "answer", "cancel"
     pub tool_call_id: Option<String>,
     pub reason: Option<String>,
     pub result: Option<serde_json::Value>,
+    /// Structured answers for an "answer" action
+    pub answers: Option<Vec<crate::core::types::QuestionAnswer>>,
+    /// Free-text reply for an "answer" action, parsed against the pending questions
+    pub text: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateActionResponse {
     pub success: bool,
@@ -181,7 +253,7 @@ pub struct CreateActionResponse {
 
 // ============== File Types ==============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UploadFileResponse {
     pub attachment_id: String,
@@ -191,7 +263,7 @@ pub struct UploadFileResponse {
     pub created_at: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FileResponse {
     pub id: String,
@@ -217,6 +289,25 @@ impl From<Attachment> for FileResponse {
     }
 }
 
+// ============== Token Types ==============
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTokenRequest {
+    pub label: String,
+    pub scopes: Vec<ApiTokenScope>,
+    /// Lifetime in seconds; tokens without one never expire
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTokenResponse {
+    pub token: ApiToken,
+    /// The token secret; it is not stored and cannot be retrieved again
+    pub secret: String,
+}
+
 // ============== Event Types ==============
 
 #[derive(Debug, Serialize)]
@@ -287,18 +378,29 @@ pub struct ErrorEventData {
 
 // ============== WebSocket Types ==============
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(tag = "action", rename_all = "camelCase")]
 pub enum WebSocketMessage {
+    /// Follow a session, replaying buffered events after `last_event_id`
     #[serde(rename = "subscribe")]
-    Subscribe { session_id: SessionId },
+    Subscribe {
+        session_id: SessionId,
+        #[serde(default)]
+        last_event_id: Option<EventId>,
+    },
     #[serde(rename = "unsubscribe")]
     Unsubscribe { session_id: SessionId },
+    /// Same request body as `POST /v1/sessions/:id/actions`
+    #[serde(rename = "action")]
+    Action {
+        session_id: SessionId,
+        request: CreateActionRequest,
+    },
     #[serde(rename = "ping")]
     Ping,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum WebSocketResponse {
     #[serde(rename = "subscribed")]
@@ -306,16 +408,32 @@ pub enum WebSocketResponse {
     #[serde(rename = "unsubscribed")]
     Unsubscribed { session_id: SessionId },
     #[serde(rename = "event")]
-    Event { event: SseEvent },
+    Event {
+        event: crate::streaming::StreamingEvent,
+    },
+    #[serde(rename = "actionResult")]
+    ActionResult {
+        session_id: SessionId,
+        success: bool,
+        message: String,
+    },
     #[serde(rename = "pong")]
     Pong,
     #[serde(rename = "error")]
     Error { message: String },
 }
 
+// ============== Common Responses ==============
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SuccessResponse {
+    pub success: bool,
+}
+
 // ============== Error Response ==============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
     pub error: String,
@@ -3,6 +3,7 @@
 
 use crate::database::Database;
 use crate::storage::models::*;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 /// Repository for chat history operations
@@ -21,8 +22,9 @@ impl ChatHistoryRepository {
     /// Create a new session
     pub async fn create_session(&self, session: &Session) -> Result<(), String> {
         let sql = r#"
-            INSERT INTO sessions (id, project_id, title, status, created_at, updated_at, last_event_id, metadata)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO sessions (id, project_id, title, status, created_at, updated_at, last_event_id, metadata,
+                                  head_message_id, forked_from_session_id, forked_from_message_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#;
 
         self.db
@@ -37,6 +39,9 @@ impl ChatHistoryRepository {
                     serde_json::json!(session.updated_at),
                     serde_json::json!(session.last_event_id),
                     serde_json::json!(session.metadata.as_ref().map(|m| m.to_string())),
+                    serde_json::json!(session.head_message_id),
+                    serde_json::json!(session.forked_from.as_ref().map(|f| &f.session_id)),
+                    serde_json::json!(session.forked_from.as_ref().map(|f| &f.message_id)),
                 ],
             )
             .await?;
@@ -159,36 +164,45 @@ impl ChatHistoryRepository {
 
     // ============== Message Operations ==============
 
-    /// Create a new message
+    /// Create a new message and make it the head of its session
+    ///
+    /// A message without a `parent_id` continues the selected branch. The
+    /// parent is resolved and the head moved in one transaction, so
+    /// concurrent writers cannot attach two messages to the same head.
     pub async fn create_message(&self, message: &Message) -> Result<(), String> {
-        let sql = r#"
+        let insert = r#"
             INSERT INTO messages (id, session_id, role, content, created_at, tool_call_id, parent_id)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, COALESCE(?, (SELECT head_message_id FROM sessions WHERE id = ?)))
         "#;
-
-        self.db
-            .execute(
-                sql,
-                vec![
-                    serde_json::json!(message.id),
-                    serde_json::json!(message.session_id),
-                    serde_json::json!(message.role.as_str()),
-                    serde_json::json!(serde_json::to_string(&message.content).unwrap()),
-                    serde_json::json!(message.created_at),
-                    serde_json::json!(message.tool_call_id),
-                    serde_json::json!(message.parent_id),
-                ],
-            )
-            .await?;
-
-        // Update session's updated_at timestamp
         let updated_at = chrono::Utc::now().timestamp();
+
         self.db
-            .execute(
-                "UPDATE sessions SET updated_at = ? WHERE id = ?",
+            .execute_in_transaction(
+                "",
                 vec![
-                    serde_json::json!(updated_at),
-                    serde_json::json!(&message.session_id),
+                    (
+                        insert.to_string(),
+                        vec![
+                            serde_json::json!(message.id),
+                            serde_json::json!(message.session_id),
+                            serde_json::json!(message.role.as_str()),
+                            serde_json::json!(serde_json::to_string(&message.content).unwrap()),
+                            serde_json::json!(message.created_at),
+                            serde_json::json!(message.tool_call_id),
+                            serde_json::json!(message.parent_id),
+                            serde_json::json!(message.session_id),
+                        ],
+                    ),
+                    // Move the session's head and updated_at timestamp
+                    (
+                        "UPDATE sessions SET updated_at = ?, head_message_id = ? WHERE id = ?"
+                            .to_string(),
+                        vec![
+                            serde_json::json!(updated_at),
+                            serde_json::json!(&message.id),
+                            serde_json::json!(&message.session_id),
+                        ],
+                    ),
                 ],
             )
             .await?;
@@ -196,51 +210,171 @@ impl ChatHistoryRepository {
         Ok(())
     }
 
-    /// Get messages for a session
+    /// Get the messages of the selected branch of a session, oldest first
     pub async fn get_messages(
         &self,
         session_id: &str,
         limit: Option<usize>,
         before_id: Option<&str>,
     ) -> Result<Vec<Message>, String> {
-        let mut sql = "SELECT * FROM messages WHERE session_id = ?".to_string();
-        let mut params: Vec<serde_json::Value> = vec![serde_json::json!(session_id)];
+        let mut messages = match self.get_head_message_id(session_id).await? {
+            Some(head) => self.get_branch(session_id, &head).await?,
+            None => Vec::new(),
+        };
 
         if let Some(before) = before_id {
-            // Get created_at of the before message
-            let before_result = self
-                .db
-                .query(
-                    "SELECT created_at FROM messages WHERE id = ?",
-                    vec![serde_json::json!(before)],
-                )
-                .await?;
-
-            if let Some(row) = before_result.rows.first() {
-                if let Some(created_at) = row.get("created_at").and_then(|v| v.as_i64()) {
-                    sql.push_str(" AND created_at < ?");
-                    params.push(serde_json::json!(created_at));
-                }
+            if let Some(position) = messages.iter().position(|m| m.id == before) {
+                messages.truncate(position);
             }
         }
 
-        sql.push_str(" ORDER BY created_at DESC");
-
         if let Some(limit) = limit {
-            sql.push_str(&format!(" LIMIT {}", limit));
+            let excess = messages.len().saturating_sub(limit);
+            messages.drain(..excess);
         }
 
-        let result = self.db.query(&sql, params).await?;
+        Ok(messages)
+    }
+
+    /// Get the messages of every branch of a session, oldest first
+    pub async fn get_all_messages(&self, session_id: &str) -> Result<Vec<Message>, String> {
+        // rowid breaks ties between messages created within the same second
+        let result = self
+            .db
+            .query(
+                "SELECT * FROM messages WHERE session_id = ? ORDER BY created_at, rowid",
+                vec![serde_json::json!(session_id)],
+            )
+            .await?;
+
+        result.rows.iter().map(row_to_message).collect()
+    }
+
+    /// Get the messages from the root of a session's tree down to `leaf_id`
+    pub async fn get_branch(
+        &self,
+        session_id: &str,
+        leaf_id: &str,
+    ) -> Result<Vec<Message>, String> {
+        // Walk up the parent chain; the depth bound stops on a corrupt,
+        // cyclic chain
+        let sql = r#"
+            WITH RECURSIVE branch(id, depth) AS (
+                SELECT ?, 0
+                UNION ALL
+                SELECT m.parent_id, branch.depth + 1
+                FROM messages m JOIN branch ON m.id = branch.id
+                WHERE m.session_id = ?
+                  AND m.parent_id IS NOT NULL
+                  AND branch.depth < (SELECT COUNT(*) FROM messages WHERE session_id = ?)
+            )
+            SELECT m.* FROM branch JOIN messages m ON m.id = branch.id
+            WHERE m.session_id = ?
+            ORDER BY branch.depth DESC
+        "#;
+
+        let result = self
+            .db
+            .query(
+                sql,
+                vec![
+                    serde_json::json!(leaf_id),
+                    serde_json::json!(session_id),
+                    serde_json::json!(session_id),
+                    serde_json::json!(session_id),
+                ],
+            )
+            .await?;
+
+        result.rows.iter().map(row_to_message).collect()
+    }
+
+    /// Get the last message of the selected branch
+    pub async fn get_head_message_id(&self, session_id: &str) -> Result<Option<MessageId>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT head_message_id FROM sessions WHERE id = ?",
+                vec![serde_json::json!(session_id)],
+            )
+            .await?;
 
-        let mut messages: Vec<Message> = result
+        Ok(result
             .rows
+            .first()
+            .and_then(|row| row.get("head_message_id"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()))
+    }
+
+    /// Move the head of a session; `None` makes the next message a new root
+    pub async fn set_head_message_id(
+        &self,
+        session_id: &str,
+        message_id: Option<&str>,
+    ) -> Result<(), String> {
+        self.db
+            .execute(
+                "UPDATE sessions SET head_message_id = ? WHERE id = ?",
+                vec![serde_json::json!(message_id), serde_json::json!(session_id)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Select the branch through `message_id`, following the newest reply at
+    /// each fork below it, and return the id of its last message
+    pub async fn select_branch(
+        &self,
+        session_id: &str,
+        message_id: &str,
+    ) -> Result<MessageId, String> {
+        let messages = self.get_all_messages(session_id).await?;
+        if !messages.iter().any(|m| m.id == message_id) {
+            return Err(format!(
+                "Message '{}' not found in session '{}'",
+                message_id, session_id
+            ));
+        }
+
+        let leaf_id = latest_leaf(&messages, message_id);
+        self.set_head_message_id(session_id, Some(&leaf_id)).await?;
+        Ok(leaf_id)
+    }
+
+    /// List the branches of a session's message tree, oldest first
+    pub async fn list_branches(&self, session_id: &str) -> Result<Vec<MessageBranch>, String> {
+        let head = self.get_head_message_id(session_id).await?;
+        let messages = self.get_all_messages(session_id).await?;
+
+        let parent_of: HashMap<&str, Option<&str>> = messages
             .iter()
-            .map(row_to_message)
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|m| (m.id.as_str(), m.parent_id.as_deref()))
+            .collect();
+        let parents: HashSet<&str> = parent_of.values().flatten().copied().collect();
+        let depth = |leaf: &str| {
+            let mut count = 0;
+            let mut next = Some(leaf);
+            while let Some(parent) = next.and_then(|id| parent_of.get(id)) {
+                count += 1;
+                if count >= messages.len() {
+                    break;
+                }
+                next = *parent;
+            }
+            count
+        };
 
-        // Reverse to get chronological order
-        messages.reverse();
-        Ok(messages)
+        Ok(messages
+            .iter()
+            .filter(|m| !parents.contains(m.id.as_str()))
+            .map(|leaf| MessageBranch {
+                leaf_message_id: leaf.id.clone(),
+                message_count: depth(&leaf.id),
+                updated_at: leaf.created_at,
+                active: head.as_deref() == Some(leaf.id.as_str()),
+            })
+            .collect())
     }
 
     /// Delete all messages for a session
@@ -259,8 +393,8 @@ impl ChatHistoryRepository {
     /// Create a new event
     pub async fn create_event(&self, event: &SessionEvent) -> Result<(), String> {
         let sql = r#"
-            INSERT INTO events (id, session_id, event_type, payload, created_at)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO events (id, session_id, seq, event_type, payload, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
         "#;
 
         self.db
@@ -269,6 +403,7 @@ impl ChatHistoryRepository {
                 vec![
                     serde_json::json!(event.id),
                     serde_json::json!(event.session_id),
+                    serde_json::json!(event.seq),
                     serde_json::json!(event.event_type.as_str()),
                     serde_json::json!(event.payload.to_string()),
                     serde_json::json!(event.created_at),
@@ -279,7 +414,11 @@ impl ChatHistoryRepository {
         Ok(())
     }
 
-    /// Get events for a session, optionally after a specific event ID (for resume)
+    /// Get events for a session, oldest first, optionally after a specific
+    /// event ID (for resume) and capped at the `limit` oldest matches
+    ///
+    /// An `after_event_id` the session has no stored event for yields nothing,
+    /// rather than a replay of the whole session.
     pub async fn get_events(
         &self,
         session_id: &str,
@@ -290,24 +429,16 @@ impl ChatHistoryRepository {
         let mut params: Vec<serde_json::Value> = vec![serde_json::json!(session_id)];
 
         if let Some(after_id) = after_event_id {
-            // Get created_at of the after event
-            let after_result = self
-                .db
-                .query(
-                    "SELECT created_at FROM events WHERE id = ?",
-                    vec![serde_json::json!(after_id)],
-                )
-                .await?;
-
-            if let Some(row) = after_result.rows.first() {
-                if let Some(created_at) = row.get("created_at").and_then(|v| v.as_i64()) {
-                    sql.push_str(" AND created_at > ?");
-                    params.push(serde_json::json!(created_at));
+            match self.get_event_seq(session_id, after_id).await? {
+                Some(seq) => {
+                    sql.push_str(" AND seq > ?");
+                    params.push(serde_json::json!(seq));
                 }
+                None => return Ok(Vec::new()),
             }
         }
 
-        sql.push_str(" ORDER BY created_at ASC");
+        sql.push_str(" ORDER BY seq ASC");
 
         if let Some(limit) = limit {
             sql.push_str(&format!(" LIMIT {}", limit));
@@ -322,25 +453,149 @@ impl ChatHistoryRepository {
             .collect::<Result<Vec<_>, _>>()
     }
 
-    /// Delete old events for a session (cleanup)
+    /// Delete a session's events older than the given event (cleanup)
     pub async fn delete_events_before(
         &self,
         session_id: &str,
-        before_timestamp: i64,
+        before_event_id: &str,
     ) -> Result<u64, String> {
+        let seq = match self.get_event_seq(session_id, before_event_id).await? {
+            Some(seq) => seq,
+            None => return Ok(0),
+        };
+
         let result = self
             .db
             .execute(
-                "DELETE FROM events WHERE session_id = ? AND created_at < ?",
-                vec![
-                    serde_json::json!(session_id),
-                    serde_json::json!(before_timestamp),
-                ],
+                "DELETE FROM events WHERE session_id = ? AND seq < ?",
+                vec![serde_json::json!(session_id), serde_json::json!(seq)],
             )
             .await?;
 
         Ok(result.rows_affected)
     }
+
+    /// Get the sequence number of an event within its session
+    pub async fn get_event_seq(
+        &self,
+        session_id: &str,
+        event_id: &str,
+    ) -> Result<Option<i64>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT seq FROM events WHERE session_id = ? AND id = ?",
+                vec![serde_json::json!(session_id), serde_json::json!(event_id)],
+            )
+            .await?;
+
+        Ok(result
+            .rows
+            .first()
+            .and_then(|row| row.get("seq"))
+            .and_then(|v| v.as_i64()))
+    }
+
+    /// Get the id of a session's latest stored event
+    pub async fn get_last_event_id(&self, session_id: &str) -> Result<Option<EventId>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT id FROM events WHERE session_id = ? ORDER BY seq DESC LIMIT 1",
+                vec![serde_json::json!(session_id)],
+            )
+            .await?;
+
+        Ok(result
+            .rows
+            .first()
+            .and_then(|row| row.get("id"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()))
+    }
+
+    /// Get the highest sequence number used in a session, 0 if it has no events
+    pub async fn get_last_event_seq(&self, session_id: &str) -> Result<i64, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT MAX(seq) AS seq FROM events WHERE session_id = ?",
+                vec![serde_json::json!(session_id)],
+            )
+            .await?;
+
+        Ok(result
+            .rows
+            .first()
+            .and_then(|row| row.get("seq"))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0))
+    }
+
+    // ============== Todo Operations ==============
+
+    /// Get the todo list of a session in list order
+    pub async fn get_todos(&self, session_id: &str) -> Result<Vec<TodoItem>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT * FROM todos WHERE session_id = ? ORDER BY position ASC",
+                vec![serde_json::json!(session_id)],
+            )
+            .await?;
+
+        Ok(result.rows.iter().map(row_to_todo).collect())
+    }
+
+    /// Replace the todo list of a session
+    pub async fn replace_todos(&self, session_id: &str, todos: &[TodoItem]) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut statements = vec![(
+            "DELETE FROM todos WHERE session_id = ?".to_string(),
+            vec![serde_json::json!(session_id)],
+        )];
+
+        for (position, todo) in todos.iter().enumerate() {
+            statements.push((
+                r#"
+                    INSERT INTO todos (session_id, id, content, status, priority, position, updated_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#
+                .to_string(),
+                vec![
+                    serde_json::json!(session_id),
+                    serde_json::json!(todo.id),
+                    serde_json::json!(todo.content),
+                    serde_json::json!(todo.status.as_str()),
+                    serde_json::json!(todo.priority.as_str()),
+                    serde_json::json!(position),
+                    serde_json::json!(now),
+                ],
+            ));
+        }
+
+        self.db.batch(statements).await?;
+        Ok(())
+    }
+}
+
+// ============== Message Tree ==============
+
+/// Last message below `message_id` when following the newest reply at each
+/// fork; `messages` are in creation order
+fn latest_leaf(messages: &[Message], message_id: &str) -> MessageId {
+    let mut current = message_id;
+    for _ in 0..messages.len() {
+        match messages
+            .iter()
+            .rev()
+            .find(|m| m.parent_id.as_deref() == Some(current))
+        {
+            Some(reply) => current = &reply.id,
+            None => break,
+        }
+    }
+    current.to_string()
 }
 
 // ============== Row Conversions ==============
@@ -375,6 +630,20 @@ fn row_to_session(row: &serde_json::Value) -> Session {
             .get("metadata")
             .and_then(|v| v.as_str())
             .and_then(|s| serde_json::from_str(s).ok()),
+        head_message_id: row
+            .get("head_message_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        forked_from: match (
+            row.get("forked_from_session_id").and_then(|v| v.as_str()),
+            row.get("forked_from_message_id").and_then(|v| v.as_str()),
+        ) {
+            (Some(session_id), Some(message_id)) => Some(SessionFork {
+                session_id: session_id.to_string(),
+                message_id: message_id.to_string(),
+            }),
+            _ => None,
+        },
     }
 }
 
@@ -436,6 +705,7 @@ fn row_to_event(row: &serde_json::Value) -> Result<SessionEvent, String> {
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .to_string(),
+        seq: row.get("seq").and_then(|v| v.as_i64()).unwrap_or(0),
         event_type: row
             .get("event_type")
             .and_then(|v| v.as_str())
@@ -446,6 +716,31 @@ fn row_to_event(row: &serde_json::Value) -> Result<SessionEvent, String> {
     })
 }
 
+fn row_to_todo(row: &serde_json::Value) -> TodoItem {
+    TodoItem {
+        id: row
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        content: row
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        status: row
+            .get("status")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+        priority: row
+            .get("priority")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -482,6 +777,8 @@ mod tests {
             updated_at: chrono::Utc::now().timestamp(),
             last_event_id: None,
             metadata: Some(serde_json::json!({"key": "value"})),
+            head_message_id: None,
+            forked_from: None,
         };
 
         repo.create_session(&session)
@@ -514,6 +811,8 @@ mod tests {
             updated_at: chrono::Utc::now().timestamp(),
             last_event_id: None,
             metadata: None,
+            head_message_id: None,
+            forked_from: None,
         };
 
         repo.create_session(&session)
@@ -545,6 +844,8 @@ mod tests {
             updated_at: chrono::Utc::now().timestamp(),
             last_event_id: None,
             metadata: None,
+            head_message_id: None,
+            forked_from: None,
         };
         repo.create_session(&session)
             .await
@@ -573,4 +874,221 @@ mod tests {
         assert_eq!(messages.len(), 1);
         assert_eq!(messages[0].id, "msg-1");
     }
+
+    #[tokio::test]
+    async fn test_message_branches() {
+        let (db, _temp) = create_test_db().await;
+        let repo = ChatHistoryRepository::new(db);
+
+        let now = chrono::Utc::now().timestamp();
+        repo.create_session(&Session {
+            id: "branching".to_string(),
+            project_id: None,
+            title: None,
+            status: SessionStatus::Created,
+            created_at: now,
+            updated_at: now,
+            last_event_id: None,
+            metadata: None,
+            head_message_id: None,
+            forked_from: None,
+        })
+        .await
+        .unwrap();
+
+        let message = |id: &str, role: MessageRole| Message {
+            id: id.to_string(),
+            session_id: "branching".to_string(),
+            role,
+            content: MessageContent::Text {
+                text: id.to_string(),
+            },
+            created_at: now,
+            tool_call_id: None,
+            parent_id: None,
+        };
+        let ids = |messages: Vec<Message>| -> Vec<String> {
+            messages.into_iter().map(|m| m.id).collect()
+        };
+
+        repo.create_message(&message("q1", MessageRole::User))
+            .await
+            .unwrap();
+        repo.create_message(&message("a1", MessageRole::Assistant))
+            .await
+            .unwrap();
+
+        // Regenerate the answer as a sibling of a1
+        repo.set_head_message_id("branching", Some("q1"))
+            .await
+            .unwrap();
+        repo.create_message(&message("a2", MessageRole::Assistant))
+            .await
+            .unwrap();
+
+        let all = repo.get_all_messages("branching").await.unwrap();
+        assert_eq!(all[2].parent_id.as_deref(), Some("q1"));
+        assert_eq!(
+            ids(repo.get_messages("branching", None, None).await.unwrap()),
+            vec!["q1", "a2"]
+        );
+
+        let branches = repo.list_branches("branching").await.unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].leaf_message_id, "a1");
+        assert!(!branches[0].active);
+        assert_eq!(branches[1].message_count, 2);
+        assert!(branches[1].active);
+
+        assert_eq!(repo.select_branch("branching", "a1").await.unwrap(), "a1");
+        assert_eq!(
+            ids(repo.get_messages("branching", None, None).await.unwrap()),
+            vec!["q1", "a1"]
+        );
+        // Selecting a fork point follows its newest reply
+        assert_eq!(repo.select_branch("branching", "q1").await.unwrap(), "a2");
+        assert!(repo.select_branch("branching", "missing").await.is_err());
+
+        // A new root when the head is cleared
+        repo.set_head_message_id("branching", None).await.unwrap();
+        repo.create_message(&message("q2", MessageRole::User))
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(repo.get_messages("branching", None, None).await.unwrap()),
+            vec!["q2"]
+        );
+        assert_eq!(repo.list_branches("branching").await.unwrap().len(), 3);
+
+        // A corrupt, cyclic parent chain stops once the walk is as deep as
+        // the session has messages
+        repo.db
+            .execute(
+                "UPDATE messages SET parent_id = 'a2' WHERE id = 'q1'",
+                vec![],
+            )
+            .await
+            .unwrap();
+        let cyclic = repo.get_branch("branching", "a2").await.unwrap();
+        assert_eq!(cyclic.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_replace_and_get_todos() {
+        let (db, _temp) = create_test_db().await;
+        let repo = ChatHistoryRepository::new(db);
+
+        let session = Session {
+            id: "test-session-4".to_string(),
+            project_id: None,
+            title: None,
+            status: SessionStatus::Created,
+            created_at: chrono::Utc::now().timestamp(),
+            updated_at: chrono::Utc::now().timestamp(),
+            last_event_id: None,
+            metadata: None,
+            head_message_id: None,
+            forked_from: None,
+        };
+        repo.create_session(&session)
+            .await
+            .expect("Failed to create session");
+
+        let todo = |id: &str, status: TodoStatus| TodoItem {
+            id: id.to_string(),
+            content: format!("Task {}", id),
+            status,
+            priority: TodoPriority::High,
+        };
+        repo.replace_todos(
+            "test-session-4",
+            &[
+                todo("b", TodoStatus::InProgress),
+                todo("a", TodoStatus::Pending),
+            ],
+        )
+        .await
+        .expect("Failed to write todos");
+        repo.replace_todos("test-session-4", &[todo("c", TodoStatus::Completed)])
+            .await
+            .expect("Failed to replace todos");
+        repo.replace_todos(
+            "test-session-4",
+            &[
+                todo("c", TodoStatus::Completed),
+                todo("a", TodoStatus::Pending),
+            ],
+        )
+        .await
+        .expect("Failed to replace todos");
+
+        let todos = repo
+            .get_todos("test-session-4")
+            .await
+            .expect("Failed to get todos");
+        assert_eq!(
+            todos,
+            vec![
+                todo("c", TodoStatus::Completed),
+                todo("a", TodoStatus::Pending)
+            ]
+        );
+        assert!(repo.get_todos("other").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_are_ordered_by_sequence() {
+        let (db, _temp) = create_test_db().await;
+        let repo = ChatHistoryRepository::new(db);
+
+        // Ids and timestamps deliberately disagree with the sequence
+        for (seq, id) in [(1, "evt-c"), (2, "evt-a"), (3, "evt-b")] {
+            repo.create_event(&SessionEvent {
+                id: id.to_string(),
+                session_id: "test-session-5".to_string(),
+                seq,
+                event_type: EventType::Token,
+                payload: serde_json::json!({"token": id}),
+                created_at: 100 - seq,
+            })
+            .await
+            .expect("Failed to create event");
+        }
+
+        assert_eq!(repo.get_last_event_seq("test-session-5").await.unwrap(), 3);
+        assert_eq!(repo.get_last_event_seq("other").await.unwrap(), 0);
+
+        let after = repo
+            .get_events("test-session-5", Some("evt-c"), None)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = after.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["evt-a", "evt-b"]);
+        assert_eq!(after[0].seq, 2);
+
+        // The oldest matches are kept, and an unknown id replays nothing
+        let first = repo
+            .get_events("test-session-5", None, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(first[0].id, "evt-c");
+        assert!(repo
+            .get_events("test-session-5", Some("evt-missing"), None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            repo.get_last_event_id("test-session-5").await.unwrap(),
+            Some("evt-b".to_string())
+        );
+
+        let deleted = repo
+            .delete_events_before("test-session-5", "evt-b")
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        let remaining = repo.get_events("test-session-5", None, None).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "evt-b");
+    }
 }
@@ -0,0 +1,345 @@
+//! Checkpoints Repository
+//! Records file pre-images in chat_history.db and keeps their contents in a
+//! content-addressed blob store, so identical contents are stored once
+
+use crate::database::Database;
+use crate::storage::models::FileCheckpoint;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Repository for file checkpoints
+#[derive(Clone)]
+pub struct CheckpointsRepository {
+    db: Arc<Database>,
+    blob_root: PathBuf,
+}
+
+impl CheckpointsRepository {
+    pub fn new(db: Arc<Database>, blob_root: PathBuf) -> Self {
+        Self { db, blob_root }
+    }
+
+    /// Get the storage path of a blob
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        // Use first 2 chars of the hash as subdirectory, like attachments
+        let prefix = &hash[..2.min(hash.len())];
+        self.blob_root.join(prefix).join(hash)
+    }
+
+    /// Store content under its hash unless already stored
+    fn store_blob(&self, content: &[u8]) -> Result<String, String> {
+        let hash = hex::encode(Sha256::digest(content));
+        let blob_path = self.blob_path(&hash);
+        if blob_path.exists() {
+            return Ok(hash);
+        }
+
+        if let Some(parent) = blob_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create checkpoint directory: {}", e))?;
+        }
+
+        // Write atomically using temp file + rename
+        let temp_path = blob_path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        std::fs::write(&temp_path, content)
+            .map_err(|e| format!("Failed to write checkpoint content: {}", e))?;
+        std::fs::rename(&temp_path, &blob_path)
+            .map_err(|e| format!("Failed to finalize checkpoint content: {}", e))?;
+
+        Ok(hash)
+    }
+
+    /// Record the content of `path` before a tool call writes it
+    ///
+    /// `content` is `None` when the file does not exist yet. Only the first
+    /// pre-image of a path per tool call is kept; later calls return `None`.
+    pub async fn create_checkpoint(
+        &self,
+        task_id: &str,
+        session_id: &str,
+        tool_call_id: &str,
+        path: &str,
+        content: Option<&[u8]>,
+    ) -> Result<Option<FileCheckpoint>, String> {
+        let existing = self
+            .db
+            .query(
+                "SELECT 1 as exists_flag FROM checkpoints WHERE task_id = ? AND tool_call_id = ? AND path = ? LIMIT 1",
+                vec![
+                    serde_json::json!(task_id),
+                    serde_json::json!(tool_call_id),
+                    serde_json::json!(path),
+                ],
+            )
+            .await?;
+        if !existing.rows.is_empty() {
+            return Ok(None);
+        }
+
+        let content_hash = content.map(|data| self.store_blob(data)).transpose()?;
+        let id = format!("ckpt_{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
+        let now = chrono::Utc::now().timestamp();
+
+        let sql = r#"
+            INSERT INTO checkpoints (id, task_id, session_id, tool_call_id, path, content_hash, size, seq, created_at)
+            SELECT ?, ?, ?, ?, ?, ?, ?, COALESCE(MAX(seq), 0) + 1, ?
+            FROM checkpoints WHERE task_id = ?
+        "#;
+        self.db
+            .execute(
+                sql,
+                vec![
+                    serde_json::json!(id),
+                    serde_json::json!(task_id),
+                    serde_json::json!(session_id),
+                    serde_json::json!(tool_call_id),
+                    serde_json::json!(path),
+                    serde_json::json!(content_hash),
+                    serde_json::json!(content.map_or(0, |data| data.len())),
+                    serde_json::json!(now),
+                    serde_json::json!(task_id),
+                ],
+            )
+            .await?;
+
+        self.get_checkpoint(&id).await
+    }
+
+    /// Get a checkpoint by ID
+    pub async fn get_checkpoint(
+        &self,
+        checkpoint_id: &str,
+    ) -> Result<Option<FileCheckpoint>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT * FROM checkpoints WHERE id = ?",
+                vec![serde_json::json!(checkpoint_id)],
+            )
+            .await?;
+
+        Ok(result.rows.first().map(row_to_checkpoint))
+    }
+
+    /// List a task's checkpoints in the order they were taken
+    pub async fn list_checkpoints(&self, task_id: &str) -> Result<Vec<FileCheckpoint>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT * FROM checkpoints WHERE task_id = ? ORDER BY seq ASC",
+                vec![serde_json::json!(task_id)],
+            )
+            .await?;
+
+        Ok(result.rows.iter().map(row_to_checkpoint).collect())
+    }
+
+    /// Read the content recorded by a checkpoint; `None` if the file did not exist
+    pub fn read_content(&self, checkpoint: &FileCheckpoint) -> Result<Option<Vec<u8>>, String> {
+        checkpoint
+            .content_hash
+            .as_deref()
+            .map(|hash| {
+                std::fs::read(self.blob_path(hash))
+                    .map_err(|e| format!("Failed to read checkpoint content: {}", e))
+            })
+            .transpose()
+    }
+
+    /// Delete a task's checkpoints from `seq` on, and the contents no other
+    /// checkpoint refers to
+    pub async fn delete_checkpoints_from(&self, task_id: &str, seq: i64) -> Result<u64, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT DISTINCT content_hash FROM checkpoints WHERE task_id = ? AND seq >= ? AND content_hash IS NOT NULL",
+                vec![serde_json::json!(task_id), serde_json::json!(seq)],
+            )
+            .await?;
+        let hashes: Vec<String> = result
+            .rows
+            .iter()
+            .filter_map(|row| row.get("content_hash").and_then(|v| v.as_str()))
+            .map(str::to_string)
+            .collect();
+
+        let deleted = self
+            .db
+            .execute(
+                "DELETE FROM checkpoints WHERE task_id = ? AND seq >= ?",
+                vec![serde_json::json!(task_id), serde_json::json!(seq)],
+            )
+            .await?;
+
+        for hash in hashes {
+            self.release_blob(&hash).await?;
+        }
+
+        Ok(deleted.rows_affected)
+    }
+
+    /// Remove a blob once no checkpoint refers to it
+    async fn release_blob(&self, hash: &str) -> Result<(), String> {
+        let result = self
+            .db
+            .query(
+                "SELECT 1 as exists_flag FROM checkpoints WHERE content_hash = ? LIMIT 1",
+                vec![serde_json::json!(hash)],
+            )
+            .await?;
+        if result.rows.is_empty() {
+            let blob_path = self.blob_path(hash);
+            let _ = std::fs::remove_file(&blob_path);
+            if let Some(parent) = blob_path.parent() {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============== Row Conversion ==============
+
+fn row_to_checkpoint(row: &serde_json::Value) -> FileCheckpoint {
+    let text = |name: &str| {
+        row.get(name)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    FileCheckpoint {
+        id: text("id"),
+        task_id: text("task_id"),
+        session_id: text("session_id"),
+        tool_call_id: text("tool_call_id"),
+        path: text("path"),
+        content_hash: row
+            .get("content_hash")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        size: row.get("size").and_then(|v| v.as_i64()).unwrap_or(0),
+        seq: row.get("seq").and_then(|v| v.as_i64()).unwrap_or(0),
+        created_at: row.get("created_at").and_then(|v| v.as_i64()).unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn create_test_repo() -> (CheckpointsRepository, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = Arc::new(Database::new(db_path.to_string_lossy().to_string()));
+        db.connect()
+            .await
+            .expect("Failed to connect to test database");
+
+        let migrations = super::super::migrations::chat_history_migrations();
+        let runner = super::super::migrations::MigrationRunner::new(&db, &migrations);
+        runner.init().await.expect("Failed to init migrations");
+        runner.migrate().await.expect("Failed to run migrations");
+
+        let now = chrono::Utc::now().timestamp();
+        db.execute(
+            "INSERT INTO sessions (id, title, status, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+            vec![
+                serde_json::json!("session-1"),
+                serde_json::json!("Test Session"),
+                serde_json::json!("created"),
+                serde_json::json!(now),
+                serde_json::json!(now),
+            ],
+        )
+        .await
+        .expect("Failed to create test session");
+
+        let repo = CheckpointsRepository::new(db, temp_dir.path().join("checkpoints"));
+        (repo, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_are_ordered_and_deduplicated() {
+        let (repo, temp) = create_test_repo().await;
+
+        let first = repo
+            .create_checkpoint("task-1", "session-1", "call-1", "/w/a.rs", Some(b"same"))
+            .await
+            .unwrap()
+            .expect("First checkpoint should be recorded");
+        let second = repo
+            .create_checkpoint("task-1", "session-1", "call-2", "/w/b.rs", Some(b"same"))
+            .await
+            .unwrap()
+            .unwrap();
+        let new_file = repo
+            .create_checkpoint("task-1", "session-1", "call-2", "/w/c.rs", None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!((first.seq, second.seq, new_file.seq), (1, 2, 3));
+        assert_eq!(first.content_hash, second.content_hash);
+        assert_eq!(first.size, 4);
+        assert_eq!(new_file.content_hash, None);
+        assert_eq!(repo.read_content(&second).unwrap(), Some(b"same".to_vec()));
+        assert_eq!(repo.read_content(&new_file).unwrap(), None);
+
+        // A second write by the same tool call keeps the first pre-image
+        let repeated = repo
+            .create_checkpoint("task-1", "session-1", "call-1", "/w/a.rs", Some(b"changed"))
+            .await
+            .unwrap();
+        assert!(repeated.is_none());
+
+        let blobs: usize = std::fs::read_dir(temp.path().join("checkpoints"))
+            .unwrap()
+            .map(|dir| std::fs::read_dir(dir.unwrap().path()).unwrap().count())
+            .sum();
+        assert_eq!(blobs, 1);
+
+        let ids: Vec<String> = repo
+            .list_checkpoints("task-1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, vec![first.id, second.id, new_file.id]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_checkpoints_releases_unreferenced_blobs() {
+        let (repo, _temp) = create_test_repo().await;
+
+        let kept = repo
+            .create_checkpoint("task-1", "session-1", "call-1", "/w/a.rs", Some(b"shared"))
+            .await
+            .unwrap()
+            .unwrap();
+        let shared = repo
+            .create_checkpoint("task-1", "session-1", "call-2", "/w/b.rs", Some(b"shared"))
+            .await
+            .unwrap()
+            .unwrap();
+        let unique = repo
+            .create_checkpoint("task-1", "session-1", "call-3", "/w/c.rs", Some(b"unique"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let deleted = repo
+            .delete_checkpoints_from("task-1", shared.seq)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(
+            repo.list_checkpoints("task-1").await.unwrap(),
+            vec![kept.clone()]
+        );
+        assert_eq!(repo.read_content(&kept).unwrap(), Some(b"shared".to_vec()));
+        assert!(repo.read_content(&unique).is_err());
+    }
+}
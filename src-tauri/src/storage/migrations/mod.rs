@@ -95,22 +95,30 @@ impl<'a> MigrationRunner<'a> {
         Ok(applied)
     }
 
+    /// Apply a migration and record its version in one transaction, so a
+    /// failing statement leaves neither a partial schema nor a version bump
     async fn apply_migration(&self, migration: &Migration) -> Result<(), String> {
-        // Execute migration in transaction
-        self.db.execute(migration.up_sql, vec![]).await?;
-
-        // Record migration
         let now = chrono::Utc::now().timestamp();
         self.db
-            .execute(
-                "INSERT INTO _migrations (version, name, applied_at) VALUES (?, ?, ?)",
-                vec![
-                    serde_json::json!(migration.version),
-                    serde_json::json!(migration.name),
-                    serde_json::json!(now),
-                ],
+            .execute_in_transaction(
+                migration.up_sql,
+                vec![(
+                    "INSERT INTO _migrations (version, name, applied_at) VALUES (?, ?, ?)"
+                        .to_string(),
+                    vec![
+                        serde_json::json!(migration.version),
+                        serde_json::json!(migration.name),
+                        serde_json::json!(now),
+                    ],
+                )],
             )
-            .await?;
+            .await
+            .map_err(|e| {
+                format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.name, e
+                )
+            })?;
 
         Ok(())
     }
@@ -214,6 +222,93 @@ pub fn chat_history_migrations() -> MigrationRegistry {
         down_sql: Some("DROP INDEX IF EXISTS idx_attachments_message;"),
     });
 
+    registry.register(Migration {
+        version: 6,
+        name: "create_todos_table",
+        up_sql: r#"
+            CREATE TABLE todos (
+                session_id TEXT NOT NULL,
+                id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                priority TEXT NOT NULL DEFAULT 'medium',
+                position INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (session_id, id),
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_todos_session ON todos(session_id, position);
+        "#,
+        down_sql: Some("DROP TABLE todos;"),
+    });
+
+    registry.register(Migration {
+        version: 7,
+        name: "add_events_sequence",
+        up_sql: r#"
+            ALTER TABLE events ADD COLUMN seq INTEGER NOT NULL DEFAULT 0;
+            UPDATE events SET seq = rowid;
+            CREATE UNIQUE INDEX idx_events_session_seq ON events(session_id, seq);
+        "#,
+        down_sql: Some("DROP INDEX idx_events_session_seq; ALTER TABLE events DROP COLUMN seq;"),
+    });
+
+    // Messages form a tree through parent_id; existing sessions become a
+    // single branch in creation order
+    registry.register(Migration {
+        version: 8,
+        name: "add_message_branching",
+        up_sql: r#"
+            ALTER TABLE sessions ADD COLUMN head_message_id TEXT;
+            ALTER TABLE sessions ADD COLUMN forked_from_session_id TEXT;
+            ALTER TABLE sessions ADD COLUMN forked_from_message_id TEXT;
+            UPDATE messages SET parent_id = (
+                SELECT prev.id FROM messages prev
+                WHERE prev.session_id = messages.session_id
+                  AND (prev.created_at < messages.created_at
+                       OR (prev.created_at = messages.created_at AND prev.rowid < messages.rowid))
+                ORDER BY prev.created_at DESC, prev.rowid DESC
+                LIMIT 1
+            )
+            WHERE parent_id IS NULL;
+            UPDATE sessions SET head_message_id = (
+                SELECT m.id FROM messages m
+                WHERE m.session_id = sessions.id
+                ORDER BY m.created_at DESC, m.rowid DESC
+                LIMIT 1
+            );
+            CREATE INDEX idx_messages_parent ON messages(parent_id);
+        "#,
+        down_sql: Some(
+            "DROP INDEX idx_messages_parent; ALTER TABLE sessions DROP COLUMN forked_from_message_id; ALTER TABLE sessions DROP COLUMN forked_from_session_id; ALTER TABLE sessions DROP COLUMN head_message_id;",
+        ),
+    });
+
+    // Pre-images of files written by tools; contents live in a content-addressed
+    // blob store, and a NULL hash means the file did not exist
+    registry.register(Migration {
+        version: 9,
+        name: "create_checkpoints_table",
+        up_sql: r#"
+            CREATE TABLE checkpoints (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                session_id TEXT NOT NULL,
+                tool_call_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                content_hash TEXT,
+                size INTEGER NOT NULL DEFAULT 0,
+                seq INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_checkpoints_task ON checkpoints(task_id, seq);
+            CREATE UNIQUE INDEX idx_checkpoints_tool_call ON checkpoints(task_id, tool_call_id, path);
+            CREATE INDEX idx_checkpoints_hash ON checkpoints(content_hash);
+        "#,
+        down_sql: Some("DROP TABLE checkpoints;"),
+    });
+
     registry
 }
 
@@ -290,6 +385,25 @@ pub fn settings_migrations() -> MigrationRegistry {
         down_sql: Some("DROP TABLE task_settings;"),
     });
 
+    registry.register(Migration {
+        version: 3,
+        name: "create_api_tokens_table",
+        up_sql: r#"
+            CREATE TABLE api_tokens (
+                id TEXT PRIMARY KEY,
+                label TEXT NOT NULL,
+                prefix TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                scopes TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                expires_at INTEGER,
+                last_used_at INTEGER,
+                revoked_at INTEGER
+            );
+        "#,
+        down_sql: Some("DROP TABLE api_tokens;"),
+    });
+
     registry
 }
 
@@ -326,7 +440,7 @@ mod tests {
     #[test]
     fn test_chat_history_migrations_count() {
         let registry = chat_history_migrations();
-        assert_eq!(registry.migrations().len(), 5);
+        assert_eq!(registry.migrations().len(), 9);
     }
 
     #[test]
@@ -338,6 +452,133 @@ mod tests {
     #[test]
     fn test_settings_migrations_count() {
         let registry = settings_migrations();
-        assert_eq!(registry.migrations().len(), 2);
+        assert_eq!(registry.migrations().len(), 3);
+    }
+
+    async fn create_test_db() -> (crate::database::Database, tempfile::TempDir) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = crate::database::Database::new(
+            temp_dir
+                .path()
+                .join("test.db")
+                .to_string_lossy()
+                .to_string(),
+        );
+        db.connect().await.unwrap();
+        (db, temp_dir)
+    }
+
+    async fn index_exists(db: &crate::database::Database, name: &str) -> bool {
+        !db.query(
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND name = ?",
+            vec![serde_json::json!(name)],
+        )
+        .await
+        .unwrap()
+        .rows
+        .is_empty()
+    }
+
+    #[tokio::test]
+    async fn test_migrations_run_every_statement() {
+        let (db, _temp) = create_test_db().await;
+        let registry = chat_history_migrations();
+        let runner = MigrationRunner::new(&db, &registry);
+
+        let applied = runner.migrate().await.unwrap();
+        assert_eq!(applied.len(), registry.migrations().len());
+        assert!(index_exists(&db, "idx_sessions_updated").await);
+        assert!(index_exists(&db, "idx_events_session_created").await);
+    }
+
+    #[tokio::test]
+    async fn test_events_sequence_backfills_existing_rows() {
+        let (db, _temp) = create_test_db().await;
+        let full = chat_history_migrations();
+
+        // A database last migrated before events had a sequence
+        let mut before_seq = MigrationRegistry::new("chat_history");
+        for migration in full.migrations().iter().filter(|m| m.version < 7) {
+            before_seq.register(migration.clone());
+        }
+        MigrationRunner::new(&db, &before_seq)
+            .migrate()
+            .await
+            .unwrap();
+
+        db.execute(
+            "INSERT INTO sessions (id, status, created_at, updated_at) VALUES ('s1', 'created', 0, 0), ('s2', 'created', 0, 0)",
+            vec![],
+        )
+        .await
+        .unwrap();
+        for (id, session) in [("e1", "s1"), ("e2", "s2"), ("e3", "s1")] {
+            db.execute(
+                "INSERT INTO events (id, session_id, event_type, payload, created_at) VALUES (?, ?, 'status', '{}', 0)",
+                vec![serde_json::json!(id), serde_json::json!(session)],
+            )
+            .await
+            .unwrap();
+        }
+
+        MigrationRunner::new(&db, &full).migrate().await.unwrap();
+
+        assert!(index_exists(&db, "idx_events_session_seq").await);
+        let rows = db
+            .query(
+                "SELECT id, seq, rowid AS row_id FROM events ORDER BY rowid",
+                vec![],
+            )
+            .await
+            .unwrap()
+            .rows;
+        assert_eq!(rows.len(), 3);
+        for row in &rows {
+            assert!(row["seq"].as_i64().unwrap() > 0, "{:?}", row);
+            assert_eq!(row["seq"], row["row_id"]);
+        }
+
+        // The unique index rejects a second event at the same position
+        let duplicate = db
+            .execute(
+                "INSERT INTO events (id, session_id, event_type, payload, created_at, seq) VALUES ('e4', 's1', 'status', '{}', 0, ?)",
+                vec![rows[0]["seq"].clone()],
+            )
+            .await;
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_rolled_back() {
+        let (db, _temp) = create_test_db().await;
+        let mut registry = MigrationRegistry::new("test");
+        registry.register(Migration {
+            version: 1,
+            name: "create_items",
+            up_sql: "CREATE TABLE items (id TEXT PRIMARY KEY);",
+            down_sql: None,
+        });
+        registry.register(Migration {
+            version: 2,
+            name: "broken",
+            up_sql: r#"
+                CREATE TABLE tags (id TEXT PRIMARY KEY);
+                CREATE INDEX idx_missing ON missing_table(id);
+            "#,
+            down_sql: None,
+        });
+        let runner = MigrationRunner::new(&db, &registry);
+
+        let error = runner.migrate().await.unwrap_err();
+        assert!(error.contains("Migration 2 (broken) failed"), "{}", error);
+        assert_eq!(runner.current_version().await.unwrap(), 1);
+        let tags = db
+            .query(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'tags'",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert!(tags.rows.is_empty());
     }
 }
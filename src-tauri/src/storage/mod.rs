@@ -1,7 +1,7 @@
 //! Storage Layer for Cloud Backend
 //!
 //! Provides SQLite repositories for:
-//! - chat_history.db: Sessions, messages, events, attachments
+//! - chat_history.db: Sessions, messages, events, attachments, file checkpoints
 //! - agents.db: Agent configurations and agent-session associations  
 //! - settings.db: Application settings and task-specific settings
 //!
@@ -10,6 +10,7 @@
 pub mod agents;
 pub mod attachments;
 pub mod chat_history;
+pub mod checkpoints;
 pub mod migrations;
 pub mod models;
 pub mod settings;
@@ -21,6 +22,7 @@ use std::sync::Arc;
 pub use agents::{AgentUpdates, AgentsRepository};
 pub use attachments::AttachmentsRepository;
 pub use chat_history::ChatHistoryRepository;
+pub use checkpoints::CheckpointsRepository;
 pub use models::*;
 pub use settings::SettingsRepository;
 
@@ -36,6 +38,8 @@ pub struct Storage {
     pub settings: SettingsRepository,
     /// Attachments repository (chat_history.db + filesystem)
     pub attachments: AttachmentsRepository,
+    /// File checkpoints repository (chat_history.db + filesystem)
+    pub checkpoints: CheckpointsRepository,
 }
 
 impl Storage {
@@ -77,8 +81,10 @@ impl Storage {
             .map_err(|e| format!("Failed to run database migrations: {}", e))?;
 
         // Create repositories
-        // Clone chat_history_db for attachments (both use the same DB)
+        // Clone chat_history_db for attachments and checkpoints (all use the same DB)
         let chat_history_db_for_attachments = chat_history_db.clone();
+        let checkpoints =
+            CheckpointsRepository::new(chat_history_db.clone(), data_root.join("checkpoints"));
         let chat_history = ChatHistoryRepository::new(chat_history_db);
         let agents = AgentsRepository::new(agents_db);
         let settings = SettingsRepository::new(settings_db);
@@ -90,6 +96,7 @@ impl Storage {
             agents,
             settings,
             attachments,
+            checkpoints,
         })
     }
 
@@ -168,6 +175,8 @@ mod tests {
             updated_at: chrono::Utc::now().timestamp(),
             last_event_id: None,
             metadata: None,
+            head_message_id: None,
+            forked_from: None,
         };
 
         storage
@@ -1,6 +1,7 @@
 //! Core data models for storage layer
 //! These models are shared across the core runtime, server API, and storage repositories
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -14,7 +15,7 @@ pub type AttachmentId = String;
 pub type ToolCallId = String;
 
 /// Session status in lifecycle
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum SessionStatus {
     /// Session created, waiting for first message
@@ -74,6 +75,33 @@ pub struct Session {
     pub last_event_id: Option<EventId>,
     /// Additional metadata as JSON object
     pub metadata: Option<serde_json::Value>,
+    /// Last message of the selected branch; new messages are appended to it
+    #[serde(default)]
+    pub head_message_id: Option<MessageId>,
+    /// Session and message this session was forked from
+    #[serde(default)]
+    pub forked_from: Option<SessionFork>,
+}
+
+/// Origin of a forked session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionFork {
+    pub session_id: SessionId,
+    /// Last message copied into the fork
+    pub message_id: MessageId,
+}
+
+/// A branch of a session's message tree, identified by its last message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBranch {
+    pub leaf_message_id: MessageId,
+    /// Messages from the root of the tree to the leaf
+    pub message_count: usize,
+    pub updated_at: i64,
+    /// Whether this is the session's selected branch
+    pub active: bool,
 }
 
 /// Role of a message sender
@@ -178,6 +206,9 @@ pub struct ToolCall {
     pub id: ToolCallId,
     pub name: String,
     pub input: serde_json::Value,
+    /// Provider-specific metadata that must be echoed back to the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_metadata: Option<serde_json::Value>,
 }
 
 /// Event types for streaming
@@ -196,6 +227,10 @@ pub enum EventType {
     ToolResult,
     /// Error occurred
     Error,
+    /// Session todo list changed
+    TodoUpdated,
+    /// Task is waiting for answers to questions
+    QuestionAsked,
 }
 
 impl EventType {
@@ -207,6 +242,8 @@ impl EventType {
             EventType::ToolCall => "tool.call",
             EventType::ToolResult => "tool.result",
             EventType::Error => "error",
+            EventType::TodoUpdated => "todo.updated",
+            EventType::QuestionAsked => "question.asked",
         }
     }
 }
@@ -222,6 +259,8 @@ impl std::str::FromStr for EventType {
             "tool.call" => Ok(EventType::ToolCall),
             "tool.result" => Ok(EventType::ToolResult),
             "error" => Ok(EventType::Error),
+            "todo.updated" => Ok(EventType::TodoUpdated),
+            "question.asked" => Ok(EventType::QuestionAsked),
             _ => Err(format!("Unknown event type: {}", s)),
         }
     }
@@ -233,6 +272,9 @@ impl std::str::FromStr for EventType {
 pub struct SessionEvent {
     pub id: EventId,
     pub session_id: SessionId,
+    /// Position in the session's event stream, assigned when the event is buffered
+    #[serde(default)]
+    pub seq: i64,
     pub event_type: EventType,
     pub payload: serde_json::Value,
     pub created_at: i64,
@@ -263,7 +305,7 @@ pub struct AgentSession {
 }
 
 /// Task/Session settings
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskSettings {
     /// Auto-approve file edits
@@ -294,6 +336,33 @@ pub struct Attachment {
     pub origin: AttachmentOrigin,
 }
 
+/// Content of a file before a tool call wrote it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCheckpoint {
+    pub id: String,
+    pub task_id: TaskId,
+    pub session_id: SessionId,
+    pub tool_call_id: ToolCallId,
+    /// Absolute path of the file
+    pub path: String,
+    /// SHA-256 of the previous content; `None` when the file did not exist
+    pub content_hash: Option<String>,
+    pub size: i64,
+    /// Order of the checkpoint within its task
+    pub seq: i64,
+    pub created_at: i64,
+}
+
+/// A file put back to a checkpoint's content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredFile {
+    pub path: String,
+    /// The file did not exist at the checkpoint and was removed
+    pub deleted: bool,
+}
+
 /// Origin of an attachment
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -335,6 +404,84 @@ pub enum UserAction {
     Cancel,
 }
 
+/// Status of a session todo item
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Completed,
+}
+
+impl TodoStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoStatus::Pending => "pending",
+            TodoStatus::InProgress => "in_progress",
+            TodoStatus::Completed => "completed",
+        }
+    }
+}
+
+impl std::str::FromStr for TodoStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(TodoStatus::Pending),
+            "in_progress" => Ok(TodoStatus::InProgress),
+            "completed" => Ok(TodoStatus::Completed),
+            _ => Err(format!("Unknown todo status: {}", s)),
+        }
+    }
+}
+
+/// Priority of a session todo item
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoPriority {
+    High,
+    #[default]
+    Medium,
+    Low,
+}
+
+impl TodoPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoPriority::High => "high",
+            TodoPriority::Medium => "medium",
+            TodoPriority::Low => "low",
+        }
+    }
+}
+
+impl std::str::FromStr for TodoPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(TodoPriority::High),
+            "medium" => Ok(TodoPriority::Medium),
+            "low" => Ok(TodoPriority::Low),
+            _ => Err(format!("Unknown todo priority: {}", s)),
+        }
+    }
+}
+
+/// A todo item in a session's task list (maintained by the `todoWrite` tool)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoItem {
+    pub id: String,
+    pub content: String,
+    #[serde(default)]
+    pub status: TodoStatus,
+    #[serde(default)]
+    pub priority: TodoPriority,
+}
+
 /// Workspace information for a session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -345,6 +492,87 @@ pub struct WorkspaceInfo {
     pub branch: Option<String>,
 }
 
+/// Capability granted to an API token of the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum ApiTokenScope {
+    /// Read sessions, messages, tasks and events
+    #[serde(rename = "read-only")]
+    ReadOnly,
+    /// Create and drive sessions, messages, tasks and actions
+    #[serde(rename = "sessions:write")]
+    SessionsWrite,
+    /// Upload and download session files
+    #[serde(rename = "files")]
+    Files,
+    /// Everything, including token management
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenScope::ReadOnly => "read-only",
+            ApiTokenScope::SessionsWrite => "sessions:write",
+            ApiTokenScope::Files => "files",
+            ApiTokenScope::Admin => "admin",
+        }
+    }
+
+    /// Whether a token with this scope may perform requests requiring `required`
+    pub fn grants(&self, required: ApiTokenScope) -> bool {
+        match self {
+            ApiTokenScope::Admin => true,
+            ApiTokenScope::SessionsWrite => matches!(
+                required,
+                ApiTokenScope::ReadOnly | ApiTokenScope::SessionsWrite
+            ),
+            scope => *scope == required,
+        }
+    }
+}
+
+impl std::str::FromStr for ApiTokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-only" => Ok(ApiTokenScope::ReadOnly),
+            "sessions:write" => Ok(ApiTokenScope::SessionsWrite),
+            "files" => Ok(ApiTokenScope::Files),
+            "admin" => Ok(ApiTokenScope::Admin),
+            _ => Err(format!("Unknown API token scope: {}", s)),
+        }
+    }
+}
+
+/// An API token of the server; only a hash of its secret is stored
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: String,
+    pub label: String,
+    /// Leading characters of the secret, to tell tokens apart
+    pub prefix: String,
+    pub scopes: Vec<ApiTokenScope>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+impl ApiToken {
+    /// Whether the token can currently be used
+    pub fn is_active(&self, now: i64) -> bool {
+        self.revoked_at.is_none() && self.expires_at.map_or(true, |expires| expires > now)
+    }
+
+    /// Whether any of the token's scopes grants `required`
+    pub fn allows(&self, required: ApiTokenScope) -> bool {
+        self.scopes.iter().any(|scope| scope.grants(required))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,7 +2,7 @@
 //! Handles CRUD operations for application settings in settings.db
 
 use crate::database::Database;
-use crate::storage::models::TaskSettings;
+use crate::storage::models::{ApiToken, TaskSettings};
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -245,12 +245,137 @@ impl SettingsRepository {
 
         Ok(settings_map)
     }
+
+    // ============== API Token Operations ==============
+
+    /// Store a new API token with the hash of its secret
+    pub async fn create_api_token(&self, token: &ApiToken, token_hash: &str) -> Result<(), String> {
+        let scopes = serde_json::to_string(&token.scopes)
+            .map_err(|e| format!("Failed to serialize token scopes: {}", e))?;
+
+        self.db
+            .execute(
+                r#"
+                INSERT INTO api_tokens (id, label, prefix, token_hash, scopes, created_at, expires_at, last_used_at, revoked_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+                vec![
+                    serde_json::json!(token.id),
+                    serde_json::json!(token.label),
+                    serde_json::json!(token.prefix),
+                    serde_json::json!(token_hash),
+                    serde_json::json!(scopes),
+                    serde_json::json!(token.created_at),
+                    serde_json::json!(token.expires_at),
+                    serde_json::json!(token.last_used_at),
+                    serde_json::json!(token.revoked_at),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get an API token by the hash of its secret
+    pub async fn get_api_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ApiToken>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT * FROM api_tokens WHERE token_hash = ?",
+                vec![serde_json::json!(token_hash)],
+            )
+            .await?;
+
+        result.rows.first().map(row_to_api_token).transpose()
+    }
+
+    /// List all API tokens, newest first
+    pub async fn list_api_tokens(&self) -> Result<Vec<ApiToken>, String> {
+        let result = self
+            .db
+            .query(
+                "SELECT * FROM api_tokens ORDER BY created_at DESC, id",
+                vec![],
+            )
+            .await?;
+
+        result.rows.iter().map(row_to_api_token).collect()
+    }
+
+    /// Whether any API token has been issued, revoked or not
+    pub async fn has_api_tokens(&self) -> Result<bool, String> {
+        let result = self
+            .db
+            .query("SELECT id FROM api_tokens LIMIT 1", vec![])
+            .await?;
+
+        Ok(!result.rows.is_empty())
+    }
+
+    /// Record that a token was used
+    pub async fn touch_api_token(&self, id: &str, used_at: i64) -> Result<(), String> {
+        self.db
+            .execute(
+                "UPDATE api_tokens SET last_used_at = ? WHERE id = ?",
+                vec![serde_json::json!(used_at), serde_json::json!(id)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke a token; returns false if it does not exist or was already revoked
+    pub async fn revoke_api_token(&self, id: &str) -> Result<bool, String> {
+        let revoked_at = chrono::Utc::now().timestamp();
+        let result = self
+            .db
+            .execute(
+                "UPDATE api_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL",
+                vec![serde_json::json!(revoked_at), serde_json::json!(id)],
+            )
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+}
+
+// ============== Row Conversion ==============
+
+fn row_to_api_token(row: &serde_json::Value) -> Result<ApiToken, String> {
+    let scopes = row.get("scopes").and_then(|v| v.as_str()).unwrap_or("[]");
+
+    Ok(ApiToken {
+        id: row
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        label: row
+            .get("label")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        prefix: row
+            .get("prefix")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        scopes: serde_json::from_str(scopes)
+            .map_err(|e| format!("Failed to parse token scopes: {}", e))?,
+        created_at: row.get("created_at").and_then(|v| v.as_i64()).unwrap_or(0),
+        expires_at: row.get("expires_at").and_then(|v| v.as_i64()),
+        last_used_at: row.get("last_used_at").and_then(|v| v.as_i64()),
+        revoked_at: row.get("revoked_at").and_then(|v| v.as_i64()),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::database::Database;
+    use crate::storage::models::ApiTokenScope;
     use tempfile::TempDir;
 
     async fn create_test_db() -> (Arc<Database>, TempDir) {
@@ -393,6 +518,46 @@ mod tests {
         assert_eq!(updated.auto_code_review, Some(false)); // Set
     }
 
+    #[tokio::test]
+    async fn test_api_tokens() {
+        let (db, _temp) = create_test_db().await;
+        let repo = SettingsRepository::new(db);
+        assert!(!repo.has_api_tokens().await.unwrap());
+
+        let token = ApiToken {
+            id: "tok_1".to_string(),
+            label: "ci".to_string(),
+            prefix: "tc_abcd".to_string(),
+            scopes: vec![ApiTokenScope::ReadOnly, ApiTokenScope::Files],
+            created_at: 100,
+            expires_at: Some(200),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        repo.create_api_token(&token, "hash-1")
+            .await
+            .expect("Failed to create token");
+        assert!(repo.has_api_tokens().await.unwrap());
+
+        let stored = repo
+            .get_api_token_by_hash("hash-1")
+            .await
+            .unwrap()
+            .expect("Token should exist");
+        assert_eq!(stored.scopes, token.scopes);
+        assert_eq!(stored.expires_at, Some(200));
+        assert!(repo.get_api_token_by_hash("other").await.unwrap().is_none());
+
+        repo.touch_api_token("tok_1", 150).await.unwrap();
+        assert!(repo.revoke_api_token("tok_1").await.unwrap());
+        assert!(!repo.revoke_api_token("tok_1").await.unwrap());
+
+        let listed = repo.list_api_tokens().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].last_used_at, Some(150));
+        assert!(!listed[0].is_active(150));
+    }
+
     #[tokio::test]
     async fn test_delete_setting() {
         let (db, _temp) = create_test_db().await;
@@ -1,19 +1,24 @@
 //! Event Buffer
 //!
 //! Buffers events for SSE streaming with resume capability.
-//! Persists events to storage and maintains in-memory cache.
+//! Persists events to storage and maintains in-memory cache. Every event gets
+//! the next per-session sequence number, which defines replay order.
+//! Token events are only cached in memory: the final message event carries
+//! their text, so storage replay doesn't need them.
 
 use crate::storage::models::{EventId, SessionEvent, SessionId};
 use crate::storage::Storage;
 use crate::streaming::events::StreamingEvent;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 /// Event buffer for managing streaming events
 pub struct EventBuffer {
     /// In-memory cache of recent events per session
     cache: RwLock<HashMap<SessionId, Vec<SessionEvent>>>,
+    /// Last sequence number assigned per session
+    sequences: Mutex<HashMap<SessionId, i64>>,
     /// Maximum events to keep in memory per session
     max_memory_events: usize,
     /// Storage for persistence
@@ -24,6 +29,7 @@ impl EventBuffer {
     pub fn new(max_memory_events: usize) -> Self {
         Self {
             cache: RwLock::new(HashMap::new()),
+            sequences: Mutex::new(HashMap::new()),
             max_memory_events,
             storage: None,
         }
@@ -36,10 +42,28 @@ impl EventBuffer {
 
     /// Add an event to the buffer
     pub async fn add_event(&self, event: StreamingEvent) -> Result<(), String> {
-        let session_event: SessionEvent = event.into();
+        let persist = !matches!(event, StreamingEvent::Token { .. });
+        let mut session_event: SessionEvent = event.into();
 
-        // Add to in-memory cache
         {
+            // Sequences continue from storage after a restart
+            let mut sequences = self.sequences.lock().await;
+            let last_seq = match sequences.get(&session_event.session_id) {
+                Some(seq) => *seq,
+                None => match &self.storage {
+                    Some(storage) => {
+                        storage
+                            .chat_history
+                            .get_last_event_seq(&session_event.session_id)
+                            .await?
+                    }
+                    None => 0,
+                },
+            };
+            session_event.seq = last_seq + 1;
+            sequences.insert(session_event.session_id.clone(), session_event.seq);
+
+            // Add to in-memory cache under the sequence lock so it stays in order
             let mut cache = self.cache.write().await;
             let events = cache
                 .entry(session_event.session_id.clone())
@@ -54,14 +78,18 @@ impl EventBuffer {
         }
 
         // Persist to storage if available
-        if let Some(storage) = &self.storage {
+        if let (Some(storage), true) = (&self.storage, persist) {
             storage.chat_history.create_event(&session_event).await?;
         }
 
         Ok(())
     }
 
-    /// Get events for a session, optionally after a specific event ID
+    /// Get events for a session in publish order, optionally after a specific
+    /// event ID and capped at the `limit` oldest matches
+    ///
+    /// An `after_event_id` that is neither cached nor stored, such as a token
+    /// from before a restart, yields nothing rather than a full replay.
     pub async fn get_events(
         &self,
         session_id: &str,
@@ -72,28 +100,29 @@ impl EventBuffer {
         let cache = self.cache.read().await;
 
         if let Some(events) = cache.get(session_id) {
-            let mut result: Vec<StreamingEvent> = events
-                .iter()
-                .filter(|e| {
-                    if let Some(after_id) = after_event_id {
-                        e.id > after_id.to_string()
-                    } else {
-                        true
-                    }
-                })
-                .cloned()
-                .filter_map(|e| e.try_into().ok())
-                .collect();
-
-            // Apply limit
-            if let Some(lim) = limit {
-                if result.len() > lim {
-                    result = result.split_off(result.len() - lim);
+            // Resume right after the given event in publish order; an id that is
+            // no longer cached has to be resolved by storage
+            let start = match after_event_id {
+                Some(after_id) => events
+                    .iter()
+                    .position(|e| e.id == after_id)
+                    .map(|index| index + 1),
+                None if events.is_empty() => None,
+                None => Some(0),
+            };
+
+            if let Some(start) = start {
+                let mut result: Vec<StreamingEvent> = events[start..]
+                    .iter()
+                    .cloned()
+                    .filter_map(|e| e.try_into().ok())
+                    .collect();
+
+                // Apply limit
+                if let Some(lim) = limit {
+                    result.truncate(lim);
                 }
-            }
 
-            // If we have enough events from cache, return them
-            if !result.is_empty() {
                 return Ok(result);
             }
         }
@@ -115,15 +144,48 @@ impl EventBuffer {
         Ok(vec![])
     }
 
+    /// Whether the session has an event with the given ID, cached or stored
+    pub async fn has_event(&self, session_id: &str, event_id: &str) -> Result<bool, String> {
+        let cached = self
+            .cache
+            .read()
+            .await
+            .get(session_id)
+            .is_some_and(|events| events.iter().any(|e| e.id == event_id));
+        if cached {
+            return Ok(true);
+        }
+
+        match &self.storage {
+            Some(storage) => Ok(storage
+                .chat_history
+                .get_event_seq(session_id, event_id)
+                .await?
+                .is_some()),
+            None => Ok(false),
+        }
+    }
+
     /// Get the last event ID for a session
     pub async fn get_last_event_id(&self, session_id: &str) -> Option<EventId> {
         let cache = self.cache.read().await;
 
-        if let Some(events) = cache.get(session_id) {
-            return events.last().map(|e| e.id.clone());
+        if let Some(event) = cache.get(session_id).and_then(|events| events.last()) {
+            return Some(event.id.clone());
         }
+        drop(cache);
 
-        None
+        match &self.storage {
+            Some(storage) => storage
+                .chat_history
+                .get_last_event_id(session_id)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to get last event of {}: {}", session_id, e);
+                    None
+                }),
+            None => None,
+        }
     }
 
     /// Clear events for a session from memory (keeps storage)
@@ -200,6 +262,154 @@ mod tests {
             .await
             .unwrap();
         assert_eq!(events.len(), 2); // evt-3 and evt-4
+
+        // A limit keeps the oldest events after the cursor, as storage does
+        let events = buffer
+            .get_events("sess-1", Some("evt-0"), Some(2))
+            .await
+            .unwrap();
+        let ids: Vec<&str> = events.iter().map(|e| e.event_id().as_str()).collect();
+        assert_eq!(ids, vec!["evt-1", "evt-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_events_after_id_follows_publish_order() {
+        let buffer = EventBuffer::new(100);
+
+        for id in ["evt-b", "evt-a", "evt-c"] {
+            let event = StreamingEvent::Token {
+                event_id: id.to_string(),
+                session_id: "sess-1".to_string(),
+                data: TokenEventData {
+                    token: id.to_string(),
+                },
+            };
+            buffer.add_event(event).await.unwrap();
+        }
+
+        let events = buffer
+            .get_events("sess-1", Some("evt-b"), None)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = events.iter().map(|e| e.event_id().as_str()).collect();
+        assert_eq!(ids, vec!["evt-a", "evt-c"]);
+
+        // A client that is up to date gets nothing back, not a full replay
+        let events = buffer
+            .get_events("sess-1", Some("evt-c"), None)
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sequence_survives_restart_and_orders_storage_replay() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Arc::new(
+            Storage::new(
+                temp_dir.path().to_path_buf(),
+                temp_dir.path().join("attachments"),
+            )
+            .await
+            .unwrap(),
+        );
+        let status = |id: &str| StreamingEvent::Status {
+            event_id: id.to_string(),
+            session_id: "sess-1".to_string(),
+            data: StatusEventData {
+                message: id.to_string(),
+            },
+        };
+
+        let buffer = EventBuffer::new(100).with_storage(storage.clone());
+        buffer.add_event(status("evt-z")).await.unwrap();
+        buffer.add_event(status("evt-y")).await.unwrap();
+
+        // A fresh buffer (e.g. after a server restart) continues the sequence
+        let restarted = EventBuffer::new(100).with_storage(storage.clone());
+        restarted.add_event(status("evt-x")).await.unwrap();
+        assert_eq!(
+            storage
+                .chat_history
+                .get_last_event_seq("sess-1")
+                .await
+                .unwrap(),
+            3
+        );
+
+        // evt-z is not cached by the new buffer, so replay comes from storage
+        let events = restarted
+            .get_events("sess-1", Some("evt-z"), None)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = events.iter().map(|e| e.event_id().as_str()).collect();
+        assert_eq!(ids, vec!["evt-y", "evt-x"]);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_cached_but_not_persisted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Arc::new(
+            Storage::new(
+                temp_dir.path().to_path_buf(),
+                temp_dir.path().join("attachments"),
+            )
+            .await
+            .unwrap(),
+        );
+        let buffer = EventBuffer::new(100).with_storage(storage.clone());
+
+        for i in 0..3 {
+            let event = StreamingEvent::Token {
+                event_id: format!("tok-{}", i),
+                session_id: "sess-1".to_string(),
+                data: TokenEventData {
+                    token: format!("token{}", i),
+                },
+            };
+            buffer.add_event(event).await.unwrap();
+        }
+        buffer
+            .add_event(StreamingEvent::Status {
+                event_id: "status-1".to_string(),
+                session_id: "sess-1".to_string(),
+                data: StatusEventData {
+                    message: "done".to_string(),
+                },
+            })
+            .await
+            .unwrap();
+
+        let events = buffer
+            .get_events("sess-1", Some("tok-0"), None)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = events.iter().map(|e| e.event_id().as_str()).collect();
+        assert_eq!(ids, vec!["tok-1", "tok-2", "status-1"]);
+
+        // After a restart the tokens are unknown: nothing is replayed, and the
+        // latest stored event is where a resume continues from
+        let restarted = EventBuffer::new(100).with_storage(storage.clone());
+        assert!(restarted
+            .get_events("sess-1", Some("tok-0"), None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!restarted.has_event("sess-1", "tok-0").await.unwrap());
+        assert!(restarted.has_event("sess-1", "status-1").await.unwrap());
+        assert_eq!(
+            restarted.get_last_event_id("sess-1").await,
+            Some("status-1".to_string())
+        );
+
+        let stored = storage
+            .chat_history
+            .get_events("sess-1", None, None)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, "status-1");
+        assert_eq!(stored[0].seq, 4);
     }
 
     #[tokio::test]
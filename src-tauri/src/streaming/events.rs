@@ -2,11 +2,21 @@
 //!
 //! Defines event types for SSE streaming and conversion between internal and external formats.
 
-use crate::storage::models::{EventId, EventType, SessionEvent, SessionId};
+use crate::core::types::UserQuestion;
+use crate::storage::models::{EventId, EventType, SessionEvent, SessionId, TodoItem};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+/// Generate a time-ordered event id (`evt_YYYYMMDDhhmmssfff-xxxxxxxx`)
+///
+/// Ids sort by creation time for readability; replay order within a session is
+/// defined by the sequence number the `EventBuffer` assigns.
+pub fn new_event_id() -> EventId {
+    format!("evt_{}", crate::llm::tracing::ids::generate_trace_id())
+}
+
 /// Event envelope for streaming
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum StreamingEvent {
     /// Status update
@@ -63,21 +73,39 @@ pub enum StreamingEvent {
         session_id: Option<SessionId>,
         data: ErrorEventData,
     },
+    /// Session todo list changed
+    #[serde(rename = "todo.updated")]
+    TodoUpdated {
+        #[serde(rename = "eventId")]
+        event_id: EventId,
+        #[serde(rename = "sessionId")]
+        session_id: SessionId,
+        data: TodoEventData,
+    },
+    /// Task is waiting for answers to `askUserQuestions`
+    #[serde(rename = "question.asked")]
+    QuestionAsked {
+        #[serde(rename = "eventId")]
+        event_id: EventId,
+        #[serde(rename = "sessionId")]
+        session_id: SessionId,
+        data: QuestionEventData,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StatusEventData {
     pub message: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenEventData {
     pub token: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageFinalEventData {
     #[serde(rename = "messageId")]
@@ -85,7 +113,7 @@ pub struct MessageFinalEventData {
     pub content: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolCallEventData {
     #[serde(rename = "toolCallId")]
@@ -95,7 +123,7 @@ pub struct ToolCallEventData {
     pub provider_metadata: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolResultEventData {
     #[serde(rename = "toolCallId")]
@@ -104,12 +132,26 @@ pub struct ToolResultEventData {
     pub output: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorEventData {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoEventData {
+    pub todos: Vec<TodoItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionEventData {
+    pub task_id: String,
+    pub tool_call_id: String,
+    pub questions: Vec<UserQuestion>,
+}
+
 impl StreamingEvent {
     /// Get the event ID
     pub fn event_id(&self) -> &EventId {
@@ -120,6 +162,8 @@ impl StreamingEvent {
             StreamingEvent::ToolCall { event_id, .. } => event_id,
             StreamingEvent::ToolResult { event_id, .. } => event_id,
             StreamingEvent::Error { event_id, .. } => event_id,
+            StreamingEvent::TodoUpdated { event_id, .. } => event_id,
+            StreamingEvent::QuestionAsked { event_id, .. } => event_id,
         }
     }
 
@@ -132,6 +176,8 @@ impl StreamingEvent {
             StreamingEvent::ToolCall { session_id, .. } => Some(session_id),
             StreamingEvent::ToolResult { session_id, .. } => Some(session_id),
             StreamingEvent::Error { session_id, .. } => session_id.as_ref(),
+            StreamingEvent::TodoUpdated { session_id, .. } => Some(session_id),
+            StreamingEvent::QuestionAsked { session_id, .. } => Some(session_id),
         }
     }
 
@@ -144,20 +190,14 @@ impl StreamingEvent {
             StreamingEvent::ToolCall { .. } => EventType::ToolCall,
             StreamingEvent::ToolResult { .. } => EventType::ToolResult,
             StreamingEvent::Error { .. } => EventType::Error,
+            StreamingEvent::TodoUpdated { .. } => EventType::TodoUpdated,
+            StreamingEvent::QuestionAsked { .. } => EventType::QuestionAsked,
         }
     }
 
     /// Convert to SSE event string
     pub fn to_sse_string(&self) -> String {
-        let event_type = match self {
-            StreamingEvent::Status { .. } => "status",
-            StreamingEvent::Token { .. } => "token",
-            StreamingEvent::MessageFinal { .. } => "message.final",
-            StreamingEvent::ToolCall { .. } => "tool.call",
-            StreamingEvent::ToolResult { .. } => "tool.result",
-            StreamingEvent::Error { .. } => "error",
-        };
-
+        let event_type = self.event_type().as_str();
         let event_id = self.event_id();
         let data = serde_json::to_string(self).unwrap_or_default();
 
@@ -230,6 +270,24 @@ impl TryFrom<SessionEvent> for StreamingEvent {
                     data,
                 })
             }
+            EventType::TodoUpdated => {
+                let data: TodoEventData = serde_json::from_value(payload)
+                    .map_err(|e| format!("Failed to parse todo.updated event: {}", e))?;
+                Ok(StreamingEvent::TodoUpdated {
+                    event_id: event.id,
+                    session_id: event.session_id,
+                    data,
+                })
+            }
+            EventType::QuestionAsked => {
+                let data: QuestionEventData = serde_json::from_value(payload)
+                    .map_err(|e| format!("Failed to parse question.asked event: {}", e))?;
+                Ok(StreamingEvent::QuestionAsked {
+                    event_id: event.id,
+                    session_id: event.session_id,
+                    data,
+                })
+            }
         }
     }
 }
@@ -298,11 +356,32 @@ impl From<StreamingEvent> for SessionEvent {
                 EventType::Error,
                 serde_json::to_value(data).unwrap(),
             ),
+            StreamingEvent::TodoUpdated {
+                event_id,
+                session_id,
+                data,
+            } => (
+                event_id,
+                session_id,
+                EventType::TodoUpdated,
+                serde_json::to_value(data).unwrap(),
+            ),
+            StreamingEvent::QuestionAsked {
+                event_id,
+                session_id,
+                data,
+            } => (
+                event_id,
+                session_id,
+                EventType::QuestionAsked,
+                serde_json::to_value(data).unwrap(),
+            ),
         };
 
         SessionEvent {
             id,
             session_id,
+            seq: 0,
             event_type,
             payload,
             created_at: chrono::Utc::now().timestamp(),
@@ -1,13 +1,16 @@
 //! Streaming Layer
 //!
-//! Handles event streaming for SSE with buffering, throttling, and resume capability.
+//! Handles event streaming for SSE with buffering, throttling, live fan-out and
+//! resume capability.
 
 pub mod buffer;
 pub mod events;
+pub mod subscription;
 pub mod throttle;
 
 pub use buffer::{BufferStats, EventBuffer};
 pub use events::*;
+pub use subscription::SessionSubscription;
 pub use throttle::{EventThrottler, StreamingManager, ThrottleConfig};
 
 /// Create a new streaming manager with default configuration
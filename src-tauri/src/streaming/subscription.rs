@@ -0,0 +1,263 @@
+//! Session Subscriptions
+//!
+//! Live event feed for one session with resume. A subscription first replays
+//! buffered events after the client's last seen event id, then follows live
+//! events, catching up from the buffer whenever it falls behind the broadcast
+//! channel. Events are delivered once, in publish order. A last seen id the
+//! buffer no longer knows resumes from the latest event instead.
+
+use crate::storage::models::{EventId, SessionId};
+use crate::streaming::events::StreamingEvent;
+use crate::streaming::StreamingManager;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, RwLock};
+
+/// Live, resumable event feed for a session
+pub struct SessionSubscription {
+    session_id: SessionId,
+    manager: Arc<RwLock<StreamingManager>>,
+    receiver: broadcast::Receiver<StreamingEvent>,
+    /// Events read from the buffer, waiting to be delivered
+    pending: VecDeque<StreamingEvent>,
+    /// Ids read from the buffer that may still arrive on the live channel
+    replayed: HashSet<EventId>,
+    /// Id of the last event delivered, or the point the feed starts after
+    cursor: Option<EventId>,
+}
+
+impl SessionSubscription {
+    /// Subscribe to a session, resuming after `last_event_id` if given
+    ///
+    /// Without `last_event_id`, or with one that is neither cached nor stored
+    /// (e.g. a token from before a restart), only events published from now
+    /// on are delivered.
+    pub async fn new(
+        manager: Arc<RwLock<StreamingManager>>,
+        session_id: &str,
+        last_event_id: Option<&str>,
+    ) -> Self {
+        let (receiver, replay, cursor) = {
+            let guard = manager.read().await;
+            // Subscribe before reading the buffer so no event falls in between
+            let receiver = guard.subscribe();
+            let known = match last_event_id {
+                Some(last_event_id) => guard
+                    .buffer
+                    .has_event(session_id, last_event_id)
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to look up event {}: {}", last_event_id, e);
+                        false
+                    }),
+                None => false,
+            };
+            match last_event_id.filter(|_| known) {
+                Some(last_event_id) => {
+                    let replay = guard
+                        .buffer
+                        .get_events(session_id, Some(last_event_id), None)
+                        .await
+                        .unwrap_or_else(|e| {
+                            log::warn!("Failed to replay events for {}: {}", session_id, e);
+                            Vec::new()
+                        });
+                    (receiver, replay, Some(last_event_id.to_string()))
+                }
+                None => {
+                    if let Some(last_event_id) = last_event_id {
+                        log::info!(
+                            "Unknown last event id {} for {}, resuming from the latest event",
+                            last_event_id,
+                            session_id
+                        );
+                    }
+                    let cursor = guard.buffer.get_last_event_id(session_id).await;
+                    (receiver, Vec::new(), cursor)
+                }
+            }
+        };
+
+        let mut subscription = Self {
+            session_id: session_id.to_string(),
+            manager,
+            receiver,
+            pending: VecDeque::new(),
+            replayed: HashSet::new(),
+            cursor,
+        };
+        subscription.enqueue_replay(replay);
+        subscription
+    }
+
+    /// Next event of the session, or `None` once the manager is gone
+    pub async fn next(&mut self) -> Option<StreamingEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.cursor = Some(event.event_id().clone());
+                return Some(event);
+            }
+
+            match self.receiver.recv().await {
+                Ok(event) => {
+                    if event.session_id() != Some(&self.session_id)
+                        || self.replayed.remove(event.event_id())
+                    {
+                        continue;
+                    }
+                    self.pending.push_back(event);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!(
+                        "Subscriber of {} skipped {} live events, catching up from buffer",
+                        self.session_id,
+                        skipped
+                    );
+                    self.catch_up().await;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Queue everything buffered after the cursor
+    async fn catch_up(&mut self) {
+        let events = self
+            .manager
+            .read()
+            .await
+            .buffer
+            .get_events(&self.session_id, self.cursor.as_deref(), None)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to catch up events for {}: {}", self.session_id, e);
+                Vec::new()
+            });
+        self.enqueue_replay(events);
+    }
+
+    fn enqueue_replay(&mut self, events: Vec<StreamingEvent>) {
+        for event in events {
+            self.replayed.insert(event.event_id().clone());
+            self.pending.push_back(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::events::TokenEventData;
+
+    fn token(id: &str, session_id: &str) -> StreamingEvent {
+        StreamingEvent::Token {
+            event_id: id.to_string(),
+            session_id: session_id.to_string(),
+            data: TokenEventData {
+                token: id.to_string(),
+            },
+        }
+    }
+
+    async fn next_id(subscription: &mut SessionSubscription) -> String {
+        subscription.next().await.unwrap().event_id().clone()
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_then_follows_live_events() {
+        let manager = Arc::new(RwLock::new(StreamingManager::new()));
+        for id in ["evt-1", "evt-2", "evt-3"] {
+            manager
+                .read()
+                .await
+                .publish(token(id, "sess-1"))
+                .await
+                .unwrap();
+        }
+
+        let mut subscription =
+            SessionSubscription::new(manager.clone(), "sess-1", Some("evt-1")).await;
+        {
+            let manager = manager.read().await;
+            manager.publish(token("other", "sess-2")).await.unwrap();
+            manager.publish(token("evt-4", "sess-1")).await.unwrap();
+        }
+
+        assert_eq!(next_id(&mut subscription).await, "evt-2");
+        assert_eq!(next_id(&mut subscription).await, "evt-3");
+        assert_eq!(next_id(&mut subscription).await, "evt-4");
+    }
+
+    #[tokio::test]
+    async fn test_without_last_event_id_starts_from_now() {
+        let manager = Arc::new(RwLock::new(StreamingManager::new()));
+        manager
+            .read()
+            .await
+            .publish(token("evt-1", "sess-1"))
+            .await
+            .unwrap();
+
+        let mut subscription = SessionSubscription::new(manager.clone(), "sess-1", None).await;
+        manager
+            .read()
+            .await
+            .publish(token("evt-2", "sess-1"))
+            .await
+            .unwrap();
+
+        assert_eq!(next_id(&mut subscription).await, "evt-2");
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber_catches_up_without_gaps() {
+        let manager = Arc::new(RwLock::new(
+            StreamingManager::new().with_channel_capacity(2),
+        ));
+        let mut subscription = SessionSubscription::new(manager.clone(), "sess-1", None).await;
+
+        for i in 0..6 {
+            manager
+                .read()
+                .await
+                .publish(token(&format!("evt-{}", i), "sess-1"))
+                .await
+                .unwrap();
+        }
+
+        let mut ids = Vec::new();
+        for _ in 0..6 {
+            ids.push(next_id(&mut subscription).await);
+        }
+        let expected: Vec<String> = (0..6).map(|i| format!("evt-{}", i)).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_last_event_id_resumes_from_latest() {
+        let manager = Arc::new(RwLock::new(StreamingManager::new()));
+        for id in ["evt-1", "evt-2"] {
+            manager
+                .read()
+                .await
+                .publish(token(id, "sess-1"))
+                .await
+                .unwrap();
+        }
+
+        let mut subscription =
+            SessionSubscription::new(manager.clone(), "sess-1", Some("evt-evicted")).await;
+        manager
+            .read()
+            .await
+            .publish(token("evt-3", "sess-1"))
+            .await
+            .unwrap();
+
+        // Nothing from before the subscription is replayed
+        assert_eq!(next_id(&mut subscription).await, "evt-3");
+        assert!(subscription.pending.is_empty());
+        assert_eq!(subscription.cursor.as_deref(), Some("evt-3"));
+    }
+}
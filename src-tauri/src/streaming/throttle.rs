@@ -5,10 +5,15 @@
 //! - Token streaming with debouncing
 //! - Message length caps
 
+use crate::storage::Storage;
 use crate::streaming::events::StreamingEvent;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+/// Events a live subscriber may fall behind before it has to catch up from the buffer
+const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
 
 /// Throttling configuration
 #[derive(Debug, Clone)]
@@ -145,13 +150,17 @@ impl Default for EventThrottler {
 pub struct StreamingManager {
     pub buffer: crate::streaming::buffer::EventBuffer,
     pub throttler: EventThrottler,
+    /// Live fan-out of published events to subscribers
+    sender: broadcast::Sender<StreamingEvent>,
 }
 
 impl StreamingManager {
     pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(DEFAULT_CHANNEL_CAPACITY);
         Self {
             buffer: crate::streaming::buffer::EventBuffer::new(1000),
             throttler: EventThrottler::default(),
+            sender,
         }
     }
 
@@ -164,6 +173,31 @@ impl StreamingManager {
         self.throttler = EventThrottler::new(config);
         self
     }
+
+    /// Persist buffered events so clients can resume beyond the memory cache
+    pub fn with_storage(mut self, storage: Arc<Storage>) -> Self {
+        self.buffer = self.buffer.with_storage(storage);
+        self
+    }
+
+    /// Set how many events a live subscriber may lag behind
+    pub fn with_channel_capacity(mut self, capacity: usize) -> Self {
+        self.sender = broadcast::channel(capacity).0;
+        self
+    }
+
+    /// Buffer an event for resume and deliver it to live subscribers
+    pub async fn publish(&self, event: StreamingEvent) -> Result<(), String> {
+        let buffered = self.buffer.add_event(event.clone()).await;
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+        buffered
+    }
+
+    /// Subscribe to events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamingEvent> {
+        self.sender.subscribe()
+    }
 }
 
 impl Default for StreamingManager {
@@ -198,6 +232,27 @@ mod tests {
         assert_eq!(flushed, Some("Hi there".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_publish_buffers_and_broadcasts() {
+        let manager = StreamingManager::new();
+        let mut receiver = manager.subscribe();
+
+        let event = StreamingEvent::Token {
+            event_id: "evt-1".to_string(),
+            session_id: "sess-1".to_string(),
+            data: crate::streaming::events::TokenEventData {
+                token: "Hi".to_string(),
+            },
+        };
+        manager.publish(event).await.unwrap();
+
+        assert_eq!(receiver.recv().await.unwrap().event_id(), "evt-1");
+        assert_eq!(
+            manager.buffer.get_last_event_id("sess-1").await.as_deref(),
+            Some("evt-1")
+        );
+    }
+
     #[test]
     fn test_message_length_cap() {
         let throttler = EventThrottler::default();